@@ -0,0 +1,86 @@
+use cap_cli::bench::BenchDb;
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+/// Rows `cap list` and the TUI show per screen.
+const PAGE: usize = 50;
+
+const WORDS: [&str; 16] = [
+    "standup", "release", "notes", "lunch", "review", "deploy", "idea", "read", "call", "draft",
+    "bug", "travel", "sleep", "garden", "recipe", "budget",
+];
+const TAGS: [&str; 6] = ["#work", "#home", "#health", "#ideas", "#reading", "#日記"];
+
+/// Deterministic, varied memo text so FTS and tag extraction see realistic input.
+fn memo_contents(count: usize) -> impl Iterator<Item = String> {
+    (0..count).map(|i| {
+        let words: Vec<&str> = (0..8 + i % 24)
+            .map(|j| WORDS[(i * 7 + j * 13) % WORDS.len()])
+            .collect();
+        format!("{} {} #{}", words.join(" "), TAGS[i % TAGS.len()], i % 97)
+    })
+}
+
+fn generated_db(count: usize) -> BenchDb {
+    let db = BenchDb::open_in_memory().unwrap();
+    db.insert_memos(memo_contents(count)).unwrap();
+    db
+}
+
+fn fetch_and_search(c: &mut Criterion) {
+    for size in SIZES {
+        let db = generated_db(size);
+
+        let mut group = c.benchmark_group("fetch_memos");
+        group.bench_with_input(BenchmarkId::new("page", size), &db, |b, db| {
+            b.iter(|| db.fetch_memos(black_box(Some(PAGE))).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("all", size), &db, |b, db| {
+            b.iter(|| db.fetch_memos(black_box(None)).unwrap())
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("search_memos");
+        group.bench_with_input(BenchmarkId::new("fts", size), &db, |b, db| {
+            b.iter(|| {
+                db.search_memos(black_box("release notes"), Some(PAGE))
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("fts_rare", size), &db, |b, db| {
+            b.iter(|| {
+                db.search_memos(black_box("garden recipe budget"), Some(PAGE))
+                    .unwrap()
+            })
+        });
+        // Two characters is below the trigram size and scans with LIKE.
+        group.bench_with_input(BenchmarkId::new("short", size), &db, |b, db| {
+            b.iter(|| db.search_memos(black_box("日記"), Some(PAGE)).unwrap())
+        });
+        group.finish();
+    }
+}
+
+fn bulk_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_memos");
+    group.sample_size(10);
+    for size in [1_000, 10_000] {
+        let contents: Vec<String> = memo_contents(size).collect();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &contents,
+            |b, contents| {
+                b.iter_batched(
+                    || BenchDb::open_in_memory().unwrap(),
+                    |db| db.insert_memos(contents.iter().cloned()).unwrap(),
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fetch_and_search, bulk_insert);
+criterion_main!(benches);
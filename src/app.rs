@@ -1,19 +1,35 @@
 use anyhow::Result;
 
-use crate::{config, db::Db};
+use crate::{
+    config::{self, Config},
+    db::Db,
+    i18n::t,
+};
 
 pub(crate) struct AppContext {
     db: Db,
+    config: Config,
 }
 
 impl AppContext {
-    pub(crate) fn new() -> Result<Self> {
+    pub(crate) fn new(config: Config) -> Result<Self> {
         let path = config::db_path()?;
-        let db = Db::open(path)?;
-        Ok(Self { db })
+        let (mut db, backup) = Db::open_migrating(path)?;
+        db.set_compress_above(config.memo.compress_above_bytes);
+        if let Some(backup) = backup {
+            eprintln!(
+                "{}",
+                t!("db-migrated", backup = backup.display().to_string())
+            );
+        }
+        Ok(Self { db, config })
     }
 
     pub(crate) fn db(&self) -> &Db {
         &self.db
     }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
 }
@@ -0,0 +1,284 @@
+/// Elements whose content is never part of the article.
+const SKIPPED: [&str; 12] = [
+    "script", "style", "noscript", "template", "svg", "nav", "header", "footer", "aside", "form",
+    "iframe", "button",
+];
+/// Elements that start a new paragraph.
+const BLOCKS: [&str; 14] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "blockquote",
+    "pre",
+    "ul",
+    "ol",
+    "table",
+    "tr",
+    "figure",
+    "figcaption",
+    "hr",
+];
+
+/// An HTML page boiled down to its title and readable text.
+pub(crate) struct Article {
+    pub(crate) title: Option<String>,
+    pub(crate) text: String,
+}
+
+/// A small readability pass: keeps the text of `<article>` (or `<main>`, or
+/// `<body>`), drops scripts, navigation, and page chrome, and turns headings,
+/// paragraphs, and list items into Markdown-ish lines.
+pub(crate) fn extract(html: &str) -> Article {
+    let title = element_text(html, "title")
+        .or_else(|| element_text(html, "h1"))
+        .filter(|title| !title.is_empty());
+    let region = ["article", "main", "body"]
+        .into_iter()
+        .find_map(|name| element_inner(html, name))
+        .unwrap_or(html);
+    Article {
+        title,
+        text: to_text(region),
+    }
+}
+
+fn to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut skipping: Option<String> = None;
+    let mut in_pre = false;
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        if skipping.is_none() {
+            push_text(&mut out, &rest[..start], in_pre);
+        }
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, after)| after);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let (name, closing) = tag_name(&rest[1..end]);
+        rest = &rest[end + 1..];
+
+        if let Some(skipped) = &skipping {
+            if closing && name == *skipped {
+                skipping = None;
+            }
+            continue;
+        }
+        if !closing && SKIPPED.contains(&name.as_str()) {
+            skipping = Some(name);
+            continue;
+        }
+        match name.as_str() {
+            "br" => out.push('\n'),
+            "pre" => {
+                in_pre = !closing;
+                paragraph(&mut out);
+            }
+            "li" if !closing => {
+                line(&mut out);
+                out.push_str("- ");
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                paragraph(&mut out);
+                if !closing {
+                    let level = name[1..].parse().unwrap_or(1);
+                    out.push_str(&"#".repeat(level));
+                    out.push(' ');
+                }
+            }
+            name if BLOCKS.contains(&name) => paragraph(&mut out),
+            _ => {}
+        }
+    }
+    if skipping.is_none() {
+        push_text(&mut out, rest, in_pre);
+    }
+    tidy_lines(&out)
+}
+
+/// Lowercased tag name and whether it's a closing tag, from the text between `<` and `>`.
+fn tag_name(tag: &str) -> (String, bool) {
+    let (closing, tag) = match tag.strip_prefix('/') {
+        Some(tag) => (true, tag),
+        None => (false, tag),
+    };
+    let name = tag
+        .split(|ch: char| ch.is_whitespace() || ch == '/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    (name, closing)
+}
+
+fn push_text(out: &mut String, text: &str, in_pre: bool) {
+    let text = decode_entities(text);
+    if in_pre {
+        out.push_str(&text);
+        return;
+    }
+    for (idx, word) in text.split_whitespace().enumerate() {
+        let needs_space = idx > 0
+            || (text.starts_with(char::is_whitespace)
+                && !out.is_empty()
+                && !out.ends_with(char::is_whitespace));
+        if needs_space {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+        out.push(' ');
+    }
+}
+
+fn line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn paragraph(out: &mut String) {
+    line(out);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+fn tidy_lines(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = true;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank = true;
+            continue;
+        }
+        if blank && !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(line);
+        out.push('\n');
+        blank = false;
+    }
+    out
+}
+
+/// Text inside the first `<name>` element, tags stripped.
+fn element_text(html: &str, name: &str) -> Option<String> {
+    element_inner(html, name).map(|inner| {
+        to_text(inner)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// Raw HTML between the first `<name ...>` and its closing `</name>`.
+fn element_inner<'a>(html: &'a str, name: &str) -> Option<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let mut search_from = 0;
+    let start = loop {
+        let found = search_from + lower[search_from..].find(&open)?;
+        let after = lower[found + open.len()..].chars().next()?;
+        if after == '>' || after.is_whitespace() {
+            break found + lower[found..].find('>')? + 1;
+        }
+        search_from = found + open.len();
+    };
+    let end = start + lower[start..].find(&format!("</{}", name))?;
+    Some(&html[start..end])
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 8)
+            .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let named = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "mdash" => '—',
+        "ndash" => '–',
+        "hellip" => '…',
+        "rsquo" => '’',
+        "lsquo" => '‘',
+        "rdquo" => '”',
+        "ldquo" => '“',
+        _ => {
+            let code = match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => entity.strip_prefix('#')?.parse().ok()?,
+            };
+            return char::from_u32(code);
+        }
+    };
+    Some(named)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_article_and_drops_page_chrome() {
+        let html = r##"<!doctype html><html><head><title>Rust &amp; SQLite</title>
+            <style>p { color: red }</style><script>var x = "<p>";</script></head>
+            <body><nav><a href="/">Home</a></nav>
+            <article><h1>Rust &amp; SQLite</h1><!-- ad slot -->
+            <p>First   paragraph with <a href="#">a link</a>.</p>
+            <ul><li>one</li><li>two&nbsp;&#8212; &#x263A;</li></ul>
+            <pre>let x = 1;
+    let y = 2;</pre></article>
+            <footer>© someone</footer></body></html>"##;
+        let article = extract(html);
+        assert_eq!(article.title.as_deref(), Some("Rust & SQLite"));
+        assert_eq!(
+            article.text,
+            "# Rust & SQLite\n\nFirst paragraph with a link.\n\n- one\n- two — ☺\n\nlet x = 1;\n    let y = 2;\n"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_body_and_h1() {
+        let article = extract("<body><h1>Only heading</h1><div>Text<br>more</div></body>");
+        assert_eq!(article.title.as_deref(), Some("Only heading"));
+        assert_eq!(article.text, "# Only heading\n\nText\nmore\n");
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Result, anyhow, bail};
+use chrono::Local;
+use reqwest::{
+    Response,
+    header::{ACCEPT, CONTENT_TYPE},
+};
+use std::{io::Write, path::Path};
+
+use crate::{
+    attachments, config,
+    db::{self, Db},
+    domain::memo::MemoId,
+    http::HttpClient,
+};
+
+mod extract;
+
+const ARTICLE_FILE_NAME: &str = "article.md";
+/// Pages larger than this are refused rather than read into memory.
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+/// Metadata key recording when the page was last archived.
+pub(crate) const ARCHIVED_AT_KEY: &str = "archived_at";
+
+/// First `http(s)://` link in a memo, without trailing punctuation.
+pub(crate) fn find_url(content: &str) -> Option<&str> {
+    content
+        .split(|ch: char| ch.is_whitespace() || matches!(ch, '<' | '(' | '['))
+        .find(|token| token.starts_with("https://") || token.starts_with("http://"))
+        .map(|url| url.trim_end_matches(['.', ',', ';', ':', ')', ']', '>', '!', '?', '"', '\'']))
+}
+
+/// Downloads `url` and boils it down to Markdown: title, source link, then text.
+pub(crate) async fn download(http: &HttpClient, url: &str) -> Result<String> {
+    let request = http.get(url).header(
+        ACCEPT,
+        "text/html,application/xhtml+xml;q=0.9,text/plain;q=0.8",
+    );
+    let response = http.send(request).await?.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !content_type.is_empty()
+        && !content_type.starts_with("text/")
+        && !content_type.contains("html")
+    {
+        bail!("{} is {}, not a web page", url, content_type);
+    }
+    let body = read_limited(response, url).await?;
+    Ok(render(url, &body, content_type.starts_with("text/plain")))
+}
+
+/// The body of `response` as text, or an error once it passes `MAX_PAGE_BYTES`.
+/// A `Content-Length` over the limit is refused before anything is read.
+async fn read_limited(mut response: Response, url: &str) -> Result<String> {
+    let too_large = || anyhow!("{} is larger than {} MB", url, MAX_PAGE_BYTES / 1024 / 1024);
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_PAGE_BYTES as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_PAGE_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+fn render(url: &str, body: &str, plain_text: bool) -> String {
+    if plain_text {
+        return format!("<{}>\n\n{}\n", url, body.trim());
+    }
+    let article = extract::extract(body);
+    match article.title {
+        Some(title) => format!("# {}\n\n<{}>\n\n{}", title, url, article.text),
+        None => format!("<{}>\n\n{}", url, article.text),
+    }
+}
+
+/// Saves the archived page next to the memo's other attachments, replacing an
+/// earlier copy, and indexes it for search.
+pub(crate) fn store(db: &Db, memo_id: &MemoId, markdown: &str) -> Result<()> {
+    store_in(&config::attachments_dir()?, db, memo_id, markdown)
+}
+
+/// The page replaces the old copy only once its rows are committed, so a
+/// failure leaves both the file and the rows as they were.
+fn store_in(attachments: &Path, db: &Db, memo_id: &MemoId, markdown: &str) -> Result<()> {
+    let dir = attachments.join(memo_id.as_str());
+    let staged = attachments::stage_file(&dir, ARTICLE_FILE_NAME, |file| {
+        file.write_all(markdown.as_bytes())
+    })?;
+    let path = dir.join(ARTICLE_FILE_NAME);
+    db.atomically(|db| {
+        db::remove_attachment(db, memo_id, ARTICLE_FILE_NAME)?;
+        db::add_attachment(db, memo_id, ARTICLE_FILE_NAME, &path, markdown.len() as u64)?;
+        db::set_archive_text(db, memo_id, markdown)?;
+        db::set_meta(db, memo_id, ARCHIVED_AT_KEY, &Local::now().to_rfc3339())
+    })?;
+    staged.keep()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+    use std::fs;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    #[test]
+    fn find_url_takes_the_first_link_without_punctuation() {
+        assert_eq!(
+            find_url("read (https://example.com/post?id=1). later http://b.org"),
+            Some("https://example.com/post?id=1")
+        );
+        assert_eq!(
+            find_url("<https://example.com/a>"),
+            Some("https://example.com/a")
+        );
+        assert_eq!(find_url("no links, just ftp://old.host"), None);
+    }
+
+    #[tokio::test]
+    async fn download_renders_the_readable_part_of_a_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/post"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "<html><head><title>A post</title></head><body><nav>menu</nav>\
+                 <main><p>The body.</p></main></body></html>",
+                "text/html; charset=utf-8",
+            ))
+            .mount(&server)
+            .await;
+        let url = format!("{}/post", server.uri());
+        let markdown = download(&HttpClient::new().unwrap(), &url).await.unwrap();
+        assert_eq!(markdown, format!("# A post\n\n<{}>\n\nThe body.\n", url));
+    }
+
+    #[tokio::test]
+    async fn oversized_pages_are_refused() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(vec![b'a'; MAX_PAGE_BYTES + 1], "text/plain"),
+            )
+            .mount(&server)
+            .await;
+        let error = download(&HttpClient::new().unwrap(), &server.uri())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("larger than"));
+    }
+
+    #[test]
+    fn a_failed_store_keeps_the_earlier_copy() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = db::add_memo(&db, &NewMemo::new("https://example.com")).unwrap();
+        let attachments = tempfile::tempdir().unwrap();
+        store_in(attachments.path(), &db, &memo_id, "first copy").unwrap();
+        let dir = attachments.path().join(memo_id.as_str());
+        assert_eq!(
+            fs::read_to_string(dir.join(ARTICLE_FILE_NAME)).unwrap(),
+            "first copy"
+        );
+
+        db.conn().execute_batch("DROP TABLE archives_fts").unwrap();
+        assert!(store_in(attachments.path(), &db, &memo_id, "second copy").is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join(ARTICLE_FILE_NAME)).unwrap(),
+            "first copy"
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(db::fetch_attachments(&db, &memo_id).unwrap().len(), 1);
+        let meta = db::fetch_meta(&db, &memo_id).unwrap();
+        assert_eq!(
+            meta.iter()
+                .filter(|(key, _)| key == ARCHIVED_AT_KEY)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn archived_text_is_searchable() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = db::add_memo(&db, &NewMemo::new("read later https://example.com")).unwrap();
+        db::set_archive_text(&db, &memo_id, "# Trigram tokenizers\n\nsqlite internals").unwrap();
+        let found = db::search_memos(&db, "tokenizer", None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].memo_id.as_str(), memo_id.as_str());
+
+        // Too short for the index, so found by a scan.
+        assert_eq!(db::search_memos(&db, "sq", None).unwrap().len(), 1);
+
+        db::set_archive_text(&db, &memo_id, "replaced").unwrap();
+        assert!(db::search_memos(&db, "tokenizer", None).unwrap().is_empty());
+    }
+}
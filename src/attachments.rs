@@ -0,0 +1,230 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::NamedTempFile;
+
+use crate::{
+    config::{self, MemoConfig},
+    db::{self, Db},
+    domain::memo::{MemoId, NewMemo},
+    format,
+};
+
+const PASTE_FILE_NAME: &str = "pasted.txt";
+const STUB_PREVIEW_CHARS: usize = 80;
+
+pub(crate) fn exceeds_max_size(content: &str, config: &MemoConfig) -> bool {
+    config.max_size_bytes > 0 && content.len() > config.max_size_bytes
+}
+
+/// An attachment written under a temporary name in its memo's folder. `keep`
+/// gives it its real name once the rows pointing at it are committed; dropped
+/// without that, it is removed again.
+pub(crate) struct StagedFile {
+    file: Option<NamedTempFile>,
+    path: PathBuf,
+}
+
+impl StagedFile {
+    pub(crate) fn keep(mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            file.persist(&self.path)
+                .with_context(|| format!("couldn't write {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        let Some(file) = self.file.take() else {
+            return;
+        };
+        drop(file);
+        // The memo's folder was made for this file; anything else in it stays.
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Stores `content` as a text attachment and adds a short stub memo pointing at it,
+/// so oversized pastes don't slow down list and search.
+/// The stub keeps `new_memo`'s tags, mood, and timestamp.
+pub(crate) fn add_memo_as_attachment(db: &Db, new_memo: &NewMemo) -> Result<MemoId> {
+    // Inside a caller's transaction the file would be kept before that commits.
+    debug_assert!(db.conn().is_autocommit(), "use stage_memo_as_attachment");
+    let (memo_id, staged) = db.atomically(|db| stage_memo_as_attachment(db, new_memo))?;
+    staged.keep()?;
+    Ok(memo_id)
+}
+
+/// [`add_memo_as_attachment`] for callers running their own transaction: the
+/// file is only kept once they commit and call [`StagedFile::keep`].
+pub(crate) fn stage_memo_as_attachment(
+    db: &Db,
+    new_memo: &NewMemo,
+) -> Result<(MemoId, StagedFile)> {
+    let content = new_memo.content.as_str();
+    let size_bytes = content.len() as u64;
+    let stub = NewMemo {
+        content: stub_content(content, size_bytes),
+        ..new_memo.clone()
+    };
+    stage_memo_with_file(db, &stub, PASTE_FILE_NAME, size_bytes, |file| {
+        file.write_all(content.as_bytes())
+    })
+}
+
+/// Copies a recording or picture into the attachments folder and adds a memo for
+/// it: `new_memo`'s text, if any, followed by a `[<kind>: name, size]` marker.
+pub(crate) fn add_file_memo(
+    db: &Db,
+    new_memo: &NewMemo,
+    source: &Path,
+    kind: &str,
+) -> Result<MemoId> {
+    let file_name = source
+        .file_name()
+        .with_context(|| format!("{} is not a file", source.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let size_bytes = fs::metadata(source)
+        .with_context(|| format!("couldn't read {}", source.display()))?
+        .len();
+    let marker = format!(
+        "[{}: {}, {}]",
+        kind,
+        file_name,
+        format::format_bytes(size_bytes)
+    );
+    let content = match new_memo.content.trim() {
+        "" => marker,
+        text => format!("{} {}", text, marker),
+    };
+    let new_memo = NewMemo {
+        content,
+        ..new_memo.clone()
+    };
+    let (memo_id, staged) = db.atomically(|db| {
+        stage_memo_with_file(db, &new_memo, &file_name, size_bytes, |file| {
+            io::copy(&mut File::open(source)?, file).map(drop)
+        })
+    })?;
+    staged.keep()?;
+    Ok(memo_id)
+}
+
+/// Adds `new_memo` with one attachment that `write` fills, staged until the
+/// caller commits.
+fn stage_memo_with_file(
+    db: &Db,
+    new_memo: &NewMemo,
+    file_name: &str,
+    size_bytes: u64,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> Result<(MemoId, StagedFile)> {
+    let memo_id = db::add_memo(db, new_memo)?;
+    let dir = config::attachments_dir()?.join(memo_id.as_str());
+    let staged = stage_file(&dir, file_name, write)?;
+    db::add_attachment(db, &memo_id, file_name, &staged.path, size_bytes)?;
+    Ok((memo_id, staged))
+}
+
+/// Writes `dir/file_name` with `write` under a temporary name; an earlier
+/// file by that name stays in place until [`StagedFile::keep`].
+pub(crate) fn stage_file(
+    dir: &Path,
+    file_name: &str,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> Result<StagedFile> {
+    fs::create_dir_all(dir)?;
+    let mut staged = StagedFile {
+        file: Some(NamedTempFile::new_in(dir)?),
+        path: dir.join(file_name),
+    };
+    if let Some(file) = staged.file.as_mut() {
+        write(file.as_file_mut())
+            .with_context(|| format!("couldn't write {}", staged.path.display()))?;
+    }
+    Ok(staged)
+}
+
+/// Runs a `[transcribe]` or `[ocr]` `command` through the shell with the
+/// attachment's path as `$1`, and returns its stdout. `tool` names the command
+/// in errors.
+pub(crate) fn run_command(tool: &str, command: &str, path: &Path) -> Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("sh")
+        .arg(path)
+        .output()
+        .with_context(|| format!("failed to run {} `{}`", tool, command))?;
+    if !output.status.success() {
+        bail!(
+            "{} `{}` failed: {}",
+            tool,
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn stub_content(content: &str, size_bytes: u64) -> String {
+    let first_line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let mut preview: String = first_line.chars().take(STUB_PREVIEW_CHARS).collect();
+    if first_line.chars().count() > STUB_PREVIEW_CHARS {
+        preview.push_str("...");
+    }
+    format!(
+        "{} [attachment: {}, {}]",
+        preview,
+        PASTE_FILE_NAME,
+        format::format_bytes(size_bytes)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn staged(dir: &Path, content: &str) -> StagedFile {
+        fs::create_dir_all(dir).unwrap();
+        let mut file = NamedTempFile::new_in(dir).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        StagedFile {
+            file: Some(file),
+            path: dir.join(PASTE_FILE_NAME),
+        }
+    }
+
+    #[test]
+    fn staged_files_appear_only_once_kept() {
+        let root = env::temp_dir().join(format!("cap-staged-{}", std::process::id()));
+        let kept = root.join("kept");
+        let file = staged(&kept, "long log");
+        assert!(!kept.join(PASTE_FILE_NAME).exists());
+        file.keep().unwrap();
+        assert_eq!(
+            fs::read_to_string(kept.join(PASTE_FILE_NAME)).unwrap(),
+            "long log"
+        );
+
+        // A batch that fails before its commit drops what it staged.
+        let dropped = root.join("dropped");
+        drop(staged(&dropped, "long log"));
+        assert!(!dropped.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
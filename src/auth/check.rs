@@ -0,0 +1,263 @@
+use anyhow::Result;
+use reqwest::{Response, StatusCode};
+use serde::de::IgnoredAny;
+use std::time::{Duration, Instant};
+
+use super::{current_account, fresh_access_token, supabase, supabase_anon_key, supabase_url};
+use crate::{
+    db::Db,
+    http::{self, HttpClient},
+};
+
+/// Table the sync backend stores memos in.
+const REMOTE_TABLE: &str = "memos";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+pub(crate) struct Check {
+    pub(crate) status: CheckStatus,
+    pub(crate) message: String,
+    /// What to change when the check didn't pass.
+    pub(crate) fix: Option<String>,
+}
+
+impl Check {
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Probes the configured backend step by step; later steps are skipped once
+/// one fails in a way that makes them meaningless.
+pub(crate) fn run(db: &Db) -> Result<Vec<Check>> {
+    http::block_on(run_checks(db))
+}
+
+async fn run_checks(db: &Db) -> Result<Vec<Check>> {
+    let url = supabase_url();
+    let url = url.trim_end_matches('/');
+    let anon_key = supabase_anon_key();
+    let mut checks = Vec::new();
+
+    if url == supabase::default_supabase_url() || anon_key == supabase::default_supabase_anon_key()
+    {
+        checks.push(Check::fail(
+            "backend is still the built-in placeholder",
+            "export SUPABASE_URL=https://<project>.supabase.co and SUPABASE_ANON_KEY=<anon key> \
+             (Project Settings > API in the Supabase dashboard)",
+        ));
+        return Ok(checks);
+    }
+    checks.push(Check::ok(format!("backend configured: {}", url)));
+
+    let client = HttpClient::with_timeout(REQUEST_TIMEOUT)?;
+    let started = Instant::now();
+    let request = client
+        .get(format!("{}/auth/v1/health", url))
+        .header("apikey", &anon_key);
+    match client.send(request).await {
+        Ok(response) if response.status().is_success() => checks.push(Check::ok(format!(
+            "reachable ({} ms)",
+            started.elapsed().as_millis()
+        ))),
+        Ok(response) => {
+            checks.push(Check::fail(
+                format!("auth service answered {}", response.status()),
+                "check that SUPABASE_URL is the project URL, without a path",
+            ));
+            return Ok(checks);
+        }
+        Err(err) => {
+            checks.push(Check::fail(
+                format!("unreachable: {:#}", err),
+                "check SUPABASE_URL and your network connection",
+            ));
+            return Ok(checks);
+        }
+    }
+
+    let request = client
+        .get(format!("{}/auth/v1/settings", url))
+        .header("apikey", &anon_key);
+    let settings = client.send(request).await?;
+    if matches!(
+        settings.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) {
+        checks.push(Check::fail(
+            "anon key rejected",
+            "copy the anon (public) key from Project Settings > API into SUPABASE_ANON_KEY",
+        ));
+        return Ok(checks);
+    }
+    checks.push(Check::ok("anon key accepted"));
+
+    let session = match current_account(db)? {
+        None => {
+            checks.push(Check::warn(
+                "not logged in",
+                "run cap login to check your session and table access",
+            ));
+            None
+        }
+        Some(account) => match fresh_access_token(db, false).await {
+            Ok(token) => {
+                let request = client
+                    .get(format!("{}/auth/v1/user", url))
+                    .header("apikey", &anon_key)
+                    .bearer_auth(&token);
+                let user = client.send(request).await?;
+                if user.status().is_success() {
+                    checks.push(Check::ok(format!("session valid for {}", account)));
+                    Some(token)
+                } else {
+                    checks.push(Check::fail(
+                        format!("session for {} rejected ({})", account, user.status()),
+                        "run cap login again",
+                    ));
+                    None
+                }
+            }
+            Err(err) => {
+                checks.push(Check::fail(
+                    format!("session for {} can't be refreshed: {:#}", account, err),
+                    "run cap login again",
+                ));
+                None
+            }
+        },
+    };
+
+    let table_url = format!("{}/rest/v1/{}?select=*&limit=1", url, REMOTE_TABLE);
+    let request = client
+        .get(&table_url)
+        .header("apikey", &anon_key)
+        .bearer_auth(&anon_key);
+    let anonymous = client.send(request).await?;
+    if table_missing(&anonymous) {
+        checks.push(Check::fail(
+            format!("table `{}` not found", REMOTE_TABLE),
+            format!(
+                "create a `{}` table in the public schema and reload the API schema cache",
+                REMOTE_TABLE
+            ),
+        ));
+        return Ok(checks);
+    }
+    let anonymous_rows = row_count(anonymous).await;
+
+    let mut own_rows = None;
+    if let Some(token) = session {
+        let request = client
+            .get(&table_url)
+            .header("apikey", &anon_key)
+            .bearer_auth(&token);
+        let own = client.send(request).await?;
+        if own.status().is_success() {
+            checks.push(Check::ok(format!("signed-in access to `{}`", REMOTE_TABLE)));
+            own_rows = Some(row_count(own).await);
+        } else {
+            checks.push(Check::fail(
+                format!("signed-in read of `{}` failed ({})", REMOTE_TABLE, own.status()),
+                format!(
+                    "add a SELECT policy on {} for authenticated users, e.g. USING (auth.uid() = user_id)",
+                    REMOTE_TABLE
+                ),
+            ));
+        }
+    }
+    checks.push(row_security(anonymous_rows, own_rows));
+    Ok(checks)
+}
+
+/// Rows in a successful PostgREST listing; refusals and odd bodies count as none.
+async fn row_count(response: Response) -> usize {
+    if !response.status().is_success() {
+        return 0;
+    }
+    response
+        .json::<Vec<IgnoredAny>>()
+        .await
+        .map(|rows| rows.len())
+        .unwrap_or(0)
+}
+
+/// Judges row-level security from what an anonymous and a signed-in request
+/// could read. Seeing nothing anonymously only proves something when the
+/// signed-in user does see rows; an empty table hides nothing either way.
+fn row_security(anonymous_rows: usize, own_rows: Option<usize>) -> Check {
+    if anonymous_rows > 0 {
+        return Check::fail(
+            format!("anonymous requests can read `{}`", REMOTE_TABLE),
+            format!(
+                "enable row-level security: ALTER TABLE {} ENABLE ROW LEVEL SECURITY, \
+                 with policies limited to auth.uid() = user_id",
+                REMOTE_TABLE
+            ),
+        );
+    }
+    match own_rows {
+        Some(rows) if rows > 0 => Check::ok(format!(
+            "table `{}` present and hidden from anonymous requests",
+            REMOTE_TABLE
+        )),
+        Some(_) => Check::warn(
+            format!(
+                "table `{}` is empty, so row-level security can't be checked",
+                REMOTE_TABLE
+            ),
+            "upload a memo with cap sync push, then run the check again",
+        ),
+        None => Check::warn(
+            format!(
+                "anonymous requests see no rows in `{}`, which is inconclusive without a session",
+                REMOTE_TABLE
+            ),
+            "run cap login so the check can compare with a signed-in read",
+        ),
+    }
+}
+
+/// PostgREST answers 404 for tables missing from its schema cache.
+fn table_missing(response: &Response) -> bool {
+    response.status() == StatusCode::NOT_FOUND
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_rows_need_a_signed_in_comparison() {
+        assert!(row_security(1, Some(1)).status == CheckStatus::Fail);
+        assert!(row_security(0, Some(3)).status == CheckStatus::Ok);
+        assert!(row_security(0, Some(0)).status == CheckStatus::Warn);
+        assert!(row_security(0, None).status == CheckStatus::Warn);
+    }
+}
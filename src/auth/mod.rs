@@ -1,25 +1,115 @@
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Local};
 use std::env;
 
-use crate::db::{Db, set_kv};
+use crate::{
+    db::{Db, delete_kv, get_kv, set_kv},
+    http,
+    i18n::t,
+};
 
+mod check;
+mod oauth;
 mod supabase;
 
+pub(crate) use check::{CheckStatus, run as check_backend};
+pub(crate) use oauth::OAuthLogin;
+
+const ACCESS_TOKEN_KEY: &str = "auth_access_token";
+const REFRESH_TOKEN_KEY: &str = "auth_refresh_token";
+const EXPIRES_AT_KEY: &str = "auth_expires_at";
+/// Address a one-time code was last sent to, until `cap login --verify` uses it.
+const OTP_EMAIL_KEY: &str = "auth_otp_email";
+/// Tokens this close to expiry are refreshed so callers get a usable one.
+const REFRESH_MARGIN_SECONDS: i64 = 60;
+
 pub(crate) fn login(db: &Db, email: &str, password: &str) -> Result<()> {
-    let supabase_url =
-        env::var("SUPABASE_URL").unwrap_or_else(|_| supabase::default_supabase_url().to_string());
-    let supabase_anon_key = env::var("SUPABASE_ANON_KEY")
-        .unwrap_or_else(|_| supabase::default_supabase_anon_key().to_string());
-
-    let login_response = supabase::login(email, password, &supabase_url, &supabase_anon_key)?;
-    set_kv(db, "auth_access_token", &login_response.access_token)?;
-    set_kv(db, "auth_refresh_token", &login_response.refresh_token)?;
-    set_kv(
-        db,
-        "auth_expires_in",
-        &login_response.expires_in.to_string(),
-    )?;
-    set_kv(db, "auth_user_id", &login_response.user.id)?;
-    println!("Logged in as {}", login_response.user.id);
+    let login_response = http::block_on(supabase_client()?.login(email, password))?;
+    store_session(db, &login_response)?;
+    set_kv(db, "auth_email", email)?;
+    println!(
+        "{}",
+        t!("logged-in", user = login_response.user.id.as_str())
+    );
+    Ok(())
+}
+
+/// First half of an email code sign-in; `verify_otp` finishes it.
+pub(crate) fn send_otp(db: &Db, email: &str) -> Result<()> {
+    http::block_on(supabase_client()?.send_otp(email))?;
+    set_kv(db, OTP_EMAIL_KEY, email)?;
+    println!("{}", t!("otp-sent", email = email));
+    Ok(())
+}
+
+pub(crate) fn verify_otp(db: &Db, code: &str) -> Result<()> {
+    let Some(email) = get_kv(db, OTP_EMAIL_KEY)? else {
+        bail!("no sign-in code pending; request one with cap login --otp <email>");
+    };
+    let session = http::block_on(supabase_client()?.verify_otp(&email, code.trim()))
+        .context("code rejected; it may have expired, request a new one with cap login --otp")?;
+    store_session(db, &session)?;
+    set_kv(db, "auth_email", &email)?;
+    delete_kv(db, OTP_EMAIL_KEY)?;
+    println!("{}", t!("logged-in", user = session.user.id.as_str()));
     Ok(())
 }
+
+/// The stored access token, refreshed first when it has expired (or is about
+/// to) or when `force_refresh` is set.
+pub(crate) fn access_token(db: &Db, force_refresh: bool) -> Result<String> {
+    http::block_on(fresh_access_token(db, force_refresh))
+}
+
+async fn fresh_access_token(db: &Db, force_refresh: bool) -> Result<String> {
+    let (Some(access_token), Some(refresh_token)) = (
+        get_kv(db, ACCESS_TOKEN_KEY)?,
+        get_kv(db, REFRESH_TOKEN_KEY)?,
+    ) else {
+        bail!("not logged in; run cap login first");
+    };
+    let expires_at =
+        get_kv(db, EXPIRES_AT_KEY)?.and_then(|value| DateTime::parse_from_rfc3339(&value).ok());
+    let fresh = expires_at.is_some_and(|expires_at| {
+        expires_at > Local::now() + Duration::seconds(REFRESH_MARGIN_SECONDS)
+    });
+    if fresh && !force_refresh {
+        return Ok(access_token);
+    }
+    let session = supabase_client()?
+        .refresh(&refresh_token)
+        .await
+        .context("session refresh failed; run cap login again")?;
+    store_session(db, &session)?;
+    Ok(session.access_token)
+}
+
+fn store_session(db: &Db, session: &supabase::LoginResponse) -> Result<()> {
+    let expires_at = Local::now() + Duration::seconds(session.expires_in);
+    set_kv(db, ACCESS_TOKEN_KEY, &session.access_token)?;
+    set_kv(db, REFRESH_TOKEN_KEY, &session.refresh_token)?;
+    set_kv(db, "auth_expires_in", &session.expires_in.to_string())?;
+    set_kv(db, EXPIRES_AT_KEY, &expires_at.to_rfc3339())?;
+    set_kv(db, "auth_user_id", &session.user.id)?;
+    Ok(())
+}
+
+fn supabase_client() -> Result<supabase::SupabaseClient> {
+    supabase::SupabaseClient::new(&supabase_url(), &supabase_anon_key())
+}
+
+pub(crate) fn supabase_url() -> String {
+    env::var("SUPABASE_URL").unwrap_or_else(|_| supabase::default_supabase_url().to_string())
+}
+
+pub(crate) fn supabase_anon_key() -> String {
+    env::var("SUPABASE_ANON_KEY")
+        .unwrap_or_else(|_| supabase::default_supabase_anon_key().to_string())
+}
+
+pub(crate) fn current_account(db: &Db) -> Result<Option<String>> {
+    if let Some(email) = get_kv(db, "auth_email")? {
+        return Ok(Some(email));
+    }
+    get_kv(db, "auth_user_id")
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{store_session, supabase_client};
+use crate::{db::Db, http};
+
+const CALLBACK_PATH: &str = "/callback";
+/// How long to wait for the browser to come back before giving up.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long one connection may take to send its request line, so a client
+/// that connects and stays silent can't hold up the sign-in.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const DONE_PAGE: &str = "<!doctype html><title>cap</title>\
+    <p>Signed in to cap. You can close this tab and return to the terminal.</p>";
+
+/// A browser sign-in in progress: Supabase's PKCE flow redirecting to a
+/// one-shot listener on the loopback interface.
+pub(crate) struct OAuthLogin {
+    listener: TcpListener,
+    verifier: String,
+    /// Page the user opens to sign in with the provider.
+    pub(crate) url: String,
+}
+
+impl OAuthLogin {
+    pub(crate) fn start(provider: &str) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .context("couldn't listen on localhost for the sign-in callback")?;
+        let redirect_to = format!(
+            "http://127.0.0.1:{}{}",
+            listener.local_addr()?.port(),
+            CALLBACK_PATH
+        );
+
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        let verifier = URL_SAFE_NO_PAD.encode(secret);
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let url = supabase_client()?.authorize_url(provider, &redirect_to, &challenge)?;
+        Ok(Self {
+            listener,
+            verifier,
+            url,
+        })
+    }
+
+    /// Waits for the provider to redirect back, then trades the code for a session.
+    pub(crate) fn finish(self, db: &Db) -> Result<String> {
+        let code = wait_for_code(&self.listener)?;
+        let session = http::block_on(supabase_client()?.exchange_pkce(&code, &self.verifier))?;
+        store_session(db, &session)?;
+        Ok(session.user.id)
+    }
+}
+
+fn wait_for_code(listener: &TcpListener) -> Result<String> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + CALLBACK_TIMEOUT;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // Browsers also ask for favicons and the like; only the callback counts.
+                if let Some(result) = handle_callback(stream)? {
+                    return result;
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                if Instant::now() > deadline {
+                    bail!("timed out waiting for the browser sign-in");
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn handle_callback(mut stream: TcpStream) -> Result<Option<Result<String>>> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    if BufReader::new(&stream)
+        .read_line(&mut request_line)
+        .is_err()
+    {
+        // Timed out or hung up; keep waiting for the real callback.
+        return Ok(None);
+    }
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let url = Url::parse(&format!("http://127.0.0.1{}", target))?;
+    if url.path() != CALLBACK_PATH {
+        write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+        )?;
+        return Ok(None);
+    }
+
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let result = match (query("code"), query("error_description").or(query("error"))) {
+        (Some(code), _) => Ok(code),
+        (None, Some(error)) => Err(anyhow::anyhow!("sign-in failed: {}", error)),
+        // Supabase puts implicit-flow tokens in the fragment, which never reaches us.
+        (None, None) => Err(anyhow::anyhow!(
+            "sign-in returned no code; is the PKCE flow enabled for this project?"
+        )),
+    };
+    let body = match &result {
+        Ok(_) => DONE_PAGE.to_string(),
+        Err(err) => format!(
+            "<!doctype html><title>cap</title><p>{}</p>",
+            escape_html(&err.to_string())
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    Ok(Some(result))
+}
+
+/// `value` made safe to show as HTML text; the provider's error text comes
+/// from the query string, which anyone can write.
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn callback(target: &str) -> (Option<Result<String>>, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n", target).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let result = handle_callback(stream).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        (result, response)
+    }
+
+    #[test]
+    fn provider_errors_are_shown_as_text() {
+        let (result, response) =
+            callback("/callback?error_description=%3Cscript%3Ealert(1)%3C%2Fscript%3E");
+        assert!(result.unwrap().is_err());
+        assert!(response.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!response.contains("<script>"));
+
+        let (result, _) = callback("/callback?code=abc");
+        assert_eq!(result.unwrap().unwrap(), "abc");
+        assert!(callback("/favicon.ico").0.is_none());
+    }
+}
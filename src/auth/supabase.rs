@@ -1,7 +1,9 @@
 use anyhow::Result;
-use reqwest::blocking::Client;
+use reqwest::{Response, Url};
 use serde::{Deserialize, Serialize};
 
+use crate::http::HttpClient;
+
 const DEFAULT_SUPABASE_URL: &str = "https://your-project.supabase.co";
 const DEFAULT_SUPABASE_ANON_KEY: &str = "your_anon_key";
 
@@ -13,26 +15,120 @@ pub(crate) fn default_supabase_anon_key() -> &'static str {
     DEFAULT_SUPABASE_ANON_KEY
 }
 
-pub(crate) fn login(
-    email: &str,
-    password: &str,
-    supabase_url: &str,
-    supabase_anon_key: &str,
-) -> Result<LoginResponse> {
-    let url = format!(
-        "{}/auth/v1/token?grant_type=password",
-        supabase_url.trim_end_matches('/')
-    );
+/// Supabase Auth endpoints of one project.
+pub(crate) struct SupabaseClient {
+    http: HttpClient,
+    base_url: String,
+    anon_key: String,
+}
+
+impl SupabaseClient {
+    pub(crate) fn new(base_url: &str, anon_key: &str) -> Result<Self> {
+        Ok(Self::with_http(HttpClient::new()?, base_url, anon_key))
+    }
+
+    pub(crate) fn with_http(http: HttpClient, base_url: &str, anon_key: &str) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            anon_key: anon_key.to_string(),
+        }
+    }
+
+    pub(crate) async fn login(&self, email: &str, password: &str) -> Result<LoginResponse> {
+        let response = self
+            .post(
+                "/auth/v1/token?grant_type=password",
+                &LoginRequest { email, password },
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// The page that starts a provider sign-in and redirects to `redirect_to` with a code.
+    pub(crate) fn authorize_url(
+        &self,
+        provider: &str,
+        redirect_to: &str,
+        code_challenge: &str,
+    ) -> Result<String> {
+        let url = Url::parse_with_params(
+            &format!("{}/auth/v1/authorize", self.base_url),
+            [
+                ("provider", provider),
+                ("redirect_to", redirect_to),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "s256"),
+            ],
+        )?;
+        Ok(url.into())
+    }
 
-    let client = Client::new();
-    let response = client
-        .post(url)
-        .header("apikey", supabase_anon_key)
-        .json(&LoginRequest { email, password })
-        .send()?
-        .error_for_status()?;
+    /// Emails a one-time code to an existing account.
+    pub(crate) async fn send_otp(&self, email: &str) -> Result<()> {
+        self.post(
+            "/auth/v1/otp",
+            &OtpRequest {
+                email,
+                create_user: false,
+            },
+        )
+        .await?;
+        Ok(())
+    }
 
-    Ok(response.json()?)
+    /// Completes an email sign-in with the code from [`Self::send_otp`].
+    pub(crate) async fn verify_otp(&self, email: &str, token: &str) -> Result<LoginResponse> {
+        let response = self
+            .post(
+                "/auth/v1/verify",
+                &VerifyRequest {
+                    kind: "email",
+                    email,
+                    token,
+                },
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Completes a PKCE sign-in with the code from the redirect.
+    pub(crate) async fn exchange_pkce(
+        &self,
+        auth_code: &str,
+        code_verifier: &str,
+    ) -> Result<LoginResponse> {
+        let response = self
+            .post(
+                "/auth/v1/token?grant_type=pkce",
+                &PkceRequest {
+                    auth_code,
+                    code_verifier,
+                },
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Trades a refresh token for a new session; Supabase rotates the refresh token too.
+    pub(crate) async fn refresh(&self, refresh_token: &str) -> Result<LoginResponse> {
+        let response = self
+            .post(
+                "/auth/v1/token?grant_type=refresh_token",
+                &RefreshRequest { refresh_token },
+            )
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    async fn post(&self, path: &str, body: &impl Serialize) -> Result<Response> {
+        let request = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .header("apikey", &self.anon_key)
+            .json(body);
+        Ok(self.http.send(request).await?.error_for_status()?)
+    }
 }
 
 #[derive(Deserialize)]
@@ -53,3 +149,196 @@ struct LoginRequest<'a> {
     email: &'a str,
     password: &'a str,
 }
+
+#[derive(Serialize)]
+struct OtpRequest<'a> {
+    email: &'a str,
+    create_user: bool,
+}
+
+#[derive(Serialize)]
+struct VerifyRequest<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    email: &'a str,
+    token: &'a str,
+}
+
+#[derive(Serialize)]
+struct PkceRequest<'a> {
+    auth_code: &'a str,
+    code_verifier: &'a str,
+}
+
+#[derive(Serialize)]
+struct RefreshRequest<'a> {
+    refresh_token: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_json, header, method, path, query_param},
+    };
+
+    use super::*;
+
+    const ANON_KEY: &str = "test-anon-key";
+
+    fn session(access_token: &str, refresh_token: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": access_token,
+            "refresh_token": refresh_token,
+            "expires_in": 3600,
+            "token_type": "bearer",
+            "user": { "id": "user-1", "email": "me@example.com" },
+        }))
+    }
+
+    fn client(server: &MockServer) -> SupabaseClient {
+        SupabaseClient::new(&format!("{}/", server.uri()), ANON_KEY).unwrap()
+    }
+
+    #[tokio::test]
+    async fn login_posts_credentials_and_returns_the_session() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .and(query_param("grant_type", "password"))
+            .and(header("apikey", ANON_KEY))
+            .and(body_json(
+                json!({ "email": "me@example.com", "password": "hunter2" }),
+            ))
+            .respond_with(session("access-1", "refresh-1"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session = client(&server)
+            .login("me@example.com", "hunter2")
+            .await
+            .unwrap();
+        assert_eq!(session.access_token, "access-1");
+        assert_eq!(session.refresh_token, "refresh-1");
+        assert_eq!(session.expires_in, 3600);
+        assert_eq!(session.user.id, "user-1");
+    }
+
+    #[tokio::test]
+    async fn rejected_login_is_an_error_and_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": "invalid_grant",
+                "error_description": "Invalid login credentials",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .login("me@example.com", "wrong")
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("400"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn refresh_returns_the_rotated_tokens() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .and(query_param("grant_type", "refresh_token"))
+            .and(body_json(json!({ "refresh_token": "refresh-1" })))
+            .respond_with(session("access-2", "refresh-2"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session = client(&server).refresh("refresh-1").await.unwrap();
+        assert_eq!(session.access_token, "access-2");
+        assert_eq!(session.refresh_token, "refresh-2");
+    }
+
+    #[tokio::test]
+    async fn refresh_is_not_retried_after_an_outage() {
+        // The server may have rotated the refresh token before failing, so a
+        // second try could spend a token that no longer works.
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert!(client(&server).refresh("refresh-1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn otp_codes_are_sent_and_verified_by_email() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/otp"))
+            .and(body_json(
+                json!({ "email": "me@example.com", "create_user": false }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/verify"))
+            .and(body_json(
+                json!({ "type": "email", "email": "me@example.com", "token": "123456" }),
+            ))
+            .respond_with(session("access-1", "refresh-1"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        client.send_otp("me@example.com").await.unwrap();
+        let session = client.verify_otp("me@example.com", "123456").await.unwrap();
+        assert_eq!(session.user.id, "user-1");
+    }
+
+    #[tokio::test]
+    async fn pkce_exchange_sends_code_and_verifier() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/v1/token"))
+            .and(query_param("grant_type", "pkce"))
+            .and(body_json(
+                json!({ "auth_code": "code-1", "code_verifier": "verifier-1" }),
+            ))
+            .respond_with(session("access-1", "refresh-1"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session = client(&server)
+            .exchange_pkce("code-1", "verifier-1")
+            .await
+            .unwrap();
+        assert_eq!(session.access_token, "access-1");
+    }
+
+    #[test]
+    fn authorize_url_carries_the_pkce_challenge() {
+        let client = SupabaseClient::new("https://example.supabase.co/", ANON_KEY).unwrap();
+        let url = client
+            .authorize_url("github", "http://127.0.0.1:5000/callback", "challenge")
+            .unwrap();
+        assert_eq!(
+            url,
+            "https://example.supabase.co/auth/v1/authorize?provider=github\
+             &redirect_to=http%3A%2F%2F127.0.0.1%3A5000%2Fcallback\
+             &code_challenge=challenge&code_challenge_method=s256"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Just enough of the database for `benches/` to drive; everything else stays
+//! crate-private.
+
+use anyhow::Result;
+
+use crate::{
+    db::{self, Db},
+    domain::memo::NewMemo,
+};
+
+pub struct BenchDb(Db);
+
+impl BenchDb {
+    pub fn open_in_memory() -> Result<Self> {
+        Ok(Self(Db::open_in_memory()?))
+    }
+
+    /// Adds every memo in one transaction, the way imports and merges write.
+    pub fn insert_memos<I>(&self, contents: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let tx = self.0.conn().unchecked_transaction()?;
+        for content in contents {
+            db::add_memo(&self.0, &NewMemo::new(content))?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Number of memos `cap list` would show with `limit`.
+    pub fn fetch_memos(&self, limit: Option<usize>) -> Result<usize> {
+        Ok(db::fetch_memos(&self.0, limit)?.len())
+    }
+
+    /// Number of matches `cap search` would show with `limit`.
+    pub fn search_memos(&self, query: &str, limit: Option<usize>) -> Result<usize> {
+        Ok(db::search_memos(&self.0, query, limit)?.len())
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::BTreeMap, error::Error, fmt};
+
+use crate::{
+    attachments,
+    config::Config,
+    db::{self, Db},
+    domain::{
+        memo::{MemoId, NewMemo},
+        meta, notebook,
+    },
+    ingest,
+};
+
+/// Metadata key recording which tool captured a memo.
+const SOURCE_META_KEY: &str = "source";
+
+/// A memo handed over by an editor plugin, script, or keybinding rather than
+/// typed at the prompt. This is the JSON that `cap capture` and
+/// `cap serve --listen` accept, so fields may be added but never renamed.
+#[derive(Deserialize, Default)]
+pub(crate) struct CaptureRequest {
+    pub(crate) content: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    pub(crate) notebook: Option<String>,
+    /// RFC 3339 time the memo was written; defaults to now.
+    pub(crate) created_at: Option<String>,
+    /// What captured the memo, e.g. `vim`; stored as the `source` field.
+    pub(crate) source: Option<String>,
+    #[serde(default)]
+    pub(crate) meta: BTreeMap<String, String>,
+}
+
+/// A capture refused for something the sender can fix. `code` goes into JSON
+/// error replies for plugins to match on, so codes may be added but never
+/// renamed: `empty_content`, `invalid_field` and `ignored`, plus `invalid_json`
+/// for requests that don't parse and `internal` for everything else.
+#[derive(Debug)]
+pub(crate) struct Rejected {
+    code: &'static str,
+    message: String,
+}
+
+impl Rejected {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for Rejected {}
+
+/// `{"error": "...", "code": "..."}` for a failed capture.
+pub(crate) fn error_json(err: &anyhow::Error) -> serde_json::Value {
+    let code = if err.downcast_ref::<serde_json::Error>().is_some() {
+        "invalid_json"
+    } else {
+        err.downcast_ref::<Rejected>()
+            .map_or("internal", |rejected| rejected.code)
+    };
+    json!({ "error": format!("{:#}", err), "code": code })
+}
+
+impl CaptureRequest {
+    pub(crate) fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Saves a captured memo the way `cap add` would, minus the prompts: the tidy
+/// rules apply and oversized content goes straight to an attachment.
+pub(crate) fn capture(db: &Db, config: &Config, request: CaptureRequest) -> Result<MemoId> {
+    let Some(new_memo) = prepare(config, request)? else {
+        return Err(Rejected::new("ignored", "memo matches an ignore rule").into());
+    };
+    save(db, config, &new_memo)
+}
+
+/// Turns a request into the memo to save, or `None` when an ignore rule drops it.
+pub(crate) fn prepare(config: &Config, request: CaptureRequest) -> Result<Option<NewMemo>> {
+    let invalid = |err: anyhow::Error| Rejected::new("invalid_field", format!("{:#}", err));
+    let notebook = request
+        .notebook
+        .as_deref()
+        .map(notebook::parse_notebook)
+        .transpose()
+        .map_err(invalid)?;
+    let created_at = request
+        .created_at
+        .as_deref()
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|value| value.with_timezone(&Local))
+                .map_err(|_| {
+                    Rejected::new(
+                        "invalid_field",
+                        format!("created_at `{}` is not an RFC 3339 time", value),
+                    )
+                })
+        })
+        .transpose()?;
+    let mut fields = Vec::new();
+    for (key, value) in request.meta {
+        fields.push((meta::parse_meta_key(&key).map_err(invalid)?, value));
+    }
+    if let Some(source) = request.source {
+        fields.push((SOURCE_META_KEY.to_string(), source));
+    }
+    let mut new_memo = NewMemo::new(request.content)
+        .with_tags(&request.tags)
+        .with_meta(&fields)
+        .with_notebook(config.notebook.resolve(notebook))
+        .with_created_at(created_at);
+    let keep = ingest::prepare_new_memo(config, &mut new_memo);
+    if new_memo.content.trim().is_empty() {
+        return Err(Rejected::new("empty_content", "memo content is empty").into());
+    }
+    Ok(keep.then_some(new_memo))
+}
+
+pub(crate) fn save(db: &Db, config: &Config, new_memo: &NewMemo) -> Result<MemoId> {
+    if attachments::exceeds_max_size(&new_memo.content, &config.memo) {
+        return attachments::add_memo_as_attachment(db, new_memo);
+    }
+    db::add_memo(db, new_memo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> CaptureRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn json_fields_map_onto_the_memo() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = capture(
+            &db,
+            &Config::default(),
+            parse(
+                r#"{"content": "from emacs", "tags": ["Inbox"],
+                    "created_at": "2024-03-05T09:30:00+01:00",
+                    "source": "emacs", "meta": {"buffer": "notes.org"}}"#,
+            ),
+        )
+        .unwrap();
+
+        let memo = db::fetch_memo_by_id_prefix(&db, memo_id.as_str()).unwrap();
+        assert_eq!(memo.content, "from emacs");
+        assert_eq!(memo.tags, ["inbox"]);
+        let created = DateTime::parse_from_rfc3339(&memo.created_at).unwrap();
+        assert_eq!(
+            created,
+            DateTime::parse_from_rfc3339("2024-03-05T08:30:00Z").unwrap()
+        );
+        assert_eq!(
+            db::fetch_meta(&db, &memo_id).unwrap(),
+            [
+                ("buffer".to_string(), "notes.org".to_string()),
+                ("source".to_string(), "emacs".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_payloads_are_rejected() {
+        let db = Db::open_in_memory().unwrap();
+        let config = Config::default();
+        let err = capture(
+            &db,
+            &config,
+            parse(r#"{"content": "x", "created_at": "monday"}"#),
+        );
+        assert!(format!("{:#}", err.unwrap_err()).contains("RFC 3339"));
+        let err = capture(
+            &db,
+            &config,
+            parse(r#"{"content": "x", "meta": {"a b": "c"}}"#),
+        );
+        assert!(err.is_err());
+        assert!(db::fetch_memos(&db, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors_carry_a_stable_code() {
+        let db = Db::open_in_memory().unwrap();
+        let config = Config::default();
+        let code = |json: &str| {
+            let result = serde_json::from_str::<CaptureRequest>(json)
+                .map_err(anyhow::Error::from)
+                .and_then(|request| capture(&db, &config, request));
+            error_json(&result.unwrap_err())["code"].clone()
+        };
+        assert_eq!(code(r#"{"tags": []}"#), "invalid_json");
+        assert_eq!(code(r#"{"content": "  "}"#), "empty_content");
+        assert_eq!(
+            code(r#"{"content": "x", "meta": {"a b": "c"}}"#),
+            "invalid_field"
+        );
+        assert_eq!(
+            code(r#"{"content": "x", "created_at": "monday"}"#),
+            "invalid_field"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result, bail};
+use clap::CommandFactory;
+use std::{collections::HashMap, ffi::OsString};
+
+use crate::cli::args::Cli;
+
+/// Expands a user alias in the subcommand position, like `git` aliases: with
+/// `todo = "add --tag todo"`, `cap todo buy milk` runs `cap add --tag todo buy milk`.
+/// Built-in subcommands always win, and aliases may refer to other aliases.
+/// Global flags such as `--plain` may come before the alias.
+pub(crate) fn expand(
+    mut args: Vec<OsString>,
+    aliases: &HashMap<String, String>,
+) -> Result<Vec<OsString>> {
+    if aliases.is_empty() {
+        return Ok(args);
+    }
+    let builtins = builtin_names();
+    let mut seen = Vec::new();
+    let position = args
+        .iter()
+        .skip(1)
+        .position(|arg| !arg.to_str().is_some_and(|arg| arg.starts_with('-')))
+        .map_or(args.len(), |idx| idx + 1);
+    while let Some(name) = args
+        .get(position)
+        .and_then(|arg| arg.to_str())
+        .map(str::to_string)
+    {
+        if builtins.contains(&name) {
+            break;
+        }
+        let Some(definition) = aliases.get(&name) else {
+            break;
+        };
+        if seen.contains(&name) {
+            bail!("alias `{}` expands to itself", name);
+        }
+        let words = shell_words::split(definition)
+            .with_context(|| format!("alias `{}` in the config can't be parsed", name))?;
+        if words.is_empty() {
+            bail!("alias `{}` in the config is empty", name);
+        }
+        args.splice(
+            position..position + 1,
+            words.into_iter().map(OsString::from),
+        );
+        seen.push(name);
+    }
+    Ok(args)
+}
+
+/// Moves a leading `--plain` behind the next word. Any flag ahead of a
+/// subcommand makes clap read it as memo text, so `cap --plain list` would
+/// otherwise save a memo saying "list".
+pub(crate) fn hoist_plain_flag(mut args: Vec<OsString>) -> Vec<OsString> {
+    if args.len() > 2 && args[1] == "--plain" {
+        let flag = args.remove(1);
+        args.insert(2, flag);
+    }
+    args
+}
+
+fn builtin_names() -> Vec<String> {
+    let command = Cli::command();
+    let mut names = vec!["help".to_string()];
+    for subcommand in command.get_subcommands() {
+        names.push(subcommand.get_name().to_string());
+        names.extend(subcommand.get_all_aliases().map(str::to_string));
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn args(words: &[&str]) -> Vec<OsString> {
+        words.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn leading_plain_flag_moves_behind_the_subcommand() {
+        let hoisted = hoist_plain_flag(args(&["cap", "--plain", "list", "--ids"]));
+        assert_eq!(hoisted, args(&["cap", "list", "--plain", "--ids"]));
+        assert_eq!(
+            hoist_plain_flag(args(&["cap", "--plain"])),
+            args(&["cap", "--plain"])
+        );
+        let cli = Cli::try_parse_from(hoisted).unwrap();
+        assert!(cli.plain && cli.content.is_empty() && cli.command.is_some());
+    }
+
+    #[test]
+    fn aliases_expand_in_the_subcommand_position() {
+        let aliases = HashMap::from([
+            ("todo".to_string(), "t --tag later".to_string()),
+            ("t".to_string(), "add --tag todo".to_string()),
+            ("list".to_string(), "stats".to_string()),
+        ]);
+        let expanded = expand(args(&["cap", "todo", "buy", "milk"]), &aliases).unwrap();
+        assert_eq!(
+            expanded,
+            args(&[
+                "cap", "add", "--tag", "todo", "--tag", "later", "buy", "milk"
+            ])
+        );
+        let builtin = expand(args(&["cap", "list"]), &aliases).unwrap();
+        assert_eq!(builtin, args(&["cap", "list"]));
+
+        let after_flag = expand(args(&["cap", "--plain", "t", "milk"]), &aliases).unwrap();
+        assert_eq!(
+            after_flag,
+            args(&["cap", "--plain", "add", "--tag", "todo", "milk"])
+        );
+    }
+}
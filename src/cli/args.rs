@@ -1,30 +1,713 @@
-use clap::{ArgAction, Parser, Subcommand};
+use chrono::{DateTime, Local, NaiveDate};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCandidates, ArgValueCompleter};
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use crate::{
+    cli::completion,
+    config::{ListColumn, ListOutput},
+    date_parse,
+    domain::{location, meta, mood, notebook},
+    import,
+};
 
 #[derive(Parser)]
 #[command(name = "cap")]
-#[command(about = "A tiny memo app", version)]
+#[command(
+    about = "A tiny memo app",
+    version,
+    disable_version_flag = true,
+    args_conflicts_with_subcommands = true
+)]
 pub(crate) struct Cli {
-    pub(crate) content: Option<String>,
+    /// Memo text; unquoted words are joined with spaces (`cap buy milk`)
+    pub(crate) content: Vec<String>,
 
     #[arg(short = 'v', long = "version", action = ArgAction::Version)]
     pub(crate) version: Option<bool>,
 
+    /// Screen-reader friendly output: no bars or truncation, and a line-based prompt
+    /// instead of the TUI
+    #[arg(long, global = true)]
+    pub(crate) plain: bool,
+
     #[command(subcommand)]
-    pub(crate) command: Option<Command>,
+    pub(crate) command: Option<CliCommand>,
+}
+
+/// `cap doctor` looks at the database before opening it would migrate it, so
+/// it stands apart from the commands that run with the database open.
+#[derive(Subcommand)]
+pub(crate) enum CliCommand {
+    #[command(flatten)]
+    App(Box<Command>),
+    /// Check whether the database was written by an older build and needs upgrading
+    Doctor {
+        /// Back up a database from before schema versioning, then upgrade it; cap
+        /// refuses to open one until this has run
+        #[arg(long)]
+        migrate_legacy: bool,
+    },
 }
 
 #[derive(Subcommand)]
 pub(crate) enum Command {
     Add {
-        content: String,
+        #[arg(required_unless_present_any = ["lines", "audio", "image"])]
+        content: Vec<String>,
+        /// Attach an audio recording, transcribed into the memo when [transcribe] is configured
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["lines", "private"])]
+        audio: Option<PathBuf>,
+        /// Attach a picture or screenshot; its text is made searchable when [ocr] is configured
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["lines", "private", "audio"]
+        )]
+        image: Option<PathBuf>,
+        /// Add each non-empty line of FILE (`-` for stdin) as its own memo
+        #[arg(long, value_name = "FILE", conflicts_with = "content")]
+        lines: Option<PathBuf>,
+        /// Split --lines input on this string instead of newlines
+        #[arg(long, value_name = "STR", requires = "lines")]
+        delimiter: Option<String>,
+        /// Encrypt the memo with your passphrase
+        #[arg(long)]
+        private: bool,
+        /// Attach a tag without writing it inline; repeatable
+        #[arg(
+            short = 't',
+            long = "tag",
+            value_name = "TAG",
+            conflicts_with = "private",
+            add = ArgValueCandidates::new(completion::tag_candidates)
+        )]
+        tags: Vec<String>,
+        /// Rate the moment 1-5 (or with a face emoji like 🙂)
+        #[arg(long, value_parser = mood::parse_mood, conflicts_with = "private")]
+        mood: Option<u8>,
+        /// Store a `key=value` field with the memo; repeatable
+        #[arg(
+            long = "meta",
+            value_name = "KEY=VALUE",
+            value_parser = meta::parse_meta_pair,
+            conflicts_with = "private"
+        )]
+        meta: Vec<(String, String)>,
+        /// File the memo under this notebook instead of the configured default
+        #[arg(
+            short = 'n',
+            long,
+            value_name = "NAME",
+            value_parser = notebook::parse_notebook,
+            add = ArgValueCandidates::new(completion::notebook_candidates)
+        )]
+        notebook: Option<String>,
+        /// Where you are: `LAT,LON`, or `here` to ask the configured locator command
+        #[arg(long, value_name = "LAT,LON", conflicts_with = "private")]
+        location: Option<String>,
+        /// Backdate the memo, e.g. `2024-03-01 14:30`, `yesterday 9am`, `jan 3`
+        #[arg(long, value_name = "DATETIME", value_parser = date_parse::parse_datetime_arg)]
+        at: Option<DateTime<Local>>,
     },
+    /// Sign in to the sync backend with a password, in the browser with --oauth, or
+    /// with an emailed code (--otp, then --verify)
     Login {
-        #[arg(long)]
-        email: String,
-        #[arg(long)]
-        password: String,
+        #[arg(
+            long,
+            required_unless_present_any = ["oauth", "otp", "verify"],
+            conflicts_with_all = ["oauth", "otp", "verify"]
+        )]
+        email: Option<String>,
+        /// Prompted for when omitted
+        #[arg(long, requires = "email")]
+        password: Option<String>,
+        /// Sign in with a provider in the browser; the project must allow
+        /// `http://127.0.0.1` redirect URLs
+        #[arg(long, value_enum, value_name = "PROVIDER", conflicts_with_all = ["otp", "verify"])]
+        oauth: Option<OAuthProvider>,
+        /// Email a one-time sign-in code to this address
+        #[arg(long, value_name = "EMAIL", conflicts_with = "verify")]
+        otp: Option<String>,
+        /// Finish an --otp sign-in with the emailed code
+        #[arg(long, value_name = "CODE")]
+        verify: Option<String>,
     },
     Version,
+    /// Choose the passphrase private memos are encrypted with, once before the first --private
+    Passphrase,
+    /// Work with the stored backend session
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommand,
+    },
     #[command(alias = "ls")]
-    List,
+    List {
+        /// Prompt for the passphrase and show private memos
+        #[arg(long)]
+        unlock: bool,
+        /// Prefix each memo with its short id
+        #[arg(long)]
+        ids: bool,
+        /// Mark memos with local changes that haven't been synced yet with `*`
+        #[arg(long)]
+        show_sync: bool,
+        /// Print one line per memo or an aligned table; defaults to `[list] output`
+        #[arg(long, value_enum)]
+        output: Option<ListOutput>,
+        /// Table columns, e.g. `id,created,tags,content`; defaults to `[list] columns`
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+        columns: Vec<ListColumn>,
+        /// Show times in this zone: an IANA name like `Asia/Shanghai`, `local`,
+        /// or `original` for the zone each memo was written in
+        #[arg(long, value_name = "TZ", default_value = "local")]
+        timezone: String,
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+    /// Find memos containing some text, best matches first; narrow with the list filters
+    Search {
+        /// Text to look for; words are joined with spaces and matched as one phrase
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Prefix each memo with its short id
+        #[arg(long)]
+        ids: bool,
+        /// Show times in this zone: an IANA name, `local`, or `original`
+        #[arg(long, value_name = "TZ", default_value = "local")]
+        timezone: String,
+        /// Print the SQL the search runs and why each memo matched
+        #[arg(long)]
+        explain: bool,
+        /// Browse the results in the TUI; only --tag and --notebook carry over
+        #[arg(
+            short = 'i',
+            long,
+            conflicts_with_all = [
+                "ids", "since", "until", "mood", "limit", "meta", "all_notebooks", "near",
+                "untagged", "has_attachment", "archived", "mention", "explain"
+            ]
+        )]
+        interactive: bool,
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+    /// Open the TUI, the same as running `cap` on its own
+    Tui {
+        /// Start with this search applied and history focused, e.g. `tag:work standup`;
+        /// `tag:NAME` and `notebook:NAME` narrow the history
+        #[arg(short = 'q', long, value_name = "QUERY")]
+        query: Option<String>,
+    },
+    /// Print one memo in full with its time, notebook, tags, mood, location, and fields
+    Show {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        /// Prompt for the passphrase if the memo is private
+        #[arg(long)]
+        unlock: bool,
+        /// Show the time in this zone: an IANA name, `local`, or `original`
+        #[arg(long, value_name = "TZ", default_value = "local")]
+        timezone: String,
+    },
+    /// Show, set, or remove a memo's `key=value` fields
+    Meta {
+        #[command(subcommand)]
+        command: MetaCommand,
+    },
+    /// Delete memos by id
+    Rm {
+        /// Memo ids or unique id prefixes
+        #[arg(
+            required_unless_present = "interactive",
+            add = ArgValueCompleter::new(completion::memo_id_candidates)
+        )]
+        ids: Vec<String>,
+        /// Pick the memos to delete from a list of recent ones
+        #[arg(short, long, conflicts_with = "ids")]
+        interactive: bool,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Copy the database safely, even while the TUI or a server has it open
+    Backup {
+        /// Where to write the copy; defaults to a dated file in the backups folder
+        path: Option<PathBuf>,
+    },
+    /// Purge expired tombstones, then VACUUM and ANALYZE the database
+    Compact {
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Archive memos the `[[retention]]` rules say are old enough; meant for cron
+    Maintain {
+        /// List the memos that would be archived without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Import memos from another cap database
+    Merge {
+        other_db: PathBuf,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Combine memos into the oldest one
+    MergeMemos {
+        /// Two or more memo ids or unique id prefixes
+        #[arg(
+            required = true,
+            num_args = 2..,
+            value_name = "ID",
+            add = ArgValueCompleter::new(completion::memo_id_candidates)
+        )]
+        ids: Vec<String>,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Print memos as JSON lines, deletions included, for backups and other tools
+    Export {
+        /// Only memos changed on or after this day
+        #[arg(long, value_name = "DATE", value_parser = date_parse::parse_date_arg)]
+        since: Option<NaiveDate>,
+        /// Only memos changed since the last --since-last export, which is then remembered
+        #[arg(long, conflicts_with = "since")]
+        since_last: bool,
+    },
+    /// Bring in notes exported from another app; notes already present are skipped
+    Import {
+        /// The app the export comes from
+        #[arg(long, value_enum, value_name = "APP")]
+        from: import::Source,
+        /// The export file; not used with `apple-notes`
+        path: Option<PathBuf>,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Break a memo into several by adding `---` lines in $EDITOR
+    Split {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+    },
+    /// Hard-delete synced tombstones older than the retention window
+    Purge {
+        /// Override the configured retention window, in days
+        #[arg(long)]
+        older_than_days: Option<u32>,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Show memo counts per week, streaks, top tags, and database size
+    Stats {
+        /// Show the mood trend instead
+        #[arg(long)]
+        mood: bool,
+        /// Print every figure, sync lag included, as JSON
+        #[arg(long, conflicts_with_all = ["mood", "prometheus"])]
+        json: bool,
+        /// Print the figures in the Prometheus text format, e.g. for a textfile collector
+        #[arg(long, conflicts_with = "mood")]
+        prometheus: bool,
+    },
+    /// Show current and longest streaks and today's progress toward the daily goal
+    Streak,
+    /// List everyone @mentioned in memos, most mentioned first
+    Mentions,
+    /// Start tracking time on an activity, stopping any running one
+    Start {
+        #[arg(required = true)]
+        label: Vec<String>,
+    },
+    /// Stop the running activity, with an optional note
+    Stop {
+        note: Vec<String>,
+    },
+    /// Sum tracked time per activity and per day
+    Timesheet {
+        /// This week, from Monday (the default)
+        #[arg(long, conflicts_with = "since")]
+        week: bool,
+        /// From this day on instead, e.g. `30d`, `jan 3`
+        #[arg(long, value_name = "DATE", value_parser = date_parse::parse_date_arg)]
+        since: Option<NaiveDate>,
+    },
+    /// Walk through last week's memos one at a time to tag, archive, or mark as todo;
+    /// an interrupted review resumes where it stopped
+    Review {
+        #[arg(value_enum)]
+        period: ReviewPeriod,
+    },
+    /// Show the most frequent keywords and word pairs
+    Topics {
+        /// Only memos from this day on, e.g. `30d`, `2w`, `jan 3`
+        #[arg(long, value_name = "DATE", value_parser = date_parse::parse_date_arg)]
+        since: Option<NaiveDate>,
+        /// How many keywords and pairs to show
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    Sync {
+        #[command(subcommand)]
+        command: SyncCommand,
+    },
+    /// Edit memos in $EDITOR; deleting a block deletes the memo
+    Edit {
+        /// Memo ids or unique id prefixes; defaults to the newest memo
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        ids: Vec<String>,
+        /// Edit every memo (narrow with --tag) in one file
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+        /// With --all, only memos carrying this tag
+        #[arg(
+            short = 't',
+            long,
+            value_name = "TAG",
+            requires = "all",
+            add = ArgValueCandidates::new(completion::tag_candidates)
+        )]
+        tag: Option<String>,
+        #[command(flatten)]
+        confirm: ConfirmArgs,
+    },
+    /// Print where cap keeps its data, database, config, and backups
+    Path {
+        /// Print only this path, for scripts
+        which: Option<DataPath>,
+    },
+    /// Open the data directory (or the folder holding another path) in the file manager
+    Open {
+        #[arg(default_value = "data")]
+        which: DataPath,
+    },
+    /// Print a shell completion script, e.g. `source <(cap completions bash)`
+    Completions {
+        #[arg(value_parser = ["bash", "elvish", "fish", "powershell", "zsh"])]
+        shell: String,
+    },
+    /// Print the man page, or write one per subcommand with --out-dir
+    Man {
+        /// Write `cap.1` and a `cap-<subcommand>.1` page for every subcommand here
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Add a memo from JSON on stdin and print its id as JSON, for editor plugins
+    ///
+    /// Input: {"content": "...", "tags": [...], "notebook": "...", "created_at": "RFC 3339",
+    /// "source": "vim", "meta": {"key": "value"}}; only content is required.
+    /// Output: {"id": "..."}, or {"error": "...", "code": "..."} and a failing exit status,
+    /// where code is invalid_json, empty_content, invalid_field, ignored or internal.
+    Capture {
+        /// Extra `key=value` field on top of the JSON's `meta`; repeatable
+        #[arg(
+            long = "meta",
+            value_name = "KEY=VALUE",
+            value_parser = meta::parse_meta_pair
+        )]
+        meta: Vec<(String, String)>,
+    },
+    /// Upload a memo to a gist or paste service and print the share URL
+    Publish {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        /// Where to upload; the URL is kept in the memo's `gist_url` or `paste_url` field
+        #[arg(long, value_enum, default_value_t = PublishService::Gist)]
+        service: PublishService,
+    },
+    /// Send a memo, end-to-end encrypted, to another cap account's inbox
+    Share {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        /// The recipient's account email; they must have opened `cap inbox` once
+        #[arg(long, value_name = "EMAIL")]
+        to: String,
+    },
+    /// List memos shared with this account
+    Inbox {
+        /// Add every shared memo as a memo of your own and clear the inbox
+        #[arg(long)]
+        accept: bool,
+    },
+    /// Turn a memo into a Taskwarrior task, Todoist task, or GitHub issue, and keep
+    /// the link in its `taskwarrior_uuid`, `todoist_url`, or `github_issue_url` field
+    Promote {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        /// Where to create the task; the memo's first line becomes its title
+        #[arg(long, value_enum)]
+        to: PromoteTarget,
+    },
+    /// Publish a memo as a post and keep its URL in the memo's `mastodon_url` field
+    Post {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        /// Where to post; set up in the `[post]` config section
+        #[arg(long, value_enum)]
+        to: PostTarget,
+    },
+    /// Turn a memo's audio recording into text with the [transcribe] backend
+    Transcribe {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+    },
+    /// Read the text in a memo's attached images so search finds it
+    Ocr {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+    },
+    /// Bring a memo back at a time, e.g. `in 30m`, `18:00`, `2024-06-01 09:00`
+    Remind {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        /// When to remind you; a time already past today means tomorrow
+        #[arg(required_unless_present = "clear", conflicts_with = "clear")]
+        when: Vec<String>,
+        /// Remove the memo's reminder instead
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Show reminders as they come due until stopped; see [remind] in the config
+    Remindd,
+    /// Save a readable copy of the page a memo links to, so it stays searchable offline
+    Fetch {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+    },
+    /// Serve memos to other tools over WebDAV or a unix socket
+    #[command(group(ArgGroup::new("mode").required(true).args(["webdav", "listen"])))]
+    Serve {
+        /// Read-only WebDAV share: one Markdown file per memo, a folder per month
+        #[arg(long)]
+        webdav: bool,
+        /// Address to listen on; use `0.0.0.0:PORT` to reach it from other machines, which
+        /// needs `[serve] webdav_token`
+        #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:8420")]
+        addr: String,
+        /// Take memos on a unix socket, one per line or as JSON (default ~/.capmind/cap.sock)
+        #[arg(long, value_name = "SOCKET", num_args = 0..=1)]
+        listen: Option<Option<PathBuf>>,
+    },
+}
+
+// Filters shared by `list` and `search`; a doc comment here would become
+// `cap list --help`'s description.
+#[derive(Args)]
+pub(crate) struct FilterArgs {
+    /// Only memos from this day on, e.g. `2024-03-01`, `last tuesday`, `jan 3`
+    #[arg(long, value_name = "DATE", value_parser = date_parse::parse_date_arg)]
+    pub(crate) since: Option<NaiveDate>,
+    /// Only memos up to and including this day
+    #[arg(long, value_name = "DATE", value_parser = date_parse::parse_date_arg)]
+    pub(crate) until: Option<NaiveDate>,
+    /// Only memos rated with this mood, e.g. `4` or `3-5`
+    #[arg(long, value_name = "MOOD", value_parser = mood::parse_mood_range)]
+    pub(crate) mood: Option<RangeInclusive<u8>>,
+    /// Only memos carrying this tag
+    #[arg(
+        short = 't',
+        long,
+        value_name = "TAG",
+        add = ArgValueCandidates::new(completion::tag_candidates)
+    )]
+    pub(crate) tag: Option<String>,
+    /// Only memos that @mention this name
+    #[arg(
+        long,
+        value_name = "NAME",
+        add = ArgValueCandidates::new(completion::mention_candidates)
+    )]
+    pub(crate) mention: Option<String>,
+    /// Show at most this many memos
+    #[arg(long, value_name = "N")]
+    pub(crate) limit: Option<usize>,
+    /// Only memos with this field, e.g. `project=alpha`; repeatable
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = meta::parse_meta_pair)]
+    pub(crate) meta: Vec<(String, String)>,
+    /// Only memos in this notebook; defaults to the configured notebook
+    #[arg(
+        short = 'n',
+        long,
+        value_name = "NAME",
+        value_parser = notebook::parse_notebook,
+        add = ArgValueCandidates::new(completion::notebook_candidates)
+    )]
+    pub(crate) notebook: Option<String>,
+    /// Only memos without any tag
+    #[arg(long, conflicts_with = "tag")]
+    pub(crate) untagged: bool,
+    /// Only memos with an attached file
+    #[arg(long)]
+    pub(crate) has_attachment: bool,
+    /// Include memos tagged `#archived`, which are left out otherwise
+    #[arg(long)]
+    pub(crate) archived: bool,
+    /// Ignore the default notebook and include memos from every notebook
+    #[arg(long, conflicts_with = "notebook")]
+    pub(crate) all_notebooks: bool,
+    /// Only memos captured near this point, e.g. `52.52,13.405`
+    #[arg(long, value_name = "LAT,LON", value_parser = location::parse_location)]
+    pub(crate) near: Option<location::Location>,
+    /// Distance from --near, e.g. `800m`, `5km`, `2mi`
+    #[arg(
+        long,
+        value_name = "DISTANCE",
+        default_value = "5km",
+        value_parser = location::parse_radius,
+        requires = "near"
+    )]
+    pub(crate) radius: f64,
+}
+
+/// Safety switches shared by commands that delete or overwrite memos.
+#[derive(Args)]
+pub(crate) struct ConfirmArgs {
+    /// Show what would change without changing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+    /// Don't ask for confirmation; needed when stdin isn't a terminal
+    #[arg(short = 'y', long, conflicts_with = "dry_run")]
+    pub(crate) yes: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum DataPath {
+    /// The data directory
+    Data,
+    /// The SQLite database file
+    Db,
+    /// The config file
+    Config,
+    /// The backups directory
+    Backups,
+    /// The attachments directory
+    Attachments,
+}
+
+#[derive(Subcommand)]
+pub(crate) enum MetaCommand {
+    /// List a memo's fields
+    Get {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+    },
+    /// Set a field, replacing any previous value
+    Set {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        #[arg(value_parser = meta::parse_meta_key)]
+        key: String,
+        value: String,
+    },
+    /// Remove a field
+    Unset {
+        /// Memo id or unique id prefix
+        #[arg(add = ArgValueCompleter::new(completion::memo_id_candidates))]
+        id: String,
+        #[arg(value_parser = meta::parse_meta_key)]
+        key: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum OAuthProvider {
+    Github,
+    Google,
+}
+
+impl OAuthProvider {
+    /// Provider name as Supabase spells it.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Google => "google",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum ReviewPeriod {
+    /// Monday to Sunday of the previous week
+    Week,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum PromoteTarget {
+    Taskwarrior,
+    Todoist,
+    GithubIssue,
+}
+
+impl PromoteTarget {
+    /// Metadata key the created task's id or URL is stored under.
+    pub(crate) fn meta_key(self) -> &'static str {
+        match self {
+            Self::Taskwarrior => "taskwarrior_uuid",
+            Self::Todoist => "todoist_url",
+            Self::GithubIssue => "github_issue_url",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum PublishService {
+    Gist,
+    Paste,
+}
+
+impl PublishService {
+    /// Metadata key the share URL is stored under.
+    pub(crate) fn meta_key(self) -> &'static str {
+        match self {
+            Self::Gist => "gist_url",
+            Self::Paste => "paste_url",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum PostTarget {
+    Mastodon,
+}
+
+impl PostTarget {
+    /// Metadata key the post URL is stored under.
+    pub(crate) fn meta_key(self) -> &'static str {
+        match self {
+            Self::Mastodon => "mastodon_url",
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub(crate) enum AuthCommand {
+    /// Check the backend URL, anon key, session, and memos table, with fixes for problems
+    Check,
+    /// Print a valid access token for scripts, e.g.
+    /// `curl -H "Authorization: Bearer $(cap auth token)"`
+    Token {
+        /// Refresh the session even if the current token is still valid
+        #[arg(long)]
+        refresh: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub(crate) enum SyncCommand {
+    /// Show last sync time, pending changes, and backend/account info
+    Status,
+    /// Upload pending changes and deletions to the backend
+    Push,
+    /// Download changes from the backend, merging memos edited on both sides
+    Pull,
 }
@@ -0,0 +1,261 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::domain::memo::Memo;
+
+const BLOCK_MARKER: &str = "--- memo ";
+
+const SPLIT_MARKER: &str = "---";
+
+const SPLIT_INSTRUCTIONS: &str = "\
+# Put a line containing only `---` wherever the memo should be split, then save.
+# The first part stays in this memo; each later part becomes a new memo.
+# Everything above the `--- memo` line is ignored.
+";
+
+/// Put before content lines that look like a block marker, so they stay content.
+const ESCAPE: char = '\\';
+
+const INSTRUCTIONS: &str = "\
+# Edit the memos below, then save and quit.
+# Each memo starts at its `--- memo <id>` line; keep those lines intact.
+# Delete a whole block, or empty its content, to delete that memo.
+# Everything above the first `--- memo` line is ignored.
+";
+
+/// Edits that cut at least this many bytes, and more than half of the memo,
+/// are confirmed like deletions.
+const LARGE_DELETION_BYTES: usize = 200;
+
+/// What the user changed in the editor, by memo id.
+pub(crate) struct BulkEdit {
+    pub(crate) updated: Vec<(String, String)>,
+    pub(crate) deleted: Vec<String>,
+}
+
+/// Whether replacing `old` with `new` throws away most of a sizeable memo.
+pub(crate) fn is_large_deletion(old: &str, new: &str) -> bool {
+    old.len().saturating_sub(new.len()) >= LARGE_DELETION_BYTES && new.len() * 2 < old.len()
+}
+
+/// Renders memos into one editable document, oldest first.
+pub(crate) fn render(memos: &[Memo]) -> String {
+    let mut document = String::from(INSTRUCTIONS);
+    for memo in memos.iter().rev() {
+        document.push('\n');
+        document.push_str(&format!(
+            "{}{}  ({})\n",
+            BLOCK_MARKER,
+            memo.memo_id.as_str(),
+            memo.created_at
+        ));
+        for line in memo.content.lines() {
+            if is_marker_like(line) {
+                document.push(ESCAPE);
+            }
+            document.push_str(line);
+            document.push('\n');
+        }
+    }
+    document
+}
+
+/// A block marker, or one already escaped any number of times.
+fn is_marker_like(line: &str) -> bool {
+    line.trim_start_matches(ESCAPE).starts_with(BLOCK_MARKER)
+}
+
+/// Diffs an edited document against the memos it was rendered from. Unknown or
+/// repeated ids are rejected so a typo can't silently drop a memo.
+pub(crate) fn parse(original: &[Memo], document: &str) -> Result<BulkEdit> {
+    let mut edited: HashMap<String, String> = HashMap::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    for line in document.lines() {
+        if let Some(rest) = line.strip_prefix(BLOCK_MARKER) {
+            if let Some((memo_id, lines)) = current.take() {
+                insert_block(&mut edited, memo_id, &lines)?;
+            }
+            let memo_id = rest.split_whitespace().next().unwrap_or_default();
+            current = Some((memo_id.to_string(), Vec::new()));
+        } else if let Some((_, lines)) = current.as_mut() {
+            match line.strip_prefix(ESCAPE) {
+                Some(unescaped) if is_marker_like(line) => lines.push(unescaped),
+                _ => lines.push(line),
+            }
+        }
+    }
+    if let Some((memo_id, lines)) = current.take() {
+        insert_block(&mut edited, memo_id, &lines)?;
+    }
+
+    for memo_id in edited.keys() {
+        if !original.iter().any(|memo| memo.memo_id.as_str() == memo_id) {
+            bail!(
+                "unknown memo id `{}` in edited file; nothing changed",
+                memo_id
+            );
+        }
+    }
+
+    let mut result = BulkEdit {
+        updated: Vec::new(),
+        deleted: Vec::new(),
+    };
+    for memo in original {
+        let memo_id = memo.memo_id.as_str();
+        match edited.get(memo_id) {
+            Some(content) if content.is_empty() => result.deleted.push(memo_id.to_string()),
+            Some(content) if content != memo.content.trim() => {
+                result.updated.push((memo_id.to_string(), content.clone()));
+            }
+            Some(_) => {}
+            None => result.deleted.push(memo_id.to_string()),
+        }
+    }
+    Ok(result)
+}
+
+fn insert_block(
+    edited: &mut HashMap<String, String>,
+    memo_id: String,
+    lines: &[&str],
+) -> Result<()> {
+    // Blank lines separating blocks aren't part of the memo.
+    let content = lines.join("\n").trim().to_string();
+    if edited.insert(memo_id.clone(), content).is_some() {
+        bail!(
+            "memo id `{}` appears twice in edited file; nothing changed",
+            memo_id
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn render_split(memo: &Memo) -> String {
+    format!(
+        "{}\n{}{}\n{}\n",
+        SPLIT_INSTRUCTIONS,
+        BLOCK_MARKER,
+        memo.memo_id.as_str(),
+        memo.content
+    )
+}
+
+/// Returns the non-empty parts of a split document, in order.
+pub(crate) fn parse_split(memo: &Memo, document: &str) -> Result<Vec<String>> {
+    let mut lines = document.lines();
+    let header = format!("{}{}", BLOCK_MARKER, memo.memo_id.as_str());
+    if !lines.any(|line| line.trim_end() == header) {
+        bail!("the `{}` line was removed; nothing changed", header);
+    }
+    let mut parts = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in lines {
+        if line.trim_end() == SPLIT_MARKER {
+            parts.push(current.join("\n"));
+            current.clear();
+        } else {
+            current.push(line);
+        }
+    }
+    parts.push(current.join("\n"));
+    Ok(parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memo(memo_id: &str, content: &str) -> Memo {
+        Memo {
+            memo_id: memo_id.to_string().into(),
+            content: content.to_string(),
+            created_at: "2024-05-01T09:00:00+00:00".to_string(),
+            updated_at: "2024-05-01T09:00:00+00:00".to_string(),
+            private: false,
+            tags: Vec::new(),
+            timezone: None,
+            notebook: None,
+            location: None,
+            mood: None,
+            dirty: false,
+        }
+    }
+
+    #[test]
+    fn an_untouched_document_changes_nothing() {
+        let memos = [memo("b2", "second\n\nwith a gap"), memo("a1", "first")];
+        let edit = parse(&memos, &render(&memos)).unwrap();
+        assert!(edit.updated.is_empty());
+        assert!(edit.deleted.is_empty());
+    }
+
+    #[test]
+    fn edited_and_removed_blocks_update_and_delete() {
+        let memos = [
+            memo("c3", "third"),
+            memo("b2", "second"),
+            memo("a1", "first"),
+        ];
+        let document = render(&memos)
+            .replace("second", "second, edited")
+            .replace("--- memo c3  (2024-05-01T09:00:00+00:00)\nthird\n", "")
+            .replace("\nfirst\n", "\n\n");
+        let edit = parse(&memos, &document).unwrap();
+        assert_eq!(
+            edit.updated,
+            [("b2".to_string(), "second, edited".to_string())]
+        );
+        assert_eq!(edit.deleted, ["c3", "a1"]);
+    }
+
+    #[test]
+    fn only_cutting_most_of_a_long_memo_is_a_large_deletion() {
+        let long = "a line of notes\n".repeat(20);
+        assert!(is_large_deletion(&long, "a line of notes"));
+        assert!(!is_large_deletion(&long, &long[..long.len() / 2 + 1]));
+        assert!(!is_large_deletion("short memo", ""));
+    }
+
+    #[test]
+    fn marker_lines_inside_content_survive_a_round_trip() {
+        let content = "notes\n--- memo a1\n\\--- memo b2 already escaped";
+        let memos = [memo("b2", content), memo("a1", "first")];
+        let document = render(&memos);
+        assert!(document.contains("\n\\--- memo a1\n"));
+
+        let edit = parse(&memos, &document.replace("notes", "more notes")).unwrap();
+        assert_eq!(
+            edit.updated,
+            [("b2".to_string(), format!("more {}", content))]
+        );
+        assert!(edit.deleted.is_empty());
+    }
+
+    #[test]
+    fn unknown_and_repeated_ids_are_rejected() {
+        let memos = [memo("a1", "first")];
+        assert!(parse(&memos, "--- memo zz\nother\n").is_err());
+        assert!(parse(&memos, "--- memo a1\none\n--- memo a1\ntwo\n").is_err());
+    }
+
+    #[test]
+    fn split_lines_cut_a_memo_into_its_non_empty_parts() {
+        let original = memo("a1", "one\ntwo");
+        let document =
+            render_split(&original).replace("one\ntwo", "one\n---\ntwo\n---  \n\n---\nthree");
+        assert_eq!(
+            parse_split(&original, &document).unwrap(),
+            ["one", "two", "three"]
+        );
+        assert_eq!(
+            parse_split(&original, &render_split(&original)).unwrap(),
+            ["one\ntwo"]
+        );
+        assert!(parse_split(&original, "one\n---\ntwo").is_err());
+    }
+}
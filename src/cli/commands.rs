@@ -1,45 +1,1766 @@
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
 use crossterm::terminal;
+use std::{
+    collections::HashSet,
+    env, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process,
+};
 
 use crate::{
     app::AppContext,
-    auth,
-    cli::args::{Cli, Command},
-    db,
-    domain::memo::NewMemo,
-    format, tui,
+    archive, attachments, auth, capture,
+    cli::{
+        args::{
+            AuthCommand, Command, ConfirmArgs, DataPath, FilterArgs, MetaCommand, OAuthProvider,
+            PostTarget, PromoteTarget, PublishService, SyncCommand,
+        },
+        bulk_edit, completion, editor, man, opener, prompt, review,
+    },
+    config::{self, ListColumn, ListOutput},
+    crypto, date_parse, db,
+    domain::{
+        location::Location,
+        memo::{self, Memo, NewMemo},
+        mentions, mood, tags, tidy,
+    },
+    export, format, http,
+    i18n::t,
+    import, ingest, locator, metrics, ocr, post, promote, publish, remind, retention, serve, stats,
+    sync, timesheet, topics, transcribe, tui, workspace,
 };
 
-pub(crate) fn dispatch(app: &AppContext, cli: Cli) -> Result<()> {
-    match cli.command {
-        Some(Command::List) => list_memos(app),
-        Some(Command::Login { email, password }) => auth::login(app.db(), &email, &password),
+pub(crate) fn dispatch(
+    app: &AppContext,
+    command: Option<Command>,
+    content: Vec<String>,
+) -> Result<()> {
+    match command {
+        Some(Command::List {
+            unlock,
+            ids,
+            show_sync,
+            output,
+            columns,
+            timezone,
+            filters,
+        }) => {
+            let filter = memo_filter(app, filters, None);
+            let layout = match output.unwrap_or(app.config().list.output) {
+                ListOutput::Lines => ListLayout::Lines {
+                    show_ids: ids,
+                    show_sync,
+                },
+                ListOutput::Table => {
+                    let mut columns = if columns.is_empty() {
+                        app.config().list.columns.clone()
+                    } else {
+                        columns
+                    };
+                    if show_sync && !columns.contains(&ListColumn::Sync) {
+                        columns.insert(0, ListColumn::Sync);
+                    }
+                    ListLayout::Table(columns)
+                }
+            };
+            list_memos(app, unlock, &layout, &timezone, &filter)
+        }
+        Some(Command::Search {
+            query,
+            interactive: true,
+            filters,
+            ..
+        }) => {
+            let start = tui::StartQuery {
+                text: query.join(" "),
+                tag: filters.tag.as_deref().map(tags::normalize_tag),
+                notebook: filters.notebook,
+            };
+            open_tui(app, Some(start))
+        }
+        Some(Command::Search {
+            query,
+            explain: true,
+            filters,
+            ..
+        }) => explain_search(app, &memo_filter(app, filters, Some(query.join(" ")))),
+        Some(Command::Search {
+            query,
+            ids,
+            timezone,
+            filters,
+            ..
+        }) => {
+            let filter = memo_filter(app, filters, Some(query.join(" ")));
+            let layout = ListLayout::Lines {
+                show_ids: ids,
+                show_sync: false,
+            };
+            list_memos(app, false, &layout, &timezone, &filter)
+        }
+        Some(Command::Edit {
+            ids,
+            all,
+            tag,
+            confirm,
+        }) => edit_memos(app, &ids, all, tag.as_deref(), &confirm),
+        Some(Command::Tui { query }) => open_tui(app, query.as_deref().map(tui::StartQuery::parse)),
+        Some(Command::Rm {
+            interactive: true,
+            confirm,
+            ..
+        }) => pick_memos_to_remove(app, &confirm),
+        Some(Command::Rm { ids, confirm, .. }) => remove_memos(app, &ids, &confirm),
+        Some(Command::Login {
+            oauth: Some(provider),
+            ..
+        }) => login_with_oauth(app, provider),
+        Some(Command::Login {
+            email: Some(email),
+            password,
+            ..
+        }) => {
+            let password = match password {
+                Some(password) => password,
+                None => rpassword::prompt_password("Password: ")?,
+            };
+            auth::login(app.db(), &email, &password)
+        }
+        Some(Command::Login {
+            otp: Some(email), ..
+        }) => auth::send_otp(app.db(), &email),
+        Some(Command::Login {
+            verify: Some(code), ..
+        }) => auth::verify_otp(app.db(), &code),
+        Some(Command::Login { .. }) => bail!("pass --email, --oauth, or --otp"),
+        Some(Command::Auth {
+            command: AuthCommand::Check,
+        }) => check_backend(app),
+        Some(Command::Auth {
+            command: AuthCommand::Token { refresh },
+        }) => {
+            println!("{}", auth::access_token(app.db(), refresh)?);
+            Ok(())
+        }
+        Some(Command::Passphrase) => set_passphrase(app),
         Some(Command::Version) => {
             println!("cap {}", env!("CARGO_PKG_VERSION"));
             Ok(())
         }
-        Some(Command::Add { content }) => add_memo(app, &content),
-        None if cli.content.is_some() => add_memo(app, cli.content.as_deref().unwrap_or_default()),
-        None => tui::run_tui(app.db()),
+        Some(Command::Backup { path }) => backup_database(app, path),
+        Some(Command::Compact { confirm }) => compact_database(app, &confirm),
+        Some(Command::Maintain { dry_run }) => run_maintenance(app, dry_run),
+        Some(Command::Merge { other_db, confirm }) => merge_database(app, &other_db, &confirm),
+        Some(Command::MergeMemos { ids, confirm }) => merge_memos(app, &ids, &confirm),
+        Some(Command::Export { since, since_last }) => {
+            let since = since.map(date_parse::start_of_day);
+            export::export(app.db(), since, since_last, &mut io::stdout().lock())?;
+            Ok(())
+        }
+        Some(Command::Import {
+            from,
+            path,
+            confirm,
+        }) => import_notes(app, from, path.as_deref(), &confirm),
+        Some(Command::Show {
+            id,
+            unlock,
+            timezone,
+        }) => show_memo(app, &id, unlock, &timezone),
+        Some(Command::Meta { command }) => run_meta_command(app, command),
+        Some(Command::Split { id }) => split_memo(app, &id),
+        Some(Command::Purge {
+            older_than_days,
+            confirm,
+        }) => purge_tombstones(app, older_than_days, &confirm),
+        Some(Command::Stats { json: true, .. }) => {
+            let metrics = metrics::collect(app.db(), app.config().streak.daily_goal, Local::now())?;
+            println!("{}", serde_json::to_string_pretty(&metrics)?);
+            Ok(())
+        }
+        Some(Command::Stats {
+            prometheus: true, ..
+        }) => {
+            let metrics = metrics::collect(app.db(), app.config().streak.daily_goal, Local::now())?;
+            print!("{}", metrics.to_prometheus());
+            Ok(())
+        }
+        Some(Command::Stats { mood: false, .. }) => print_stats(app),
+        Some(Command::Stats { mood: true, .. }) => print_mood_trend(app),
+        Some(Command::Streak) => print_streak(app),
+        Some(Command::Mentions) => print_mentions(app),
+        Some(Command::Start { label }) => start_timer(app, &label.join(" ")),
+        Some(Command::Stop { note }) => stop_timer(app, &note.join(" ")),
+        Some(Command::Timesheet { since, .. }) => print_timesheet(app, since),
+        Some(Command::Topics { since, limit }) => print_topics(app, since, limit),
+        Some(Command::Review { period }) => review::run(
+            app.db(),
+            period,
+            Local::now().date_naive(),
+            &mut io::stdin().lock(),
+            &mut io::stdout(),
+        ),
+        Some(Command::Sync {
+            command: SyncCommand::Status,
+        }) => sync::print_status(app.db()),
+        Some(Command::Sync {
+            command: SyncCommand::Push,
+        }) => sync::push(app.db()),
+        Some(Command::Sync {
+            command: SyncCommand::Pull,
+        }) => sync::pull(app.db()),
+        Some(Command::Path { which }) => print_paths(which),
+        Some(Command::Open { which }) => open_path(which),
+        Some(Command::Completions { shell }) => completion::print_registration(&shell),
+        Some(Command::Man { out_dir }) => man::run(out_dir.as_deref()),
+        Some(Command::Capture { meta }) => capture_from_stdin(app, meta),
+        Some(Command::Publish { id, service }) => publish_memo(app, &id, service),
+        Some(Command::Share { id, to }) => share_memo(app, &id, &to),
+        Some(Command::Inbox { accept }) => {
+            let key = crypto::unlock(app.db(), &crypto::read_passphrase()?)?;
+            if accept {
+                sync::accept_inbox(app.db(), &key)
+            } else {
+                sync::print_inbox(app.db(), &key)
+            }
+        }
+        Some(Command::Promote { id, to }) => promote_memo(app, &id, to),
+        Some(Command::Post { id, to }) => post_memo(app, &id, to),
+        Some(Command::Fetch { id }) => fetch_article(app, &id),
+        Some(Command::Transcribe { id }) => transcribe_memo(app, &id),
+        Some(Command::Ocr { id }) => ocr_memo(app, &id),
+        Some(Command::Remind { id, when, clear }) => remind_memo(app, &id, &when.join(" "), clear),
+        Some(Command::Remindd) => remind::run(app.db(), &app.config().remind),
+        Some(Command::Serve {
+            webdav: true, addr, ..
+        }) => serve::run_webdav(app.db(), app.config(), &addr),
+        Some(Command::Serve { listen, .. }) => listen_on_socket(app, listen.flatten()),
+        Some(Command::Add {
+            lines: Some(source),
+            delimiter,
+            private,
+            tags,
+            mood,
+            meta,
+            notebook,
+            location,
+            at,
+            ..
+        }) => {
+            let template = NewMemo::new(String::new())
+                .with_tags(&tags)
+                .with_mood(mood)
+                .with_meta(&meta)
+                .with_notebook(app.config().notebook.resolve(notebook))
+                .with_location(resolve_location(app, location.as_deref())?)
+                .with_created_at(at);
+            add_memos_from_lines(app, &source, delimiter.as_deref(), private, &template)
+        }
+        Some(Command::Add {
+            content,
+            image: Some(image),
+            tags,
+            mood,
+            meta,
+            notebook,
+            location,
+            at,
+            ..
+        }) => add_image_memo(
+            app,
+            &image,
+            NewMemo::new(content.join(" "))
+                .with_tags(&tags)
+                .with_mood(mood)
+                .with_meta(&meta)
+                .with_notebook(app.config().notebook.resolve(notebook))
+                .with_location(resolve_location(app, location.as_deref())?)
+                .with_created_at(at),
+        ),
+        Some(Command::Add {
+            content,
+            audio: Some(audio),
+            tags,
+            mood,
+            meta,
+            notebook,
+            location,
+            at,
+            ..
+        }) => add_audio_memo(
+            app,
+            &audio,
+            NewMemo::new(content.join(" "))
+                .with_tags(&tags)
+                .with_mood(mood)
+                .with_meta(&meta)
+                .with_notebook(app.config().notebook.resolve(notebook))
+                .with_location(resolve_location(app, location.as_deref())?)
+                .with_created_at(at),
+        ),
+        Some(Command::Add {
+            content,
+            private: true,
+            notebook,
+            at,
+            ..
+        }) => add_private_memo(
+            app,
+            &content.join(" "),
+            app.config().notebook.resolve(notebook),
+            at,
+        ),
+        Some(Command::Add {
+            content,
+            tags,
+            mood,
+            meta,
+            notebook,
+            location,
+            at,
+            ..
+        }) => add_memo(
+            app,
+            NewMemo::new(content.join(" "))
+                .with_tags(&tags)
+                .with_mood(mood)
+                .with_meta(&meta)
+                .with_notebook(app.config().notebook.resolve(notebook))
+                .with_location(resolve_location(app, location.as_deref())?)
+                .with_created_at(at),
+        ),
+        None if !content.is_empty() => add_memo(
+            app,
+            NewMemo::new(content.join(" ")).with_notebook(app.config().notebook.resolve(None)),
+        ),
+        None => open_tui(app, None),
     }
 }
 
-fn add_memo(app: &AppContext, content: &str) -> Result<()> {
-    let new_memo = NewMemo::new(content);
+fn open_tui(app: &AppContext, start: Option<tui::StartQuery>) -> Result<()> {
+    if app.config().ui.plain {
+        tui::run_plain(app, start)
+    } else {
+        tui::run_tui(app, start)
+    }
+}
+
+/// Columns available for a memo line; plain output never truncates.
+fn line_width(app: &AppContext) -> usize {
+    if app.config().ui.plain {
+        return usize::MAX;
+    }
+    terminal::size()
+        .map(|(width, _)| width as usize)
+        .unwrap_or(80)
+}
+
+/// Links only go to a terminal that is known to open them.
+fn use_hyperlinks(app: &AppContext) -> bool {
+    !app.config().ui.plain && io::stdout().is_terminal() && format::terminal_supports_hyperlinks()
+}
+
+/// Colors only go to a terminal, and never with `--plain` or `NO_COLOR` set.
+fn use_color(app: &AppContext) -> bool {
+    !app.config().ui.plain && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal()
+}
+
+/// A `#` bar for charts; plain output leaves it out since the count says the same.
+fn bar(app: &AppContext, len: usize) -> String {
+    if app.config().ui.plain {
+        return String::new();
+    }
+    "#".repeat(len)
+}
+
+fn add_memo(app: &AppContext, mut new_memo: NewMemo) -> Result<()> {
+    workspace::record(&app.config().workspace, &mut new_memo);
+    if !ingest::prepare_new_memo(app.config(), &mut new_memo) {
+        println!("{}", t!("memo-ignored"));
+        return Ok(());
+    }
+    let content = new_memo.content.as_str();
+    let memo_config = &app.config().memo;
+    if attachments::exceeds_max_size(content, memo_config) {
+        let question = t!(
+            "prompt-save-as-attachment",
+            size = format::format_bytes(content.len() as u64),
+            limit = format::format_bytes(memo_config.max_size_bytes as u64)
+        );
+        if !prompt::confirm(&question, true)? {
+            bail!("memo not saved; raise memo.max_size_bytes in the config to store it inline");
+        }
+        attachments::add_memo_as_attachment(app.db(), &new_memo)?;
+        return Ok(());
+    }
     db::add_memo(app.db(), &new_memo)?;
     Ok(())
 }
 
-fn list_memos(app: &AppContext) -> Result<()> {
-    let memos = db::fetch_memos(app.db(), None)?;
-    let terminal_width = terminal::size()
-        .map(|(width, _)| width as usize)
-        .unwrap_or(80);
+fn add_audio_memo(app: &AppContext, audio: &Path, mut new_memo: NewMemo) -> Result<()> {
+    workspace::record(&app.config().workspace, &mut new_memo);
+    if !ingest::prepare_new_memo(app.config(), &mut new_memo) {
+        println!("{}", t!("memo-ignored"));
+        return Ok(());
+    }
+    let memo_id = attachments::add_file_memo(app.db(), &new_memo, audio, "audio")?;
+    let file = audio.display().to_string();
+    if !transcribe::is_configured(&app.config().transcribe) {
+        println!("{}", t!("audio-saved-untranscribed", file = file));
+        return Ok(());
+    }
+    db::set_meta(app.db(), &memo_id, transcribe::STATUS_KEY, "pending")?;
+    // Transcription can take minutes, so it runs in a detached `cap transcribe`.
+    process::Command::new(env::current_exe()?)
+        .arg("transcribe")
+        .arg(memo_id.as_str())
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .spawn()
+        .context("couldn't start the background transcription")?;
+    println!("{}", t!("audio-transcribing", file = file));
+    Ok(())
+}
+
+fn transcribe_memo(app: &AppContext, id: &str) -> Result<()> {
+    let memo = db::fetch_memo_by_id_prefix(app.db(), id)?;
+    let transcript =
+        transcribe::transcribe_memo(app.db(), &app.config().transcribe, &memo.memo_id)?;
+    println!("{}", transcript);
+    Ok(())
+}
+
+fn add_image_memo(app: &AppContext, image: &Path, mut new_memo: NewMemo) -> Result<()> {
+    workspace::record(&app.config().workspace, &mut new_memo);
+    if !ingest::prepare_new_memo(app.config(), &mut new_memo) {
+        println!("{}", t!("memo-ignored"));
+        return Ok(());
+    }
+    let memo_id = attachments::add_file_memo(app.db(), &new_memo, image, "image")?;
+    if !ocr::is_configured(&app.config().ocr) {
+        return Ok(());
+    }
+    // The image is saved either way; unreadable text only costs searchability.
+    if let Err(err) = ocr::ocr_memo(app.db(), &app.config().ocr, &memo_id) {
+        eprintln!("{}", t!("ocr-failed", error = format!("{:#}", err)));
+    }
+    Ok(())
+}
+
+fn ocr_memo(app: &AppContext, id: &str) -> Result<()> {
+    let memo = db::fetch_memo_by_id_prefix(app.db(), id)?;
+    let text = ocr::ocr_memo(app.db(), &app.config().ocr, &memo.memo_id)?;
+    println!("{}", text);
+    Ok(())
+}
+
+fn remind_memo(app: &AppContext, id: &str, when: &str, clear: bool) -> Result<()> {
+    let memo = db::fetch_memo_by_id_prefix(app.db(), id)?;
+    if clear {
+        if remind::clear(app.db(), &memo.memo_id)? {
+            println!("{}", t!("remind-cleared"));
+        } else {
+            println!("{}", t!("remind-none"));
+        }
+        return Ok(());
+    }
+    let now = Local::now();
+    let Some(at) = date_parse::parse_reminder(when, now) else {
+        bail!(
+            "unknown time `{}` (try in 30m, 18:00 or 2024-06-01 09:00)",
+            when
+        );
+    };
+    if at <= now {
+        bail!("`{}` has already passed", when);
+    }
+    remind::set(app.db(), &memo.memo_id, at)?;
+    println!(
+        "{}",
+        t!(
+            "remind-set",
+            time = format::format_display_time(&at.to_rfc3339())
+        )
+    );
+    Ok(())
+}
+
+/// Prints `{"id": "..."}`, or `{"error": "...", "code": "..."}` and fails.
+fn capture_from_stdin(app: &AppContext, meta: Vec<(String, String)>) -> Result<()> {
+    let result = serde_json::from_reader::<_, capture::CaptureRequest>(io::stdin().lock())
+        .context(r#"stdin is not a capture request like {"content": "..."}"#)
+        .and_then(|mut request| {
+            request.meta.extend(meta);
+            capture::capture(app.db(), app.config(), request)
+        });
+    match result {
+        Ok(memo_id) => {
+            println!("{}", serde_json::json!({ "id": memo_id.as_str() }));
+            Ok(())
+        }
+        Err(err) => {
+            println!("{}", capture::error_json(&err));
+            Err(err)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn listen_on_socket(app: &AppContext, path: Option<PathBuf>) -> Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => config::socket_path()?,
+    };
+    serve::run_socket(app.db(), app.config(), &path)
+}
+
+#[cfg(not(unix))]
+fn listen_on_socket(_app: &AppContext, _path: Option<PathBuf>) -> Result<()> {
+    bail!("--listen needs unix domain sockets, which this platform lacks");
+}
+
+fn publish_memo(app: &AppContext, id: &str, service: PublishService) -> Result<()> {
+    let db = app.db();
+    let memo = db::fetch_memo_by_id_prefix(db, id)?;
+    if memo.private {
+        bail!("private memos can't be published");
+    }
+    let config = &app.config().publish;
+    let client = http::HttpClient::new()?;
+    let url = http::block_on(async {
+        match service {
+            PublishService::Gist => {
+                let file_name = format!("memo-{}.md", short_id(memo.memo_id.as_str()));
+                publish::gist(&client, config, &file_name, &memo.content).await
+            }
+            PublishService::Paste => publish::paste(&client, config, &memo.content).await,
+        }
+    })?;
+    db::set_meta(db, &memo.memo_id, service.meta_key(), &url)?;
+    println!("{}", url);
+    Ok(())
+}
+
+fn post_memo(app: &AppContext, id: &str, target: PostTarget) -> Result<()> {
+    let db = app.db();
+    let memo = db::fetch_memo_by_id_prefix(db, id)?;
+    if memo.private {
+        bail!("private memos can't be posted");
+    }
+    let config = &app.config().post;
+    let client = http::HttpClient::new()?;
+    let url = http::block_on(async {
+        match target {
+            PostTarget::Mastodon => {
+                post::mastodon(&client, config, memo.memo_id.as_str(), &memo.content).await
+            }
+        }
+    })?;
+    db::set_meta(db, &memo.memo_id, target.meta_key(), &url)?;
+    println!("{}", url);
+    Ok(())
+}
+
+fn promote_memo(app: &AppContext, id: &str, target: PromoteTarget) -> Result<()> {
+    let db = app.db();
+    let memo = db::fetch_memo_by_id_prefix(db, id)?;
+    if memo.private {
+        bail!("private memos can't be promoted");
+    }
+    let config = &app.config().promote;
+    let (title, body) = (memo.title(), memo.body());
+    let link = match target {
+        PromoteTarget::Taskwarrior => promote::taskwarrior(config, title)?,
+        PromoteTarget::Todoist => {
+            let client = http::HttpClient::new()?;
+            http::block_on(promote::todoist(&client, config, title, body))?
+        }
+        PromoteTarget::GithubIssue => {
+            let client = http::HttpClient::new()?;
+            http::block_on(promote::github_issue(
+                &client,
+                config,
+                &app.config().publish,
+                title,
+                body,
+            ))?
+        }
+    };
+    db::set_meta(db, &memo.memo_id, target.meta_key(), &link)?;
+    println!("{}", link);
+    Ok(())
+}
+
+fn fetch_article(app: &AppContext, id: &str) -> Result<()> {
+    let db = app.db();
+    let memo = db::fetch_memo_by_id_prefix(db, id)?;
+    // Archived text is indexed in plain text, which would leak a private memo's link.
+    if memo.private {
+        bail!("private memos can't be archived");
+    }
+    let Some(url) = archive::find_url(&memo.content) else {
+        bail!(
+            "memo {} has no link to fetch",
+            short_id(memo.memo_id.as_str())
+        );
+    };
+    let client = http::HttpClient::new()?;
+    let markdown = http::block_on(archive::download(&client, url))?;
+    archive::store(db, &memo.memo_id, &markdown)?;
+    println!(
+        "{}",
+        t!(
+            "article-archived",
+            url = url,
+            size = format::format_bytes(markdown.len() as u64)
+        )
+    );
+    Ok(())
+}
+
+pub(crate) fn doctor(migrate_legacy: bool) -> Result<()> {
+    let path = config::db_path()?;
+    let shown = path.display().to_string();
+    if !path.exists() {
+        println!("{}", t!("doctor-no-database", path = shown));
+        return Ok(());
+    }
+    let pending = db::pending_migrations(&path)?;
+    if pending.is_empty() {
+        println!("{}", t!("doctor-up-to-date", path = shown));
+        return Ok(());
+    }
+    let changes = pending.join(", ");
+    if !db::is_legacy(&path)? {
+        // Versioned databases are upgraded, with a backup, whenever cap opens them.
+        println!(
+            "{}",
+            t!("doctor-outdated-schema", path = shown, changes = changes)
+        );
+        return Ok(());
+    }
+    if !migrate_legacy {
+        println!(
+            "{}",
+            t!("doctor-legacy-schema", path = shown, changes = changes)
+        );
+        return Ok(());
+    }
+    let (_, backup) = db::Db::migrate_legacy(path)?;
+    let backup = backup.map(|backup| backup.display().to_string());
+    println!(
+        "{}",
+        t!(
+            "doctor-migrated",
+            changes = changes,
+            backup = backup.unwrap_or_default()
+        )
+    );
+    Ok(())
+}
+
+fn check_backend(app: &AppContext) -> Result<()> {
+    let checks = auth::check_backend(app.db())?;
+    for check in &checks {
+        let mark = match check.status {
+            auth::CheckStatus::Ok => "ok  ",
+            auth::CheckStatus::Warn => "warn",
+            auth::CheckStatus::Fail => "FAIL",
+        };
+        println!("[{}] {}", mark, check.message);
+        if let Some(fix) = &check.fix {
+            println!("       fix: {}", fix);
+        }
+    }
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == auth::CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        bail!("{} backend checks failed", failed);
+    }
+    Ok(())
+}
+
+fn login_with_oauth(app: &AppContext, provider: OAuthProvider) -> Result<()> {
+    let login = auth::OAuthLogin::start(provider.as_str())?;
+    println!("{}", t!("oauth-open-browser", provider = provider.as_str()));
+    println!();
+    println!("    {}", login.url);
+    println!();
+    // The URL is printed either way, for terminals without a local browser.
+    let _ = opener::open_url(&login.url);
+    println!("{}", t!("oauth-waiting"));
+    let user_id = login.finish(app.db())?;
+    println!("{}", t!("logged-in", user = user_id));
+    Ok(())
+}
+
+fn resolve_location(app: &AppContext, value: Option<&str>) -> Result<Option<Location>> {
+    value
+        .map(|value| locator::resolve(value, &app.config().location))
+        .transpose()
+}
+
+fn set_passphrase(app: &AppContext) -> Result<()> {
+    if crypto::is_set_up(app.db())? {
+        bail!("a passphrase is already set");
+    }
+    crypto::set_up(app.db(), &crypto::read_new_passphrase()?)?;
+    println!("{}", t!("passphrase-set"));
+    Ok(())
+}
+
+fn add_private_memo(
+    app: &AppContext,
+    content: &str,
+    notebook: Option<String>,
+    at: Option<DateTime<Local>>,
+) -> Result<()> {
+    let key = crypto::unlock(app.db(), &crypto::read_passphrase()?)?;
+    let content = memo::normalize_content(&tidy::tidy(content, &app.config().tidy));
+    let new_memo = NewMemo::encrypted(crypto::encrypt(&key, &content)?)
+        .with_notebook(notebook)
+        .with_created_at(at);
+    db::add_memo(app.db(), &new_memo)?;
+    Ok(())
+}
+
+fn add_memos_from_lines(
+    app: &AppContext,
+    source: &Path,
+    delimiter: Option<&str>,
+    private: bool,
+    template: &NewMemo,
+) -> Result<()> {
+    let input = if source == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(source)
+            .with_context(|| format!("failed to read {}", source.display()))?
+    };
+    let entries: Vec<&str> = match delimiter {
+        Some(delimiter) => input.split(delimiter).collect(),
+        None => input.lines().collect(),
+    };
+    let entries: Vec<&str> = entries
+        .into_iter()
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if entries.is_empty() {
+        bail!("no memos found in {}", source.display());
+    }
+    let key = if private {
+        Some(crypto::unlock(app.db(), &crypto::read_passphrase()?)?)
+    } else {
+        None
+    };
+
+    // Batches are non-interactive, so oversized entries go straight to attachments.
+    let memo_config = &app.config().memo;
+    let mut staged = Vec::new();
+    let mut ignored = 0;
+    let tx = app.db().conn().unchecked_transaction()?;
+    for entry in &entries {
+        if let Some(key) = &key {
+            let entry = &memo::normalize_content(&tidy::tidy(entry, &app.config().tidy));
+            let new_memo = NewMemo::encrypted(crypto::encrypt(key, entry)?)
+                .with_notebook(template.notebook.clone())
+                .with_created_at(template.created_at);
+            db::add_memo(app.db(), &new_memo)?;
+            continue;
+        }
+        let mut new_memo = NewMemo {
+            content: entry.to_string(),
+            ..template.clone()
+        };
+        if !ingest::prepare_new_memo(app.config(), &mut new_memo) {
+            ignored += 1;
+            continue;
+        }
+        if attachments::exceeds_max_size(&new_memo.content, memo_config) {
+            staged.push(attachments::stage_memo_as_attachment(app.db(), &new_memo)?.1);
+        } else {
+            db::add_memo(app.db(), &new_memo)?;
+        }
+    }
+    tx.commit()?;
+    let as_attachments = staged.len();
+    for file in staged {
+        file.keep()?;
+    }
+
+    let added = entries.len() - ignored;
+    if as_attachments > 0 {
+        println!(
+            "{}",
+            t!(
+                "memos-added-with-attachments",
+                count = added,
+                attachments = as_attachments
+            )
+        );
+    } else {
+        println!("{}", t!("memos-added", count = added));
+    }
+    if ignored > 0 {
+        println!("{}", t!("memos-ignored", count = ignored));
+    }
+    Ok(())
+}
+
+/// The query `list` and `search` run: the shared filters, plus search text.
+fn memo_filter(app: &AppContext, filters: FilterArgs, text: Option<String>) -> db::MemoFilter {
+    let notebook = if filters.all_notebooks {
+        None
+    } else {
+        app.config().notebook.resolve(filters.notebook)
+    };
+    let unfiled = notebook
+        .as_deref()
+        .is_some_and(|name| app.config().notebook.holds_unfiled(name));
+    let tag = filters.tag.as_deref().map(tags::normalize_tag);
+    // Asking for the archived tag itself brings archived memos back.
+    let hide_archived = !filters.archived && tag.as_deref() != Some(tags::ARCHIVED_TAG);
+    db::MemoFilter {
+        text,
+        since: filters.since.map(date_parse::start_of_day),
+        until: filters
+            .until
+            .and_then(|date| date.succ_opt())
+            .map(date_parse::start_of_day),
+        tag,
+        mention: filters.mention.as_deref().map(mentions::normalize_mention),
+        mood: filters.mood,
+        meta: filters.meta,
+        notebook,
+        unfiled,
+        near: filters.near.map(|center| (center, filters.radius)),
+        untagged: filters.untagged,
+        has_attachment: filters.has_attachment,
+        hide_archived,
+        limit: filters.limit,
+    }
+}
+
+enum ListLayout {
+    Lines { show_ids: bool, show_sync: bool },
+    Table(Vec<ListColumn>),
+}
+
+fn list_memos(
+    app: &AppContext,
+    unlock: bool,
+    layout: &ListLayout,
+    timezone: &str,
+    filter: &db::MemoFilter,
+) -> Result<()> {
+    let zone = format::DisplayZone::parse(timezone)?;
+    let mut memos = db::fetch_filtered_memos(app.db(), filter)?;
+    let key = if unlock {
+        Some(crypto::unlock(app.db(), &crypto::read_passphrase()?)?)
+    } else {
+        None
+    };
+    crypto::reveal_memos(&mut memos, key.as_ref());
+    let terminal_width = line_width(app);
+    let style = app.config().list.line_style();
+    let links = use_hyperlinks(app);
+    let (show_ids, show_sync) = match layout {
+        ListLayout::Lines {
+            show_ids,
+            show_sync,
+        } => (*show_ids, *show_sync),
+        ListLayout::Table(columns) => {
+            let headers: Vec<&str> = columns.iter().map(|column| column.header()).collect();
+            let rows: Vec<Vec<String>> = memos
+                .iter()
+                .map(|memo| {
+                    columns
+                        .iter()
+                        .map(|column| list_cell(memo, *column, zone))
+                        .collect()
+                })
+                .collect();
+            let lines = format::format_table(&headers, &rows, terminal_width, &style);
+            let (header, lines) = lines.split_first().context("table without a header")?;
+            println!("{}", header);
+            let show_ids = columns.contains(&ListColumn::Id);
+            for (line, memo) in lines.iter().zip(&memos) {
+                if links {
+                    let id = memo.memo_id.as_str();
+                    let id = show_ids.then(|| (short_id(id), id));
+                    println!("{}", format::link_memo_line(line, id, &memo.content));
+                } else {
+                    println!("{}", line);
+                }
+            }
+            return Ok(());
+        }
+    };
     for memo in memos {
-        let display_time = format::format_display_time(&memo.created_at);
-        let line = format::format_memo_line(&display_time, &memo.content, terminal_width);
+        let display_time =
+            format::format_display_time_in(&memo.created_at, memo.timezone.as_deref(), zone);
+        let mut display_time = if show_ids {
+            format!("{}  {}", short_id(memo.memo_id.as_str()), display_time)
+        } else {
+            display_time
+        };
+        if show_sync {
+            display_time = format!("{} {}", memo.sync_marker(), display_time);
+        }
+        let mut line =
+            format::format_memo_line(&display_time, memo.title(), terminal_width, &style);
+        if links {
+            let id = memo.memo_id.as_str();
+            let id = show_ids.then(|| (short_id(id), id));
+            line = format::link_memo_line(&line, id, &memo.content);
+        }
         println!("{}", line);
     }
 
     Ok(())
 }
+
+fn share_memo(app: &AppContext, id: &str, email: &str) -> Result<()> {
+    let memo = db::fetch_memo_by_id_prefix(app.db(), id)?;
+    let key = crypto::unlock(app.db(), &crypto::read_passphrase()?)?;
+    sync::share(app.db(), &key, &memo, email)
+}
+
+fn explain_search(app: &AppContext, filter: &db::MemoFilter) -> Result<()> {
+    let explanation = db::explain_filtered_memos(app.db(), filter)?;
+    match explanation.fts_phrase {
+        Some(phrase) => println!("{}", t!("explain-fts", phrase = phrase)),
+        None => println!("{}", t!("explain-scan")),
+    }
+    println!();
+    for line in explanation.sql.lines().map(str::trim) {
+        if !line.is_empty() {
+            println!("  {}", line);
+        }
+    }
+    for (index, value) in explanation.params.iter().enumerate() {
+        println!("  ?{} = {}", index + 1, value);
+    }
+    println!();
+    if explanation.matches.is_empty() {
+        println!("{}", t!("explain-no-matches"));
+        return Ok(());
+    }
+    println!("{}", t!("explain-ranking"));
+    let (mut memos, reasons): (Vec<_>, Vec<_>) = explanation.matches.into_iter().unzip();
+    crypto::reveal_memos(&mut memos, None);
+    let terminal_width = line_width(app);
+    let style = app.config().list.line_style();
+    for (memo, reasons) in memos.iter().zip(reasons) {
+        let prefix = format!(
+            "{}  {}",
+            short_id(memo.memo_id.as_str()),
+            format::format_display_time(&memo.created_at)
+        );
+        println!(
+            "{}",
+            format::format_memo_line(&prefix, memo.title(), terminal_width, &style)
+        );
+        if let Some(reasons) = reasons {
+            println!("    {}", describe_match(&reasons));
+        }
+    }
+    Ok(())
+}
+
+fn describe_match(reasons: &db::MatchReasons) -> String {
+    let mut found = Vec::new();
+    match reasons.content_score {
+        Some(score) if reasons.content => found.push(t!(
+            "explain-content-ranked",
+            score = format!("{:.2}", score)
+        )),
+        _ if reasons.content => found.push(t!("explain-content")),
+        _ => {}
+    }
+    match reasons.archive_score {
+        Some(score) if reasons.archive => found.push(t!(
+            "explain-archive-ranked",
+            score = format!("{:.2}", score)
+        )),
+        _ if reasons.archive => found.push(t!("explain-archive")),
+        _ => {}
+    }
+    if reasons.image_text {
+        found.push(t!("explain-image"));
+    }
+    if reasons.timestamp {
+        found.push(t!("explain-time"));
+    }
+    t!("explain-matched", reasons = found.join(", "))
+}
+
+fn list_cell(memo: &Memo, column: ListColumn, zone: format::DisplayZone) -> String {
+    match column {
+        ListColumn::Id => short_id(memo.memo_id.as_str()).to_string(),
+        ListColumn::Created => {
+            format::format_display_time_in(&memo.created_at, memo.timezone.as_deref(), zone)
+        }
+        ListColumn::Updated => {
+            format::format_display_time_in(&memo.updated_at, memo.timezone.as_deref(), zone)
+        }
+        ListColumn::Notebook => memo.notebook.clone().unwrap_or_default(),
+        ListColumn::Tags => memo
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>()
+            .join(" "),
+        ListColumn::Mood => memo.mood.map(|mood| mood.to_string()).unwrap_or_default(),
+        ListColumn::Sync => memo.sync_marker().to_string(),
+        ListColumn::Content => memo.title().to_string(),
+    }
+}
+
+fn show_memo(app: &AppContext, id: &str, unlock: bool, timezone: &str) -> Result<()> {
+    let db = app.db();
+    let zone = format::DisplayZone::parse(timezone)?;
+    let mut memo = db::fetch_memo_by_id_prefix(db, id)?;
+    if memo.private {
+        let key = if unlock {
+            Some(crypto::unlock(db, &crypto::read_passphrase()?)?)
+        } else {
+            None
+        };
+        crypto::reveal_memos(std::slice::from_mut(&mut memo), key.as_ref());
+    }
+
+    println!("{}", t!("show-id", value = memo.memo_id.as_str()));
+    println!(
+        "{}",
+        t!(
+            "show-created",
+            value =
+                format::format_display_time_in(&memo.created_at, memo.timezone.as_deref(), zone)
+        )
+    );
+    if let Some(notebook) = &memo.notebook {
+        println!("{}", t!("show-notebook", value = notebook.as_str()));
+    }
+    if !memo.tags.is_empty() {
+        let tags: Vec<String> = memo.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        println!("{}", t!("show-tags", value = tags.join(" ")));
+    }
+    if let Some(mood) = memo.mood {
+        println!("{}", t!("show-mood", mood = mood, max = mood::MAX_MOOD));
+    }
+    if let Some(location) = memo.location {
+        println!("{}", t!("show-location", value = location.to_string()));
+    }
+    for (key, value) in db::fetch_meta(db, &memo.memo_id)? {
+        println!("{}", t!("show-meta", key = key, value = value));
+    }
+    println!();
+    println!("{}", memo.content);
+    Ok(())
+}
+
+const SHORT_ID_LEN: usize = 8;
+/// Recent memos offered by `cap rm -i`.
+const PICKER_MEMOS: usize = 200;
+
+fn short_id(memo_id: &str) -> &str {
+    memo_id.get(..SHORT_ID_LEN).unwrap_or(memo_id)
+}
+
+fn edit_memos(
+    app: &AppContext,
+    ids: &[String],
+    all: bool,
+    tag: Option<&str>,
+    confirm: &ConfirmArgs,
+) -> Result<()> {
+    let db = app.db();
+    let memos = if all {
+        let filter = db::MemoFilter {
+            tag: tag.map(tags::normalize_tag),
+            ..Default::default()
+        };
+        db::fetch_filtered_memos(db, &filter)?
+    } else if ids.is_empty() {
+        db::fetch_memos(db, Some(1))?
+    } else {
+        ids.iter()
+            .map(|id| db::fetch_memo_by_id_prefix(db, id))
+            .collect::<Result<Vec<_>>>()?
+    };
+    // Ciphertext can't be edited as text; private memos stay out of the file.
+    let (private, memos): (Vec<_>, Vec<_>) = memos.into_iter().partition(|memo| memo.private);
+    if memos.is_empty() {
+        bail!("no memos to edit");
+    }
+    if !private.is_empty() {
+        println!("{}", t!("private-memos-skipping", count = private.len()));
+    }
+
+    let document = editor::edit_text(&bulk_edit::render(&memos))?;
+    let mut changes = bulk_edit::parse(&memos, &document)?;
+    for (_, content) in &mut changes.updated {
+        *content = tidy::tidy(content, &app.config().tidy);
+    }
+    let color = use_color(app);
+    let original = |memo_id: &str| {
+        memos
+            .iter()
+            .find(|memo| memo.memo_id.as_str() == memo_id)
+            .map(|memo| memo.content.as_str())
+            .unwrap_or_default()
+    };
+    // Every diff is shown before anything is written, so the prompt below
+    // is answered knowing what will change.
+    let mut shrunk = 0;
+    for (memo_id, content) in &changes.updated {
+        let old = original(memo_id);
+        print!(
+            "{}",
+            format::format_diff(short_id(memo_id), old, content, color)
+        );
+        if bulk_edit::is_large_deletion(old, content) {
+            shrunk += 1;
+        }
+    }
+    for memo_id in &changes.deleted {
+        print!(
+            "{}",
+            format::format_diff(short_id(memo_id), original(memo_id), "", color)
+        );
+    }
+    // Plain edits are what the user just saved; only deleting a memo, or most
+    // of one, needs a second look.
+    if confirm.dry_run || !changes.deleted.is_empty() || shrunk > 0 {
+        if shrunk > 0 {
+            println!("{}", t!("edit-large-deletions", count = shrunk));
+        }
+        let action = t!(
+            "action-edit-memos",
+            updated = changes.updated.len(),
+            deleted = changes.deleted.len()
+        );
+        if !prompt::proceed(&action, confirm)? {
+            return Ok(());
+        }
+    }
+    db.atomically(|db| {
+        for (memo_id, content) in &changes.updated {
+            db::update_memo_content(db, &memo_id.clone().into(), content)?;
+        }
+        for memo_id in &changes.deleted {
+            db::delete_memo(db, &memo_id.clone().into())?;
+        }
+        Ok(())
+    })?;
+    println!(
+        "{}",
+        t!(
+            "memos-edited",
+            updated = changes.updated.len(),
+            deleted = changes.deleted.len()
+        )
+    );
+    Ok(())
+}
+
+fn merge_database(app: &AppContext, other_db: &Path, confirm: &ConfirmArgs) -> Result<()> {
+    let plan = db::plan_merge(app.db(), other_db)?;
+    let report = &plan.report;
+    let summary = t!(
+        "database-merge-summary",
+        merged = report.merged,
+        path = other_db.display().to_string(),
+        present = report.duplicate_ids,
+        duplicates = report.duplicate_contents
+    );
+    if (confirm.dry_run || report.merged > 0)
+        && !prompt::proceed(
+            &t!("action-merge-database", summary = summary.as_str()),
+            confirm,
+        )?
+    {
+        return Ok(());
+    }
+    db::merge_database(app.db(), &plan)?;
+    println!("{}", t!("database-merged", summary = summary));
+    if report.skipped_private > 0 {
+        println!(
+            "{}",
+            t!("private-memos-skipped", count = report.skipped_private)
+        );
+    }
+    Ok(())
+}
+
+fn import_notes(
+    app: &AppContext,
+    from: import::Source,
+    path: Option<&Path>,
+    confirm: &ConfirmArgs,
+) -> Result<()> {
+    let notes = import::read(from, path)?;
+    let plan = import::plan(app.db(), app.config(), notes)?;
+    let report = &plan.report;
+    let summary = t!(
+        "import-summary",
+        imported = report.imported,
+        path = path.map_or_else(
+            || from.name().to_string(),
+            |path| path.display().to_string()
+        ),
+        present = report.duplicates,
+        ignored = report.ignored
+    );
+    if (confirm.dry_run || report.imported > 0)
+        && !prompt::proceed(&t!("action-import", summary = summary.as_str()), confirm)?
+    {
+        return Ok(());
+    }
+    import::import(app.db(), app.config(), &plan)?;
+    println!("{}", t!("notes-imported", summary = summary));
+    Ok(())
+}
+
+fn pick_memos_to_remove(app: &AppContext, confirm: &ConfirmArgs) -> Result<()> {
+    let mut memos = db::fetch_memos(app.db(), Some(PICKER_MEMOS))?;
+    if memos.is_empty() {
+        bail!("no memos to delete");
+    }
+    crypto::reveal_memos(&mut memos, None);
+    let picked = tui::pick_memos(memos, &app.config().list.line_style(), |count| {
+        t!("picker-delete", count = count)
+    })?;
+    if picked.is_empty() {
+        println!("{}", t!("picker-cancelled"));
+        return Ok(());
+    }
+    delete_listed_memos(app, picked, confirm)
+}
+
+fn remove_memos(app: &AppContext, ids: &[String], confirm: &ConfirmArgs) -> Result<()> {
+    let db = app.db();
+    let mut memos = ids
+        .iter()
+        .map(|id| db::fetch_memo_by_id_prefix(db, id))
+        .collect::<Result<Vec<_>>>()?;
+    let mut seen = HashSet::new();
+    memos.retain(|memo| seen.insert(memo.memo_id.as_str().to_string()));
+    crypto::reveal_memos(&mut memos, None);
+    delete_listed_memos(app, memos, confirm)
+}
+
+/// Lists `memos`, then deletes them once the user agrees.
+fn delete_listed_memos(app: &AppContext, memos: Vec<Memo>, confirm: &ConfirmArgs) -> Result<()> {
+    let db = app.db();
+
+    let terminal_width = line_width(app);
+    let style = app.config().list.line_style();
+    for memo in &memos {
+        let prefix = format!(
+            "{}  {}",
+            short_id(memo.memo_id.as_str()),
+            format::format_display_time(&memo.created_at)
+        );
+        println!(
+            "{}",
+            format::format_memo_line(&prefix, memo.title(), terminal_width, &style)
+        );
+    }
+    if !prompt::proceed(&t!("action-delete-memos", count = memos.len()), confirm)? {
+        return Ok(());
+    }
+    let tx = db.conn().unchecked_transaction()?;
+    for memo in &memos {
+        db::delete_memo(db, &memo.memo_id)?;
+    }
+    tx.commit()?;
+    println!("{}", t!("memos-deleted", count = memos.len()));
+    Ok(())
+}
+
+fn merge_memos(app: &AppContext, ids: &[String], confirm: &ConfirmArgs) -> Result<()> {
+    let db = app.db();
+    let mut memos = ids
+        .iter()
+        .map(|id| db::fetch_memo_by_id_prefix(db, id))
+        .collect::<Result<Vec<_>>>()?;
+    if memos.iter().any(|memo| memo.private) {
+        bail!("private memos can't be merged");
+    }
+    let mut seen = HashSet::new();
+    memos.retain(|memo| seen.insert(memo.memo_id.as_str().to_string()));
+    memos.sort_by_key(|memo| DateTime::parse_from_rfc3339(&memo.created_at).ok());
+    let Some((kept, merged)) = memos.split_first().filter(|(_, rest)| !rest.is_empty()) else {
+        bail!("merging memos needs at least two different ids");
+    };
+
+    let content = memos
+        .iter()
+        .map(|memo| memo.content.trim())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let action = t!(
+        "action-merge-memos",
+        count = memos.len(),
+        id = short_id(kept.memo_id.as_str())
+    );
+    if !prompt::proceed(&action, confirm)? {
+        return Ok(());
+    }
+    let tx = db.conn().unchecked_transaction()?;
+    db::update_memo_content(db, &kept.memo_id, &content)?;
+    for memo in merged {
+        db::copy_explicit_tags(db, &memo.memo_id, &kept.memo_id)?;
+        db::copy_meta(db, &memo.memo_id, &kept.memo_id)?;
+        db::delete_memo(db, &memo.memo_id)?;
+    }
+    tx.commit()?;
+    println!(
+        "{}",
+        t!(
+            "memos-merged",
+            count = memos.len(),
+            id = short_id(kept.memo_id.as_str())
+        )
+    );
+    Ok(())
+}
+
+fn split_memo(app: &AppContext, id: &str) -> Result<()> {
+    let db = app.db();
+    let memo = db::fetch_memo_by_id_prefix(db, id)?;
+    if memo.private {
+        bail!("private memos can't be split");
+    }
+    let document = editor::edit_text(&bulk_edit::render_split(&memo))?;
+    let parts = bulk_edit::parse_split(&memo, &document)?;
+    let Some((first, rest)) = parts.split_first() else {
+        bail!("memo is empty after editing; nothing changed (use cap edit to delete it)");
+    };
+    let created_at = DateTime::parse_from_rfc3339(&memo.created_at)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Local));
+
+    let tx = db.conn().unchecked_transaction()?;
+    if *first != memo.content.trim() {
+        db::update_memo_content(db, &memo.memo_id, first)?;
+    }
+    for part in rest {
+        let new_memo = NewMemo::new(part.as_str())
+            .with_notebook(memo.notebook.clone())
+            .with_location(memo.location)
+            .with_created_at(created_at);
+        let new_id = db::add_memo(db, &new_memo)?;
+        db::copy_explicit_tags(db, &memo.memo_id, &new_id)?;
+        db::copy_meta(db, &memo.memo_id, &new_id)?;
+    }
+    tx.commit()?;
+    println!("{}", t!("memo-split", count = parts.len()));
+    Ok(())
+}
+
+fn run_meta_command(app: &AppContext, command: MetaCommand) -> Result<()> {
+    let db = app.db();
+    match command {
+        MetaCommand::Get { id } => {
+            let memo = db::fetch_memo_by_id_prefix(db, &id)?;
+            for (key, value) in db::fetch_meta(db, &memo.memo_id)? {
+                println!("{}={}", key, value);
+            }
+        }
+        MetaCommand::Set { id, key, value } => {
+            let memo = db::fetch_memo_by_id_prefix(db, &id)?;
+            if memo.private {
+                bail!("private memos can't carry metadata");
+            }
+            db::set_meta(db, &memo.memo_id, &key, &value)?;
+        }
+        MetaCommand::Unset { id, key } => {
+            let memo = db::fetch_memo_by_id_prefix(db, &id)?;
+            if !db::unset_meta(db, &memo.memo_id, &key)? {
+                bail!(
+                    "memo {} has no `{}` field",
+                    short_id(memo.memo_id.as_str()),
+                    key
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+const DATA_PATHS: [(DataPath, &str); 5] = [
+    (DataPath::Data, "Data:"),
+    (DataPath::Db, "Database:"),
+    (DataPath::Config, "Config:"),
+    (DataPath::Backups, "Backups:"),
+    (DataPath::Attachments, "Attachments:"),
+];
+
+fn resolve_data_path(which: DataPath) -> Result<PathBuf> {
+    match which {
+        DataPath::Data => config::data_dir(),
+        DataPath::Db => config::db_path(),
+        DataPath::Config => config::config_path(),
+        DataPath::Backups => config::backups_dir(),
+        DataPath::Attachments => config::attachments_dir(),
+    }
+}
+
+fn print_paths(which: Option<DataPath>) -> Result<()> {
+    if let Some(which) = which {
+        println!("{}", resolve_data_path(which)?.display());
+        return Ok(());
+    }
+    for (which, label) in DATA_PATHS {
+        let path = resolve_data_path(which)?;
+        let missing = if path.exists() {
+            ""
+        } else {
+            "  (not created yet)"
+        };
+        println!("{:<13}{}{}", label, path.display(), missing);
+    }
+    Ok(())
+}
+
+/// Files open their containing folder; directories are created first if needed.
+fn open_path(which: DataPath) -> Result<()> {
+    let path = resolve_data_path(which)?;
+    let dir = if matches!(which, DataPath::Db | DataPath::Config) {
+        path.parent().map(Path::to_path_buf).unwrap_or(path)
+    } else {
+        fs::create_dir_all(&path)?;
+        path
+    };
+    opener::open_in_file_manager(&dir)
+}
+
+fn purge_tombstones(
+    app: &AppContext,
+    older_than_days: Option<u32>,
+    confirm: &ConfirmArgs,
+) -> Result<()> {
+    let days = older_than_days.unwrap_or(app.config().sync.tombstone_retention_days);
+    let cutoff = sync::tombstone_cutoff(days);
+    let purgeable = db::count_purgeable_tombstones(app.db(), cutoff)?;
+    let action = t!("action-purge", count = purgeable);
+    if (confirm.dry_run || purgeable > 0) && !prompt::proceed(&action, confirm)? {
+        return Ok(());
+    }
+    let purged = db::purge_tombstones(app.db(), cutoff)?;
+    println!("{}", t!("tombstones-purged", count = purged));
+    Ok(())
+}
+
+fn run_maintenance(app: &AppContext, dry_run: bool) -> Result<()> {
+    let due = retention::due_for_archive(app.db(), &app.config().retention, Local::now())?;
+    if dry_run {
+        for memo in &due {
+            let prefix = format!(
+                "{}  {}",
+                short_id(memo.memo_id.as_str()),
+                format::format_display_time(&memo.created_at)
+            );
+            println!(
+                "{}",
+                format::format_memo_line(
+                    &prefix,
+                    memo.title(),
+                    line_width(app),
+                    &app.config().list.line_style(),
+                )
+            );
+        }
+        println!("{}", t!("maintain-would-archive", count = due.len()));
+        return Ok(());
+    }
+    retention::archive(app.db(), &due)?;
+    println!("{}", t!("maintain-archived", count = due.len()));
+    Ok(())
+}
+
+fn backup_database(app: &AppContext, path: Option<PathBuf>) -> Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => config::backups_dir()?.join(format!(
+            "capmind-{}.db",
+            Local::now().format("%Y%m%d-%H%M%S")
+        )),
+    };
+    db::backup(app.db(), &path)?;
+    println!(
+        "{}",
+        t!("database-backed-up", path = path.display().to_string())
+    );
+    Ok(())
+}
+
+fn compact_database(app: &AppContext, confirm: &ConfirmArgs) -> Result<()> {
+    let size_before = db::database_size(app.db())?;
+    let cutoff = sync::tombstone_cutoff(app.config().sync.tombstone_retention_days);
+    let purgeable = db::count_purgeable_tombstones(app.db(), cutoff)?;
+    let action = t!(
+        "action-compact",
+        count = purgeable,
+        size = format::format_bytes(size_before)
+    );
+    if (confirm.dry_run || purgeable > 0) && !prompt::proceed(&action, confirm)? {
+        return Ok(());
+    }
+    let purged = db::purge_tombstones(app.db(), cutoff)?;
+    db::vacuum(app.db())?;
+    let size_after = db::database_size(app.db())?;
+    println!(
+        "{}",
+        t!(
+            "database-compacted",
+            count = purged,
+            reclaimed = format::format_bytes(size_before.saturating_sub(size_after)),
+            before = format::format_bytes(size_before),
+            after = format::format_bytes(size_after)
+        )
+    );
+    Ok(())
+}
+
+fn print_mentions(app: &AppContext) -> Result<()> {
+    let counts = db::fetch_mention_counts(app.db())?;
+    let width = counts
+        .iter()
+        .map(|(name, _)| name.chars().count() + 1)
+        .max()
+        .unwrap_or(0);
+    for (name, count) in counts {
+        println!(
+            "{:<width$}  {:>4}",
+            format!("@{}", name),
+            count,
+            width = width
+        );
+    }
+    Ok(())
+}
+
+fn print_topics(app: &AppContext, since: Option<NaiveDate>, limit: usize) -> Result<()> {
+    let filter = db::MemoFilter {
+        since: since.map(date_parse::start_of_day),
+        ..Default::default()
+    };
+    let memos = db::fetch_filtered_memos(app.db(), &filter)?;
+    let topics = topics::collect(&memos, limit);
+    match since {
+        Some(date) => println!("{} memos since {}", topics.memos, date),
+        None => println!("{} memos", topics.memos),
+    }
+    for (title, counts) in [("Keywords", &topics.keywords), ("Pairs", &topics.bigrams)] {
+        if counts.is_empty() {
+            continue;
+        }
+        let width = counts
+            .iter()
+            .map(|(term, _)| term.chars().count())
+            .max()
+            .unwrap_or(0);
+        println!();
+        println!("{}:", title);
+        for (term, count) in counts {
+            println!("  {:<width$}  {:>4}", term, count, width = width);
+        }
+    }
+    Ok(())
+}
+
+fn start_timer(app: &AppContext, label: &str) -> Result<()> {
+    if let Some(stopped) = timesheet::start(app.db(), label)? {
+        print_stopped(&stopped);
+    }
+    println!("{}", t!("timer-tracking", label = label.trim()));
+    Ok(())
+}
+
+fn stop_timer(app: &AppContext, note: &str) -> Result<()> {
+    let stopped = timesheet::stop(app.db(), Some(note))?;
+    print_stopped(&stopped);
+    Ok(())
+}
+
+fn print_stopped(stopped: &timesheet::Stopped) {
+    println!(
+        "{}",
+        t!(
+            "timer-stopped",
+            label = stopped.label.as_str(),
+            duration = format::format_duration(stopped.seconds)
+        )
+    );
+}
+
+fn print_timesheet(app: &AppContext, since: Option<NaiveDate>) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start_date = since.unwrap_or_else(|| {
+        today - chrono::Duration::days(i64::from(today.weekday().num_days_from_monday()))
+    });
+    let sheet = timesheet::collect(app.db(), date_parse::start_of_day(start_date))?;
+    println!("Since {}", start_date.format("%Y-%m-%d %a"));
+    if sheet.by_label.is_empty() {
+        println!("No tracked time");
+        return Ok(());
+    }
+    let width = sheet
+        .by_label
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .chain(sheet.by_day.iter().map(|(day, _)| day.chars().count()))
+        .max()
+        .unwrap_or(0)
+        .max("Total".len());
+    println!();
+    for (day, seconds) in &sheet.by_day {
+        println!(
+            "  {:<width$}  {:>8}",
+            day,
+            format::format_duration(*seconds),
+            width = width
+        );
+    }
+    println!();
+    for (label, seconds) in &sheet.by_label {
+        let running = if sheet.running.as_deref() == Some(label.as_str()) {
+            "  (running)"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<width$}  {:>8}{}",
+            label,
+            format::format_duration(*seconds),
+            running,
+            width = width
+        );
+    }
+    println!(
+        "  {:<width$}  {:>8}",
+        "Total",
+        format::format_duration(sheet.total_seconds),
+        width = width
+    );
+    Ok(())
+}
+
+fn print_streak(app: &AppContext) -> Result<()> {
+    let streak = stats::streak(app.db(), app.config().streak.daily_goal)?;
+    if streak.goal_met() {
+        println!(
+            "{}",
+            t!(
+                "streak-goal-met",
+                today = streak.today_count,
+                goal = streak.daily_goal
+            )
+        );
+    } else {
+        println!(
+            "{}",
+            t!(
+                "streak-to-go",
+                today = streak.today_count,
+                goal = streak.daily_goal,
+                remaining = streak.daily_goal - streak.today_count
+            )
+        );
+    }
+    println!("{}", t!("stats-current-streak", days = streak.current));
+    println!("{}", t!("stats-longest-streak", days = streak.longest));
+    Ok(())
+}
+
+fn print_mood_trend(app: &AppContext) -> Result<()> {
+    let trend = stats::mood_trend(app.db())?;
+    let Some(average) = trend.average else {
+        println!("{}", t!("mood-none"));
+        return Ok(());
+    };
+    println!(
+        "{}",
+        t!(
+            "mood-average",
+            average = format!("{:.1}", average),
+            count = trend.rated_memos
+        )
+    );
+    println!();
+    println!("{}", t!("mood-per-week"));
+    for (week, average, count) in &trend.weekly {
+        match average {
+            Some(average) => println!(
+                "  {}  {:>4.1} {:<10} ({})",
+                week,
+                average,
+                bar(app, (average * 2.0).round() as usize),
+                count
+            ),
+            None => println!("  {}     -", week),
+        }
+    }
+    println!();
+    println!("{}", t!("mood-ratings"));
+    let max_count = trend.distribution.iter().copied().max().unwrap_or(0).max(1);
+    for (idx, count) in trend.distribution.iter().enumerate() {
+        let bar_len = (count * 30).div_ceil(max_count);
+        println!("  {}  {:>4} {}", idx + 1, count, bar(app, bar_len));
+    }
+    Ok(())
+}
+
+fn print_stats(app: &AppContext) -> Result<()> {
+    let daily_goal = app.config().streak.daily_goal;
+    let stats = app
+        .db()
+        .read_snapshot(|db| stats::collect(db, daily_goal))?;
+    println!("{}", t!("stats-memos", count = stats.total_memos));
+    println!(
+        "{}",
+        t!("stats-current-streak", days = stats.current_streak)
+    );
+    println!(
+        "{}",
+        t!("stats-longest-streak", days = stats.longest_streak)
+    );
+    println!(
+        "{}",
+        t!(
+            "stats-database-size",
+            size = format::format_bytes(stats.db_size_bytes)
+        )
+    );
+    println!();
+    println!("{}", t!("stats-memos-per-week"));
+    let max_count = stats
+        .weekly_counts
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for (week, count) in &stats.weekly_counts {
+        let bar_len = (*count * 30).div_ceil(max_count) as usize;
+        println!("  {}  {:>4} {}", week, count, bar(app, bar_len));
+    }
+    if !stats.top_tags.is_empty() {
+        println!();
+        println!("{}", t!("stats-top-tags"));
+        for (tag, count) in &stats.top_tags {
+            println!("  #{} ({})", tag, count);
+        }
+    }
+    Ok(())
+}
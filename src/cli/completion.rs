@@ -0,0 +1,96 @@
+use anyhow::{Result, bail};
+use clap_complete::{CompletionCandidate, env::Shells};
+use std::{env, ffi::OsStr, io};
+
+use crate::{config, db, format, i18n::t};
+
+/// Environment variable that switches `cap` into completion mode.
+pub(crate) const COMPLETE_VAR: &str = "COMPLETE";
+/// Memo ids offered for one prefix; the newest come first.
+const MAX_MEMO_CANDIDATES: usize = 50;
+/// Width of the time and first line shown next to each memo id.
+const MEMO_HELP_WIDTH: usize = 60;
+
+/// Prints the shell snippet that wires `cap` up for dynamic completion.
+pub(crate) fn print_registration(shell: &str) -> Result<()> {
+    let shells = Shells::builtins();
+    let Some(completer) = shells.completer(shell) else {
+        bail!("unsupported shell `{}`", shell);
+    };
+    let bin = env::current_exe()?;
+    completer.write_registration(
+        COMPLETE_VAR,
+        "cap",
+        "cap",
+        &bin.to_string_lossy(),
+        &mut io::stdout(),
+    )?;
+    Ok(())
+}
+
+/// Existing tag names, most used first. Errors yield no candidates rather than
+/// breaking the shell prompt.
+pub(crate) fn tag_candidates() -> Vec<CompletionCandidate> {
+    counted_candidates(open_db().and_then(|db| db::fetch_tag_counts(&db)))
+}
+
+/// Mentioned names, most mentioned first.
+pub(crate) fn mention_candidates() -> Vec<CompletionCandidate> {
+    counted_candidates(open_db().and_then(|db| db::fetch_mention_counts(&db)))
+}
+
+/// Existing notebook names, alphabetically.
+pub(crate) fn notebook_candidates() -> Vec<CompletionCandidate> {
+    counted_candidates(open_db().and_then(|db| db::fetch_notebook_counts(&db)))
+}
+
+/// Ids of memos starting with what has been typed so far, each with its time
+/// and first line so the right one is easy to pick.
+pub(crate) fn memo_id_candidates(current: &OsStr) -> Vec<CompletionCandidate> {
+    let prefix = current.to_string_lossy();
+    let Ok(memos) =
+        open_db().and_then(|db| db::fetch_memos_by_id_prefix(&db, &prefix, MAX_MEMO_CANDIDATES))
+    else {
+        return Vec::new();
+    };
+    let style = config::load()
+        .map(|config| config.list.line_style())
+        .unwrap_or_default();
+    memos
+        .into_iter()
+        .map(|memo| {
+            let private = t!("completion-private-memo");
+            let content = if memo.private {
+                private.as_str()
+            } else {
+                memo.content.as_str()
+            };
+            let help = format::format_memo_line(
+                &format::format_display_time(&memo.created_at),
+                content,
+                MEMO_HELP_WIDTH,
+                &style,
+            );
+            CompletionCandidate::new(memo.memo_id.as_str()).help(Some(help.into()))
+        })
+        .collect()
+}
+
+/// Completion runs on every Tab press, so it only reads: a database that
+/// doesn't exist yet or still needs migrating offers no candidates.
+fn open_db() -> Result<db::Db> {
+    db::Db::open_read_only(&config::db_path()?)
+}
+
+fn counted_candidates(counts: Result<Vec<(String, usize)>>) -> Vec<CompletionCandidate> {
+    let Ok(counts) = counts else {
+        return Vec::new();
+    };
+    counts
+        .into_iter()
+        .map(|(name, count)| {
+            let help = t!("completion-memo-count", count = count);
+            CompletionCandidate::new(name).help(Some(help.into()))
+        })
+        .collect()
+}
@@ -0,0 +1,39 @@
+use anyhow::{Context, Result, bail};
+use std::{env, fs, io::Write, process};
+use tempfile::Builder;
+
+/// Opens `initial` in `$VISUAL`/`$EDITOR` (falling back to `vi`) and returns the
+/// saved text. The editor value may carry arguments, e.g. `code --wait`.
+pub(crate) fn edit_text(initial: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // A fresh, private file: other users can't predict its name or read it.
+    let mut file = Builder::new()
+        .prefix("cap-edit-")
+        .suffix(".md")
+        .tempfile()?;
+    file.write_all(initial.as_bytes())?;
+    file.flush()?;
+    let path = file.path();
+
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .with_context(|| format!("failed to start editor `{}`", editor));
+    // Editors that save by renaming leave a new file at the same path.
+    let edited = fs::read_to_string(path);
+
+    let status = status?;
+    if !status.success() {
+        bail!(
+            "editor `{}` exited with {}; nothing changed",
+            editor,
+            status
+        );
+    }
+    Ok(edited?)
+}
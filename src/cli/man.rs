@@ -0,0 +1,21 @@
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use clap_mangen::Man;
+use std::{fs, io, path::Path};
+
+use crate::cli::args::Cli;
+
+/// Prints `cap(1)`, or with `out_dir` writes it plus a `cap-<subcommand>.1` page
+/// for every subcommand, ready for `$PREFIX/share/man/man1`.
+pub(crate) fn run(out_dir: Option<&Path>) -> Result<()> {
+    let Some(out_dir) = out_dir else {
+        Man::new(Cli::command()).render(&mut io::stdout())?;
+        return Ok(());
+    };
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create {}", out_dir.display()))?;
+    clap_mangen::generate_to(Cli::command(), out_dir)
+        .with_context(|| format!("failed to write man pages to {}", out_dir.display()))?;
+    println!("Wrote man pages to {}", out_dir.display());
+    Ok(())
+}
@@ -1,2 +1,10 @@
+pub(crate) mod alias;
 pub(crate) mod args;
+mod bulk_edit;
 pub(crate) mod commands;
+pub(crate) mod completion;
+mod editor;
+mod man;
+mod opener;
+mod prompt;
+mod review;
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result, bail};
+use std::{ffi::OsStr, path::Path, process};
+
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(target_os = "windows")]
+const OPENER: &str = "explorer";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const OPENER: &str = "xdg-open";
+
+/// Shows `path` in the platform file manager without waiting for it to close.
+pub(crate) fn open_in_file_manager(path: &Path) -> Result<()> {
+    open(path.as_os_str()).with_context(|| {
+        format!(
+            "couldn't open a file manager; the folder is {}",
+            path.display()
+        )
+    })
+}
+
+/// Opens `url` in the default browser.
+pub(crate) fn open_url(url: &str) -> Result<()> {
+    open(OsStr::new(url))
+}
+
+fn open(target: &OsStr) -> Result<()> {
+    let status = process::Command::new(OPENER)
+        .arg(target)
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .status()
+        .with_context(|| format!("failed to run `{}`", OPENER))?;
+    // explorer.exe reports failure even when the window opened.
+    if !status.success() && !cfg!(target_os = "windows") {
+        bail!("`{}` exited with {}", OPENER, status);
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{Result, bail};
+use std::io::{self, IsTerminal, Write};
+
+use crate::{cli::args::ConfirmArgs, i18n::t};
+
+/// Asks a yes/no question on stdin; `default` answers an empty reply. Without
+/// a terminal there's no one to agree, so the answer is no.
+pub(crate) fn confirm(question: &str, default: bool) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    ask(question, default)
+}
+
+fn ask(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    print!("{} {} ", question, hint);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
+
+/// Gate for destructive commands. `action` is imperative, e.g. `Delete 3 memos`:
+/// `--dry-run` prints it and stops, `--yes` goes ahead, and otherwise the user is
+/// asked. Without a terminal there's no one to ask, so `--yes` is required.
+pub(crate) fn proceed(action: &str, confirm: &ConfirmArgs) -> Result<bool> {
+    if confirm.dry_run {
+        println!("{}", t!("prompt-dry-run", action = action));
+        return Ok(false);
+    }
+    if confirm.yes {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        bail!(t!("prompt-not-a-terminal", action = action));
+    }
+    let approved = ask(&format!("{}?", action), false)?;
+    if !approved {
+        println!("{}", t!("prompt-nothing-changed"));
+    }
+    Ok(approved)
+}
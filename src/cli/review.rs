@@ -0,0 +1,230 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Days, NaiveDate};
+use std::io::{BufRead, Write};
+
+use crate::{
+    cli::args::ReviewPeriod,
+    crypto, date_parse,
+    db::{self, Db},
+    domain::{memo::Memo, tags},
+    format,
+    i18n::t,
+};
+
+/// Tag the todo action adds.
+const TODO_TAG: &str = "todo";
+
+/// First and last day of the period before the one `today` is in: Monday to
+/// Sunday of last week.
+pub(crate) fn period_days(period: ReviewPeriod, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match period {
+        ReviewPeriod::Week => {
+            let this_monday = today - Days::new(u64::from(today.weekday().num_days_from_monday()));
+            (this_monday - Days::new(7), this_monday - Days::new(1))
+        }
+    }
+}
+
+/// Walks the period's memos oldest first, reading one action per memo from
+/// `input`. Progress is kept per period in the kv table, so quitting or an
+/// interrupted session picks up after the last memo handled.
+pub(crate) fn run(
+    db: &Db,
+    period: ReviewPeriod,
+    today: NaiveDate,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<()> {
+    let (first, last) = period_days(period, today);
+    let progress_key = format!("review_week_{}", first);
+    let done_until = db::get_kv(db, &progress_key)?;
+    let filter = db::MemoFilter {
+        since: Some(date_parse::start_of_day(first)),
+        until: last.succ_opt().map(date_parse::start_of_day),
+        ..Default::default()
+    };
+    let mut memos = db::fetch_filtered_memos(db, &filter)?;
+    crypto::reveal_memos(&mut memos, None);
+    memos.reverse();
+    let total = memos.len();
+    let handled = match &done_until {
+        Some(done_until) => memos
+            .iter()
+            .take_while(|memo| !is_after(memo, done_until))
+            .count(),
+        None => 0,
+    };
+    if handled == total {
+        writeln!(
+            out,
+            "{}",
+            t!("review-nothing-left", week = first.to_string())
+        )?;
+        return Ok(());
+    }
+
+    for (index, memo) in memos.iter().enumerate().skip(handled) {
+        writeln!(out)?;
+        write_memo(out, memo, index + 1, total)?;
+        let action = loop {
+            write!(out, "{} ", t!("review-actions"))?;
+            out.flush()?;
+            let Some(answer) = read_answer(input)? else {
+                break Action::Quit;
+            };
+            match answer.as_str() {
+                "t" => {
+                    write!(out, "{} ", t!("review-tags"))?;
+                    out.flush()?;
+                    let tags: Vec<String> = read_answer(input)?
+                        .unwrap_or_default()
+                        .split([' ', ','])
+                        .map(tags::normalize_tag)
+                        .filter(|tag| !tag.is_empty())
+                        .collect();
+                    break Action::Tag(tags);
+                }
+                "a" => break Action::Tag(vec![tags::ARCHIVED_TAG.to_string()]),
+                "d" => break Action::Tag(vec![TODO_TAG.to_string()]),
+                "s" | "" => break Action::Skip,
+                "q" => break Action::Quit,
+                _ => {}
+            }
+        };
+        match action {
+            Action::Tag(tags) => db::add_explicit_tags(db, &memo.memo_id, &tags)?,
+            Action::Skip => {}
+            Action::Quit => {
+                writeln!(out, "{}", t!("review-paused", done = index, total = total))?;
+                return Ok(());
+            }
+        }
+        db::set_kv(db, &progress_key, &memo.created_at)?;
+    }
+    writeln!(out, "{}", t!("review-finished", week = first.to_string()))?;
+    Ok(())
+}
+
+enum Action {
+    Tag(Vec<String>),
+    Skip,
+    Quit,
+}
+
+/// The next trimmed, lowercased line; `None` at end of input.
+fn read_answer(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_lowercase()))
+}
+
+fn is_after(memo: &Memo, done_until: &str) -> bool {
+    match (
+        DateTime::parse_from_rfc3339(&memo.created_at),
+        DateTime::parse_from_rfc3339(done_until),
+    ) {
+        (Ok(created), Ok(done_until)) => created > done_until,
+        _ => memo.created_at.as_str() > done_until,
+    }
+}
+
+fn write_memo(out: &mut impl Write, memo: &Memo, position: usize, total: usize) -> Result<()> {
+    let mut header = format!(
+        "[{}/{}] {}",
+        position,
+        total,
+        format::format_display_time(&memo.created_at)
+    );
+    for tag in &memo.tags {
+        header.push_str(&format!(" #{}", tag));
+    }
+    writeln!(out, "{}", header)?;
+    writeln!(out, "{}", memo.content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+    use chrono::{Local, TimeZone};
+
+    fn add_on(db: &Db, content: &str, day: u32) {
+        let created_at = Local.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap();
+        db::add_memo(db, &NewMemo::new(content).with_created_at(Some(created_at))).unwrap();
+    }
+
+    fn review(db: &Db, answers: &str) -> String {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 15).unwrap();
+        let mut out = Vec::new();
+        run(
+            db,
+            ReviewPeriod::Week,
+            today,
+            &mut answers.as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn last_week_runs_monday_to_sunday() {
+        let wednesday = NaiveDate::from_ymd_opt(2024, 5, 15).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 13).unwrap();
+        let expected = (
+            NaiveDate::from_ymd_opt(2024, 5, 6).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 5, 12).unwrap(),
+        );
+        assert_eq!(period_days(ReviewPeriod::Week, wednesday), expected);
+        assert_eq!(period_days(ReviewPeriod::Week, monday), expected);
+    }
+
+    #[test]
+    fn actions_apply_and_an_interrupted_review_resumes() {
+        let db = Db::open_in_memory().unwrap();
+        add_on(&db, "too old", 5);
+        add_on(&db, "call the bank", 6);
+        add_on(&db, "idea: weekly digest", 8);
+        add_on(&db, "receipt photo", 12);
+        add_on(&db, "this week", 13);
+
+        let out = review(&db, "d\nq\n");
+        assert!(
+            out.contains("[1/3]") && out.contains("call the bank"),
+            "{}",
+            out
+        );
+        assert!(
+            !out.contains("too old") && out.contains("Paused after 1 of 3"),
+            "{}",
+            out
+        );
+
+        let out = review(&db, "t\nProduct, writing\na\n");
+        assert!(!out.contains("[1/3]") && out.contains("[3/3]"), "{}", out);
+        assert!(review(&db, "").contains("2024-05-06"));
+
+        let tagged: Vec<(String, Vec<String>)> = db::fetch_memos(&db, None)
+            .unwrap()
+            .into_iter()
+            .map(|memo| (memo.content, memo.tags))
+            .collect();
+        let tags_of = |content: &str| {
+            let mut tags = tagged
+                .iter()
+                .find(|(memo, _)| memo == content)
+                .unwrap()
+                .1
+                .clone();
+            tags.sort();
+            tags
+        };
+        assert_eq!(tags_of("call the bank"), ["todo"]);
+        assert_eq!(tags_of("idea: weekly digest"), ["product", "writing"]);
+        assert_eq!(tags_of("receipt photo"), ["archived"]);
+        assert!(tags_of("this week").is_empty());
+    }
+}
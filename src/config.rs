@@ -1,9 +1,525 @@
-use anyhow::Result;
-use std::{env, fs, path::PathBuf};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, path::PathBuf};
 
-pub(crate) fn db_path() -> Result<PathBuf> {
+use crate::{domain::rules::Rules, format::LineStyle};
+
+const DEFAULT_TOMBSTONE_RETENTION_DAYS: u32 = 30;
+const DEFAULT_MAX_MEMO_SIZE_BYTES: usize = 64 * 1024;
+const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+const DEFAULT_PASTE_URL: &str = "https://dpaste.com/api/v2/";
+const DEFAULT_TODOIST_API_URL: &str = "https://api.todoist.com/rest/v2";
+const DEFAULT_TRANSCRIBE_MODEL: &str = "whisper-1";
+const DEFAULT_OCR_LANGUAGE: &str = "eng";
+const DEFAULT_SNOOZE_MINUTES: u32 = 10;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    pub(crate) memo: MemoConfig,
+    pub(crate) tidy: TidyConfig,
+    pub(crate) sync: SyncConfig,
+    pub(crate) tui: TuiConfig,
+    pub(crate) streak: StreakConfig,
+    pub(crate) notebook: NotebookConfig,
+    pub(crate) location: LocationConfig,
+    pub(crate) workspace: WorkspaceConfig,
+    pub(crate) ui: UiConfig,
+    pub(crate) list: ListConfig,
+    pub(crate) publish: PublishConfig,
+    pub(crate) promote: PromoteConfig,
+    pub(crate) post: PostConfig,
+    pub(crate) serve: ServeConfig,
+    pub(crate) transcribe: TranscribeConfig,
+    pub(crate) ocr: OcrConfig,
+    pub(crate) remind: RemindConfig,
+    /// Tag or drop new memos automatically; see `RuleConfig`.
+    pub(crate) rules: Rules,
+    /// Archive old memos when `cap maintain` runs; see `RetentionConfig`.
+    pub(crate) retention: Vec<RetentionConfig>,
+    /// Extra subcommands, e.g. `todo = "add --tag todo"`.
+    pub(crate) aliases: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct MemoConfig {
+    /// Memos larger than this are offered to be stored as an attachment; 0 disables the guard.
+    pub(crate) max_size_bytes: usize,
+    /// Memos longer than this are stored zstd-compressed, e.g. pasted logs; 0 keeps all as text.
+    pub(crate) compress_above_bytes: usize,
+}
+
+impl Default for MemoConfig {
+    fn default() -> Self {
+        Self {
+            max_size_bytes: DEFAULT_MAX_MEMO_SIZE_BYTES,
+            compress_above_bytes: 0,
+        }
+    }
+}
+
+/// Clean-up rules applied to memo text on add and edit; all off by default.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct TidyConfig {
+    /// Strips spaces at line ends and blank lines at the end of the memo.
+    pub(crate) trim_trailing_whitespace: bool,
+    /// Rewrites `*`, `+` and `•` bullets as `-`, and `1)` as `1.`.
+    pub(crate) normalize_list_markers: bool,
+    /// Shortens runs of three or more blank lines to two.
+    pub(crate) collapse_blank_lines: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct SyncConfig {
+    pub(crate) tombstone_retention_days: u32,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            tombstone_retention_days: DEFAULT_TOMBSTONE_RETENTION_DAYS,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct TuiConfig {
+    /// Chords that submit the input, e.g. `ctrl+enter`, `alt+enter`, `f2`.
+    pub(crate) submit_keys: Vec<String>,
+    /// Shows history timestamps as `5m ago` instead of the full local time.
+    pub(crate) relative_time: bool,
+    /// Abbreviations the editor expands on space or Tab, e.g.
+    /// `";mtg" = "Meeting {date}\n- "`; `{date}` and `{time}` become the current local time.
+    pub(crate) snippets: HashMap<String, String>,
+    /// Underlines unknown words in the editor; F7 cycles through suggestions.
+    pub(crate) spellcheck: bool,
+    /// Word list or hunspell `.dic` for `spellcheck`; defaults to the system one.
+    pub(crate) dictionary: Option<PathBuf>,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        Self {
+            submit_keys: ["ctrl+enter", "ctrl+j", "alt+enter", "f2"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            relative_time: false,
+            snippets: HashMap::new(),
+            spellcheck: false,
+            dictionary: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct UiConfig {
+    /// Message language, e.g. `zh-CN`; defaults to the system locale.
+    pub(crate) locale: Option<String>,
+    /// Always use the plain output of `--plain`.
+    pub(crate) plain: bool,
+}
+
+/// Defaults for `cap list`; `--output` and `--columns` override them.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct ListConfig {
+    pub(crate) output: ListOutput,
+    /// Columns of `--output table`, in order.
+    pub(crate) columns: Vec<ListColumn>,
+    /// Marks text cut to fit the terminal, e.g. `…`; empty cuts without a mark.
+    pub(crate) ellipsis: String,
+    /// How line breaks in a memo show up on its one-line summary.
+    pub(crate) newlines: NewlineStyle,
+    /// Cuts only between whole graphemes; turn off for terminals that draw
+    /// joined emoji as their separate parts.
+    pub(crate) grapheme_truncation: bool,
+}
+
+impl ListConfig {
+    /// How memo lines are flattened and shortened, here and in the TUI.
+    pub(crate) fn line_style(&self) -> LineStyle {
+        LineStyle {
+            ellipsis: self.ellipsis.clone(),
+            newline_marker: self.newlines == NewlineStyle::Marker,
+            graphemes: self.grapheme_truncation,
+        }
+    }
+}
+
+impl Default for ListConfig {
+    fn default() -> Self {
+        Self {
+            output: ListOutput::Lines,
+            columns: vec![
+                ListColumn::Id,
+                ListColumn::Created,
+                ListColumn::Tags,
+                ListColumn::Content,
+            ],
+            ellipsis: "...".to_string(),
+            newlines: NewlineStyle::Space,
+            grapheme_truncation: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum NewlineStyle {
+    /// Folded into a space.
+    Space,
+    /// Shown as `⏎`.
+    Marker,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ListOutput {
+    /// One line per memo: time and content
+    Lines,
+    /// Aligned columns with a header row
+    Table,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ListColumn {
+    Id,
+    Created,
+    Updated,
+    Notebook,
+    Tags,
+    Mood,
+    Sync,
+    Content,
+}
+
+impl ListColumn {
+    pub(crate) fn header(self) -> &'static str {
+        match self {
+            Self::Id => "ID",
+            Self::Created => "CREATED",
+            Self::Updated => "UPDATED",
+            Self::Notebook => "NOTEBOOK",
+            Self::Tags => "TAGS",
+            Self::Mood => "MOOD",
+            Self::Sync => "SYNC",
+            Self::Content => "CONTENT",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct StreakConfig {
+    /// Memos per day needed for the day to count toward a streak.
+    pub(crate) daily_goal: usize,
+    /// Shows the current streak and today's progress in the TUI status bar.
+    pub(crate) show_in_tui: bool,
+}
+
+impl Default for StreakConfig {
+    fn default() -> Self {
+        Self {
+            daily_goal: 1,
+            show_in_tui: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct NotebookConfig {
+    /// Notebook new memos go into, and the one `cap list` and the TUI show by default.
+    pub(crate) default: Option<String>,
+}
+
+impl NotebookConfig {
+    /// The notebook given on the command line, falling back to the default.
+    pub(crate) fn resolve(&self, notebook: Option<String>) -> Option<String> {
+        notebook.or_else(|| self.default.clone())
+    }
+
+    /// Whether `notebook` also shows memos in no notebook, which is the case
+    /// for the default one: memos written before it was set belong there.
+    pub(crate) fn holds_unfiled(&self, notebook: &str) -> bool {
+        self.default.as_deref() == Some(notebook)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct LocationConfig {
+    /// Shell command printing `lat,lon`, run for `--location here`.
+    pub(crate) command: Option<String>,
+}
+
+/// Labels memos written with `cap add` or in the TUI with the project `cap` runs in.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct WorkspaceConfig {
+    /// `tag` adds the project name as a tag, `meta` as the `project` field.
+    pub(crate) record: WorkspaceRecord,
+    /// Only inside a git repository; otherwise any folder counts as a project.
+    pub(crate) git_only: bool,
+    /// Where the memo was written, stored as fields, e.g. `["tmux", "host", "ssh"]`.
+    pub(crate) context: Vec<ContextField>,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ContextField {
+    /// `tmux_session` and `tmux_window` of the pane `cap` runs in.
+    Tmux,
+    /// `hostname` of this machine.
+    Host,
+    /// `ssh_from`, the address an SSH session came from.
+    Ssh,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WorkspaceRecord {
+    #[default]
+    Off,
+    Tag,
+    Meta,
+}
+
+/// Where `cap promote` creates tasks. GitHub issues use the `[publish]` token
+/// and API URL.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct PromoteConfig {
+    /// Taskwarrior executable.
+    pub(crate) taskwarrior_command: String,
+    /// Todoist API token; `TODOIST_API_TOKEN` wins when set.
+    pub(crate) todoist_token: Option<String>,
+    /// Todoist REST API root.
+    pub(crate) todoist_api_url: String,
+    /// Repository issues are opened in, as `owner/name`.
+    pub(crate) github_repo: Option<String>,
+}
+
+impl Default for PromoteConfig {
+    fn default() -> Self {
+        Self {
+            taskwarrior_command: "task".to_string(),
+            todoist_token: None,
+            todoist_api_url: DEFAULT_TODOIST_API_URL.to_string(),
+            github_repo: None,
+        }
+    }
+}
+
+/// Where `cap post` publishes memos.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct PostConfig {
+    /// Mastodon instance, e.g. `https://mastodon.social`.
+    pub(crate) mastodon_url: Option<String>,
+    /// Access token with the `write:statuses` scope; `MASTODON_TOKEN` wins when set.
+    pub(crate) mastodon_token: Option<String>,
+    /// `public`, `unlisted`, `private`, or `direct`; the account's default when unset.
+    pub(crate) mastodon_visibility: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct PublishConfig {
+    /// Token for `cap publish` (`gist` scope) and `cap promote --to github-issue`
+    /// (`repo` scope); `GITHUB_TOKEN` wins when set.
+    pub(crate) github_token: Option<String>,
+    /// Makes new gists public instead of secret.
+    pub(crate) gist_public: bool,
+    /// GitHub API root; change it for GitHub Enterprise.
+    pub(crate) github_api_url: String,
+    /// dpaste-compatible endpoint taking a `content` form field.
+    pub(crate) paste_url: String,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        Self {
+            github_token: None,
+            gist_public: false,
+            github_api_url: DEFAULT_GITHUB_API_URL.to_string(),
+            paste_url: DEFAULT_PASTE_URL.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct ServeConfig {
+    /// Bearer token for `POST /capture` on `cap serve --webdav`; the endpoint is off while
+    /// unset.
+    pub(crate) capture_token: Option<String>,
+    /// Token for WebDAV and `/metrics` when `cap serve --webdav` listens beyond loopback,
+    /// sent as a Bearer token or as the Basic password; such addresses are refused while unset.
+    pub(crate) webdav_token: Option<String>,
+    /// Origins whose pages may call `/capture`, e.g. `https://news.ycombinator.com`; `*` allows any.
+    pub(crate) cors_origins: Vec<String>,
+}
+
+/// How `cap add --audio` recordings become text; `command` wins when both are set.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct TranscribeConfig {
+    /// Shell command printing the transcript of the audio file at `$1`,
+    /// e.g. `whisper-cli -m ~/models/ggml-base.bin -nt -np -f "$1"`.
+    pub(crate) command: Option<String>,
+    /// OpenAI-compatible `/v1/audio/transcriptions` endpoint.
+    pub(crate) api_url: Option<String>,
+    /// Bearer token for `api_url`.
+    pub(crate) api_key: Option<String>,
+    /// Model name sent to `api_url`.
+    pub(crate) model: String,
+}
+
+impl Default for TranscribeConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            api_url: None,
+            api_key: None,
+            model: DEFAULT_TRANSCRIBE_MODEL.to_string(),
+        }
+    }
+}
+
+/// How text is read from images attached with `cap add --image`; `command` wins
+/// when both are set.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct OcrConfig {
+    /// Shell command printing the text in the image at `$1`, e.g. `tesseract "$1" stdout`.
+    pub(crate) command: Option<String>,
+    /// OCR.space-compatible `parse/image` endpoint.
+    pub(crate) api_url: Option<String>,
+    /// Sent as the `apikey` header to `api_url`.
+    pub(crate) api_key: Option<String>,
+    /// Language code sent to `api_url`.
+    pub(crate) language: String,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            api_url: None,
+            api_key: None,
+            language: DEFAULT_OCR_LANGUAGE.to_string(),
+        }
+    }
+}
+
+/// How `cap remindd` shows the reminders set with `cap remind`.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct RemindConfig {
+    /// Desktop notifications with snooze and done buttons; off prints reminders instead.
+    pub(crate) notifications: bool,
+    /// How far the snooze button moves a reminder.
+    pub(crate) snooze_minutes: u32,
+}
+
+impl Default for RemindConfig {
+    fn default() -> Self {
+        Self {
+            notifications: false,
+            snooze_minutes: DEFAULT_SNOOZE_MINUTES,
+        }
+    }
+}
+
+/// A `[[retention]]` entry. `cap maintain` tags the matching memos older than
+/// `archive_after_days` as `#archived`; a rule without `tag` or `notebook`
+/// covers every memo.
+///
+/// ```toml
+/// [[retention]]
+/// tag = "fleeting"
+/// archive_after_days = 30
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct RetentionConfig {
+    /// Only memos with this tag.
+    pub(crate) tag: Option<String>,
+    /// Only memos in this notebook.
+    pub(crate) notebook: Option<String>,
+    /// Age in days after which matching memos are archived.
+    pub(crate) archive_after_days: u32,
+}
+
+/// A `[[rules]]` entry, checked against every new public memo:
+///
+/// ```toml
+/// [[rules]]
+/// content = "^TODO"
+/// tags = ["todo"]
+///
+/// [[rules]]
+/// url_host = "github.com"
+/// tags = ["dev"]
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct RuleConfig {
+    /// Regular expression the memo text must match.
+    pub(crate) content: Option<String>,
+    /// The memo must link to this host or one of its subdomains.
+    pub(crate) url_host: Option<String>,
+    /// Tags added when the rule matches.
+    pub(crate) tags: Vec<String>,
+    /// Don't save matching memos at all.
+    pub(crate) ignore: bool,
+}
+
+pub(crate) fn load() -> Result<Config> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let raw = fs::read_to_string(&path)?;
+    toml::from_str(&raw).with_context(|| format!("invalid config file {}", path.display()))
+}
+
+pub(crate) fn data_dir() -> Result<PathBuf> {
     let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
     let dir = PathBuf::from(home).join(".capmind");
     fs::create_dir_all(&dir)?;
-    Ok(dir.join("capmind.db"))
+    Ok(dir)
+}
+
+/// Where `cap serve --listen` takes memos unless told otherwise.
+pub(crate) fn socket_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("cap.sock"))
+}
+
+pub(crate) fn db_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("capmind.db"))
+}
+
+pub(crate) fn attachments_dir() -> Result<PathBuf> {
+    let dir = data_dir()?.join("attachments");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Where database backups are kept; not created until something writes there.
+pub(crate) fn backups_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("backups"))
+}
+
+pub(crate) fn config_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("config.toml"))
 }
@@ -0,0 +1,157 @@
+use anyhow::{Result, anyhow, bail};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore},
+};
+use std::env;
+
+use crate::{
+    db::{Db, get_kv, set_kv},
+    domain::memo::Memo,
+};
+
+const SALT_KEY: &str = "crypto_salt";
+const CHECK_KEY: &str = "crypto_check";
+const CHECK_PLAINTEXT: &str = "cap-private-memo";
+const NONCE_LEN: usize = 12;
+pub(crate) const LOCKED_PLACEHOLDER: &str = "[locked]";
+
+#[derive(Clone)]
+pub(crate) struct MemoKey(Key);
+
+/// Whether a passphrase for private memos has been chosen yet.
+pub(crate) fn is_set_up(db: &Db) -> Result<bool> {
+    Ok(get_kv(db, CHECK_KEY)?.is_some())
+}
+
+/// Chooses the passphrase private memos are encrypted with: records a fresh
+/// salt and a check value later unlocks compare against. Only done once, so a
+/// mistyped passphrase can't lock away memos written with the old one.
+pub(crate) fn set_up(db: &Db, passphrase: &str) -> Result<MemoKey> {
+    if is_set_up(db)? {
+        bail!("a passphrase is already set; unlock with it instead");
+    }
+    if passphrase.is_empty() {
+        bail!("the passphrase can't be empty");
+    }
+    let mut salt = vec![0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    db.atomically(|db| {
+        set_kv(db, SALT_KEY, &STANDARD.encode(&salt))?;
+        set_kv(db, CHECK_KEY, &encrypt(&key, CHECK_PLAINTEXT)?)
+    })?;
+    Ok(key)
+}
+
+/// Derives the memo key from `passphrase`, rejecting passphrases that don't
+/// match the one chosen with [`set_up`].
+pub(crate) fn unlock(db: &Db, passphrase: &str) -> Result<MemoKey> {
+    let (Some(salt), Some(check)) = (get_kv(db, SALT_KEY)?, get_kv(db, CHECK_KEY)?) else {
+        bail!("no passphrase set yet; choose one with cap passphrase");
+    };
+    let key = derive_key(passphrase, &STANDARD.decode(salt)?)?;
+    if decrypt(&key, &check).ok().as_deref() != Some(CHECK_PLAINTEXT) {
+        bail!("wrong passphrase");
+    }
+    Ok(key)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<MemoKey> {
+    let mut key_bytes = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key_bytes)
+        .map_err(|err| anyhow!("failed to derive key: {}", err))?;
+    Ok(MemoKey(Key::from(key_bytes)))
+}
+
+pub(crate) fn encrypt(key: &MemoKey, plaintext: &str) -> Result<String> {
+    let cipher = ChaCha20Poly1305::new(&key.0);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow!("failed to encrypt memo"))?;
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(payload))
+}
+
+pub(crate) fn decrypt(key: &MemoKey, encoded: &str) -> Result<String> {
+    let payload = STANDARD.decode(encoded)?;
+    if payload.len() < NONCE_LEN {
+        bail!("encrypted memo is truncated");
+    }
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&key.0);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("failed to decrypt memo"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Replaces private memo content with plaintext when a key is available, or
+/// with the locked placeholder otherwise.
+pub(crate) fn reveal_memos(memos: &mut [Memo], key: Option<&MemoKey>) {
+    for memo in memos.iter_mut().filter(|memo| memo.private) {
+        memo.content = key
+            .and_then(|key| decrypt(key, &memo.content).ok())
+            .unwrap_or_else(|| LOCKED_PLACEHOLDER.to_string());
+    }
+}
+
+/// Reads the passphrase from `CAP_PASSPHRASE`, falling back to a hidden prompt.
+pub(crate) fn read_passphrase() -> Result<String> {
+    if let Ok(passphrase) = env::var("CAP_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    Ok(rpassword::prompt_password("Passphrase: ")?)
+}
+
+/// Asks for a new passphrase twice, so a typo can't become the only key to
+/// private memos. `CAP_PASSPHRASE` is taken as is.
+pub(crate) fn read_new_passphrase() -> Result<String> {
+    if let Ok(passphrase) = env::var("CAP_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("New passphrase: ")?;
+    if rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        bail!("the passphrases don't match; nothing was set");
+    }
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memos_round_trip_only_with_the_chosen_passphrase() {
+        let db = Db::open_in_memory().unwrap();
+        assert!(unlock(&db, "secret").is_err());
+        let key = set_up(&db, "secret").unwrap();
+        assert!(set_up(&db, "other").is_err());
+
+        let ciphertext = encrypt(&key, "meet at noon").unwrap();
+        assert_ne!(ciphertext, "meet at noon");
+        let key = unlock(&db, "secret").unwrap();
+        assert_eq!(decrypt(&key, &ciphertext).unwrap(), "meet at noon");
+
+        let err = unlock(&db, "Secret").err().unwrap();
+        assert_eq!(err.to_string(), "wrong passphrase");
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let db = Db::open_in_memory().unwrap();
+        let key = set_up(&db, "secret").unwrap();
+        let mut payload = STANDARD
+            .decode(encrypt(&key, "meet at noon").unwrap())
+            .unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        assert!(decrypt(&key, &STANDARD.encode(&payload)).is_err());
+        assert!(decrypt(&key, &STANDARD.encode([0u8; 4])).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{Result, anyhow, bail};
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Weekday,
+};
+
+/// Parses a calendar date typed by a human, relative to `today`.
+///
+/// Accepts ISO dates (`2024-03-01`), `today`, `yesterday`, weekday names
+/// (`monday`, `last monday`), `N days|weeks|months|years ago` (or the short
+/// `30d`, `2w`, `6mo`, `1y`), and month-day
+/// dates (`jan 3`, `3 jan`, `jan 3 2024`). A month-day without a year means
+/// the most recent one, so `dec 31` typed in January is last year's.
+pub(crate) fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase().replace(',', " ");
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    match words.as_slice() {
+        ["today"] => Some(today),
+        ["yesterday"] => Some(today - Duration::days(1)),
+        ["last", "week"] => Some(today - Duration::weeks(1)),
+        ["last", "month"] => today.checked_sub_months(Months::new(1)),
+        ["last", "year"] => today.checked_sub_months(Months::new(12)),
+        [day] | ["last", day] if parse_weekday(day).is_some() => {
+            parse_weekday(day).map(|weekday| previous_weekday(today, weekday))
+        }
+        [amount, unit, "ago"] => {
+            let amount: u32 = amount.parse().ok()?;
+            match unit.trim_end_matches('s') {
+                "day" => today.checked_sub_signed(Duration::try_days(i64::from(amount))?),
+                "week" => today.checked_sub_signed(Duration::try_weeks(i64::from(amount))?),
+                "month" => today.checked_sub_months(Months::new(amount)),
+                "year" => today.checked_sub_months(Months::new(amount.checked_mul(12)?)),
+                _ => None,
+            }
+        }
+        [short] if short.starts_with(|ch: char| ch.is_ascii_digit()) => {
+            let split = short.find(|ch: char| !ch.is_ascii_digit())?;
+            let (amount, unit) = short.split_at(split);
+            let amount: u32 = amount.parse().ok()?;
+            match unit {
+                "d" => today.checked_sub_signed(Duration::try_days(i64::from(amount))?),
+                "w" => today.checked_sub_signed(Duration::try_weeks(i64::from(amount))?),
+                "mo" => today.checked_sub_months(Months::new(amount)),
+                "y" => today.checked_sub_months(Months::new(amount.checked_mul(12)?)),
+                _ => None,
+            }
+        }
+        [first, second] => {
+            let (month, day) = month_and_day(first, second)?;
+            most_recent_month_day(today, month, day)
+        }
+        [first, second, year] => {
+            let (month, day) = month_and_day(first, second)?;
+            NaiveDate::from_ymd_opt(year.parse().ok()?, month, day)
+        }
+        _ => None,
+    }
+}
+
+/// Like [`parse_date`], but for command-line flags: returns an error naming the
+/// flag's value when it can't be understood.
+pub(crate) fn parse_date_arg(input: &str) -> Result<NaiveDate> {
+    parse_date(input, Local::now().date_naive()).ok_or_else(|| {
+        anyhow!(
+            "unknown date `{}` (try 2024-03-01, yesterday, last tuesday, 2 weeks ago, 30d or jan 3)",
+            input
+        )
+    })
+}
+
+/// Parses a point in time: RFC 3339, `2024-03-01 14:30`, or any [`parse_date`]
+/// phrase optionally followed by a time (`yesterday 9am`, `jan 3 18:00`). A bare
+/// time means today; a bare date means its local midnight.
+pub(crate) fn parse_datetime(input: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let input = input.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Some(timestamp.with_timezone(&Local));
+    }
+    for pattern in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, pattern) {
+            return Some(local_datetime(naive));
+        }
+    }
+    if input.eq_ignore_ascii_case("now") {
+        return Some(now);
+    }
+
+    let today = now.date_naive();
+    let (date_part, time) = match input.rsplit_once(char::is_whitespace) {
+        Some((rest, last)) => match parse_time(last) {
+            Some(time) => (rest.trim(), Some(time)),
+            None => (input, None),
+        },
+        None => match parse_time(input) {
+            Some(time) => return Some(local_datetime(today.and_time(time))),
+            None => (input, None),
+        },
+    };
+    let date = parse_date(date_part, today)?;
+    Some(local_datetime(
+        date.and_time(time.unwrap_or(NaiveTime::MIN)),
+    ))
+}
+
+/// [`parse_datetime`] for command-line flags; rejects times in the future.
+pub(crate) fn parse_datetime_arg(input: &str) -> Result<DateTime<Local>> {
+    let now = Local::now();
+    let Some(timestamp) = parse_datetime(input, now) else {
+        bail!(
+            "unknown date/time `{}` (try 2024-03-01 14:30, yesterday 9am or jan 3)",
+            input
+        );
+    };
+    if timestamp > now {
+        bail!("`{}` is in the future", input);
+    }
+    Ok(timestamp)
+}
+
+/// Parses when a reminder should fire: `in 30m`, `in 2h`, `in 3d`, or any
+/// [`parse_datetime`] input. A bare time already past today means tomorrow.
+pub(crate) fn parse_reminder(input: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let input = input.trim().to_lowercase();
+    if let Some(offset) = input.strip_prefix("in ") {
+        let offset = offset.trim();
+        let split = offset.find(|ch: char| !ch.is_ascii_digit())?;
+        let (amount, unit) = offset.split_at(split);
+        let amount: i64 = amount.parse().ok()?;
+        let offset = match unit.trim() {
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(amount)?,
+            "h" | "hour" | "hours" => Duration::try_hours(amount)?,
+            "d" | "day" | "days" => Duration::try_days(amount)?,
+            _ => return None,
+        };
+        return now.checked_add_signed(offset);
+    }
+    let at = parse_datetime(&input, now)?;
+    match parse_time(&input) {
+        Some(_) if at <= now => at.checked_add_signed(Duration::days(1)),
+        _ => Some(at),
+    }
+}
+
+/// Local midnight at the start of `date`.
+pub(crate) fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    local_datetime(date.and_time(NaiveTime::MIN))
+}
+
+/// Resolves a wall-clock time, taking the earlier reading across a DST overlap.
+fn local_datetime(naive: NaiveDateTime) -> DateTime<Local> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&naive))
+}
+
+/// `14:30`, `14:30:05`, `9am`, `9:15pm`.
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let value = value.to_lowercase();
+    for pattern in ["%H:%M:%S", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(&value, pattern) {
+            return Some(time);
+        }
+    }
+    let (clock, is_pm) = if let Some(clock) = value.strip_suffix("am") {
+        (clock, false)
+    } else {
+        (value.strip_suffix("pm")?, true)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    let hour = match (hour, is_pm) {
+        (12, false) => 0,
+        (12, true) => 12,
+        (hour, true) => hour + 12,
+        (hour, false) => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn month_and_day(first: &str, second: &str) -> Option<(u32, u32)> {
+    match (parse_month(first), parse_month(second)) {
+        (Some(month), None) => Some((month, parse_day(second)?)),
+        (None, Some(month)) => Some((month, parse_day(first)?)),
+        _ => None,
+    }
+}
+
+fn parse_day(value: &str) -> Option<u32> {
+    let digits = value.trim_end_matches(|ch: char| ch.is_ascii_alphabetic());
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+fn parse_month(value: &str) -> Option<u32> {
+    let months = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    if value.len() < 3 {
+        return None;
+    }
+    months
+        .iter()
+        .position(|month| value.starts_with(month))
+        .map(|idx| idx as u32 + 1)
+}
+
+fn most_recent_month_day(today: NaiveDate, month: u32, day: u32) -> Option<NaiveDate> {
+    match NaiveDate::from_ymd_opt(today.year(), month, day) {
+        Some(date) if date <= today => Some(date),
+        _ => NaiveDate::from_ymd_opt(today.year() - 1, month, day),
+    }
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// The most recent `weekday` strictly before `today`.
+fn previous_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let today_index = i64::from(today.weekday().num_days_from_monday());
+    let target_index = i64::from(weekday.num_days_from_monday());
+    let mut days_back = (today_index - target_index).rem_euclid(7);
+    if days_back == 0 {
+        days_back = 7;
+    }
+    today - Duration::days(days_back)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// A Wednesday.
+    fn today() -> NaiveDate {
+        date("2024-05-15")
+    }
+
+    fn parsed(input: &str) -> Option<NaiveDate> {
+        parse_date(input, today())
+    }
+
+    #[test]
+    fn relative_phrases_count_back_from_today() {
+        assert_eq!(parsed(" Today "), Some(today()));
+        assert_eq!(parsed("yesterday"), Some(date("2024-05-14")));
+        assert_eq!(parsed("3 days ago"), Some(date("2024-05-12")));
+        assert_eq!(parsed("1 week ago"), Some(date("2024-05-08")));
+        assert_eq!(parsed("30d"), Some(date("2024-04-15")));
+        assert_eq!(parsed("2w"), Some(date("2024-05-01")));
+        assert_eq!(parsed("6mo"), Some(date("2023-11-15")));
+        assert_eq!(parsed("1y"), Some(date("2023-05-15")));
+        assert_eq!(parsed("last month"), Some(date("2024-04-15")));
+        assert_eq!(parsed("99999999d"), None);
+        assert_eq!(parsed("99999999 weeks ago"), None);
+        assert_eq!(parsed("5x"), None);
+        assert_eq!(parsed("soon"), None);
+    }
+
+    #[test]
+    fn weekdays_mean_the_last_one_before_today() {
+        assert_eq!(parsed("monday"), Some(date("2024-05-13")));
+        assert_eq!(parsed("last fri"), Some(date("2024-05-10")));
+        // Today's own weekday means a week ago, not today.
+        assert_eq!(parsed("wednesday"), Some(date("2024-05-08")));
+    }
+
+    #[test]
+    fn month_days_without_a_year_are_the_most_recent() {
+        assert_eq!(parsed("2024-03-01"), Some(date("2024-03-01")));
+        assert_eq!(parsed("jan 3"), Some(date("2024-01-03")));
+        assert_eq!(parsed("3rd January"), Some(date("2024-01-03")));
+        assert_eq!(parsed("dec 31"), Some(date("2023-12-31")));
+        assert_eq!(parsed("mar 3, 2021"), Some(date("2021-03-03")));
+        assert_eq!(parsed("feb 30"), None);
+        assert_eq!(
+            parse_date("feb 29", date("2025-03-01")),
+            Some(date("2024-02-29"))
+        );
+    }
+
+    #[test]
+    fn times_follow_an_optional_date() {
+        let now = local_datetime(today().and_hms_opt(12, 0, 0).unwrap());
+        let at = |input: &str| parse_datetime(input, now).map(|value| value.naive_local());
+        let on = |day: &str, hms: (u32, u32)| Some(date(day).and_hms_opt(hms.0, hms.1, 0).unwrap());
+        assert_eq!(at("9am"), on("2024-05-15", (9, 0)));
+        assert_eq!(at("yesterday 9:15pm"), on("2024-05-14", (21, 15)));
+        assert_eq!(at("jan 3 18:00"), on("2024-01-03", (18, 0)));
+        assert_eq!(at("2024-03-01 14:30"), on("2024-03-01", (14, 30)));
+        assert_eq!(at("monday"), on("2024-05-13", (0, 0)));
+        assert_eq!(at("12am"), on("2024-05-15", (0, 0)));
+        assert_eq!(at("now"), Some(now.naive_local()));
+        assert_eq!(at("13pm"), None);
+    }
+
+    #[test]
+    fn reminders_point_forward() {
+        let now = local_datetime(today().and_hms_opt(12, 0, 0).unwrap());
+        let at = |input: &str| parse_reminder(input, now).map(|value| value.naive_local());
+        let on = |day: &str, hms: (u32, u32)| Some(date(day).and_hms_opt(hms.0, hms.1, 0).unwrap());
+        assert_eq!(at("in 30m"), on("2024-05-15", (12, 30)));
+        assert_eq!(at("in 2 hours"), on("2024-05-15", (14, 0)));
+        assert_eq!(at("in 1d"), on("2024-05-16", (12, 0)));
+        assert_eq!(at("6pm"), on("2024-05-15", (18, 0)));
+        assert_eq!(at("9am"), on("2024-05-16", (9, 0)));
+        assert_eq!(at("2024-06-01 09:00"), on("2024-06-01", (9, 0)));
+        assert_eq!(at("in a while"), None);
+        assert_eq!(at("in 99999999999d"), None);
+        assert_eq!(at("in 9999999999999999h"), None);
+    }
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::{db::Db, domain::memo::MemoId};
+
+/// Replaces the archived page text of a memo, making it searchable.
+pub(crate) fn set_archive_text(db: &Db, memo_id: &MemoId, text: &str) -> Result<()> {
+    db.conn().execute(
+        "DELETE FROM archives_fts WHERE memo_id = ?1",
+        params![memo_id.as_str()],
+    )?;
+    db.conn().execute(
+        "INSERT INTO archives_fts (memo_id, text) VALUES (?1, ?2)",
+        params![memo_id.as_str(), text],
+    )?;
+    Ok(())
+}
+
+/// Replaces the text read from a memo's images, making it searchable. Empty
+/// text just removes the old one.
+pub(crate) fn set_image_text(db: &Db, memo_id: &MemoId, text: &str) -> Result<()> {
+    db.conn().execute(
+        "DELETE FROM image_text_fts WHERE memo_id = ?1",
+        params![memo_id.as_str()],
+    )?;
+    if !text.is_empty() {
+        db.conn().execute(
+            "INSERT INTO image_text_fts (memo_id, text) VALUES (?1, ?2)",
+            params![memo_id.as_str(), text],
+        )?;
+    }
+    Ok(())
+}
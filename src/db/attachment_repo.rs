@@ -0,0 +1,48 @@
+use anyhow::Result;
+use chrono::Local;
+use rusqlite::params;
+use std::path::{Path, PathBuf};
+
+use crate::{db::Db, domain::memo::MemoId};
+
+/// Forgets a memo's attachment by file name; the file itself is left to the caller.
+pub(crate) fn remove_attachment(db: &Db, memo_id: &MemoId, file_name: &str) -> Result<()> {
+    db.conn().execute(
+        "DELETE FROM attachments WHERE memo_id = ?1 AND file_name = ?2",
+        params![memo_id.as_str(), file_name],
+    )?;
+    Ok(())
+}
+
+/// A memo's attachments as `(file_name, path)`, oldest first.
+pub(crate) fn fetch_attachments(db: &Db, memo_id: &MemoId) -> Result<Vec<(String, PathBuf)>> {
+    let mut stmt = db
+        .conn()
+        .prepare("SELECT file_name, path FROM attachments WHERE memo_id = ?1 ORDER BY id")?;
+    let rows = stmt.query_map(params![memo_id.as_str()], |row| {
+        Ok((row.get(0)?, PathBuf::from(row.get::<_, String>(1)?)))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+pub(crate) fn add_attachment(
+    db: &Db,
+    memo_id: &MemoId,
+    file_name: &str,
+    path: &Path,
+    size_bytes: u64,
+) -> Result<()> {
+    let now = Local::now().to_rfc3339();
+    db.conn().execute(
+        "INSERT INTO attachments (memo_id, file_name, path, size_bytes, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            memo_id.as_str(),
+            file_name,
+            path.to_string_lossy(),
+            size_bytes as i64,
+            now
+        ],
+    )?;
+    Ok(())
+}
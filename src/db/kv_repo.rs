@@ -13,7 +13,6 @@ pub(crate) fn set_kv(db: &Db, key: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
-#[allow(dead_code)]
 pub(crate) fn get_kv(db: &Db, key: &str) -> Result<Option<String>> {
     let mut stmt = db.conn().prepare("SELECT value FROM kv WHERE key = ?1")?;
     let mut rows = stmt.query(params![key])?;
@@ -24,7 +23,42 @@ pub(crate) fn get_kv(db: &Db, key: &str) -> Result<Option<String>> {
     }
 }
 
+pub(crate) fn delete_kv(db: &Db, key: &str) -> Result<()> {
+    db.conn()
+        .execute("DELETE FROM kv WHERE key = ?1", params![key])?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub(crate) fn get_auth_token(db: &Db) -> Result<Option<String>> {
     get_kv(db, "auth_access_token")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_can_be_set_replaced_and_deleted() {
+        let db = Db::open_in_memory().unwrap();
+        assert_eq!(get_kv(&db, "key").unwrap(), None);
+
+        set_kv(&db, "key", "first").unwrap();
+        assert_eq!(get_kv(&db, "key").unwrap().as_deref(), Some("first"));
+        set_kv(&db, "key", "second ✓").unwrap();
+        assert_eq!(get_kv(&db, "key").unwrap().as_deref(), Some("second ✓"));
+
+        delete_kv(&db, "key").unwrap();
+        assert_eq!(get_kv(&db, "key").unwrap(), None);
+        delete_kv(&db, "key").unwrap();
+    }
+
+    #[test]
+    fn keys_are_independent() {
+        let db = Db::open_in_memory().unwrap();
+        set_kv(&db, "a", "1").unwrap();
+        set_kv(&db, "b", "2").unwrap();
+        delete_kv(&db, "a").unwrap();
+        assert_eq!(get_kv(&db, "b").unwrap().as_deref(), Some("2"));
+    }
+}
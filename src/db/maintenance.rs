@@ -0,0 +1,56 @@
+use anyhow::{Result, bail};
+use rusqlite::{Connection, backup::Backup};
+use std::{fs, path::Path, time::Duration};
+
+use crate::db::Db;
+
+pub(crate) fn database_size(db: &Db) -> Result<u64> {
+    let page_count: i64 = db
+        .conn()
+        .query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = db
+        .conn()
+        .query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok((page_count * page_size) as u64)
+}
+
+/// Pages copied per backup step; other connections may write between steps.
+const BACKUP_PAGES_PER_STEP: i32 = 256;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
+
+/// Writes a consistent copy of the database to `dest`, which must not exist yet.
+/// SQLite's online backup copies the pages a step at a time, so it works in WAL
+/// mode and while the TUI or a server holds the database open, and starts over
+/// if one of them writes mid-copy.
+pub(super) fn backup_into(conn: &Connection, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        bail!("{} already exists", dest.display());
+    }
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut copy = Connection::open(dest)?;
+    let result = Backup::new(conn, &mut copy).and_then(|backup| {
+        backup.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)
+    });
+    drop(copy);
+    if let Err(err) = result {
+        // Don't leave a half-written copy that looks like a backup.
+        let _ = fs::remove_file(dest);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+pub(crate) fn backup(db: &Db, dest: &Path) -> Result<()> {
+    backup_into(db.conn(), dest)
+}
+
+pub(crate) fn vacuum(db: &Db) -> Result<()> {
+    db.conn().execute_batch(
+        "INSERT INTO memos_fts (memos_fts) VALUES ('optimize');
+         ANALYZE;
+         VACUUM;",
+    )?;
+    Ok(())
+}
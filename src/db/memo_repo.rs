@@ -1,52 +1,1426 @@
-use anyhow::Result;
-use chrono::Local;
-use rusqlite::params;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local};
+use rusqlite::{
+    params, params_from_iter,
+    types::{Type, Value, ValueRef},
+};
+use sha2::{Digest, Sha256};
+use std::{fmt::Write, ops::RangeInclusive};
 
 use crate::{
-    db::Db,
-    domain::memo::{Memo, MemoId, NewMemo},
+    db::{Db, mention_repo, meta_repo, tag_repo},
+    domain::{
+        location::{Location, METERS_PER_DEGREE},
+        memo::{self, Memo, MemoId, NewMemo},
+        mentions, tags,
+    },
+    format,
 };
 
 pub(crate) fn add_memo(db: &Db, new_memo: &NewMemo) -> Result<MemoId> {
     let now = Local::now().to_rfc3339();
+    let created_at = new_memo
+        .created_at
+        .map(|value| value.to_rfc3339())
+        .unwrap_or_else(|| now.clone());
+    let content = memo::normalize_content(&new_memo.content);
+    let stored = stored_content(db, &content, new_memo.private)?;
     let memo_id = MemoId::new();
-    db.conn().execute(
-        "INSERT INTO memos (
-            memo_id,
-            content,
-            created_at,
-            updated_at,
-            deleted,
-            dirty,
-            server_rev
-        ) VALUES (?1, ?2, ?3, ?4, 0, 1, 0)",
-        params![memo_id.as_str(), &new_memo.content, now, now],
-    )?;
+    db.atomically(|db| {
+        db.conn().execute(
+            "INSERT INTO memos (
+                memo_id,
+                content,
+                created_at,
+                updated_at,
+                deleted,
+                dirty,
+                server_rev,
+                private,
+                timezone,
+                captured_at,
+                mood,
+                notebook,
+                latitude,
+                longitude,
+                content_hash
+            ) VALUES (?1, ?2, ?3, ?4, 0, 1, 0, ?5, ?6, ?4, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                memo_id.as_str(),
+                &stored,
+                created_at,
+                now,
+                new_memo.private,
+                format::local_timezone_name(),
+                new_memo.mood,
+                new_memo.notebook,
+                new_memo.location.map(|location| location.latitude),
+                new_memo.location.map(|location| location.longitude),
+                content_hash(&content)
+            ],
+        )?;
+        index_compressed(db, &memo_id, &stored, &content)?;
+        if !new_memo.private {
+            tag_repo::set_memo_tags(db, &memo_id, &tags::extract_tags(&content))?;
+            mention_repo::set_memo_mentions(db, &memo_id, &mentions::extract_mentions(&content))?;
+            tag_repo::add_explicit_tags(db, &memo_id, &new_memo.tags)?;
+            for (key, value) in &new_memo.meta {
+                meta_repo::set_meta(db, &memo_id, key, value)?;
+            }
+        }
+        Ok(())
+    })?;
     Ok(memo_id)
 }
 
+/// zstd's default level; pasted logs and articles shrink well at little cost.
+const ZSTD_LEVEL: i32 = 3;
+
+/// What goes in `memos.content`: the text itself, or its zstd bytes as a blob
+/// when it is over the configured size and compressing saves space. Private
+/// memos are ciphertext, which doesn't compress.
+fn stored_content(db: &Db, content: &str, private: bool) -> Result<Value> {
+    let threshold = db.compress_above_bytes;
+    if private || threshold == 0 || content.len() <= threshold {
+        return Ok(Value::Text(content.to_string()));
+    }
+    let compressed = zstd::encode_all(content.as_bytes(), ZSTD_LEVEL)?;
+    Ok(if compressed.len() < content.len() {
+        Value::Blob(compressed)
+    } else {
+        Value::Text(content.to_string())
+    })
+}
+
+/// The search index triggers only see text, so compressed memos are indexed
+/// here, from the text before it was compressed.
+fn index_compressed(db: &Db, memo_id: &MemoId, stored: &Value, content: &str) -> Result<()> {
+    if let Value::Blob(_) = stored {
+        db.conn().execute(
+            "INSERT INTO memos_fts (rowid, content)
+                SELECT id, ?2 FROM memos WHERE memo_id = ?1 AND private = 0 AND deleted = 0",
+            params![memo_id.as_str(), content],
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads `memos.content` at `idx`, decompressing it when it was stored compressed.
+pub(super) fn content_from_row(row: &rusqlite::Row<'_>, idx: usize) -> rusqlite::Result<String> {
+    let ValueRef::Blob(compressed) = row.get_ref(idx)? else {
+        return row.get(idx);
+    };
+    zstd::decode_all(compressed)
+        .map_err(Into::into)
+        .and_then(|bytes| String::from_utf8(bytes).map_err(Into::into))
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(idx, Type::Blob, err))
+}
+
+/// Hex SHA-256 of the stored content, kept in `memos.content_hash` so imports
+/// can spot identical memos through an index instead of comparing text.
+pub(super) fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// Whether any memo, deleted ones included, already has exactly this content.
+pub(crate) fn content_exists(db: &Db, content: &str) -> Result<bool> {
+    Ok(db.conn().query_row(
+        "SELECT EXISTS (SELECT 1 FROM memos WHERE content_hash = ?1)",
+        params![content_hash(&memo::normalize_content(content))],
+        |row| row.get(0),
+    )?)
+}
+
 pub(crate) fn fetch_memos(db: &Db, limit: Option<usize>) -> Result<Vec<Memo>> {
     let limit_value = limit.map(|value| value as i64).unwrap_or(-1);
     let mut stmt = db.conn().prepare(
-        "SELECT memo_id, created_at, updated_at, content
+        "SELECT memo_id, created_at, updated_at, content, private,
+            (SELECT group_concat(tag, ' ') FROM memo_tags
+             WHERE memo_tags.memo_id = memos.memo_id),
+            timezone, notebook, latitude, longitude, mood, dirty
          FROM memos
          WHERE deleted = 0
-         ORDER BY created_at DESC
+         ORDER BY julianday(created_at) DESC, id DESC
          LIMIT ?1",
     )?;
 
-    let rows = stmt.query_map(params![limit_value], |row| {
-        Ok(Memo {
-            memo_id: row.get::<_, String>(0)?.into(),
-            created_at: row.get(1)?,
-            updated_at: row.get(2)?,
-            content: row.get(3)?,
+    let rows = stmt.query_map(params![limit_value], memo_from_row)?;
+
+    let mut memos = Vec::new();
+    for row in rows {
+        memos.push(row?);
+    }
+    Ok(memos)
+}
+
+/// Queries shorter than a trigram can't use the FTS index and fall back to a scan.
+const FTS_MIN_QUERY_CHARS: usize = 3;
+
+/// Finds memos whose content, archived page, or image text contains `query`
+/// (case-insensitively), best matches first. Timestamps still match by
+/// substring so `2024-03` keeps working. Private memos are never indexed and
+/// are not returned.
+pub(crate) fn search_memos(db: &Db, query: &str, limit: Option<usize>) -> Result<Vec<Memo>> {
+    fetch_filtered_memos(
+        db,
+        &MemoFilter {
+            text: Some(query.to_string()),
+            limit,
+            ..MemoFilter::default()
+        },
+    )
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn memo_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Memo> {
+    Ok(Memo {
+        memo_id: row.get::<_, String>(0)?.into(),
+        created_at: row.get(1)?,
+        updated_at: row.get(2)?,
+        content: content_from_row(row, 3)?,
+        private: row.get(4)?,
+        tags: split_tags(row.get(5)?),
+        timezone: row.get(6)?,
+        notebook: row.get(7)?,
+        location: match (row.get(8)?, row.get(9)?) {
+            (Some(latitude), Some(longitude)) => Some(Location {
+                latitude,
+                longitude,
+            }),
+            _ => None,
+        },
+        mood: row.get(10)?,
+        dirty: row.get(11)?,
+    })
+}
+
+/// Narrows a memo listing. `since` is inclusive, `until` exclusive; `tag` is a
+/// normalized tag name; every `meta` pair must match; `near` is a point and a
+/// radius in meters.
+#[derive(Default)]
+pub(crate) struct MemoFilter {
+    /// Search text, matched case-insensitively against content, archived pages,
+    /// and image text, and by substring against timestamps so `2024-03` keeps
+    /// working. Results are ranked best match first, and private memos only
+    /// match by timestamp since they are never indexed.
+    pub(crate) text: Option<String>,
+    pub(crate) since: Option<DateTime<Local>>,
+    pub(crate) until: Option<DateTime<Local>>,
+    pub(crate) tag: Option<String>,
+    /// A normalized name the memo must `@mention`.
+    pub(crate) mention: Option<String>,
+    pub(crate) mood: Option<RangeInclusive<u8>>,
+    pub(crate) meta: Vec<(String, String)>,
+    pub(crate) notebook: Option<String>,
+    /// With `notebook`, also memos in no notebook at all.
+    pub(crate) unfiled: bool,
+    pub(crate) near: Option<(Location, f64)>,
+    /// Only memos without any tag.
+    pub(crate) untagged: bool,
+    /// Only memos with at least one attachment.
+    pub(crate) has_attachment: bool,
+    /// Leave out memos tagged `#archived`.
+    pub(crate) hide_archived: bool,
+    pub(crate) limit: Option<usize>,
+}
+
+/// Why a memo matched the search text, for `cap search --explain`.
+pub(crate) struct MatchReasons {
+    /// The content contains the text.
+    pub(crate) content: bool,
+    /// bm25 score of the content match, lower is better; `None` for short
+    /// queries, which are matched by a scan rather than the index.
+    pub(crate) content_score: Option<f64>,
+    /// The archived page contains the text.
+    pub(crate) archive: bool,
+    /// bm25 score of the archived page's match; `None` for short queries.
+    pub(crate) archive_score: Option<f64>,
+    pub(crate) image_text: bool,
+    pub(crate) timestamp: bool,
+}
+
+/// What `fetch_filtered_memos` runs and finds.
+pub(crate) struct FilterExplanation {
+    pub(crate) sql: String,
+    pub(crate) params: Vec<String>,
+    /// The FTS5 phrase the text is matched as; `None` when the text is too
+    /// short for the index and is matched with `LIKE` instead.
+    pub(crate) fts_phrase: Option<String>,
+    /// Matches in result order; reasons are present when there is search text.
+    pub(crate) matches: Vec<(Memo, Option<MatchReasons>)>,
+}
+
+pub(crate) fn fetch_filtered_memos(db: &Db, filter: &MemoFilter) -> Result<Vec<Memo>> {
+    Ok(run_filter(db, filter, false)?
+        .matches
+        .into_iter()
+        .map(|(memo, _)| memo)
+        .collect())
+}
+
+/// Runs the filter like `fetch_filtered_memos`, keeping the query and why
+/// each memo matched.
+pub(crate) fn explain_filtered_memos(db: &Db, filter: &MemoFilter) -> Result<FilterExplanation> {
+    run_filter(db, filter, true)
+}
+
+fn run_filter(db: &Db, filter: &MemoFilter, explain: bool) -> Result<FilterExplanation> {
+    let mut joins = String::new();
+    let mut reasons = String::new();
+    let mut fts_phrase = None;
+    let mut conditions = vec!["deleted = 0".to_string()];
+    let mut ranking = String::new();
+    let mut values: Vec<Value> = Vec::new();
+    let mut bind = |value: Value| {
+        values.push(value);
+        format!("?{}", values.len())
+    };
+    if let Some(text) = &filter.text {
+        let pattern = bind(Value::Text(format!("%{}%", escape_like(text))));
+        if text.chars().count() >= FTS_MIN_QUERY_CHARS {
+            // A quoted FTS5 string is matched as one phrase, i.e. a plain substring here.
+            let quoted = format!("\"{}\"", text.replace('"', "\"\""));
+            fts_phrase = Some(quoted.clone());
+            let phrase = bind(Value::Text(quoted));
+            joins = format!(
+                "LEFT JOIN (
+                    SELECT rowid, bm25(memos_fts) AS rank FROM memos_fts
+                    WHERE memos_fts MATCH {0}
+                 ) AS hits ON hits.rowid = memos.id
+                 LEFT JOIN (
+                    SELECT memo_id AS archived_id, bm25(archives_fts) AS rank FROM archives_fts
+                    WHERE archives_fts MATCH {0}
+                 ) AS archive_hits ON archive_hits.archived_id = memos.memo_id
+                 LEFT JOIN (
+                    SELECT memo_id AS imaged_id, bm25(image_text_fts) AS rank FROM image_text_fts
+                    WHERE image_text_fts MATCH {0}
+                 ) AS image_hits ON image_hits.imaged_id = memos.memo_id",
+                phrase
+            );
+            let image_text = "(private = 0 AND image_hits.imaged_id IS NOT NULL)";
+            conditions.push(format!(
+                "(hits.rowid IS NOT NULL
+                  OR archive_hits.archived_id IS NOT NULL
+                  OR {}
+                  OR created_at LIKE {} ESCAPE '\\')",
+                image_text, pattern
+            ));
+            ranking = "hits.rank IS NULL, hits.rank, archive_hits.rank IS NULL,
+                archive_hits.rank, image_hits.rank IS NULL, image_hits.rank, "
+                .to_string();
+            reasons = format!(
+                ", hits.rowid IS NOT NULL, hits.rank, archive_hits.archived_id IS NOT NULL,
+                 archive_hits.rank, {},
+                 created_at LIKE {} ESCAPE '\\'",
+                image_text, pattern
+            );
+        } else {
+            let scan = |table: &str| {
+                format!(
+                    "EXISTS (
+                        SELECT 1 FROM {0}
+                        WHERE {0}.memo_id = memos.memo_id AND {0}.text LIKE {1} ESCAPE '\\')",
+                    table, pattern
+                )
+            };
+            let archive_text = scan("archives_fts");
+            let image_text = format!("(private = 0 AND {})", scan("image_text_fts"));
+            // The index holds the text of public memos even when `memos.content`
+            // is compressed, so memo text is scanned there too.
+            let memo_text = format!(
+                "EXISTS (
+                    SELECT 1 FROM memos_fts
+                    WHERE memos_fts.rowid = memos.id AND memos_fts.content LIKE {} ESCAPE '\\')",
+                pattern
+            );
+            reasons = format!(
+                ", {1}, NULL, {2}, NULL, {3},
+                 created_at LIKE {0} ESCAPE '\\'",
+                pattern, memo_text, archive_text, image_text
+            );
+            conditions.push(format!(
+                "({1}
+                  OR {2}
+                  OR {3}
+                  OR created_at LIKE {0} ESCAPE '\\')",
+                pattern, memo_text, archive_text, image_text
+            ));
+        }
+    }
+    if let Some(since) = filter.since {
+        let placeholder = bind(Value::Text(since.to_rfc3339()));
+        conditions.push(format!(
+            "julianday(created_at) >= julianday({})",
+            placeholder
+        ));
+    }
+    if let Some(until) = filter.until {
+        let placeholder = bind(Value::Text(until.to_rfc3339()));
+        conditions.push(format!(
+            "julianday(created_at) < julianday({})",
+            placeholder
+        ));
+    }
+    if let Some(tag) = &filter.tag {
+        let placeholder = bind(Value::Text(tag.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM memo_tags
+                WHERE memo_tags.memo_id = memos.memo_id AND memo_tags.tag = {})",
+            placeholder
+        ));
+    }
+    if let Some(name) = &filter.mention {
+        let placeholder = bind(Value::Text(name.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM memo_mentions
+                WHERE memo_mentions.memo_id = memos.memo_id AND memo_mentions.name = {})",
+            placeholder
+        ));
+    }
+    if let Some(mood) = &filter.mood {
+        let low = bind(Value::Integer(i64::from(*mood.start())));
+        let high = bind(Value::Integer(i64::from(*mood.end())));
+        conditions.push(format!("mood BETWEEN {} AND {}", low, high));
+    }
+    if let Some(notebook) = &filter.notebook {
+        let placeholder = bind(Value::Text(notebook.clone()));
+        if filter.unfiled {
+            conditions.push(format!("(notebook = {} OR notebook IS NULL)", placeholder));
+        } else {
+            conditions.push(format!("notebook = {}", placeholder));
+        }
+    }
+    if let Some((center, radius)) = filter.near {
+        // A coarse box lets SQLite skip most rows; the exact distance is checked below.
+        let lat_span = radius / METERS_PER_DEGREE;
+        let low = bind(Value::Real(center.latitude - lat_span));
+        let high = bind(Value::Real(center.latitude + lat_span));
+        conditions.push(format!("latitude BETWEEN {} AND {}", low, high));
+        let lon_span = lat_span / center.latitude.to_radians().cos();
+        if (center.longitude - lon_span) > -180.0 && (center.longitude + lon_span) < 180.0 {
+            let low = bind(Value::Real(center.longitude - lon_span));
+            let high = bind(Value::Real(center.longitude + lon_span));
+            conditions.push(format!("longitude BETWEEN {} AND {}", low, high));
+        } else {
+            conditions.push("longitude IS NOT NULL".to_string());
+        }
+    }
+    if filter.untagged {
+        conditions.push(
+            "NOT EXISTS (SELECT 1 FROM memo_tags WHERE memo_tags.memo_id = memos.memo_id)"
+                .to_string(),
+        );
+    }
+    if filter.has_attachment {
+        conditions.push(
+            "EXISTS (SELECT 1 FROM attachments WHERE attachments.memo_id = memos.memo_id)"
+                .to_string(),
+        );
+    }
+    if filter.hide_archived {
+        let placeholder = bind(Value::Text(tags::ARCHIVED_TAG.to_string()));
+        conditions.push(format!(
+            "NOT EXISTS (SELECT 1 FROM memo_tags
+                WHERE memo_tags.memo_id = memos.memo_id AND memo_tags.tag = {})",
+            placeholder
+        ));
+    }
+    for (key, value) in &filter.meta {
+        let key = bind(Value::Text(key.clone()));
+        let value = bind(Value::Text(value.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM memo_meta
+                WHERE memo_meta.memo_id = memos.memo_id
+                  AND memo_meta.key = {} AND memo_meta.value = {})",
+            key, value
+        ));
+    }
+
+    if !explain {
+        reasons.clear();
+    }
+    let sql = format!(
+        "SELECT memo_id, created_at, updated_at, content, private,
+            (SELECT group_concat(tag, ' ') FROM memo_tags
+             WHERE memo_tags.memo_id = memos.memo_id),
+            timezone, notebook, latitude, longitude, mood, dirty{}
+         FROM memos
+         {}
+         WHERE {}
+         ORDER BY {}julianday(created_at) DESC, id DESC
+         {}",
+        reasons,
+        joins,
+        conditions.join(" AND "),
+        ranking,
+        // With `near`, rows are still dropped after the query, so limit afterwards.
+        match filter.limit {
+            Some(limit) if filter.near.is_none() => format!("LIMIT {}", limit),
+            _ => String::new(),
+        }
+    );
+    let mut stmt = db.conn().prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(&values), |row| {
+        let reasons = if reasons.is_empty() {
+            None
+        } else {
+            Some(MatchReasons {
+                content: row.get(12)?,
+                content_score: row.get(13)?,
+                archive: row.get(14)?,
+                archive_score: row.get(15)?,
+                image_text: row.get(16)?,
+                timestamp: row.get(17)?,
+            })
+        };
+        Ok((memo_from_row(row)?, reasons))
+    })?;
+
+    let mut matches = Vec::new();
+    for row in rows {
+        matches.push(row?);
+    }
+    if let Some((center, radius)) = filter.near {
+        matches.retain(|(memo, _)| {
+            memo.location
+                .is_some_and(|location| location.distance_to(&center) <= radius)
+        });
+    }
+    if let Some(limit) = filter.limit {
+        matches.truncate(limit);
+    }
+    Ok(FilterExplanation {
+        sql,
+        params: values.iter().map(show_value).collect(),
+        fts_phrase,
+        matches,
+    })
+}
+
+/// A bound parameter as SQL would spell it.
+fn show_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Real(value) => value.to_string(),
+        Value::Text(value) => format!("'{}'", value.replace('\'', "''")),
+        Value::Blob(value) => format!("<{} bytes>", value.len()),
+    }
+}
+
+fn split_tags(joined: Option<String>) -> Vec<String> {
+    joined
+        .map(|value| value.split(' ').map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) struct PendingCounts {
+    pub(crate) changes: usize,
+    pub(crate) deletions: usize,
+}
+
+pub(crate) fn count_pending_changes(db: &Db) -> Result<PendingCounts> {
+    let (changes, deletions) = db.conn().query_row(
+        "SELECT
+            COALESCE(SUM(CASE WHEN deleted = 0 THEN 1 ELSE 0 END), 0),
+            COALESCE(SUM(CASE WHEN deleted = 1 THEN 1 ELSE 0 END), 0)
+         FROM memos
+         WHERE dirty = 1",
+        [],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+    )?;
+    Ok(PendingCounts {
+        changes: changes as usize,
+        deletions: deletions as usize,
+    })
+}
+
+/// A memo, live or deleted, as it is sent to the server or exported.
+pub(crate) struct PendingMemo {
+    pub(crate) memo_id: String,
+    pub(crate) content: String,
+    pub(crate) created_at: String,
+    pub(crate) updated_at: String,
+    pub(crate) deleted: bool,
+    pub(crate) private: bool,
+    pub(crate) notebook: Option<String>,
+}
+
+/// Memos with changes the server hasn't seen yet.
+pub(crate) fn fetch_pending_memos(db: &Db) -> Result<Vec<PendingMemo>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT memo_id, content, created_at, updated_at, deleted, private, notebook
+         FROM memos
+         WHERE dirty = 1
+         ORDER BY id",
+    )?;
+    let rows = stmt.query_map([], pending_memo_from_row)?;
+    let mut memos = Vec::new();
+    for row in rows {
+        memos.push(row?);
+    }
+    Ok(memos)
+}
+
+/// Memos changed at or after `since`, deletions included, in the order they
+/// were added; every memo without `since`.
+pub(crate) fn fetch_changed_memos(
+    db: &Db,
+    since: Option<DateTime<Local>>,
+) -> Result<Vec<PendingMemo>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT memo_id, content, created_at, updated_at, deleted, private, notebook
+         FROM memos
+         WHERE ?1 IS NULL OR julianday(updated_at) >= julianday(?1)
+         ORDER BY id",
+    )?;
+    let rows = stmt.query_map(
+        params![since.map(|since| since.to_rfc3339())],
+        pending_memo_from_row,
+    )?;
+    let mut memos = Vec::new();
+    for row in rows {
+        memos.push(row?);
+    }
+    Ok(memos)
+}
+
+fn pending_memo_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<PendingMemo> {
+    Ok(PendingMemo {
+        memo_id: row.get(0)?,
+        content: content_from_row(row, 1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        deleted: row.get(4)?,
+        private: row.get(5)?,
+        notebook: row.get(6)?,
+    })
+}
+
+/// Clears the dirty flag of memos the server accepted and keeps the accepted
+/// text as the base the next merge starts from. A memo edited since it was
+/// read has a newer `updated_at` and stays dirty for the next sync.
+pub(crate) fn mark_synced(db: &Db, memos: &[PendingMemo]) -> Result<()> {
+    db.atomically(|db| {
+        let mut stmt = db.conn().prepare(
+            "UPDATE memos SET dirty = 0, server_rev = server_rev + 1, synced_content = ?3
+             WHERE memo_id = ?1 AND updated_at = ?2",
+        )?;
+        for memo in memos {
+            stmt.execute(params![memo.memo_id, memo.updated_at, memo.content])?;
+        }
+        Ok(())
+    })
+}
+
+/// A memo as the server has it.
+pub(crate) struct ServerMemo {
+    pub(crate) memo_id: String,
+    pub(crate) content: String,
+    pub(crate) created_at: String,
+    pub(crate) updated_at: String,
+    pub(crate) deleted: bool,
+    pub(crate) private: bool,
+    pub(crate) notebook: Option<String>,
+}
+
+/// The local side of a memo the server sent a newer version of.
+pub(crate) struct LocalSyncState {
+    pub(crate) content: String,
+    pub(crate) deleted: bool,
+    pub(crate) private: bool,
+    pub(crate) dirty: bool,
+    /// The text both sides last agreed on; `None` before the first sync.
+    pub(crate) synced_content: Option<String>,
+}
+
+pub(crate) fn fetch_sync_state(db: &Db, memo_id: &str) -> Result<Option<LocalSyncState>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT content, deleted, private, dirty, synced_content FROM memos WHERE memo_id = ?1",
+    )?;
+    let mut rows = stmt.query_map(params![memo_id], |row| {
+        Ok(LocalSyncState {
+            content: content_from_row(row, 0)?,
+            deleted: row.get(1)?,
+            private: row.get(2)?,
+            dirty: row.get(3)?,
+            synced_content: row.get(4)?,
         })
     })?;
+    Ok(rows.next().transpose()?)
+}
+
+/// Stores the server's version of a memo as the local one, adding it when it
+/// is new here. The memo is then in sync.
+pub(crate) fn store_remote_memo(db: &Db, memo: &ServerMemo) -> Result<()> {
+    let stored = stored_content(db, &memo.content, memo.private)?;
+    db.conn().execute(
+        "INSERT INTO memos (
+            memo_id, content, created_at, updated_at, deleted, dirty, server_rev, private,
+            captured_at, notebook, content_hash, synced_content
+        ) VALUES (?1, ?2, ?3, ?4, ?5, 0, 1, ?6, ?3, ?7, ?8, ?9)
+        ON CONFLICT (memo_id) DO UPDATE SET
+            content = excluded.content,
+            updated_at = excluded.updated_at,
+            deleted = excluded.deleted,
+            dirty = 0,
+            server_rev = server_rev + 1,
+            private = excluded.private,
+            notebook = excluded.notebook,
+            content_hash = excluded.content_hash,
+            synced_content = excluded.synced_content",
+        params![
+            memo.memo_id,
+            &stored,
+            memo.created_at,
+            memo.updated_at,
+            memo.deleted,
+            memo.private,
+            memo.notebook,
+            content_hash(&memo.content),
+            memo.content
+        ],
+    )?;
+    let memo_id = MemoId::from(memo.memo_id.clone());
+    index_compressed(db, &memo_id, &stored, &memo.content)?;
+    let tags = if memo.private || memo.deleted {
+        Vec::new()
+    } else {
+        tags::extract_tags(&memo.content)
+    };
+    tag_repo::set_memo_tags(db, &memo_id, &tags)
+}
+
+/// Replaces a memo's content with the merge of both sides' edits. It stays
+/// dirty so the merge reaches the server, and `synced_content` moves to the
+/// server's version, the base of the next merge.
+pub(crate) fn store_merged_content(
+    db: &Db,
+    memo_id: &str,
+    merged: &str,
+    synced_content: &str,
+) -> Result<()> {
+    let memo_id = MemoId::from(memo_id.to_string());
+    let stored = stored_content(db, merged, false)?;
+    db.conn().execute(
+        "UPDATE memos
+         SET content = ?2, content_hash = ?3, synced_content = ?4, updated_at = ?5, dirty = 1
+         WHERE memo_id = ?1",
+        params![
+            memo_id.as_str(),
+            &stored,
+            content_hash(merged),
+            synced_content,
+            Local::now().to_rfc3339()
+        ],
+    )?;
+    index_compressed(db, &memo_id, &stored, merged)?;
+    tag_repo::set_memo_tags(db, &memo_id, &tags::extract_tags(merged))
+}
 
+/// Tombstones [`purge_tombstones`] would remove, for asking before it does.
+pub(crate) fn count_purgeable_tombstones(
+    db: &Db,
+    deleted_before: DateTime<Local>,
+) -> Result<usize> {
+    let count: i64 = db.conn().query_row(
+        "SELECT COUNT(*) FROM memos
+         WHERE deleted = 1
+           AND dirty = 0
+           AND julianday(updated_at) < julianday(?1)",
+        params![deleted_before.to_rfc3339()],
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
+pub(crate) fn purge_tombstones(db: &Db, deleted_before: DateTime<Local>) -> Result<usize> {
+    let purged = db.conn().execute(
+        "DELETE FROM memos
+         WHERE deleted = 1
+           AND dirty = 0
+           AND julianday(updated_at) < julianday(?1)",
+        params![deleted_before.to_rfc3339()],
+    )?;
+    Ok(purged)
+}
+
+pub(crate) fn fetch_created_at(db: &Db) -> Result<Vec<String>> {
+    let mut stmt = db
+        .conn()
+        .prepare("SELECT created_at FROM memos WHERE deleted = 0")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut values = Vec::new();
+    for row in rows {
+        values.push(row?);
+    }
+    Ok(values)
+}
+
+/// Resolves a full memo id or a unique prefix of one, as shown by `cap list --ids`.
+pub(crate) fn fetch_memo_by_id_prefix(db: &Db, prefix: &str) -> Result<Memo> {
+    let mut matches = fetch_memos_by_id_prefix(db, prefix, 2)?;
+    match matches.len() {
+        0 => bail!("no memo with id `{}`", prefix),
+        1 => Ok(matches.remove(0)),
+        _ => bail!("memo id `{}` is ambiguous; type more of it", prefix),
+    }
+}
+
+/// Live memos whose id starts with `prefix`, newest first.
+pub(crate) fn fetch_memos_by_id_prefix(db: &Db, prefix: &str, limit: usize) -> Result<Vec<Memo>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT memo_id, created_at, updated_at, content, private,
+            (SELECT group_concat(tag, ' ') FROM memo_tags
+             WHERE memo_tags.memo_id = memos.memo_id),
+            timezone, notebook, latitude, longitude, mood, dirty
+         FROM memos
+         WHERE deleted = 0 AND memo_id LIKE ?1 ESCAPE '\\'
+         ORDER BY julianday(created_at) DESC, id DESC
+         LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(
+        params![format!("{}%", escape_like(prefix)), limit as i64],
+        memo_from_row,
+    )?;
     let mut memos = Vec::new();
     for row in rows {
         memos.push(row?);
     }
     Ok(memos)
 }
+
+/// Replaces a memo's content and re-extracts its inline tags.
+pub(crate) fn update_memo_content(db: &Db, memo_id: &MemoId, content: &str) -> Result<()> {
+    let content = &memo::normalize_content(content);
+    let stored = stored_content(db, content, false)?;
+    db.atomically(|db| {
+        db.conn().execute(
+            "UPDATE memos SET content = ?2, content_hash = ?3, updated_at = ?4, dirty = 1
+             WHERE memo_id = ?1 AND deleted = 0",
+            params![
+                memo_id.as_str(),
+                &stored,
+                content_hash(content),
+                Local::now().to_rfc3339()
+            ],
+        )?;
+        index_compressed(db, memo_id, &stored, content)?;
+        tag_repo::set_memo_tags(db, memo_id, &tags::extract_tags(content))?;
+        mention_repo::set_memo_mentions(db, memo_id, &mentions::extract_mentions(content))?;
+        Ok(())
+    })
+}
+
+/// Marks a memo deleted; the tombstone syncs before `cap purge` removes it.
+pub(crate) fn delete_memo(db: &Db, memo_id: &MemoId) -> Result<()> {
+    db.conn().execute(
+        "UPDATE memos SET deleted = 1, updated_at = ?2, dirty = 1
+         WHERE memo_id = ?1 AND deleted = 0",
+        params![memo_id.as_str(), Local::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// `(created_at, mood)` for every live memo that has a mood rating.
+pub(crate) fn fetch_moods(db: &Db) -> Result<Vec<(String, u8)>> {
+    let mut stmt = db
+        .conn()
+        .prepare("SELECT created_at, mood FROM memos WHERE deleted = 0 AND mood IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let mut moods = Vec::new();
+    for row in rows {
+        moods.push(row?);
+    }
+    Ok(moods)
+}
+
+/// Notebook names with their live memo counts, alphabetically.
+pub(crate) fn fetch_notebook_counts(db: &Db) -> Result<Vec<(String, usize)>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT notebook, COUNT(*) FROM memos
+         WHERE deleted = 0 AND notebook IS NOT NULL
+         GROUP BY notebook
+         ORDER BY notebook",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+
+    let mut counts = Vec::new();
+    for row in rows {
+        counts.push(row?);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::db::tag_repo::replace_explicit_tags;
+
+    fn add(db: &Db, content: &str) -> MemoId {
+        add_memo(db, &NewMemo::new(content)).unwrap()
+    }
+
+    /// Adds a memo with a literal `created_at`, offsets included.
+    fn add_at(db: &Db, content: &str, created_at: &str) -> MemoId {
+        let memo_id = add(db, content);
+        db.conn()
+            .execute(
+                "UPDATE memos SET created_at = ?2 WHERE memo_id = ?1",
+                params![memo_id.as_str(), created_at],
+            )
+            .unwrap();
+        memo_id
+    }
+
+    fn contents(memos: &[Memo]) -> Vec<&str> {
+        memos.iter().map(|memo| memo.content.as_str()).collect()
+    }
+
+    #[test]
+    fn long_content_is_stored_compressed_and_read_back_as_text() {
+        let mut db = Db::open_in_memory().unwrap();
+        db.set_compress_above(64);
+        let stored_type = |db: &Db, memo_id: &MemoId| -> String {
+            db.conn()
+                .query_row(
+                    "SELECT typeof(content) FROM memos WHERE memo_id = ?1",
+                    params![memo_id.as_str()],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        let log = "ERROR connection reset by peer #ops\n".repeat(40);
+        let memo_id = add(&db, &log);
+        assert_eq!(stored_type(&db, &memo_id), "blob");
+        assert_eq!(stored_type(&db, &add(&db, "short")), "text");
+
+        assert_eq!(
+            fetch_memos(&db, Some(2)).unwrap()[1].content,
+            log.trim_end()
+        );
+        assert_eq!(fetch_pending_memos(&db).unwrap()[0].content, log.trim_end());
+        for query in ["connection reset", "ER"] {
+            assert_eq!(
+                search_memos(&db, query, None).unwrap().len(),
+                1,
+                "{}",
+                query
+            );
+        }
+        update_memo_content(&db, &memo_id, &format!("{}edited", log)).unwrap();
+        assert_eq!(stored_type(&db, &memo_id), "blob");
+        assert_eq!(search_memos(&db, "edited", None).unwrap().len(), 1);
+        assert!(
+            replace_explicit_tags(&db, &memo_id, &[])
+                .unwrap()
+                .contains(&"ops".to_string())
+        );
+        delete_memo(&db, &memo_id).unwrap();
+        assert!(
+            search_memos(&db, "connection reset", None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn unicode_content_round_trips() {
+        let db = Db::open_in_memory().unwrap();
+        let content = "今日は晴れ 🌤️ — שלום, naïve café #日記";
+        add(&db, content);
+
+        let memos = fetch_memos(&db, None).unwrap();
+        assert_eq!(memos[0].content, content);
+        assert_eq!(memos[0].tags, ["日記"]);
+        assert_eq!(
+            contents(&search_memos(&db, "晴れ 🌤", None).unwrap()),
+            [content]
+        );
+        // Two characters is below the trigram size and takes the LIKE path.
+        assert_eq!(
+            contents(&search_memos(&db, "今日", None).unwrap()),
+            [content]
+        );
+        assert_eq!(
+            contents(&search_memos(&db, "CAFÉ", None).unwrap()),
+            [content]
+        );
+    }
+
+    #[test]
+    fn windows_line_endings_are_stored_as_unix_ones() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = add(&db, "line one\r\nline two\r\n");
+        assert_eq!(
+            fetch_memos(&db, None).unwrap()[0].content,
+            "line one\nline two"
+        );
+        assert!(content_exists(&db, "line one\nline two\n").unwrap());
+
+        update_memo_content(&db, &memo_id, "edited\r\n").unwrap();
+        assert_eq!(fetch_memos(&db, None).unwrap()[0].content, "edited");
+    }
+
+    #[test]
+    fn newest_memos_come_first_across_utc_offsets() {
+        let db = Db::open_in_memory().unwrap();
+        add_at(&db, "tokyo morning", "2024-05-02T06:00:00+09:00");
+        add_at(&db, "london night", "2024-05-01T22:00:00+00:00");
+        add_at(&db, "new york evening", "2024-05-01T20:00:00-04:00");
+
+        assert_eq!(
+            contents(&fetch_memos(&db, None).unwrap()),
+            ["new york evening", "london night", "tokyo morning"]
+        );
+    }
+
+    #[test]
+    fn memos_created_at_the_same_instant_keep_insertion_order() {
+        let db = Db::open_in_memory().unwrap();
+        for content in ["first", "second", "third"] {
+            add_at(&db, content, "2024-05-01T12:00:00+00:00");
+        }
+        assert_eq!(
+            contents(&fetch_memos(&db, None).unwrap()),
+            ["third", "second", "first"]
+        );
+    }
+
+    #[test]
+    fn limits_apply_after_ordering() {
+        let db = Db::open_in_memory().unwrap();
+        for day in 1..=5 {
+            add_at(
+                &db,
+                &format!("day {}", day),
+                &format!("2024-05-0{}T12:00:00+00:00", day),
+            );
+        }
+        assert_eq!(
+            contents(&fetch_memos(&db, Some(2)).unwrap()),
+            ["day 5", "day 4"]
+        );
+        assert!(fetch_memos(&db, Some(0)).unwrap().is_empty());
+        assert_eq!(search_memos(&db, "day", Some(3)).unwrap().len(), 3);
+        let filter = MemoFilter {
+            limit: Some(1),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &filter).unwrap()),
+            ["day 5"]
+        );
+    }
+
+    #[test]
+    fn deleted_memos_become_pending_tombstones() {
+        let db = Db::open_in_memory().unwrap();
+        let kept = add(&db, "kept memo");
+        let gone = add(&db, "gone memo");
+        delete_memo(&db, &gone).unwrap();
+
+        assert_eq!(contents(&fetch_memos(&db, None).unwrap()), ["kept memo"]);
+        assert!(search_memos(&db, "gone memo", None).unwrap().is_empty());
+        assert!(fetch_memo_by_id_prefix(&db, gone.as_str()).is_err());
+        let pending = count_pending_changes(&db).unwrap();
+        assert_eq!((pending.changes, pending.deletions), (1, 1));
+
+        // Editing a tombstone must not bring it back.
+        update_memo_content(&db, &gone, "revived?").unwrap();
+        assert_eq!(fetch_memos(&db, None).unwrap().len(), 1);
+        assert_eq!(
+            fetch_memo_by_id_prefix(&db, kept.as_str()).unwrap().content,
+            "kept memo"
+        );
+    }
+
+    #[test]
+    fn only_synced_old_tombstones_are_purged() {
+        let db = Db::open_in_memory().unwrap();
+        let synced = add(&db, "synced");
+        let unsynced = add(&db, "unsynced");
+        delete_memo(&db, &synced).unwrap();
+        delete_memo(&db, &unsynced).unwrap();
+        db.conn()
+            .execute(
+                "UPDATE memos SET dirty = 0 WHERE memo_id = ?1",
+                params![synced.as_str()],
+            )
+            .unwrap();
+
+        assert_eq!(
+            purge_tombstones(&db, Local::now() - Duration::days(1)).unwrap(),
+            0
+        );
+        let tomorrow = Local::now() + Duration::days(1);
+        assert_eq!(count_purgeable_tombstones(&db, tomorrow).unwrap(), 1);
+        assert_eq!(purge_tombstones(&db, tomorrow).unwrap(), 1);
+        assert_eq!(count_pending_changes(&db).unwrap().deletions, 1);
+    }
+
+    #[test]
+    fn private_memos_are_listed_but_never_searched() {
+        let db = Db::open_in_memory().unwrap();
+        add_memo(&db, &NewMemo::encrypted("ciphertext-secret")).unwrap();
+
+        let memos = fetch_memos(&db, None).unwrap();
+        assert!(memos[0].private);
+        assert!(memos[0].tags.is_empty());
+        assert!(search_memos(&db, "secret", None).unwrap().is_empty());
+        assert!(search_memos(&db, "ci", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_treats_wildcards_and_quotes_literally() {
+        let db = Db::open_in_memory().unwrap();
+        add(&db, "100% done");
+        add(&db, "snake_case");
+        add(&db, "say \"hi\"");
+        add(&db, "1000 done");
+
+        assert_eq!(
+            contents(&search_memos(&db, "0%", None).unwrap()),
+            ["100% done"]
+        );
+        assert_eq!(
+            contents(&search_memos(&db, "e_c", None).unwrap()),
+            ["snake_case"]
+        );
+        assert_eq!(
+            contents(&search_memos(&db, "\"hi\"", None).unwrap()),
+            ["say \"hi\""]
+        );
+    }
+
+    #[test]
+    fn id_prefixes_resolve_only_when_unique() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = add(&db, "only one");
+        assert_eq!(
+            fetch_memo_by_id_prefix(&db, &memo_id.as_str()[..8])
+                .unwrap()
+                .content,
+            "only one"
+        );
+        add(&db, "another");
+        assert!(fetch_memo_by_id_prefix(&db, "").is_err());
+        assert!(fetch_memo_by_id_prefix(&db, "not-an-id").is_err());
+    }
+
+    #[test]
+    fn id_prefix_candidates_skip_deleted_memos() {
+        let db = Db::open_in_memory().unwrap();
+        let kept = add(&db, "kept");
+        let deleted = add(&db, "deleted");
+        delete_memo(&db, &deleted).unwrap();
+        assert_eq!(
+            contents(&fetch_memos_by_id_prefix(&db, "", 10).unwrap()),
+            ["kept"]
+        );
+        assert_eq!(
+            contents(&fetch_memos_by_id_prefix(&db, &kept.as_str()[..4], 10).unwrap()),
+            ["kept"]
+        );
+        assert!(fetch_memos_by_id_prefix(&db, "%", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn editing_content_replaces_inline_tags_but_keeps_explicit_ones() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = add_memo(
+            &db,
+            &NewMemo::new("draft #old").with_tags(&["pinned".to_string()]),
+        )
+        .unwrap();
+        update_memo_content(&db, &memo_id, "final #new").unwrap();
+
+        let mut tags = fetch_memos(&db, None).unwrap().remove(0).tags;
+        tags.sort();
+        assert_eq!(tags, ["new", "pinned"]);
+    }
+
+    #[test]
+    fn replacing_explicit_tags_keeps_inline_ones() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = add_memo(
+            &db,
+            &NewMemo::new("draft #inline").with_tags(&["old".to_string(), "inline".to_string()]),
+        )
+        .unwrap();
+        let kept = tag_repo::replace_explicit_tags(&db, &memo_id, &["new".to_string()]).unwrap();
+        assert_eq!(kept, ["inline"]);
+
+        let mut tags = fetch_memos(&db, None).unwrap().remove(0).tags;
+        tags.sort();
+        assert_eq!(tags, ["inline", "new"]);
+    }
+
+    #[test]
+    fn tag_edits_in_a_rolled_back_batch_are_undone() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id =
+            add_memo(&db, &NewMemo::new("draft").with_tags(&["old".to_string()])).unwrap();
+
+        let tx = db.conn().unchecked_transaction().unwrap();
+        tag_repo::replace_explicit_tags(&db, &memo_id, &["new".to_string()]).unwrap();
+        update_memo_content(&db, &memo_id, "draft #inline").unwrap();
+        tx.rollback().unwrap();
+
+        let memo = fetch_memos(&db, None).unwrap().remove(0);
+        assert_eq!(
+            (memo.content.as_str(), memo.tags),
+            ("draft", vec!["old".to_string()])
+        );
+    }
+
+    #[test]
+    fn mentions_follow_content_edits() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = add(&db, "1:1 with @Alice");
+        add(&db, "lunch with @alice and @bob");
+        add(&db, "email bob@example.com");
+        let mentioning = |name: &str| MemoFilter {
+            mention: Some(name.to_string()),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &mentioning("bob")).unwrap()),
+            ["lunch with @alice and @bob"]
+        );
+
+        update_memo_content(&db, &memo_id, "1:1 with @carol").unwrap();
+        assert_eq!(
+            mention_repo::fetch_mention_counts(&db).unwrap(),
+            [
+                ("alice".to_string(), 1),
+                ("bob".to_string(), 1),
+                ("carol".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_combine() {
+        let db = Db::open_in_memory().unwrap();
+        add_memo(
+            &db,
+            &NewMemo::new("standup notes #work")
+                .with_notebook(Some("job".to_string()))
+                .with_mood(Some(4))
+                .with_meta(&[("project".to_string(), "cap".to_string())]),
+        )
+        .unwrap();
+        add_memo(
+            &db,
+            &NewMemo::new("weekend hike #outdoors").with_mood(Some(5)),
+        )
+        .unwrap();
+        let plain = add_memo(&db, &NewMemo::new("plain idea")).unwrap();
+        crate::db::add_attachment(&db, &plain, "sketch.png", std::path::Path::new("/x"), 1)
+            .unwrap();
+
+        let by_tag = MemoFilter {
+            tag: Some("work".to_string()),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &by_tag).unwrap()),
+            ["standup notes #work"]
+        );
+        let by_mood = MemoFilter {
+            mood: Some(5..=5),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &by_mood).unwrap()),
+            ["weekend hike #outdoors"]
+        );
+        let no_match = MemoFilter {
+            notebook: Some("job".to_string()),
+            meta: vec![("project".to_string(), "other".to_string())],
+            ..MemoFilter::default()
+        };
+        assert!(fetch_filtered_memos(&db, &no_match).unwrap().is_empty());
+        let untagged = MemoFilter {
+            untagged: true,
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &untagged).unwrap()),
+            ["plain idea"]
+        );
+        let with_attachment = MemoFilter {
+            has_attachment: true,
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &with_attachment).unwrap()),
+            ["plain idea"]
+        );
+        tag_repo::add_explicit_tags(&db, &plain, &[tags::ARCHIVED_TAG.to_string()]).unwrap();
+        let unarchived = MemoFilter {
+            hide_archived: true,
+            ..MemoFilter::default()
+        };
+        assert_eq!(fetch_filtered_memos(&db, &unarchived).unwrap().len(), 2);
+        assert_eq!(
+            fetch_notebook_counts(&db).unwrap(),
+            [("job".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn the_default_notebook_keeps_memos_from_before_it_was_set() {
+        let db = Db::open_in_memory().unwrap();
+        add(&db, "written before notebooks");
+        add_memo(
+            &db,
+            &NewMemo::new("filed at work").with_notebook(Some("work".to_string())),
+        )
+        .unwrap();
+        add_memo(
+            &db,
+            &NewMemo::new("filed at home").with_notebook(Some("home".to_string())),
+        )
+        .unwrap();
+
+        let work = |unfiled| MemoFilter {
+            notebook: Some("work".to_string()),
+            unfiled,
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &work(false)).unwrap()),
+            ["filed at work"]
+        );
+        let memos = fetch_filtered_memos(&db, &work(true)).unwrap();
+        let mut default = contents(&memos);
+        default.sort();
+        assert_eq!(default, ["filed at work", "written before notebooks"]);
+    }
+
+    #[test]
+    fn search_text_combines_with_filters() {
+        let db = Db::open_in_memory().unwrap();
+        add(&db, "release notes #work");
+        add(&db, "notes on notes #work");
+        add(&db, "reading notes #books");
+
+        let search = |text: &str, tag: &str| MemoFilter {
+            text: Some(text.to_string()),
+            tag: Some(tag.to_string()),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &search("notes", "work")).unwrap()),
+            ["notes on notes #work", "release notes #work"]
+        );
+        // Two characters take the LIKE path, which orders by time.
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &search("ng", "books")).unwrap()),
+            ["reading notes #books"]
+        );
+        assert!(
+            fetch_filtered_memos(&db, &search("release", "books"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn explain_reports_the_query_and_why_memos_matched() {
+        let db = Db::open_in_memory().unwrap();
+        add(&db, "quarterly review");
+        add(&db, "unrelated");
+
+        let search = |text: &str| MemoFilter {
+            text: Some(text.to_string()),
+            ..MemoFilter::default()
+        };
+        let explanation = explain_filtered_memos(&db, &search("review")).unwrap();
+        assert_eq!(explanation.fts_phrase.as_deref(), Some("\"review\""));
+        assert!(explanation.sql.contains("MATCH ?2"));
+        assert_eq!(explanation.params, ["'%review%'", "'\"review\"'"]);
+        let [(memo, Some(reasons))] = &explanation.matches[..] else {
+            panic!("one match with reasons");
+        };
+        assert_eq!(memo.content, "quarterly review");
+        assert!(reasons.content && reasons.content_score.is_some());
+        assert!(!reasons.timestamp && !reasons.image_text);
+        assert!(!reasons.archive && reasons.archive_score.is_none());
+
+        let explanation = explain_filtered_memos(&db, &search("un")).unwrap();
+        assert_eq!(explanation.fts_phrase, None);
+        let (_, reasons) = &explanation.matches[0];
+        let reasons = reasons.as_ref().unwrap();
+        assert!(reasons.content && reasons.content_score.is_none());
+    }
+
+    #[test]
+    fn nearby_filter_measures_real_distance() {
+        let db = Db::open_in_memory().unwrap();
+        let tokyo = Location {
+            latitude: 35.6762,
+            longitude: 139.6503,
+        };
+        let yokohama = Location {
+            latitude: 35.4437,
+            longitude: 139.6380,
+        };
+        add_memo(&db, &NewMemo::new("shibuya").with_location(Some(tokyo))).unwrap();
+        add_memo(
+            &db,
+            &NewMemo::new("minatomirai").with_location(Some(yokohama)),
+        )
+        .unwrap();
+        add(&db, "nowhere");
+
+        let near = |radius| MemoFilter {
+            near: Some((tokyo, radius)),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &near(5_000.0)).unwrap()),
+            ["shibuya"]
+        );
+        assert_eq!(fetch_filtered_memos(&db, &near(50_000.0)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn nearby_filter_reaches_across_the_antimeridian() {
+        let db = Db::open_in_memory().unwrap();
+        let east = Location {
+            latitude: -16.8,
+            longitude: 179.9,
+        };
+        let west = Location {
+            latitude: -16.8,
+            longitude: -179.9,
+        };
+        add_memo(&db, &NewMemo::new("west").with_location(Some(west))).unwrap();
+        let filter = MemoFilter {
+            near: Some((east, 30_000.0)),
+            ..MemoFilter::default()
+        };
+        assert_eq!(
+            contents(&fetch_filtered_memos(&db, &filter).unwrap()),
+            ["west"]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::{
+    db::Db,
+    domain::{memo::MemoId, mentions},
+};
+
+/// Replaces the names a memo mentions.
+pub(crate) fn set_memo_mentions(db: &Db, memo_id: &MemoId, names: &[String]) -> Result<()> {
+    db.conn().execute(
+        "DELETE FROM memo_mentions WHERE memo_id = ?1",
+        params![memo_id.as_str()],
+    )?;
+    for name in names {
+        db.conn().execute(
+            "INSERT OR IGNORE INTO memo_mentions (memo_id, name) VALUES (?1, ?2)",
+            params![memo_id.as_str(), name],
+        )?;
+    }
+    Ok(())
+}
+
+/// Everyone mentioned in a live memo, most mentioned first.
+pub(crate) fn fetch_mention_counts(db: &Db) -> Result<Vec<(String, usize)>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT memo_mentions.name, COUNT(*)
+         FROM memo_mentions
+         JOIN memos ON memos.memo_id = memo_mentions.memo_id
+         WHERE memos.deleted = 0
+         GROUP BY memo_mentions.name
+         ORDER BY COUNT(*) DESC, memo_mentions.name ASC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+    })?;
+
+    let mut counts = Vec::new();
+    for row in rows {
+        counts.push(row?);
+    }
+    Ok(counts)
+}
+
+/// Populates `memo_mentions` for memos written before mentions were stored.
+pub(super) fn backfill_mentions(db: &Db) -> Result<()> {
+    let memos = {
+        let mut stmt = db
+            .conn()
+            .prepare("SELECT memo_id, content FROM memos WHERE private = 0")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut memos = Vec::new();
+        for row in rows {
+            memos.push(row?);
+        }
+        memos
+    };
+    let tx = db.conn().unchecked_transaction()?;
+    for (memo_id, content) in memos {
+        set_memo_mentions(db, &memo_id.into(), &mentions::extract_mentions(&content))?;
+    }
+    tx.commit()?;
+    Ok(())
+}
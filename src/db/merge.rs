@@ -0,0 +1,353 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags, params};
+use std::{collections::HashSet, path::Path};
+
+use crate::{
+    db::{Db, memo_repo, mention_repo, meta_repo, schema, tag_repo},
+    domain::{memo, mentions, tags},
+};
+
+pub(crate) struct MergeReport {
+    pub(crate) merged: usize,
+    pub(crate) duplicate_ids: usize,
+    pub(crate) duplicate_contents: usize,
+    pub(crate) skipped_private: usize,
+}
+
+/// What merging another database would add, worked out before anything is written.
+pub(crate) struct MergePlan {
+    pub(crate) report: MergeReport,
+    memos: Vec<SourceMemo>,
+}
+
+struct SourceMemo {
+    memo_id: String,
+    content: String,
+    created_at: String,
+    updated_at: String,
+    timezone: Option<String>,
+    notebook: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    captured_at: Option<String>,
+    mood: Option<u8>,
+    explicit_tags: Vec<String>,
+    meta: Vec<(String, String)>,
+}
+
+/// Reads the other database's memos and finds those not here yet. Only reads
+/// this database, so a dry run or a declined prompt locks nothing.
+pub(crate) fn plan_merge(db: &Db, other_path: &Path) -> Result<MergePlan> {
+    let other = Connection::open_with_flags(other_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("failed to open {}", other_path.display()))?;
+    let source_memos = read_source_memos(&other)
+        .with_context(|| format!("{} is not a cap database", other_path.display()))?;
+    // Private memos are encrypted with the other database's key and can't be read here.
+    let skipped_private = if schema::has_column(&other, "memos", "private")? {
+        other.query_row(
+            "SELECT COUNT(*) FROM memos WHERE deleted = 0 AND private = 1",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? as usize
+    } else {
+        0
+    };
+
+    let mut report = MergeReport {
+        merged: 0,
+        duplicate_ids: 0,
+        duplicate_contents: 0,
+        skipped_private,
+    };
+    let mut memos = Vec::new();
+    let mut planned = HashSet::new();
+    for mut memo in source_memos {
+        // Both checks hit indexes; memo ids are already unique in the other database.
+        if memo_id_exists(db, &memo.memo_id)? {
+            report.duplicate_ids += 1;
+            continue;
+        }
+        // Stored and hashed the way `cap add` would have stored it.
+        memo.content = memo::normalize_content(&memo.content);
+        if planned.contains(&memo.content) || memo_repo::content_exists(db, &memo.content)? {
+            report.duplicate_contents += 1;
+            continue;
+        }
+        planned.insert(memo.content.clone());
+        memos.push(memo);
+        report.merged += 1;
+    }
+    Ok(MergePlan { report, memos })
+}
+
+/// Copies in the memos `plan` found, all or none.
+pub(crate) fn merge_database(db: &Db, plan: &MergePlan) -> Result<()> {
+    db.atomically(|db| {
+        for memo in &plan.memos {
+            insert_memo(db, memo)?;
+        }
+        Ok(())
+    })
+}
+
+fn insert_memo(db: &Db, memo: &SourceMemo) -> Result<()> {
+    let content = &memo.content;
+    db.conn().execute(
+        "INSERT INTO memos (
+                memo_id,
+                content,
+                created_at,
+                updated_at,
+                deleted,
+                dirty,
+                server_rev,
+                timezone,
+                notebook,
+                latitude,
+                longitude,
+                captured_at,
+                mood,
+                content_hash
+            ) VALUES (?1, ?2, ?3, ?4, 0, 1, 0, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            memo.memo_id,
+            content,
+            memo.created_at,
+            memo.updated_at,
+            memo.timezone,
+            memo.notebook,
+            memo.latitude,
+            memo.longitude,
+            memo.captured_at,
+            memo.mood,
+            memo_repo::content_hash(content)
+        ],
+    )?;
+    let memo_id = memo.memo_id.clone().into();
+    tag_repo::set_memo_tags(db, &memo_id, &tags::extract_tags(content))?;
+    mention_repo::set_memo_mentions(db, &memo_id, &mentions::extract_mentions(content))?;
+    tag_repo::add_explicit_tags(db, &memo_id, &memo.explicit_tags)?;
+    for (key, value) in &memo.meta {
+        meta_repo::set_meta(db, &memo_id, key, value)?;
+    }
+    Ok(())
+}
+
+fn read_source_memos(conn: &Connection) -> Result<Vec<SourceMemo>> {
+    let private_filter = if schema::has_column(conn, "memos", "private")? {
+        "AND private = 0"
+    } else {
+        ""
+    };
+    let timezone_column = if schema::has_column(conn, "memos", "timezone")? {
+        "timezone"
+    } else {
+        "NULL"
+    };
+    let notebook_column = if schema::has_column(conn, "memos", "notebook")? {
+        "notebook"
+    } else {
+        "NULL"
+    };
+    let location_columns = if schema::has_column(conn, "memos", "latitude")? {
+        "latitude, longitude"
+    } else {
+        "NULL, NULL"
+    };
+    let captured_at_column = if schema::has_column(conn, "memos", "captured_at")? {
+        "captured_at"
+    } else {
+        "NULL"
+    };
+    let mood_column = if schema::has_column(conn, "memos", "mood")? {
+        "mood"
+    } else {
+        "NULL"
+    };
+    let explicit_tags_column = if schema::has_column(conn, "memo_tags", "explicit")? {
+        "(SELECT group_concat(tag, ' ') FROM memo_tags
+          WHERE memo_tags.memo_id = memos.memo_id AND explicit = 1)"
+    } else {
+        "NULL"
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT memo_id, content, created_at, updated_at, {}, {}, {}, {}, {}, {}
+         FROM memos
+         WHERE deleted = 0 {}
+         ORDER BY julianday(created_at) ASC",
+        timezone_column,
+        explicit_tags_column,
+        notebook_column,
+        location_columns,
+        captured_at_column,
+        mood_column,
+        private_filter
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(SourceMemo {
+            memo_id: row.get(0)?,
+            content: memo_repo::content_from_row(row, 1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+            timezone: row.get(4)?,
+            explicit_tags: row
+                .get::<_, Option<String>>(5)?
+                .map(|joined| joined.split(' ').map(str::to_string).collect())
+                .unwrap_or_default(),
+            notebook: row.get(6)?,
+            latitude: row.get(7)?,
+            longitude: row.get(8)?,
+            captured_at: row.get(9)?,
+            mood: row.get(10)?,
+            meta: Vec::new(),
+        })
+    })?;
+
+    let mut memos = Vec::new();
+    for row in rows {
+        memos.push(row?);
+    }
+    drop(stmt);
+    if schema::table_exists(conn, "memo_meta")? {
+        let mut stmt = conn.prepare("SELECT key, value FROM memo_meta WHERE memo_id = ?1")?;
+        for memo in &mut memos {
+            let rows =
+                stmt.query_map(params![memo.memo_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            for row in rows {
+                memo.meta.push(row?);
+            }
+        }
+    }
+    Ok(memos)
+}
+
+fn memo_id_exists(db: &Db, memo_id: &str) -> Result<bool> {
+    Ok(db.conn().query_row(
+        "SELECT EXISTS (SELECT 1 FROM memos WHERE memo_id = ?1)",
+        params![memo_id],
+        |row| row.get(0),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, domain::memo::NewMemo};
+    use chrono::{Local, TimeZone};
+    use std::{env, fs, process};
+
+    #[test]
+    fn merging_an_overlapping_database_twice_adds_nothing_new() {
+        let path = env::temp_dir().join(format!("cap-merge-test-{}.db", process::id()));
+        let _ = fs::remove_file(&path);
+        let other = Db::open(path.clone()).unwrap();
+        db::add_memo(&other, &NewMemo::new("only in other")).unwrap();
+        db::add_memo(&other, &NewMemo::new("in both")).unwrap();
+        db::add_memo(&other, &NewMemo::new("only in other")).unwrap();
+        drop(other);
+
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("in both")).unwrap();
+        let first = plan_merge(&db, &path).unwrap().report;
+        let plan = plan_merge(&db, &path).unwrap();
+        // Planning alone writes nothing.
+        assert_eq!(db::fetch_memos(&db, None).unwrap().len(), 1);
+        merge_database(&db, &plan).unwrap();
+        let plan = plan_merge(&db, &path).unwrap();
+        merge_database(&db, &plan).unwrap();
+        let second = plan.report;
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            (first.merged, first.duplicate_ids, first.duplicate_contents),
+            (1, 0, 2)
+        );
+        assert_eq!(
+            (
+                second.merged,
+                second.duplicate_ids,
+                second.duplicate_contents
+            ),
+            (0, 1, 2)
+        );
+        assert_eq!(db::fetch_memos(&db, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn merged_content_is_normalized_before_it_is_compared() {
+        let path = env::temp_dir().join(format!("cap-merge-raw-{}.db", process::id()));
+        let _ = fs::remove_file(&path);
+        let other = Db::open(path.clone()).unwrap();
+        db::add_memo(&other, &NewMemo::new("placeholder")).unwrap();
+        db::add_memo(&other, &NewMemo::new("other placeholder")).unwrap();
+        // Written by an older cap that stored content as given.
+        other
+            .conn()
+            .execute_batch(
+                "UPDATE memos SET content = 'in both\r\n\r\n' WHERE content = 'placeholder';
+                UPDATE memos SET content = 'new\r\nline  ' WHERE content = 'other placeholder';",
+            )
+            .unwrap();
+        drop(other);
+
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("in both")).unwrap();
+        let plan = plan_merge(&db, &path).unwrap();
+        merge_database(&db, &plan).unwrap();
+        let report = plan.report;
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((report.merged, report.duplicate_contents), (1, 1));
+        assert!(memo_repo::content_exists(&db, "new\nline").unwrap());
+        let contents: Vec<String> = db::fetch_memos(&db, None)
+            .unwrap()
+            .into_iter()
+            .map(|memo| memo.content)
+            .collect();
+        assert!(contents.contains(&"new\nline".to_string()));
+    }
+
+    #[test]
+    fn mood_and_capture_time_come_along() {
+        let path = env::temp_dir().join(format!("cap-merge-mood-{}.db", process::id()));
+        let _ = fs::remove_file(&path);
+        let other = Db::open(path.clone()).unwrap();
+        let captured_at = Local.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap();
+        db::add_memo(
+            &other,
+            &NewMemo::new("written offline")
+                .with_mood(Some(2))
+                .with_created_at(Some(captured_at)),
+        )
+        .unwrap();
+        let source_captured: Option<String> = other
+            .conn()
+            .query_row("SELECT captured_at FROM memos", [], |row| row.get(0))
+            .unwrap();
+        drop(other);
+
+        let db = Db::open_in_memory().unwrap();
+        let plan = plan_merge(&db, &path).unwrap();
+        merge_database(&db, &plan).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let (mood, captured): (Option<u8>, Option<String>) = db
+            .conn()
+            .query_row("SELECT mood, captured_at FROM memos", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(mood, Some(2));
+        assert!(captured.is_some());
+        assert_eq!(captured, source_captured);
+    }
+
+    #[test]
+    fn edits_keep_the_content_hash_current() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = db::add_memo(&db, &NewMemo::new("draft")).unwrap();
+        db::update_memo_content(&db, &memo_id, "final").unwrap();
+        assert!(!memo_repo::content_exists(&db, "draft").unwrap());
+        assert!(memo_repo::content_exists(&db, "final").unwrap());
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::{db::Db, domain::memo::MemoId};
+
+pub(crate) fn set_meta(db: &Db, memo_id: &MemoId, key: &str, value: &str) -> Result<()> {
+    db.conn().execute(
+        "INSERT INTO memo_meta (memo_id, key, value) VALUES (?1, ?2, ?3)
+         ON CONFLICT (memo_id, key) DO UPDATE SET value = excluded.value",
+        params![memo_id.as_str(), key, value],
+    )?;
+    Ok(())
+}
+
+/// Returns whether the key was present.
+pub(crate) fn unset_meta(db: &Db, memo_id: &MemoId, key: &str) -> Result<bool> {
+    let removed = db.conn().execute(
+        "DELETE FROM memo_meta WHERE memo_id = ?1 AND key = ?2",
+        params![memo_id.as_str(), key],
+    )?;
+    Ok(removed > 0)
+}
+
+/// A memo's fields, sorted by key.
+pub(crate) fn fetch_meta(db: &Db, memo_id: &MemoId) -> Result<Vec<(String, String)>> {
+    let mut stmt = db
+        .conn()
+        .prepare("SELECT key, value FROM memo_meta WHERE memo_id = ?1 ORDER BY key")?;
+    let rows = stmt.query_map(params![memo_id.as_str()], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+
+    let mut fields = Vec::new();
+    for row in rows {
+        fields.push(row?);
+    }
+    Ok(fields)
+}
+
+/// Copies fields the target doesn't already have, e.g. when merging memos.
+pub(crate) fn copy_meta(db: &Db, from: &MemoId, to: &MemoId) -> Result<()> {
+    db.conn().execute(
+        "INSERT OR IGNORE INTO memo_meta (memo_id, key, value)
+            SELECT ?2, key, value FROM memo_meta WHERE memo_id = ?1",
+        params![from.as_str(), to.as_str()],
+    )?;
+    Ok(())
+}
+
+/// Every live memo's value for `key`, e.g. to find reminders that are due.
+pub(crate) fn fetch_meta_values(db: &Db, key: &str) -> Result<Vec<(MemoId, String)>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT memo_meta.memo_id, value FROM memo_meta
+         JOIN memos ON memos.memo_id = memo_meta.memo_id
+         WHERE key = ?1 AND deleted = 0",
+    )?;
+    let rows = stmt.query_map(params![key], |row| {
+        Ok((MemoId::from(row.get::<_, String>(0)?), row.get(1)?))
+    })?;
+
+    let mut values = Vec::new();
+    for row in rows {
+        values.push(row?);
+    }
+    Ok(values)
+}
@@ -1,26 +1,464 @@
-use anyhow::Result;
-use rusqlite::Connection;
-use std::path::PathBuf;
+use anyhow::{Result, bail};
+use chrono::Local;
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
 
+mod archive_repo;
+mod attachment_repo;
 mod kv_repo;
+mod maintenance;
 mod memo_repo;
+mod mention_repo;
+mod merge;
+mod meta_repo;
 mod schema;
+mod tag_repo;
+mod time_repo;
 
-pub(crate) use kv_repo::set_kv;
-pub(crate) use memo_repo::{add_memo, fetch_memos};
+pub(crate) use archive_repo::{set_archive_text, set_image_text};
+pub(crate) use attachment_repo::{add_attachment, fetch_attachments, remove_attachment};
+pub(crate) use kv_repo::{delete_kv, get_kv, set_kv};
+pub(crate) use maintenance::{backup, database_size, vacuum};
+pub(crate) use memo_repo::{
+    MatchReasons, MemoFilter, PendingMemo, ServerMemo, add_memo, content_exists,
+    count_pending_changes, count_purgeable_tombstones, delete_memo, explain_filtered_memos,
+    fetch_changed_memos, fetch_created_at, fetch_filtered_memos, fetch_memo_by_id_prefix,
+    fetch_memos, fetch_memos_by_id_prefix, fetch_moods, fetch_notebook_counts, fetch_pending_memos,
+    fetch_sync_state, mark_synced, purge_tombstones, search_memos, store_merged_content,
+    store_remote_memo, update_memo_content,
+};
+pub(crate) use mention_repo::fetch_mention_counts;
+pub(crate) use merge::{merge_database, plan_merge};
+pub(crate) use meta_repo::{copy_meta, fetch_meta, fetch_meta_values, set_meta, unset_meta};
+pub(crate) use tag_repo::{
+    add_explicit_tags, copy_explicit_tags, fetch_tag_counts, replace_explicit_tags,
+};
+pub(crate) use time_repo::{
+    TimeEntry, fetch_running_entry, fetch_time_entries, start_time_entry, stop_running_entry,
+};
 
 pub(crate) struct Db {
     conn: Connection,
+    /// Memo text longer than this is stored zstd-compressed; 0 never compresses.
+    compress_above_bytes: usize,
+}
+
+/// Whether the database at `path` predates schema versioning; see
+/// [`Db::migrate_legacy`].
+pub(crate) fn is_legacy(path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    schema::is_legacy(&conn)
+}
+
+/// Schema changes opening the database at `path` would make; empty when it is
+/// current or doesn't exist yet.
+pub(crate) fn pending_migrations(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    schema::pending_migrations(&conn)
 }
 
 impl Db {
+    #[cfg(test)]
     pub(crate) fn open(path: PathBuf) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        schema::init(&conn)?;
-        Ok(Self { conn })
+        Ok(Self::open_migrating(path)?.0)
+    }
+
+    /// Opens the database, upgrading a schema an older build wrote in place. The
+    /// old database is first copied to the `backups` folder beside it; that
+    /// copy's path is returned. A legacy database is refused instead, so that
+    /// upgrading it stays an explicit `cap doctor --migrate-legacy`.
+    pub(crate) fn open_migrating(path: PathBuf) -> Result<(Self, Option<PathBuf>)> {
+        let conn = Connection::open(&path)?;
+        if schema::is_legacy(&conn)? {
+            bail!(
+                "{} was written by an older cap; run `cap doctor --migrate-legacy` to back it up and upgrade it",
+                path.display()
+            );
+        }
+        Self::upgrade(conn, &path)
+    }
+
+    /// Backs up and upgrades a database, legacy ones included; what
+    /// `cap doctor --migrate-legacy` runs.
+    pub(crate) fn migrate_legacy(path: PathBuf) -> Result<(Self, Option<PathBuf>)> {
+        let conn = Connection::open(&path)?;
+        Self::upgrade(conn, &path)
+    }
+
+    fn upgrade(conn: Connection, path: &Path) -> Result<(Self, Option<PathBuf>)> {
+        let mut backup = None;
+        if !schema::pending_migrations(&conn)?.is_empty() {
+            let dest = path.with_file_name("backups").join(format!(
+                "capmind-pre-migration-{}.db",
+                Local::now().format("%Y%m%d-%H%M%S%.6f")
+            ));
+            maintenance::backup_into(&conn, &dest)?;
+            backup = Some(dest);
+        }
+        Ok((Self::init(conn)?, backup))
+    }
+
+    /// Opens the database without write access, for lookups such as shell
+    /// completion that must never change it. Fails when there is no database
+    /// yet or its schema is behind this build, as upgrading it needs a write.
+    pub(crate) fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        if !schema::table_exists(&conn, "memos")? || !schema::pending_migrations(&conn)?.is_empty()
+        {
+            bail!("{} isn't a current cap database", path.display());
+        }
+        Ok(Self {
+            conn,
+            compress_above_bytes: 0,
+        })
+    }
+
+    /// A fresh, fully migrated database that lives only as long as the handle.
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        let outcome = schema::init(&conn)?;
+        let db = Self {
+            conn,
+            compress_above_bytes: 0,
+        };
+        if outcome.tags_created {
+            tag_repo::backfill_tags(&db)?;
+        }
+        if outcome.mentions_created {
+            mention_repo::backfill_mentions(&db)?;
+        }
+        Ok(db)
     }
 
     pub(crate) fn conn(&self) -> &Connection {
         &self.conn
     }
+
+    /// Compresses memos longer than `bytes` from now on; see `[memo]` in the config.
+    pub(crate) fn set_compress_above(&mut self, bytes: usize) {
+        self.compress_above_bytes = bytes;
+    }
+
+    /// Runs `write` in a transaction, so either all of it is kept or none of
+    /// it is. Inside a transaction the caller already opened, `write` joins it.
+    pub(crate) fn atomically<T>(&self, write: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        if !self.conn.is_autocommit() {
+            return write(self);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let value = write(self)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Runs `read` in one read transaction, so a report built from several
+    /// queries sees a single state of the database even while the TUI or a
+    /// server writes to it. Nothing `read` does is kept.
+    pub(crate) fn read_snapshot<T>(&self, read: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let result = read(self);
+        tx.rollback()?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_a_database_from_the_first_release() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE memos (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                memo_id TEXT NOT NULL UNIQUE,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                deleted INTEGER NOT NULL DEFAULT 0,
+                dirty INTEGER NOT NULL DEFAULT 1,
+                server_rev INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE kv (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+            INSERT INTO memos (memo_id, content, created_at, updated_at)
+                VALUES ('m1', 'old memo #legacy', '2024-01-02T03:04:05+00:00',
+                        '2024-01-02T03:04:05+00:00');",
+        )
+        .unwrap();
+
+        let pending = schema::pending_migrations(&conn).unwrap();
+        assert_eq!(pending.len(), schema::MIGRATIONS.len());
+        assert!(pending.contains(&"memos columns".to_string()));
+
+        let db = Db::init(conn).unwrap();
+        assert!(schema::pending_migrations(db.conn()).unwrap().is_empty());
+        for column in [
+            "private",
+            "timezone",
+            "captured_at",
+            "mood",
+            "notebook",
+            "latitude",
+            "longitude",
+            "content_hash",
+        ] {
+            assert!(
+                schema::has_column(db.conn(), "memos", column).unwrap(),
+                "{column}"
+            );
+        }
+        let memos = fetch_memos(&db, None).unwrap();
+        assert_eq!(memos.len(), 1);
+        assert!(!memos[0].private);
+        assert_eq!(memos[0].tags, ["legacy"]);
+        assert_eq!(search_memos(&db, "old memo", None).unwrap().len(), 1);
+        assert_eq!(fetch_tag_counts(&db).unwrap(), [("legacy".to_string(), 1)]);
+        assert!(memo_repo::content_exists(&db, "old memo #legacy").unwrap());
+    }
+
+    #[test]
+    fn newer_migrations_replace_old_triggers() {
+        let db = Db::open_in_memory().unwrap();
+        let Db { conn, .. } = db;
+        let version = schema::MIGRATIONS
+            .iter()
+            .position(|migration| migration.name == "memos_fts triggers")
+            .unwrap();
+        conn.execute_batch(&format!(
+            "DROP TRIGGER memos_fts_update;
+            CREATE TRIGGER memos_fts_update AFTER UPDATE ON memos
+            BEGIN
+                DELETE FROM memos_fts WHERE rowid = old.id;
+            END;
+            PRAGMA user_version = {};",
+            version
+        ))
+        .unwrap();
+
+        let db = Db::init(conn).unwrap();
+        let trigger: String = db
+            .conn()
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE name = 'memos_fts_update'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(trigger.contains("UPDATE OF content, private, deleted"));
+        let memo_id = add_memo(&db, &crate::domain::memo::NewMemo::new("indexed")).unwrap();
+        update_memo_content(&db, &memo_id, "still indexed").unwrap();
+        assert_eq!(search_memos(&db, "still", None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn image_text_moves_from_meta_into_the_index() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = add_memo(&db, &crate::domain::memo::NewMemo::new("[image]")).unwrap();
+        let Db { conn, .. } = db;
+        let version = schema::MIGRATIONS
+            .iter()
+            .position(|migration| migration.name == "image_text_fts")
+            .unwrap();
+        conn.execute_batch(&format!(
+            "DROP TABLE image_text_fts;
+            INSERT INTO memo_meta (memo_id, key, value)
+                VALUES ('{}', 'ocr_text', 'kernel panic');
+            PRAGMA user_version = {};",
+            memo_id.as_str(),
+            version
+        ))
+        .unwrap();
+
+        let db = Db::init(conn).unwrap();
+        assert_eq!(search_memos(&db, "panic", None).unwrap().len(), 1);
+        assert!(fetch_meta(&db, &memo_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn listings_are_read_in_index_order() {
+        let db = Db::open_in_memory().unwrap();
+        let plan: Vec<String> = db
+            .conn()
+            .prepare(
+                "EXPLAIN QUERY PLAN SELECT memo_id FROM memos WHERE deleted = 0
+                 ORDER BY julianday(created_at) DESC, id DESC LIMIT 20",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(3))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(plan.iter().any(|step| step.contains("memos_listing_idx")));
+        assert!(!plan.iter().any(|step| step.contains("TEMP B-TREE")));
+    }
+
+    #[test]
+    fn legacy_databases_are_backed_up_before_migrating() {
+        let dir = std::env::temp_dir().join(format!("cap-legacy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capmind.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE memos (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    memo_id TEXT NOT NULL UNIQUE,
+                    content TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    deleted INTEGER NOT NULL DEFAULT 0,
+                    dirty INTEGER NOT NULL DEFAULT 1,
+                    server_rev INTEGER NOT NULL DEFAULT 0
+                );",
+            )
+            .unwrap();
+        assert!(!pending_migrations(&path).unwrap().is_empty());
+        assert!(is_legacy(&path).unwrap());
+
+        let refused = Db::open_migrating(path.clone()).err().unwrap();
+        assert!(refused.to_string().contains("cap doctor --migrate-legacy"));
+        assert!(is_legacy(&path).unwrap());
+        assert!(!dir.join("backups").exists());
+
+        let (_, backup) = Db::migrate_legacy(path.clone()).unwrap();
+        let backup = backup.expect("backup made");
+        assert!(backup.starts_with(dir.join("backups")));
+        assert!(is_legacy(&backup).unwrap());
+        assert!(pending_migrations(&path).unwrap().is_empty());
+        assert!(!is_legacy(&path).unwrap());
+        let (_, again) = Db::open_migrating(path).unwrap();
+        assert_eq!(again, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn databases_a_migration_behind_are_backed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capmind.db");
+        let db = Db::open(path.clone()).unwrap();
+        db.conn()
+            .pragma_update(None, "user_version", schema::MIGRATIONS.len() as i64 - 1)
+            .unwrap();
+        drop(db);
+        let newest = schema::MIGRATIONS.last().unwrap().name;
+        assert_eq!(pending_migrations(&path).unwrap(), [newest]);
+
+        let (_, backup) = Db::open_migrating(path.clone()).unwrap();
+        let backup = backup.expect("backup made");
+        assert!(backup.starts_with(dir.path().join("backups")));
+        assert_eq!(pending_migrations(&backup).unwrap(), [newest]);
+        assert!(pending_migrations(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_only_opens_leave_an_outdated_schema_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capmind.db");
+        assert!(Db::open_read_only(&path).is_err());
+        let db = Db::open(path.clone()).unwrap();
+        add_memo(&db, &crate::domain::memo::NewMemo::new("#kept")).unwrap();
+        drop(db);
+
+        let db = Db::open_read_only(&path).unwrap();
+        assert_eq!(fetch_tag_counts(&db).unwrap(), [("kept".to_string(), 1)]);
+        assert!(add_memo(&db, &crate::domain::memo::NewMemo::new("no")).is_err());
+        drop(db);
+
+        Db::open(path.clone())
+            .unwrap()
+            .conn()
+            .pragma_update(None, "user_version", schema::MIGRATIONS.len() as i64 - 1)
+            .unwrap();
+        assert!(Db::open_read_only(&path).is_err());
+        assert_eq!(pending_migrations(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn writers_wait_for_a_read_snapshot_to_end() {
+        let dir = std::env::temp_dir().join(format!("cap-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capmind.db");
+        let reader = Db::open(path.clone()).unwrap();
+        let writer = Db::open(path).unwrap();
+        writer
+            .conn()
+            .busy_timeout(std::time::Duration::ZERO)
+            .unwrap();
+        let memo = crate::domain::memo::NewMemo::new("written meanwhile");
+
+        let seen = reader
+            .read_snapshot(|db| {
+                let before = fetch_memos(db, None)?.len();
+                assert!(add_memo(&writer, &memo).is_err());
+                Ok((before, fetch_memos(db, None)?.len()))
+            })
+            .unwrap();
+        assert_eq!(seen, (0, 0));
+        assert!(reader.conn().is_autocommit());
+        add_memo(&writer, &memo).unwrap();
+        assert_eq!(fetch_memos(&reader, None).unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backups_copy_a_database_in_use_and_never_overwrite() {
+        let dir = std::env::temp_dir().join(format!("cap-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Db::open(dir.join("capmind.db")).unwrap();
+        db.conn()
+            .pragma_update(None, "journal_mode", "WAL")
+            .unwrap();
+        add_memo(&db, &crate::domain::memo::NewMemo::new("backed up #safe")).unwrap();
+        let dest = dir.join("copies").join("capmind-copy.db");
+
+        backup(&db, &dest).unwrap();
+        let copy = Db::open(dest.clone()).unwrap();
+        assert_eq!(
+            fetch_memos(&copy, None).unwrap()[0].content,
+            "backed up #safe"
+        );
+        assert!(backup(&db, &dest).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reopening_a_migrated_database_is_a_no_op() {
+        let db = Db::open_in_memory().unwrap();
+        add_memo(&db, &crate::domain::memo::NewMemo::new("kept #once")).unwrap();
+        let Db { conn, .. } = db;
+
+        let db = Db::init(conn).unwrap();
+        let memos = fetch_memos(&db, None).unwrap();
+        assert_eq!(memos.len(), 1);
+        assert_eq!(memos[0].tags, ["once"]);
+        assert_eq!(search_memos(&db, "kept", None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn atomic_writes_are_undone_together_and_join_an_open_transaction() {
+        use crate::domain::memo::NewMemo;
+        let db = Db::open_in_memory().unwrap();
+        let failed: Result<()> = db.atomically(|db| {
+            add_memo(db, &NewMemo::new("half done"))?;
+            anyhow::bail!("second step failed")
+        });
+        assert!(failed.is_err());
+        assert!(fetch_memos(&db, None).unwrap().is_empty());
+
+        let tx = db.conn().unchecked_transaction().unwrap();
+        db.atomically(|db| add_memo(db, &NewMemo::new("joined")))
+            .unwrap();
+        tx.rollback().unwrap();
+        assert!(fetch_memos(&db, None).unwrap().is_empty());
+    }
 }
@@ -1,9 +1,160 @@
-use anyhow::Result;
-use rusqlite::Connection;
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
 
-pub(super) fn init(conn: &Connection) -> Result<()> {
-    create_memos_table(conn)?;
-    create_kv_table(conn)
+use crate::{db::memo_repo, domain::memo};
+
+pub(super) struct InitOutcome {
+    pub(super) tags_created: bool,
+    pub(super) mentions_created: bool,
+}
+
+pub(super) const FTS_TABLE: &str = "memos_fts";
+
+/// One schema change, applied once per database.
+pub(super) struct Migration {
+    pub(super) name: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+/// Every schema change, oldest first; a database's `PRAGMA user_version` is
+/// how many of them it has had. Only ever append. Databases from before the
+/// version was kept start at 0 with some steps already done, so those first
+/// steps must stay safe to repeat.
+pub(super) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "memos",
+        apply: create_memos_table,
+    },
+    Migration {
+        name: "memos columns",
+        apply: migrate_memos_table,
+    },
+    Migration {
+        name: "kv",
+        apply: create_kv_table,
+    },
+    Migration {
+        name: "attachments",
+        apply: create_attachments_table,
+    },
+    Migration {
+        name: "memo_tags",
+        apply: create_tags_table,
+    },
+    Migration {
+        name: "memo_mentions",
+        apply: create_mentions_table,
+    },
+    Migration {
+        name: FTS_TABLE,
+        apply: create_fts_table,
+    },
+    Migration {
+        name: "time_entries",
+        apply: create_time_entries_table,
+    },
+    Migration {
+        name: "memo_meta",
+        apply: create_memo_meta_table,
+    },
+    Migration {
+        name: "archives_fts",
+        apply: create_archives_table,
+    },
+    Migration {
+        name: "memos_fts triggers",
+        apply: recreate_fts_triggers,
+    },
+    Migration {
+        name: "memos_fts compressed content",
+        apply: skip_compressed_content,
+    },
+    Migration {
+        name: "memos listing index",
+        apply: create_listing_index,
+    },
+    Migration {
+        name: "image_text_fts",
+        apply: create_image_text_table,
+    },
+];
+
+pub(super) fn init(conn: &Connection) -> Result<InitOutcome> {
+    let tags_created = !table_exists(conn, "memo_tags")?;
+    let mentions_created = !table_exists(conn, "memo_mentions")?;
+    let applied = schema_version(conn)?;
+    if applied < MIGRATIONS.len() {
+        let tx = conn.unchecked_transaction()?;
+        for migration in &MIGRATIONS[applied..] {
+            (migration.apply)(conn)
+                .with_context(|| format!("schema migration `{}` failed", migration.name))?;
+        }
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+        tx.commit()?;
+    }
+    Ok(InitOutcome {
+        tags_created,
+        mentions_created,
+    })
+}
+
+fn schema_version(conn: &Connection) -> Result<usize> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version.max(0) as usize)
+}
+
+pub(super) fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Builds from before schema versioning left `user_version` at 0. Their
+/// databases are only upgraded on request, through `cap doctor --migrate-legacy`.
+pub(super) fn is_legacy(conn: &Connection) -> Result<bool> {
+    Ok(table_exists(conn, "memos")? && schema_version(conn)? == 0)
+}
+
+/// The migrations `init` would apply to a database an older build wrote.
+/// Empty for a current database, and for a new one.
+pub(super) fn pending_migrations(conn: &Connection) -> Result<Vec<String>> {
+    if !table_exists(conn, "memos")? {
+        return Ok(Vec::new());
+    }
+    let applied = schema_version(conn)?.min(MIGRATIONS.len());
+    Ok(MIGRATIONS[applied..]
+        .iter()
+        .map(|migration| migration.name.to_string())
+        .collect())
+}
+
+pub(super) fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if row.get::<_, String>(1)? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {};",
+            table, column, definition
+        ))?;
+    }
+    Ok(())
 }
 
 fn create_memos_table(conn: &Connection) -> Result<()> {
@@ -28,6 +179,62 @@ fn create_memos_table(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+// Columns added after the initial release; existing databases gain them on open.
+fn migrate_memos_table(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "memos", "private", "INTEGER NOT NULL DEFAULT 0")?;
+    // IANA zone the memo was captured in; created_at already carries the UTC offset.
+    add_column_if_missing(conn, "memos", "timezone", "TEXT")?;
+    // When cap recorded the memo; differs from created_at for backfilled memos.
+    add_column_if_missing(conn, "memos", "captured_at", "TEXT")?;
+    add_column_if_missing(conn, "memos", "mood", "INTEGER")?;
+    // NULL means the memo belongs to no notebook.
+    add_column_if_missing(conn, "memos", "notebook", "TEXT")?;
+    add_column_if_missing(conn, "memos", "latitude", "REAL")?;
+    add_column_if_missing(conn, "memos", "longitude", "REAL")?;
+    // Hex SHA-256 of content, for spotting duplicates on import without reading every memo.
+    if !has_column(conn, "memos", "content_hash")? {
+        add_column_if_missing(conn, "memos", "content_hash", "TEXT")?;
+        backfill_content_hashes(conn)?;
+    }
+    // The content the server last accepted or sent, the base of three-way merges.
+    add_column_if_missing(conn, "memos", "synced_content", "TEXT")?;
+    // Timestamps keep their capture offset, so order by the instant, not the text.
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS memos_created_instant_idx
+            ON memos (julianday(created_at) DESC);
+        CREATE INDEX IF NOT EXISTS memos_content_hash_idx
+            ON memos (content_hash);",
+    )?;
+    Ok(())
+}
+
+/// Hashes every memo in one transaction, joining the caller's when there is
+/// one, so a large database isn't written row by row. Content stored before it
+/// was normalized is hashed as it would be stored now, the way imports look it up.
+fn backfill_content_hashes(conn: &Connection) -> Result<()> {
+    let tx = if conn.is_autocommit() {
+        Some(conn.unchecked_transaction()?)
+    } else {
+        None
+    };
+    {
+        let mut select = conn.prepare("SELECT id, content FROM memos")?;
+        let mut update = conn.prepare("UPDATE memos SET content_hash = ?2 WHERE id = ?1")?;
+        let rows = select.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (id, content) = row?;
+            let hash = memo_repo::content_hash(&memo::normalize_content(&content));
+            update.execute(params![id, hash])?;
+        }
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    Ok(())
+}
+
 fn create_kv_table(conn: &Connection) -> Result<()> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS kv (
@@ -37,3 +244,188 @@ fn create_kv_table(conn: &Connection) -> Result<()> {
     )?;
     Ok(())
 }
+
+fn create_attachments_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS attachments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memo_id TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS attachments_memo_id_idx
+            ON attachments (memo_id);",
+    )?;
+    Ok(())
+}
+
+// Pairs the start/stop memos written by `cap start` and `cap stop`.
+fn create_time_entries_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS time_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            label TEXT NOT NULL,
+            start_memo_id TEXT NOT NULL,
+            stop_memo_id TEXT,
+            started_at TEXT NOT NULL,
+            stopped_at TEXT
+        );",
+    )?;
+    Ok(())
+}
+
+// Free-form `key=value` fields set with `--meta` or `cap meta set`.
+fn create_memo_meta_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS memo_meta (
+            memo_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (memo_id, key)
+        );
+        CREATE INDEX IF NOT EXISTS memo_meta_key_value_idx
+            ON memo_meta (key, value);",
+    )?;
+    Ok(())
+}
+
+// Readable text of the pages linked from memos, saved by `cap fetch`; searched
+// alongside memo content.
+fn create_archives_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS archives_fts
+            USING fts5(memo_id UNINDEXED, text, tokenize = 'trigram');
+        CREATE TRIGGER IF NOT EXISTS archives_fts_purge AFTER DELETE ON memos
+        BEGIN
+            DELETE FROM archives_fts WHERE memo_id = old.memo_id;
+        END;",
+    )?;
+    Ok(())
+}
+
+fn create_tags_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS memo_tags (
+            memo_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (memo_id, tag)
+        );
+        CREATE INDEX IF NOT EXISTS memo_tags_tag_idx
+            ON memo_tags (tag);",
+    )?;
+    // Tags given with `--tag` rather than written inline; they survive content edits.
+    add_column_if_missing(conn, "memo_tags", "explicit", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn create_mentions_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS memo_mentions (
+            memo_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            PRIMARY KEY (memo_id, name)
+        );
+        CREATE INDEX IF NOT EXISTS memo_mentions_name_idx
+            ON memo_mentions (name);",
+    )?;
+    Ok(())
+}
+
+// Trigram tokenizing keeps substring matching (and CJK text) working like the old
+// in-memory filter. Rows are keyed by `memos.id`; private and deleted memos are
+// never indexed.
+fn create_fts_table(conn: &Connection) -> Result<()> {
+    let backfill = !table_exists(conn, FTS_TABLE)?;
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS memos_fts
+            USING fts5(content, tokenize = 'trigram');
+        CREATE TRIGGER IF NOT EXISTS memos_fts_insert AFTER INSERT ON memos
+        WHEN new.private = 0 AND new.deleted = 0
+        BEGIN
+            INSERT INTO memos_fts (rowid, content) VALUES (new.id, new.content);
+        END;
+        CREATE TRIGGER IF NOT EXISTS memos_fts_update AFTER UPDATE ON memos
+        BEGIN
+            DELETE FROM memos_fts WHERE rowid = old.id;
+            INSERT INTO memos_fts (rowid, content)
+                SELECT new.id, new.content WHERE new.private = 0 AND new.deleted = 0;
+        END;
+        CREATE TRIGGER IF NOT EXISTS memos_fts_delete AFTER DELETE ON memos
+        BEGIN
+            DELETE FROM memos_fts WHERE rowid = old.id;
+        END;",
+    )?;
+    if backfill {
+        conn.execute_batch(
+            "INSERT INTO memos_fts (rowid, content)
+                SELECT id, content FROM memos WHERE private = 0 AND deleted = 0;",
+        )?;
+    }
+    Ok(())
+}
+
+// Only content, privacy and deletion change what the index holds; other updates
+// (sync flags, notebook moves) used to rewrite the row's index entry for nothing.
+fn recreate_fts_triggers(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS memos_fts_update;
+        CREATE TRIGGER memos_fts_update AFTER UPDATE OF content, private, deleted ON memos
+        BEGIN
+            DELETE FROM memos_fts WHERE rowid = old.id;
+            INSERT INTO memos_fts (rowid, content)
+                SELECT new.id, new.content WHERE new.private = 0 AND new.deleted = 0;
+        END;",
+    )?;
+    Ok(())
+}
+
+// Content over `[memo] compress_above_bytes` is stored as a zstd blob, which the
+// triggers can't read; the repo indexes those memos from their text itself.
+fn skip_compressed_content(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS memos_fts_insert;
+        CREATE TRIGGER memos_fts_insert AFTER INSERT ON memos
+        WHEN new.private = 0 AND new.deleted = 0 AND typeof(new.content) = 'text'
+        BEGIN
+            INSERT INTO memos_fts (rowid, content) VALUES (new.id, new.content);
+        END;
+        DROP TRIGGER IF EXISTS memos_fts_update;
+        CREATE TRIGGER memos_fts_update AFTER UPDATE OF content, private, deleted ON memos
+        BEGIN
+            DELETE FROM memos_fts WHERE rowid = old.id;
+            INSERT INTO memos_fts (rowid, content)
+                SELECT new.id, new.content
+                WHERE new.private = 0 AND new.deleted = 0 AND typeof(new.content) = 'text';
+        END;",
+    )?;
+    Ok(())
+}
+
+// Listings filter on `deleted` before ordering by the instant, so an index on
+// the instant alone left SQLite sorting every live memo in a temporary b-tree.
+fn create_listing_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP INDEX IF EXISTS memos_created_instant_idx;
+        CREATE INDEX IF NOT EXISTS memos_listing_idx
+            ON memos (deleted, julianday(created_at) DESC, id DESC);",
+    )?;
+    Ok(())
+}
+
+// Text read from memos' images by `cap ocr`, searched like archived pages. It
+// used to be kept unindexed in the `ocr_text` field.
+fn create_image_text_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS image_text_fts
+            USING fts5(memo_id UNINDEXED, text, tokenize = 'trigram');
+        CREATE TRIGGER IF NOT EXISTS image_text_fts_purge AFTER DELETE ON memos
+        BEGIN
+            DELETE FROM image_text_fts WHERE memo_id = old.memo_id;
+        END;
+        INSERT INTO image_text_fts (memo_id, text)
+            SELECT memo_id, value FROM memo_meta WHERE key = 'ocr_text';
+        DELETE FROM memo_meta WHERE key = 'ocr_text';",
+    )?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use crate::{
+    db::{Db, memo_repo},
+    domain::{memo::MemoId, tags},
+};
+
+/// Replaces a memo's inline hashtags; explicit tags are left alone.
+pub(crate) fn set_memo_tags(db: &Db, memo_id: &MemoId, tags: &[String]) -> Result<()> {
+    db.atomically(|db| {
+        db.conn().execute(
+            "DELETE FROM memo_tags WHERE memo_id = ?1 AND explicit = 0",
+            params![memo_id.as_str()],
+        )?;
+        for tag in tags {
+            db.conn().execute(
+                "INSERT OR IGNORE INTO memo_tags (memo_id, tag) VALUES (?1, ?2)",
+                params![memo_id.as_str(), tag],
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Attaches tags that aren't written in the content, e.g. from `cap add --tag`.
+pub(crate) fn add_explicit_tags(db: &Db, memo_id: &MemoId, tags: &[String]) -> Result<()> {
+    db.atomically(|db| {
+        for tag in tags {
+            db.conn().execute(
+                "INSERT INTO memo_tags (memo_id, tag, explicit) VALUES (?1, ?2, 1)
+                 ON CONFLICT (memo_id, tag) DO UPDATE SET explicit = 1",
+                params![memo_id.as_str(), tag],
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Makes `tags` the memo's explicit tags, dropping any others. Inline hashtags
+/// come from the content and stay; returns those that were asked to go but remain.
+pub(crate) fn replace_explicit_tags(
+    db: &Db,
+    memo_id: &MemoId,
+    tags: &[String],
+) -> Result<Vec<String>> {
+    let content = db.conn().query_row(
+        "SELECT content FROM memos WHERE memo_id = ?1 AND private = 0",
+        params![memo_id.as_str()],
+        |row| memo_repo::content_from_row(row, 0),
+    )?;
+    let inline = tags::extract_tags(&content);
+    db.atomically(|db| {
+        db.conn().execute(
+            "DELETE FROM memo_tags WHERE memo_id = ?1 AND explicit = 1",
+            params![memo_id.as_str()],
+        )?;
+        set_memo_tags(db, memo_id, &inline)?;
+        add_explicit_tags(db, memo_id, tags)
+    })?;
+    Ok(inline
+        .into_iter()
+        .filter(|tag| !tags.contains(tag))
+        .collect())
+}
+
+pub(crate) fn copy_explicit_tags(db: &Db, from: &MemoId, to: &MemoId) -> Result<()> {
+    db.conn().execute(
+        "INSERT INTO memo_tags (memo_id, tag, explicit)
+            SELECT ?2, tag, 1 FROM memo_tags WHERE memo_id = ?1 AND explicit = 1
+         ON CONFLICT (memo_id, tag) DO UPDATE SET explicit = 1",
+        params![from.as_str(), to.as_str()],
+    )?;
+    Ok(())
+}
+
+pub(crate) fn fetch_tag_counts(db: &Db) -> Result<Vec<(String, usize)>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT memo_tags.tag, COUNT(*)
+         FROM memo_tags
+         JOIN memos ON memos.memo_id = memo_tags.memo_id
+         WHERE memos.deleted = 0
+         GROUP BY memo_tags.tag
+         ORDER BY COUNT(*) DESC, memo_tags.tag ASC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+    })?;
+
+    let mut counts = Vec::new();
+    for row in rows {
+        counts.push(row?);
+    }
+    Ok(counts)
+}
+
+/// Populates `memo_tags` from inline hashtags for memos written before tags were stored.
+pub(super) fn backfill_tags(db: &Db) -> Result<()> {
+    let memos = {
+        let mut stmt = db
+            .conn()
+            .prepare("SELECT memo_id, content FROM memos WHERE private = 0")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut memos = Vec::new();
+        for row in rows {
+            memos.push(row?);
+        }
+        memos
+    };
+    let tx = db.conn().unchecked_transaction()?;
+    for (memo_id, content) in memos {
+        set_memo_tags(db, &memo_id.into(), &tags::extract_tags(&content))?;
+    }
+    tx.commit()?;
+    Ok(())
+}
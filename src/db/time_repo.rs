@@ -0,0 +1,76 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{OptionalExtension, params};
+
+use crate::{db::Db, domain::memo::MemoId};
+
+pub(crate) struct TimeEntry {
+    pub(crate) label: String,
+    pub(crate) started_at: String,
+    /// `None` while the timer is still running.
+    pub(crate) stopped_at: Option<String>,
+}
+
+pub(crate) fn start_time_entry(
+    db: &Db,
+    label: &str,
+    start_memo_id: &MemoId,
+    started_at: DateTime<Local>,
+) -> Result<()> {
+    db.conn().execute(
+        "INSERT INTO time_entries (label, start_memo_id, started_at) VALUES (?1, ?2, ?3)",
+        params![label, start_memo_id.as_str(), started_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+pub(crate) fn fetch_running_entry(db: &Db) -> Result<Option<TimeEntry>> {
+    let entry = db
+        .conn()
+        .query_row(
+            "SELECT label, started_at, stopped_at FROM time_entries
+             WHERE stopped_at IS NULL
+             ORDER BY id DESC
+             LIMIT 1",
+            [],
+            entry_from_row,
+        )
+        .optional()?;
+    Ok(entry)
+}
+
+pub(crate) fn stop_running_entry(
+    db: &Db,
+    stop_memo_id: &MemoId,
+    stopped_at: DateTime<Local>,
+) -> Result<()> {
+    db.conn().execute(
+        "UPDATE time_entries SET stop_memo_id = ?1, stopped_at = ?2 WHERE stopped_at IS NULL",
+        params![stop_memo_id.as_str(), stopped_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Entries that were running at any point after `since`, oldest first.
+pub(crate) fn fetch_time_entries(db: &Db, since: DateTime<Local>) -> Result<Vec<TimeEntry>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT label, started_at, stopped_at FROM time_entries
+         WHERE stopped_at IS NULL OR julianday(stopped_at) > julianday(?1)
+         ORDER BY julianday(started_at) ASC",
+    )?;
+    let rows = stmt.query_map(params![since.to_rfc3339()], entry_from_row)?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        entries.push(row?);
+    }
+    Ok(entries)
+}
+
+fn entry_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TimeEntry> {
+    Ok(TimeEntry {
+        label: row.get(0)?,
+        started_at: row.get(1)?,
+        stopped_at: row.get(2)?,
+    })
+}
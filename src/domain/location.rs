@@ -0,0 +1,114 @@
+use anyhow::{Result, bail};
+use std::fmt;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+/// Length of one degree of latitude, close enough for bounding boxes.
+pub(crate) const METERS_PER_DEGREE: f64 = 111_320.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Location {
+    pub(crate) latitude: f64,
+    pub(crate) longitude: f64,
+}
+
+impl Location {
+    /// Great-circle distance in meters.
+    pub(crate) fn distance_to(&self, other: &Location) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.5},{:.5}", self.latitude, self.longitude)
+    }
+}
+
+/// Parses `lat,lon` in decimal degrees, e.g. `52.52,13.405`.
+pub(crate) fn parse_location(value: &str) -> Result<Location> {
+    let Some((lat, lon)) = value.split_once(',') else {
+        bail!("expected LAT,LON in decimal degrees, got `{}`", value);
+    };
+    let (Ok(latitude), Ok(longitude)) = (lat.trim().parse::<f64>(), lon.trim().parse::<f64>())
+    else {
+        bail!("expected LAT,LON in decimal degrees, got `{}`", value);
+    };
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        bail!("`{}` is out of range (latitude ±90, longitude ±180)", value);
+    }
+    Ok(Location {
+        latitude,
+        longitude,
+    })
+}
+
+/// Parses a distance like `5km`, `800m`, or `2mi` into meters; a bare number is km.
+pub(crate) fn parse_radius(value: &str) -> Result<f64> {
+    let value = value.trim().to_lowercase();
+    let split = value
+        .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit.trim() {
+        "" | "km" => 1000.0,
+        "m" => 1.0,
+        "mi" => 1609.344,
+        _ => bail!("unknown distance unit in `{}`; use m, km, or mi", value),
+    };
+    match number.parse::<f64>() {
+        Ok(number) if number > 0.0 => Ok(number * scale),
+        _ => bail!("radius must be a positive distance like `5km`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(latitude: f64, longitude: f64) -> Location {
+        Location {
+            latitude,
+            longitude,
+        }
+    }
+
+    #[test]
+    fn locations_are_decimal_degrees_in_range() {
+        assert_eq!(
+            parse_location(" 52.52 , 13.405 ").unwrap(),
+            at(52.52, 13.405)
+        );
+        assert_eq!(parse_location("-90,180").unwrap(), at(-90.0, 180.0));
+        for bad in ["52.52", "north,east", "91,0", "0,-180.5", ""] {
+            assert!(parse_location(bad).is_err(), "{bad:?} should be rejected");
+        }
+        assert_eq!(at(52.52, 13.405).to_string(), "52.52000,13.40500");
+    }
+
+    #[test]
+    fn radii_default_to_kilometers() {
+        assert_eq!(parse_radius("5").unwrap(), 5_000.0);
+        assert_eq!(parse_radius("2.5KM").unwrap(), 2_500.0);
+        assert_eq!(parse_radius("800 m").unwrap(), 800.0);
+        assert_eq!(parse_radius("1mi").unwrap(), 1_609.344);
+        for bad in ["0", "-3km", "5ft", "km", ""] {
+            assert!(parse_radius(bad).is_err(), "{bad:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn distances_follow_the_great_circle() {
+        let berlin = at(52.52, 13.405);
+        let paris = at(48.8566, 2.3522);
+        let distance = berlin.distance_to(&paris);
+        assert!((distance - 877_500.0).abs() < 2_000.0, "{distance}");
+        assert_eq!(berlin.distance_to(&berlin), 0.0);
+        // A fifth of a degree apart across the antimeridian, not 359.8 degrees.
+        let across = at(-16.8, 179.9).distance_to(&at(-16.8, -179.9));
+        assert!((21_000.0..22_000.0).contains(&across), "{across}");
+    }
+}
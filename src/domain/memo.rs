@@ -1,5 +1,9 @@
+use chrono::{DateTime, Local};
+use unicode_normalization::UnicodeNormalization;
 use uuid::Uuid;
 
+use super::{location::Location, tags};
+
 #[derive(Clone, Debug)]
 pub(crate) struct MemoId(String);
 
@@ -27,17 +31,187 @@ pub(crate) struct Memo {
     pub(crate) created_at: String,
     #[allow(dead_code)]
     pub(crate) updated_at: String,
+    pub(crate) private: bool,
+    pub(crate) tags: Vec<String>,
+    /// IANA zone name at capture time, when it could be detected.
+    pub(crate) timezone: Option<String>,
+    pub(crate) notebook: Option<String>,
+    pub(crate) location: Option<Location>,
+    pub(crate) mood: Option<u8>,
+    /// Changed locally and not yet pushed to the sync backend.
+    pub(crate) dirty: bool,
+}
+
+impl Memo {
+    /// `*` while the memo has local changes the server hasn't seen, else a space.
+    pub(crate) fn sync_marker(&self) -> char {
+        if self.dirty { '*' } else { ' ' }
+    }
+
+    /// The first non-blank line, without its `# ` heading marker, for
+    /// one-line listings; `cap show` has the rest.
+    pub(crate) fn title(&self) -> &str {
+        let line = self
+            .content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default();
+        let heading = line.trim_start_matches('#');
+        if heading.len() < line.len() && heading.starts_with(' ') {
+            heading.trim_start()
+        } else {
+            line
+        }
+    }
+
+    /// What follows the title line, trimmed.
+    pub(crate) fn body(&self) -> &str {
+        let content = self.content.trim_start();
+        content.split_once('\n').map_or("", |(_, rest)| rest.trim())
+    }
 }
 
 #[derive(Clone, Debug)]
 pub(crate) struct NewMemo {
     pub(crate) content: String,
+    pub(crate) private: bool,
+    /// Backfilled creation time; defaults to now.
+    pub(crate) created_at: Option<DateTime<Local>>,
+    /// Normalized tags attached in addition to inline hashtags.
+    pub(crate) tags: Vec<String>,
+    /// Optional 1-5 rating for journaling.
+    pub(crate) mood: Option<u8>,
+    /// `key=value` fields stored alongside the memo.
+    pub(crate) meta: Vec<(String, String)>,
+    pub(crate) notebook: Option<String>,
+    /// Where the memo was captured.
+    pub(crate) location: Option<Location>,
 }
 
 impl NewMemo {
     pub(crate) fn new(content: impl Into<String>) -> Self {
         Self {
             content: content.into(),
+            private: false,
+            created_at: None,
+            tags: Vec::new(),
+            mood: None,
+            meta: Vec::new(),
+            notebook: None,
+            location: None,
         }
     }
+
+    /// A private memo whose `content` is already encrypted.
+    pub(crate) fn encrypted(ciphertext: impl Into<String>) -> Self {
+        Self {
+            content: ciphertext.into(),
+            private: true,
+            created_at: None,
+            tags: Vec::new(),
+            mood: None,
+            meta: Vec::new(),
+            notebook: None,
+            location: None,
+        }
+    }
+
+    pub(crate) fn with_tags(mut self, tags: &[String]) -> Self {
+        self.tags = Vec::new();
+        for tag in tags.iter().map(|tag| tags::normalize_tag(tag)) {
+            if !tag.is_empty() && !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self
+    }
+
+    pub(crate) fn with_mood(mut self, mood: Option<u8>) -> Self {
+        self.mood = mood;
+        self
+    }
+
+    pub(crate) fn with_meta(mut self, meta: &[(String, String)]) -> Self {
+        self.meta = meta.to_vec();
+        self
+    }
+
+    pub(crate) fn with_notebook(mut self, notebook: Option<String>) -> Self {
+        self.notebook = notebook;
+        self
+    }
+
+    pub(crate) fn with_location(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+
+    pub(crate) fn with_created_at(mut self, created_at: Option<DateTime<Local>>) -> Self {
+        self.created_at = created_at;
+        self
+    }
+}
+
+/// The form memo text is stored in: Unicode NFC, `\n` line endings, and no
+/// whitespace at the end of any line or of the memo, so the same note pasted
+/// on Windows or macOS hashes, searches, and diffs the same. Private memos are
+/// normalized like this before they are encrypted; their base64 ciphertext
+/// passes through unchanged.
+pub(crate) fn normalize_content(content: &str) -> String {
+    let composed: String = content.nfc().collect();
+    let lines: Vec<&str> = composed
+        .split("\r\n")
+        .flat_map(|line| line.split('\r'))
+        .flat_map(|line| line.split('\n'))
+        .map(str::trim_end)
+        .collect();
+    let mut normalized = lines.join("\n");
+    normalized.truncate(normalized.trim_end().len());
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings_and_trailing_whitespace_are_normalized() {
+        assert_eq!(
+            normalize_content("first  \r\nsecond\rthird \t\r\n\r\n"),
+            "first\nsecond\nthird"
+        );
+        assert_eq!(normalize_content("  indented"), "  indented");
+    }
+
+    #[test]
+    fn decomposed_accents_are_composed() {
+        assert_eq!(normalize_content("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(normalize_content("caf\u{e9}"), "caf\u{e9}");
+    }
+
+    #[test]
+    fn title_is_the_first_line_without_heading_marker() {
+        let memo = |content: &str| Memo {
+            memo_id: "m".to_string().into(),
+            content: content.to_string(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            private: false,
+            tags: Vec::new(),
+            timezone: None,
+            notebook: None,
+            location: None,
+            mood: None,
+            dirty: false,
+        };
+        assert_eq!(
+            memo("\n## Weekly review \n- shipped").title(),
+            "Weekly review"
+        );
+        assert_eq!(memo("#idea for later\nbody").title(), "#idea for later");
+        assert_eq!(memo("").title(), "");
+        assert_eq!(memo("\n## Weekly review \n- shipped\n").body(), "- shipped");
+        assert_eq!(memo("one line").body(), "");
+    }
 }
@@ -0,0 +1,65 @@
+use std::ops::Range;
+
+/// Byte ranges of the `@name` mentions that start a word in `content`, `@`
+/// included. Email addresses don't count since their `@` is mid-word.
+pub(crate) fn mention_ranges(content: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for token in content.split_inclusive(char::is_whitespace) {
+        let start = offset;
+        offset += token.len();
+        let Some(rest) = token.strip_prefix('@') else {
+            continue;
+        };
+        let name_len: usize = rest
+            .chars()
+            .take_while(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'))
+            .map(char::len_utf8)
+            .sum();
+        // `@sam.` at the end of a sentence mentions `sam`.
+        let name_len = rest[..name_len].trim_end_matches(['.', '-']).len();
+        if name_len > 0 {
+            ranges.push(start..start + 1 + name_len);
+        }
+    }
+    ranges
+}
+
+/// The people `content` mentions, lowercased and de-duplicated in order.
+pub(crate) fn extract_mentions(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for range in mention_ranges(content) {
+        let name = normalize_mention(&content[range]);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Lowercases a name and strips a leading `@` so `@Alice` and `alice` compare equal.
+pub(crate) fn normalize_mention(name: &str) -> String {
+    name.trim().trim_start_matches('@').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_start_a_word() {
+        assert_eq!(
+            extract_mentions("1:1 with @Alice and @bob.smith, cc @alice. mail me@example.com @"),
+            ["alice", "bob.smith"]
+        );
+        let content = "ask @sam.";
+        let ranges = mention_ranges(content);
+        assert_eq!(
+            ranges
+                .iter()
+                .map(|r| &content[r.clone()])
+                .collect::<Vec<_>>(),
+            ["@sam"]
+        );
+    }
+}
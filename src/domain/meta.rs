@@ -0,0 +1,48 @@
+use anyhow::{Result, bail};
+
+/// Checks a metadata key: non-empty, no `=`, no whitespace.
+pub(crate) fn parse_meta_key(value: &str) -> Result<String> {
+    let key = value.trim();
+    if key.is_empty() {
+        bail!("metadata key can't be empty");
+    }
+    if key.contains('=') || key.chars().any(char::is_whitespace) {
+        bail!("metadata key `{}` can't contain `=` or spaces", key);
+    }
+    Ok(key.to_string())
+}
+
+/// Parses a `key=value` pair as given to `--meta`.
+pub(crate) fn parse_meta_pair(value: &str) -> Result<(String, String)> {
+    let Some((key, field)) = value.split_once('=') else {
+        bail!("expected KEY=VALUE, got `{}`", value);
+    };
+    Ok((parse_meta_key(key)?, field.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_trimmed_single_words() {
+        assert_eq!(parse_meta_key(" project ").unwrap(), "project");
+        for bad in ["", "  ", "two words", "a=b", "tab\there"] {
+            assert!(parse_meta_key(bad).is_err(), "{bad:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn pairs_split_at_the_first_equals_sign() {
+        assert_eq!(
+            parse_meta_pair("url = https://example.com/?a=b").unwrap(),
+            ("url".to_string(), "https://example.com/?a=b".to_string())
+        );
+        assert_eq!(
+            parse_meta_pair("empty=").unwrap(),
+            ("empty".to_string(), String::new())
+        );
+        assert!(parse_meta_pair("no-value").is_err());
+        assert!(parse_meta_pair("=value").is_err());
+    }
+}
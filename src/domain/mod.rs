@@ -1 +1,9 @@
+pub(crate) mod location;
 pub(crate) mod memo;
+pub(crate) mod mentions;
+pub(crate) mod meta;
+pub(crate) mod mood;
+pub(crate) mod notebook;
+pub(crate) mod rules;
+pub(crate) mod tags;
+pub(crate) mod tidy;
@@ -0,0 +1,75 @@
+use anyhow::{Result, bail};
+use std::ops::RangeInclusive;
+
+pub(crate) const MIN_MOOD: u8 = 1;
+pub(crate) const MAX_MOOD: u8 = 5;
+
+const MOOD_EMOJI: [(&str, u8); 10] = [
+    ("😞", 1),
+    ("😢", 1),
+    ("🙁", 2),
+    ("😕", 2),
+    ("😐", 3),
+    ("😶", 3),
+    ("🙂", 4),
+    ("😊", 4),
+    ("😄", 5),
+    ("😁", 5),
+];
+
+/// Parses a mood rating: `1`..`5` or one of the face emoji.
+pub(crate) fn parse_mood(value: &str) -> Result<u8> {
+    let value = value.trim();
+    if let Some((_, mood)) = MOOD_EMOJI
+        .iter()
+        .find(|(emoji, _)| value.trim_end_matches('\u{fe0f}') == *emoji)
+    {
+        return Ok(*mood);
+    }
+    match value.parse::<u8>() {
+        Ok(mood) if (MIN_MOOD..=MAX_MOOD).contains(&mood) => Ok(mood),
+        _ => bail!("mood must be 1-5 or a face emoji like 🙂"),
+    }
+}
+
+/// Parses a mood filter: a single rating (`4`) or an inclusive range (`3-5`).
+pub(crate) fn parse_mood_range(value: &str) -> Result<RangeInclusive<u8>> {
+    match value.split_once('-') {
+        Some((low, high)) => {
+            let (low, high) = (parse_mood(low)?, parse_mood(high)?);
+            if low > high {
+                bail!("mood range `{}` is backwards", value);
+            }
+            Ok(low..=high)
+        }
+        None => {
+            let mood = parse_mood(value)?;
+            Ok(mood..=mood)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moods_are_numbers_or_faces() {
+        assert_eq!(parse_mood(" 4 ").unwrap(), 4);
+        assert_eq!(parse_mood("😞").unwrap(), 1);
+        // Some keyboards add a variation selector after the face.
+        assert_eq!(parse_mood("😐\u{fe0f}").unwrap(), 3);
+        for bad in ["0", "6", "-1", "great", "🎉", ""] {
+            assert!(parse_mood(bad).is_err(), "{bad} should be rejected");
+        }
+    }
+
+    #[test]
+    fn ranges_are_inclusive_and_ordered() {
+        assert_eq!(parse_mood_range("3-5").unwrap(), 3..=5);
+        assert_eq!(parse_mood_range("2").unwrap(), 2..=2);
+        assert_eq!(parse_mood_range("🙁-😄").unwrap(), 2..=5);
+        assert!(parse_mood_range("5-3").is_err());
+        assert!(parse_mood_range("1-9").is_err());
+    }
+}
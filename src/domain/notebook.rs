@@ -0,0 +1,10 @@
+use anyhow::{Result, bail};
+
+/// Checks a notebook name as given to `--notebook`: non-empty after trimming.
+pub(crate) fn parse_notebook(value: &str) -> Result<String> {
+    let name = value.trim();
+    if name.is_empty() {
+        bail!("notebook name can't be empty");
+    }
+    Ok(name.to_string())
+}
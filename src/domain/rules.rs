@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    config::RuleConfig,
+    domain::{memo::NewMemo, tags},
+};
+
+/// The `[[rules]]` from the config, ready to run against new memos. They are
+/// compiled as the config loads, so a bad pattern is reported up front.
+#[derive(Default, Deserialize)]
+#[serde(try_from = "Vec<RuleConfig>")]
+pub(crate) struct Rules {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    content: Option<Regex>,
+    url_host: Option<String>,
+    tags: Vec<String>,
+    ignore: bool,
+}
+
+impl Rules {
+    pub(crate) fn compile(configs: &[RuleConfig]) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (index, config) in configs.iter().enumerate() {
+            let content = config
+                .content
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .with_context(|| format!("rule {} has an invalid content pattern", index + 1))?;
+            rules.push(Rule {
+                content,
+                url_host: config
+                    .url_host
+                    .as_deref()
+                    .map(|host| host.trim_start_matches("www.").to_ascii_lowercase()),
+                tags: config
+                    .tags
+                    .iter()
+                    .map(|tag| tags::normalize_tag(tag))
+                    .collect(),
+                ignore: config.ignore,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Adds the tags of every matching rule to `new_memo`. Returns false when a
+    /// matching rule says to ignore the memo, which should then not be saved.
+    pub(crate) fn apply(&self, new_memo: &mut NewMemo) -> bool {
+        for rule in &self.rules {
+            if !rule.matches(&new_memo.content) {
+                continue;
+            }
+            if rule.ignore {
+                return false;
+            }
+            for tag in &rule.tags {
+                if !tag.is_empty() && !new_memo.tags.contains(tag) {
+                    new_memo.tags.push(tag.clone());
+                }
+            }
+        }
+        true
+    }
+}
+
+impl Rule {
+    /// Every condition the rule sets must hold; a rule without any matches all memos.
+    fn matches(&self, content: &str) -> bool {
+        if let Some(pattern) = &self.content
+            && !pattern.is_match(content)
+        {
+            return false;
+        }
+        if let Some(host) = &self.url_host
+            && !link_hosts(content).any(|link| host_matches(&link, host))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+impl TryFrom<Vec<RuleConfig>> for Rules {
+    type Error = String;
+
+    fn try_from(configs: Vec<RuleConfig>) -> Result<Self, String> {
+        Self::compile(&configs).map_err(|err| format!("{:#}", err))
+    }
+}
+
+/// Lowercased hosts of the `http(s)://` links in `content`.
+fn link_hosts(content: &str) -> impl Iterator<Item = String> + '_ {
+    content.split_whitespace().filter_map(|token| {
+        let start = token.find("https://").or_else(|| token.find("http://"))?;
+        let rest = token[start..].split_once("://")?.1;
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host = authority.rsplit('@').next()?.split(':').next()?;
+        Some(host.to_ascii_lowercase())
+    })
+}
+
+/// `github.com` matches `github.com`, `www.github.com`, and `gist.github.com`.
+fn host_matches(link: &str, host: &str) -> bool {
+    link == host
+        || link
+            .strip_suffix(host)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(content: Option<&str>, url_host: Option<&str>, tags: &[&str]) -> RuleConfig {
+        RuleConfig {
+            content: content.map(str::to_string),
+            url_host: url_host.map(str::to_string),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ignore: false,
+        }
+    }
+
+    fn tags_for(configs: &[RuleConfig], content: &str) -> Option<Vec<String>> {
+        let mut new_memo = NewMemo::new(content).with_tags(&["kept".to_string()]);
+        Rules::compile(configs)
+            .unwrap()
+            .apply(&mut new_memo)
+            .then_some(new_memo.tags)
+    }
+
+    #[test]
+    fn matching_rules_add_their_tags() {
+        let configs = [
+            rule(Some("^TODO"), None, &["todo"]),
+            rule(None, Some("www.GitHub.com"), &["dev", "todo"]),
+        ];
+        assert_eq!(
+            tags_for(&configs, "TODO review (https://gist.github.com/x)"),
+            Some(vec!["kept".into(), "todo".into(), "dev".into()])
+        );
+        assert_eq!(
+            tags_for(&configs, "see http://user@github.com:443/a"),
+            Some(vec!["kept".into(), "dev".into(), "todo".into()])
+        );
+        assert_eq!(
+            tags_for(&configs, "not TODO https://notgithub.com"),
+            Some(vec!["kept".into()])
+        );
+    }
+
+    #[test]
+    fn ignore_rules_drop_the_memo_and_bad_patterns_are_reported() {
+        let configs = [RuleConfig {
+            ignore: true,
+            ..rule(Some(r"^\s*(ok|test)\s*$"), None, &[])
+        }];
+        assert_eq!(tags_for(&configs, " test "), None);
+        assert!(tags_for(&configs, "test it properly").is_some());
+
+        let err = Rules::compile(&[rule(Some("(unclosed"), None, &[])]).err();
+        assert!(format!("{:#}", err.unwrap()).starts_with("rule 1 has an invalid content pattern"));
+        let err = toml::from_str::<crate::config::Config>("[[rules]]\ncontent = \"(\"").err();
+        assert!(
+            err.unwrap()
+                .to_string()
+                .contains("rule 1 has an invalid content pattern")
+        );
+    }
+}
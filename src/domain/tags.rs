@@ -0,0 +1,26 @@
+/// Tag that marks a memo as archived, by `cap review` or a retention rule.
+pub(crate) const ARCHIVED_TAG: &str = "archived";
+
+/// Extracts `#hashtags` that start a word, lowercased and de-duplicated in order.
+pub(crate) fn extract_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in content.split_whitespace() {
+        let Some(rest) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag: String = rest
+            .chars()
+            .take_while(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '/'))
+            .collect();
+        let tag = normalize_tag(&tag);
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Lowercases a tag and strips a leading `#` so `#Work` and `work` compare equal.
+pub(crate) fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
@@ -0,0 +1,119 @@
+use crate::config::TidyConfig;
+
+const MAX_BLANK_LINES: usize = 2;
+const BULLETS: [char; 3] = ['*', '+', '•'];
+
+/// Applies the enabled tidy rules to memo text. Fenced code blocks keep their
+/// list markers and blank lines as written.
+pub(crate) fn tidy(content: &str, config: &TidyConfig) -> String {
+    if !(config.trim_trailing_whitespace
+        || config.normalize_list_markers
+        || config.collapse_blank_lines)
+    {
+        return content.to_string();
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    let mut blank_run = 0;
+    for line in content.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let blank = line.trim().is_empty();
+        blank_run = if blank { blank_run + 1 } else { 0 };
+        if config.collapse_blank_lines && !in_fence && blank_run > MAX_BLANK_LINES {
+            continue;
+        }
+        let mut line = line.to_string();
+        if config.normalize_list_markers && !in_fence {
+            line = normalize_list_marker(&line);
+        }
+        if config.trim_trailing_whitespace {
+            line.truncate(line.trim_end().len());
+        }
+        lines.push(line);
+    }
+
+    let mut tidied = lines.join("\n");
+    if config.trim_trailing_whitespace {
+        tidied.truncate(tidied.trim_end().len());
+    }
+    tidied
+}
+
+fn normalize_list_marker(line: &str) -> String {
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    if let Some(bullet) = body.chars().next().filter(|ch| BULLETS.contains(ch))
+        && let Some(rest) = body[bullet.len_utf8()..].strip_prefix(' ')
+    {
+        return format!("{}- {}", indent, rest);
+    }
+    let digits = body.len()
+        - body
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    if digits > 0
+        && let Some(rest) = body[digits..].strip_prefix(") ")
+    {
+        return format!("{}{}. {}", indent, &body[..digits], rest);
+    }
+    line.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_rules() -> TidyConfig {
+        TidyConfig {
+            trim_trailing_whitespace: true,
+            normalize_list_markers: true,
+            collapse_blank_lines: true,
+        }
+    }
+
+    #[test]
+    fn disabled_rules_leave_content_alone() {
+        let content = "* a  \n\n\n\n\nb\n";
+        assert_eq!(tidy(content, &TidyConfig::default()), content);
+    }
+
+    #[test]
+    fn rules_apply_outside_code_fences() {
+        let content = "todo:  \r\n* milk\n  + eggs\n• bread\n2) call mom\n*bold* stays\n\n\n\n\nend\n```\n* code\n\n\n\n```\n\n";
+        assert_eq!(
+            tidy(content, &all_rules()),
+            "todo:\n- milk\n  - eggs\n- bread\n2. call mom\n*bold* stays\n\n\nend\n```\n* code\n\n\n\n```"
+        );
+    }
+
+    #[test]
+    fn each_rule_can_be_enabled_alone() {
+        let content = "* a  \n\n\n\nb";
+        let only = |config: TidyConfig| tidy(content, &config);
+        assert_eq!(
+            only(TidyConfig {
+                trim_trailing_whitespace: true,
+                ..Default::default()
+            }),
+            "* a\n\n\n\nb"
+        );
+        assert_eq!(
+            only(TidyConfig {
+                normalize_list_markers: true,
+                ..Default::default()
+            }),
+            "- a  \n\n\n\nb"
+        );
+        assert_eq!(
+            only(TidyConfig {
+                collapse_blank_lines: true,
+                ..Default::default()
+            }),
+            "* a  \n\n\nb"
+        );
+    }
+}
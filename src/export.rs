@@ -0,0 +1,106 @@
+//! `cap export`: memos as JSON lines, for backups and other tools.
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::db::{self, Db, PendingMemo};
+
+/// When the last `--since-last` export started.
+const LAST_EXPORT_KEY: &str = "export_last_at";
+
+/// One exported line. Private memos stay encrypted.
+#[derive(Serialize)]
+struct ExportedMemo<'a> {
+    memo_id: &'a str,
+    content: &'a str,
+    created_at: &'a str,
+    updated_at: &'a str,
+    deleted: bool,
+    private: bool,
+    notebook: Option<&'a str>,
+}
+
+/// Writes the memos changed since `since` to `out`, one JSON object per line,
+/// deletions included so a copy elsewhere can drop them too. With `since_last`
+/// it starts where the previous such export did and records this one, so a
+/// cron job only ever sends what changed. Returns how many memos were written.
+pub(crate) fn export(
+    db: &Db,
+    since: Option<DateTime<Local>>,
+    since_last: bool,
+    out: &mut impl Write,
+) -> Result<usize> {
+    // Taken before reading, so a memo edited during the export goes out again next time.
+    let started = Local::now();
+    let since = match db::get_kv(db, LAST_EXPORT_KEY)? {
+        Some(last) if since_last => {
+            Some(DateTime::parse_from_rfc3339(&last)?.with_timezone(&Local))
+        }
+        _ => since,
+    };
+    let memos = db::fetch_changed_memos(db, since)?;
+    for memo in &memos {
+        serde_json::to_writer(&mut *out, &exported_memo(memo))?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    // Only once every line is out, so a broken pipe repeats the export instead of losing it.
+    if since_last {
+        db::set_kv(db, LAST_EXPORT_KEY, &started.to_rfc3339())?;
+    }
+    Ok(memos.len())
+}
+
+fn exported_memo(memo: &PendingMemo) -> ExportedMemo<'_> {
+    ExportedMemo {
+        memo_id: &memo.memo_id,
+        content: &memo.content,
+        created_at: &memo.created_at,
+        updated_at: &memo.updated_at,
+        deleted: memo.deleted,
+        private: memo.private,
+        notebook: memo.notebook.as_deref(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+    use crate::domain::memo::NewMemo;
+
+    fn export_lines(db: &Db, since_last: bool) -> Vec<serde_json::Value> {
+        let mut out = Vec::new();
+        export(db, None, since_last, &mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn since_last_only_sends_what_changed() {
+        let db = Db::open_in_memory().unwrap();
+        let kept = db::add_memo(&db, &NewMemo::new("kept")).unwrap();
+        let edited = db::add_memo(&db, &NewMemo::new("draft")).unwrap();
+        // Timestamps are compared to the millisecond.
+        let tick = || thread::sleep(Duration::from_millis(5));
+        tick();
+        assert_eq!(export_lines(&db, true).len(), 2);
+        tick();
+        assert!(export_lines(&db, true).is_empty());
+
+        db::update_memo_content(&db, &edited, "final").unwrap();
+        db::delete_memo(&db, &kept).unwrap();
+        let lines = export_lines(&db, true);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["memo_id"], kept.as_str());
+        assert_eq!(lines[0]["deleted"], true);
+        assert_eq!(lines[1]["content"], "final");
+        assert_eq!(export_lines(&db, false).len(), 2);
+    }
+}
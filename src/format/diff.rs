@@ -0,0 +1,75 @@
+use crossterm::style::Stylize;
+use similar::{ChangeTag, TextDiff};
+
+const CONTEXT_LINES: usize = 3;
+
+/// A unified diff from `old` to `new` under `--- a/<name>` / `+++ b/<name>`
+/// headers, with removals in red and additions in green when `color` is set.
+/// Empty when nothing changed.
+pub(crate) fn format_diff(name: &str, old: &str, new: &str, color: bool) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut out = String::new();
+    for hunk in diff
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .iter_hunks()
+    {
+        if out.is_empty() {
+            out.push_str(&paint(
+                &format!("--- a/{}\n+++ b/{}\n", name, name),
+                color,
+                |text| text.bold().to_string(),
+            ));
+        }
+        out.push_str(&paint(&format!("{}\n", hunk.header()), color, |text| {
+            text.cyan().to_string()
+        }));
+        for change in hunk.iter_changes() {
+            let (sign, style): (&str, fn(&str) -> String) = match change.tag() {
+                ChangeTag::Delete => ("-", |text| text.red().to_string()),
+                ChangeTag::Insert => ("+", |text| text.green().to_string()),
+                ChangeTag::Equal => (" ", str::to_string),
+            };
+            let mut line = format!("{}{}", sign, change.value());
+            if change.missing_newline() {
+                line.push('\n');
+            }
+            out.push_str(&paint(&line, color, style));
+        }
+    }
+    out
+}
+
+/// Styles `text` line by line so colors never run past a newline.
+fn paint(text: &str, color: bool, style: impl Fn(&str) -> String) -> String {
+    if !color {
+        return text.to_string();
+    }
+    text.split_inclusive('\n')
+        .map(|line| match line.strip_suffix('\n') {
+            Some(line) => format!("{}\n", style(line)),
+            None => style(line),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_changed_lines_with_context() {
+        let diff = format_diff("1a2b3c4d", "one\ntwo\nthree", "one\n2\nthree", false);
+        assert_eq!(
+            diff,
+            "--- a/1a2b3c4d\n+++ b/1a2b3c4d\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+        );
+        assert_eq!(format_diff("x", "same", "same", false), "");
+    }
+
+    #[test]
+    fn color_wraps_each_line() {
+        let diff = format_diff("x", "old", "", true);
+        assert!(diff.contains(&format!("{}\n", "-old".red())), "{:?}", diff);
+    }
+}
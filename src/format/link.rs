@@ -0,0 +1,136 @@
+use std::env;
+
+/// Terminals setting `TERM_PROGRAM` that open OSC 8 links.
+const LINKING_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"];
+/// `TERM` values of terminals that open OSC 8 links.
+const LINKING_TERMS: &[&str] = &[
+    "xterm-kitty",
+    "xterm-ghostty",
+    "alacritty",
+    "foot",
+    "wezterm",
+];
+/// First VTE release (GNOME Terminal and friends) with OSC 8 support: 0.50.
+const FIRST_LINKING_VTE: u32 = 5000;
+
+/// Whether the terminal is known to turn OSC 8 sequences into links.
+/// `FORCE_HYPERLINK=1` or `=0` overrides the guess.
+pub(crate) fn terminal_supports_hyperlinks() -> bool {
+    supports_hyperlinks(|name| env::var(name).ok())
+}
+
+fn supports_hyperlinks(var: impl Fn(&str) -> Option<String>) -> bool {
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    if var("WT_SESSION").is_some() || var("KITTY_WINDOW_ID").is_some() {
+        return true;
+    }
+    if var("TERM_PROGRAM").is_some_and(|program| LINKING_PROGRAMS.contains(&program.as_str())) {
+        return true;
+    }
+    if var("TERM").is_some_and(|term| LINKING_TERMS.contains(&term.as_str())) {
+        return true;
+    }
+    var("VTE_VERSION")
+        .and_then(|version| version.parse::<u32>().ok())
+        .is_some_and(|version| version >= FIRST_LINKING_VTE)
+}
+
+/// `text` shown as a clickable link to `url`.
+pub(crate) fn hyperlink(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+/// Where a memo's id links to. Opening it needs a `cap://` handler registered
+/// with the system that runs `cap show` on the id.
+pub(crate) fn memo_url(memo_id: &str) -> String {
+    format!("cap://show/{memo_id}")
+}
+
+/// A memo summary `line` made from `content`, with the id at its start linked
+/// to the memo and every web address linked in full, even one cut short by
+/// truncation. `id` is the id as shown and the full id.
+pub(crate) fn link_memo_line(line: &str, id: Option<(&str, &str)>, content: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    if let Some((shown, memo_id)) = id
+        && let Some(start) = rest.find(shown)
+    {
+        out.push_str(&rest[..start]);
+        out.push_str(&hyperlink(shown, &memo_url(memo_id)));
+        rest = &rest[start + shown.len()..];
+    }
+    while let Some(start) = find_url_start(rest) {
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |len| start + len);
+        let shown = trim_url(rest[start..end].trim_end_matches(['.', '…']));
+        let target = content
+            .split(char::is_whitespace)
+            .map(trim_url)
+            .find(|url| url.starts_with(shown))
+            .unwrap_or(shown);
+        out.push_str(&rest[..start]);
+        out.push_str(&hyperlink(shown, target));
+        rest = &rest[start + shown.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn find_url_start(text: &str) -> Option<usize> {
+    ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| text.find(scheme))
+        .min()
+}
+
+fn trim_url(url: &str) -> &str {
+    let url = url.trim_start_matches(['<', '(', '[']);
+    url.trim_end_matches(['.', ',', ';', ':', ')', ']', '>', '!', '?', '"', '\''])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn ids_and_cut_off_urls_link_to_their_full_targets() {
+        let content = "Read https://example.com/articles/long-one later";
+        let line = "a1b2c3d4  10:00  Read https://example.com/arti...";
+        assert_eq!(
+            link_memo_line(line, Some(("a1b2c3d4", "a1b2c3d4-full")), content),
+            format!(
+                "{}  10:00  Read {}...",
+                hyperlink("a1b2c3d4", "cap://show/a1b2c3d4-full"),
+                hyperlink(
+                    "https://example.com/arti",
+                    "https://example.com/articles/long-one"
+                )
+            )
+        );
+        assert_eq!(link_memo_line("no links", None, "no links"), "no links");
+    }
+
+    #[test]
+    fn known_terminals_get_links_unless_forced_off() {
+        let detect = |vars: &[(&str, &str)]| {
+            let vars: HashMap<_, _> = vars.iter().copied().collect();
+            supports_hyperlinks(|name| vars.get(name).map(|value| value.to_string()))
+        };
+        assert!(detect(&[("TERM_PROGRAM", "WezTerm")]));
+        assert!(detect(&[("VTE_VERSION", "7006")]));
+        assert!(!detect(&[("VTE_VERSION", "4602")]));
+        assert!(!detect(&[("TERM", "xterm-256color")]));
+        assert!(detect(&[
+            ("TERM", "xterm-256color"),
+            ("FORCE_HYPERLINK", "1")
+        ]));
+        assert!(!detect(&[
+            ("KITTY_WINDOW_ID", "1"),
+            ("FORCE_HYPERLINK", "0")
+        ]));
+    }
+}
@@ -1,5 +1,16 @@
-pub(crate) use text::format_memo_line;
-pub(crate) use time::format_display_time;
+pub(crate) use diff::format_diff;
+pub(crate) use link::{link_memo_line, terminal_supports_hyperlinks};
+pub(crate) use size::format_bytes;
+pub(crate) use table::format_table;
+pub(crate) use text::{LineStyle, format_memo_line};
+pub(crate) use time::{
+    DisplayZone, format_display_time, format_display_time_in, format_duration,
+    format_relative_time, local_timezone_name,
+};
 
+mod diff;
+mod link;
+mod size;
+mod table;
 mod text;
 mod time;
@@ -0,0 +1,101 @@
+use unicode_width::UnicodeWidthStr;
+
+use super::text::{LineStyle, sanitize_content, truncate_with_ellipsis};
+
+const COLUMN_GAP: &str = "  ";
+
+/// Lays out `rows` under `headers` in aligned columns. Cells are flattened to
+/// one line; the last column takes whatever width is left and is truncated to
+/// fit `max_width`, while the others keep their natural width.
+pub(crate) fn format_table(
+    headers: &[&str],
+    rows: &[Vec<String>],
+    max_width: usize,
+    style: &LineStyle,
+) -> Vec<String> {
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| sanitize_content(cell, style))
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.width()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    let Some(last) = widths.len().checked_sub(1) else {
+        return Vec::new();
+    };
+    let leading: usize = widths[..last]
+        .iter()
+        .map(|width| width + COLUMN_GAP.len())
+        .sum();
+    let last_width = max_width.saturating_sub(leading).min(widths[last]);
+
+    let header_row: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    std::iter::once(&header_row)
+        .chain(&rows)
+        .map(|row| {
+            let mut line = String::new();
+            for (index, cell) in row.iter().enumerate() {
+                if index == last {
+                    line.push_str(&truncate_with_ellipsis(cell, last_width, style));
+                } else {
+                    line.push_str(cell);
+                    line.push_str(&" ".repeat(widths[index] - cell.width()));
+                    line.push_str(COLUMN_GAP);
+                }
+            }
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[test]
+    fn aligns_wide_characters_and_truncates_the_last_column() {
+        let rows = [
+            row(&["a1b2", "#工作", "first line\nsecond line"]),
+            row(&["c3", "", "short"]),
+        ];
+        let lines = format_table(&["ID", "TAGS", "CONTENT"], &rows, 24, &LineStyle::default());
+        assert_eq!(
+            lines,
+            [
+                "ID    TAGS   CONTENT",
+                "a1b2  #工作  first li...",
+                "c3           short",
+            ]
+        );
+    }
+
+    #[test]
+    fn truncation_never_splits_an_emoji_sequence() {
+        let rows = [row(&["a1", "🏳\u{fe0f}\u{200d}🌈🏳\u{fe0f}\u{200d}🌈 pride"])];
+        let lines = format_table(&["ID", "CONTENT"], &rows, 9, &LineStyle::default());
+        assert_eq!(lines[1], "a1  🏳\u{fe0f}\u{200d}🌈...");
+    }
+
+    #[test]
+    fn unlimited_width_keeps_everything() {
+        let lines = format_table(
+            &["ID"],
+            &[row(&["only"])],
+            usize::MAX,
+            &LineStyle::default(),
+        );
+        assert_eq!(lines, ["ID", "only"]);
+        assert!(format_table(&[], &[], 80, &LineStyle::default()).is_empty());
+    }
+}
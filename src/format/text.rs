@@ -1,49 +1,168 @@
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-pub(crate) fn format_memo_line(display_time: &str, content: &str, max_width: usize) -> String {
+/// Shown in place of each line break when `newline_marker` is set.
+const NEWLINE_MARKER: &str = "⏎";
+
+/// How memo text is flattened and shortened to fit one line.
+#[derive(Clone)]
+pub(crate) struct LineStyle {
+    /// Appended to text that had to be cut; may be empty.
+    pub(crate) ellipsis: String,
+    /// Shows line breaks as `⏎` instead of folding them into spaces.
+    pub(crate) newline_marker: bool,
+    /// Cuts between whole graphemes, so accents, flags and joined emoji stay intact.
+    pub(crate) graphemes: bool,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            ellipsis: "...".to_string(),
+            newline_marker: false,
+            graphemes: true,
+        }
+    }
+}
+
+pub(crate) fn format_memo_line(
+    display_time: &str,
+    content: &str,
+    max_width: usize,
+    style: &LineStyle,
+) -> String {
     if max_width == 0 {
         return String::new();
     }
 
     let prefix = format!("{}  ", display_time);
     let prefix_width = UnicodeWidthStr::width(prefix.as_str());
-    let clean_content = sanitize_content(content);
+    let clean_content = sanitize_content(content, style);
     if max_width <= prefix_width {
-        return truncate_with_ellipsis(display_time, max_width);
+        return truncate_with_ellipsis(display_time, max_width, style);
     }
 
     let content_width = max_width.saturating_sub(prefix_width);
-    let truncated = truncate_with_ellipsis(&clean_content, content_width);
+    let truncated = truncate_with_ellipsis(&clean_content, content_width, style);
     format!("{}{}", prefix, truncated)
 }
 
-fn sanitize_content(content: &str) -> String {
+pub(super) fn sanitize_content(content: &str, style: &LineStyle) -> String {
+    let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !style.newline_marker {
+        return collapse(content);
+    }
     content
-        .replace(['\n', '\r', '\t'], " ")
-        .split_whitespace()
+        .lines()
+        .map(collapse)
+        .filter(|line| !line.is_empty())
         .collect::<Vec<_>>()
-        .join(" ")
+        .join(&format!(" {NEWLINE_MARKER} "))
 }
 
-fn truncate_with_ellipsis(value: &str, max_width: usize) -> String {
+pub(super) fn truncate_with_ellipsis(value: &str, max_width: usize, style: &LineStyle) -> String {
     let value_width = UnicodeWidthStr::width(value);
     if value_width <= max_width {
         return value.to_string();
     }
-    if max_width <= 3 {
-        return ".".repeat(max_width);
+    let ellipsis_width = UnicodeWidthStr::width(style.ellipsis.as_str());
+    if max_width <= ellipsis_width {
+        return take_width(&style.ellipsis, max_width, true);
     }
 
+    let mut result = take_width(value, max_width - ellipsis_width, style.graphemes);
+    result.push_str(&style.ellipsis);
+    result
+}
+
+/// The longest start of `value` at most `max_width` columns wide.
+fn take_width(value: &str, max_width: usize, graphemes: bool) -> String {
     let mut current_width = 0;
     let mut result = String::new();
-    for ch in value.chars() {
-        let ch_width = UnicodeWidthChar::width(ch).unwrap_or(1);
-        if current_width + ch_width > max_width - 3 {
-            break;
+    if graphemes {
+        for grapheme in value.graphemes(true) {
+            let width = UnicodeWidthStr::width(grapheme);
+            if current_width + width > max_width {
+                break;
+            }
+            result.push_str(grapheme);
+            current_width += width;
+        }
+    } else {
+        for ch in value.chars() {
+            let width = UnicodeWidthChar::width(ch).unwrap_or(1);
+            if current_width + width > max_width {
+                break;
+            }
+            result.push(ch);
+            current_width += width;
         }
-        result.push(ch);
-        current_width += ch_width;
     }
-    result.push_str("...");
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation_follows_the_line_style() {
+        let style = LineStyle::default();
+        assert_eq!(
+            format_memo_line("10:00", "a long memo line", 16, &style),
+            "10:00  a long..."
+        );
+        assert_eq!(truncate_with_ellipsis("abcdef", 2, &style), "..");
+
+        let unicode = LineStyle {
+            ellipsis: "…".to_string(),
+            ..LineStyle::default()
+        };
+        assert_eq!(truncate_with_ellipsis("abcdef", 4, &unicode), "abc…");
+        let none = LineStyle {
+            ellipsis: String::new(),
+            ..LineStyle::default()
+        };
+        assert_eq!(truncate_with_ellipsis("abcdef", 4, &none), "abcd");
+    }
+
+    #[test]
+    fn line_breaks_fold_or_show_a_marker() {
+        let content = "first  line\n\n\tsecond\r\nthird";
+        assert_eq!(
+            sanitize_content(content, &LineStyle::default()),
+            "first line second third"
+        );
+        let marked = LineStyle {
+            newline_marker: true,
+            ..LineStyle::default()
+        };
+        assert_eq!(
+            sanitize_content(content, &marked),
+            "first line ⏎ second ⏎ third"
+        );
+    }
+
+    #[test]
+    fn grapheme_truncation_keeps_combined_characters_whole() {
+        let whole = LineStyle {
+            ellipsis: String::new(),
+            ..LineStyle::default()
+        };
+        let chars = LineStyle {
+            graphemes: false,
+            ..whole.clone()
+        };
+        // A family emoji is one grapheme joining three people.
+        let family = "👨\u{200d}👩\u{200d}👧 home";
+        assert_eq!(
+            truncate_with_ellipsis(family, 2, &whole),
+            "👨\u{200d}👩\u{200d}👧"
+        );
+        assert_eq!(truncate_with_ellipsis(family, 2, &chars), "👨\u{200d}");
+        assert_eq!(
+            truncate_with_ellipsis("cafe\u{301}s", 4, &whole),
+            "cafe\u{301}"
+        );
+    }
+}
@@ -1,11 +1,109 @@
-use chrono::{DateTime, Local};
+use anyhow::{Result, bail};
+use chrono::{DateTime, FixedOffset, Local, TimeZone};
+use chrono_tz::Tz;
+use std::env;
+
+const RELATIVE_MAX_DAYS: i64 = 7;
+
+const DISPLAY_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Which clock memo timestamps are rendered in.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum DisplayZone {
+    Local,
+    /// The zone (or UTC offset) each memo was captured in.
+    Original,
+    Named(Tz),
+}
+
+impl DisplayZone {
+    /// Parses `local`, `original`, or an IANA name such as `Asia/Shanghai`.
+    pub(crate) fn parse(value: &str) -> Result<Self> {
+        match value.trim() {
+            "local" => Ok(Self::Local),
+            "original" => Ok(Self::Original),
+            name => match name.parse::<Tz>() {
+                Ok(tz) => Ok(Self::Named(tz)),
+                Err(_) => bail!("unknown timezone `{}`", name),
+            },
+        }
+    }
+}
 
 pub(crate) fn format_display_time(value: &str) -> String {
-    match DateTime::parse_from_rfc3339(value) {
-        Ok(timestamp) => timestamp
-            .with_timezone(&Local)
-            .format("%Y-%m-%d %H:%M:%S")
-            .to_string(),
-        Err(_) => value.to_string(),
+    format_display_time_in(value, None, DisplayZone::Local)
+}
+
+/// Formats an RFC 3339 timestamp in `zone`; `captured_tz` is the memo's own zone
+/// name, used by [`DisplayZone::Original`].
+pub(crate) fn format_display_time_in(
+    value: &str,
+    captured_tz: Option<&str>,
+    zone: DisplayZone,
+) -> String {
+    let Ok(timestamp) = DateTime::parse_from_rfc3339(value) else {
+        return value.to_string();
+    };
+    match zone {
+        DisplayZone::Local => format_in(&timestamp, &Local),
+        DisplayZone::Named(tz) => format_in(&timestamp, &tz),
+        DisplayZone::Original => match captured_tz.and_then(|name| name.parse::<Tz>().ok()) {
+            Some(tz) => format_in(&timestamp, &tz),
+            None => timestamp.format(DISPLAY_TIME_FORMAT).to_string(),
+        },
+    }
+}
+
+fn format_in<Z: TimeZone>(timestamp: &DateTime<FixedOffset>, zone: &Z) -> String
+where
+    Z::Offset: std::fmt::Display,
+{
+    timestamp
+        .with_timezone(zone)
+        .format(DISPLAY_TIME_FORMAT)
+        .to_string()
+}
+
+/// The IANA name of the local zone: `$TZ` when it names one, else the system setting.
+pub(crate) fn local_timezone_name() -> Option<String> {
+    if let Ok(value) = env::var("TZ")
+        && value.parse::<Tz>().is_ok()
+    {
+        return Some(value);
+    }
+    iana_time_zone::get_timezone().ok()
+}
+
+/// Formats a span as `1h 05m`, `45m`, or `<1m`.
+pub(crate) fn format_duration(seconds: i64) -> String {
+    let minutes = seconds.max(0) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "<1m".to_string(),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+/// Formats recent timestamps as `just now`, `5m ago`, `3h ago` or `2d ago`; older
+/// ones fall back to the calendar date.
+pub(crate) fn format_relative_time(value: &str, now: DateTime<Local>) -> String {
+    let Ok(timestamp) = DateTime::parse_from_rfc3339(value) else {
+        return value.to_string();
+    };
+    let timestamp = timestamp.with_timezone(&Local);
+    let elapsed = now.signed_duration_since(timestamp);
+    if elapsed.num_seconds() < 0 {
+        return format_display_time(value);
+    }
+    if elapsed.num_minutes() < 1 {
+        "just now".to_string()
+    } else if elapsed.num_hours() < 1 {
+        format!("{}m ago", elapsed.num_minutes())
+    } else if elapsed.num_days() < 1 {
+        format!("{}h ago", elapsed.num_hours())
+    } else if elapsed.num_days() < RELATIVE_MAX_DAYS {
+        format!("{}d ago", elapsed.num_days())
+    } else {
+        timestamp.format("%Y-%m-%d").to_string()
     }
 }
@@ -0,0 +1,284 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rand::{Rng, RngCore, rngs::OsRng};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, header::RETRY_AFTER};
+use std::time::Duration;
+use tokio::{runtime, time::sleep};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RETRIES: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Longest `Retry-After` we'll sleep through before giving up on the request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The one HTTP client for talking to the backend: every request gets a timeout,
+/// and rate limits (429), transient 5xx answers, and dropped connections are
+/// retried with jittered exponential backoff, honoring `Retry-After`. Requests
+/// that aren't idempotent, like POST, are only retried when the server didn't
+/// act on them: it was never reached, or it answered 429. A timeout or 5xx may
+/// come after it acted.
+pub(crate) struct HttpClient {
+    client: Client,
+}
+
+impl HttpClient {
+    pub(crate) fn new() -> Result<Self> {
+        Self::with_timeout(DEFAULT_TIMEOUT)
+    }
+
+    pub(crate) fn with_timeout(timeout: Duration) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("cap/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self { client })
+    }
+
+    pub(crate) fn get(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.client.get(url.as_ref())
+    }
+
+    pub(crate) fn post(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.client.post(url.as_ref())
+    }
+
+    pub(crate) fn delete(&self, url: impl AsRef<str>) -> RequestBuilder {
+        self.client.delete(url.as_ref())
+    }
+
+    /// Sends `request`, retrying as described above. Error statuses that aren't
+    /// worth retrying are returned as-is for the caller to inspect.
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let idempotent = is_idempotent(request.method());
+        let mut attempt = 0;
+        loop {
+            // Only streaming bodies can't be cloned, and cap never sends those.
+            let Some(retry) = request.try_clone().filter(|_| attempt < MAX_RETRIES) else {
+                return Ok(self.client.execute(request).await?);
+            };
+            attempt += 1;
+            match self.client.execute(retry).await {
+                Ok(response)
+                    if response.status() == StatusCode::TOO_MANY_REQUESTS
+                        || (idempotent && is_retryable(response.status())) =>
+                {
+                    let delay = retry_after(&response).unwrap_or_else(|| backoff(attempt));
+                    if delay > MAX_RETRY_AFTER {
+                        return Ok(response);
+                    }
+                    sleep(delay).await;
+                }
+                Ok(response) => return Ok(response),
+                Err(err)
+                    if err.is_connect()
+                        || (idempotent && (err.is_timeout() || err.is_request())) =>
+                {
+                    sleep(backoff(attempt)).await;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// A `multipart/form-data` body with the text `fields` and then `file` under the
+/// `file` field, along with the `Content-Type` to send it with.
+pub(crate) fn multipart(
+    fields: &[(&str, &str)],
+    file_name: &str,
+    file: &[u8],
+) -> (String, Vec<u8>) {
+    let boundary = format!("cap-{:016x}", OsRng.next_u64());
+    let mut body = String::new();
+    for (name, value) in fields {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            boundary, name, value
+        ));
+    }
+    body.push_str(&format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n",
+        boundary,
+        file_name.replace('"', "_")
+    ));
+    let mut body = body.into_bytes();
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+/// Runs network code from the synchronous rest of cap. Each call gets its own
+/// single-threaded runtime, so this must not be called from inside one.
+pub(crate) fn block_on<T>(future: impl Future<Output = Result<T>>) -> Result<T> {
+    runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(future)
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// `Retry-After` as delay-seconds or an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Doubles per attempt, with up to 50% random jitter so clients don't retry in step.
+fn backoff(attempt: u32) -> Duration {
+    let base = BASE_BACKOFF * 2u32.pow(attempt.saturating_sub(1));
+    let jitter = base.mul_f64(OsRng.gen_range(0.0..0.5));
+    base + jitter
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    use super::*;
+
+    async fn get(server: &MockServer) -> Response {
+        let client = HttpClient::new().unwrap();
+        let request = client.get(format!("{}/resource", server.uri()));
+        client.send(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limits_until_the_server_recovers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/resource"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/resource"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(get(&server).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_retry() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(502).insert_header("Retry-After", "0"))
+            .expect(u64::from(MAX_RETRIES) + 1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(get(&server).await.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_out_a_long_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "3600"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(get(&server).await.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_returned_without_retrying() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(get(&server).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn posts_are_not_retried_after_reaching_the_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+            .expect(u64::from(MAX_RETRIES) + 1)
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::with_timeout(Duration::from_millis(100)).unwrap();
+        let request = client.post(format!("{}/resource", server.uri()));
+        let err = client.send(request).await.unwrap_err();
+        assert!(err.downcast_ref::<reqwest::Error>().unwrap().is_timeout());
+
+        let client = HttpClient::new().unwrap();
+        let request = client.client.put(format!("{}/resource", server.uri()));
+        let response = client.send(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_posts_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::new().unwrap();
+        let request = client.post(format!("{}/resource", server.uri()));
+        assert_eq!(
+            client.send(request).await.unwrap().status(),
+            StatusCode::CREATED
+        );
+    }
+
+    #[test]
+    fn backoff_grows_with_each_attempt() {
+        assert!(backoff(1) >= BASE_BACKOFF && backoff(1) < BASE_BACKOFF * 2);
+        assert!(backoff(3) >= BASE_BACKOFF * 4 && backoff(3) < BASE_BACKOFF * 8);
+    }
+}
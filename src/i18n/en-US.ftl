@@ -0,0 +1,220 @@
+# Messages shown by cap. Ids are grouped by where they appear; every id here
+# needs a counterpart in each translation.
+
+## Confirmation prompts
+
+prompt-dry-run = Dry run, nothing changed: { $action }
+prompt-nothing-changed = Nothing changed
+prompt-not-a-terminal = { $action }: stdin is not a terminal; pass --yes to confirm or --dry-run to preview
+prompt-save-as-attachment = Memo is { $size } (limit { $limit }). Save it as an attachment with a stub memo?
+
+## Actions awaiting confirmation
+
+action-delete-memos =
+    { $count ->
+        [one] Delete 1 memo
+       *[other] Delete { $count } memos
+    }
+action-edit-memos = Update { $updated } memos and delete { $deleted }
+action-merge-memos = Merge { $count } memos into { $id }
+action-merge-database = Merge { $summary }
+action-import = Import { $summary }
+action-purge = Purge { $count } deleted memos
+action-compact = Purge { $count } deleted memos and vacuum the { $size } database
+
+## Results
+
+memos-added =
+    { $count ->
+        [one] Added 1 memo
+       *[other] Added { $count } memos
+    }
+memos-added-with-attachments = Added { $count } memos ({ $attachments } saved as attachments)
+memos-ignored =
+    { $count ->
+        [one] Skipped 1 memo matching an ignore rule
+       *[other] Skipped { $count } memos matching ignore rules
+    }
+memo-ignored = Not saved: the memo matches an ignore rule
+memos-deleted =
+    { $count ->
+        [one] Deleted 1 memo
+       *[other] Deleted { $count } memos
+    }
+picker-delete = Delete memos, { $count } marked (Space marks, a marks all, Enter deletes, Esc cancels)
+picker-cancelled = Nothing picked
+memos-edited = Updated { $updated } memos, deleted { $deleted }
+edit-large-deletions = { $count } edited memos lose more than half their text
+memos-merged = Merged { $count } memos into { $id }
+memo-split = Split into { $count } memos
+database-merge-summary = { $merged } memos from { $path } ({ $present } already present, { $duplicates } duplicate content)
+database-merged = Merged { $summary }
+import-summary = { $imported } notes from { $path } ({ $present } already present, { $ignored } dropped by ignore rules)
+notes-imported = Imported { $summary }
+private-memos-skipping = Skipping { $count } private memos
+private-memos-skipped = Skipped { $count } private memos
+passphrase-set = Passphrase set; add private memos with cap add --private
+tombstones-purged = Purged { $count } deleted memos
+database-compacted = Purged { $count } deleted memos, reclaimed { $reclaimed } ({ $before } -> { $after })
+database-backed-up = Backed up the database to { $path }
+timer-tracking = Tracking { $label }
+timer-stopped = Stopped { $label } after { $duration }
+
+## Sign-in
+
+logged-in = Logged in as { $user }
+otp-sent = Sent a sign-in code to { $email }; finish with cap login --verify <code>
+oauth-open-browser = Sign in with { $provider } in your browser:
+oauth-waiting = Waiting for the sign-in to complete...
+
+## cap show
+
+show-id = Id:        { $value }
+show-created = Created:   { $value }
+show-notebook = Notebook:  { $value }
+show-tags = Tags:      { $value }
+show-mood = Mood:      { $mood }/{ $max }
+show-location = Location:  { $value }
+show-meta = Meta:      { $key }={ $value }
+
+## cap stats and cap streak
+
+stats-memos = Memos:          { $count }
+stats-current-streak = Current streak: { $days } days
+stats-longest-streak = Longest streak: { $days } days
+stats-database-size = Database size:  { $size }
+stats-memos-per-week = Memos per week:
+stats-top-tags = Top tags:
+streak-goal-met = Today:          { $today }/{ $goal } memos, goal met
+streak-to-go = Today:          { $today }/{ $goal } memos, { $remaining } to go
+streak-summary = streak { $days }d · today { $today }/{ $goal }
+mood-none = No memos with a mood yet; add one with cap add --mood 4 ...
+mood-average = Average mood:   { $average } ({ $count } rated memos)
+mood-per-week = Mood per week:
+mood-ratings = Ratings:
+
+## cap sync status
+
+sync-never = never
+sync-summary = synced { $last } · { $pending } pending · { $deletions } deletions
+sync-last = Last sync:         { $value }
+sync-pending-changes = Pending changes:   { $count }
+sync-pending-deletions = Pending deletions: { $count }
+sync-backend = Backend:           { $value }
+sync-account = Account:           { $value }
+sync-not-logged-in = not logged in
+sync-pushed = Uploaded { $count } changes
+sync-pulled = Downloaded { $count } changes ({ $merged } merged, { $conflicts } conflicted copies)
+share-sent = Shared with { $email }
+inbox-empty = Nothing has been shared with you
+inbox-entry = { $time }  from { $from }  { $text }
+inbox-accepted = Added { $count } shared memos
+remind-set = Reminder set for { $time }
+remind-cleared = Reminder removed
+remind-none = The memo has no reminder
+remind-watching = Watching for reminders; press Ctrl-C to stop
+remind-due = Reminder { $id }  { $text }
+remind-summary = cap reminder
+remind-private = A private memo
+remind-snooze = Snooze
+remind-done = Done
+
+## TUI
+
+tui-input = Input
+tui-history = History
+tui-tags = Tags
+tui-tags-all = All
+tui-stats-chart = Memos per week (S to close)
+tui-stats-summary = Summary
+tui-stats-top-tags = Top tags
+tui-passphrase = Passphrase:
+tui-notebook = notebook: { $name }
+tui-all-notebooks = all notebooks
+tui-unlocked = private memos unlocked
+tui-unlock-failed = unlock failed: { $error }
+tui-unknown-date = unknown date: { $date }
+tui-memo-too-large = { $size } exceeds { $limit }, submit again to save as attachment
+tui-saved-as-attachment = saved as attachment
+tui-memo-saved = memo saved
+tui-draft-restored = restored the unsent memo from last time
+tui-edit-tags = Tags (Tab completes, Enter saves, Esc cancels)
+tui-private-tags = private memos can't be tagged
+tui-tags-saved = tags saved
+tui-tags-inline-kept = { $tags } stay: they're written in the memo
+tui-no-suggestions = no spelling suggestions
+
+## cap --plain
+
+plain-welcome = Type a memo and press Enter to save it. Commands: /list [count], /search <text>, /help, /quit
+plain-no-memos = No memos
+explain-fts = The text is matched as the FTS5 phrase { $phrase } in memos, archived pages and image text, and as a substring in timestamps:
+explain-scan = The text is too short for the search index, so it is matched as a substring in memos, archived pages, image text and timestamps, unranked:
+explain-no-matches = No memos matched
+explain-ranking = Best match first by bm25 score (lower is better), then newest first:
+explain-matched = matched in { $reasons }
+explain-content-ranked = content (bm25 { $score })
+explain-content = content
+explain-archive-ranked = archived page (bm25 { $score })
+explain-archive = archived page
+explain-image = image text
+explain-time = timestamp
+plain-unknown-command = Unknown command { $command }; type /help for the list
+
+## cap serve
+
+serve-webdav = Serving memos read-only over WebDAV at { $url } (Ctrl-C to stop)
+serve-listen = Listening for memos on { $path } (Ctrl-C to stop)
+
+## cap fetch
+
+article-archived = Archived { $url } ({ $size }); search now covers its text
+
+## cap add --audio
+
+audio-transcribing = Saved { $file }; transcribing it in the background
+audio-saved-untranscribed = Saved { $file }; set [transcribe] command or api_url in the config to turn recordings into text
+
+## cap add --image
+
+ocr-failed = Image saved, but its text couldn't be read: { $error }
+
+## cap maintain
+
+maintain-archived =
+    { $count ->
+        [one] Archived 1 memo
+       *[other] Archived { $count } memos
+    }
+maintain-would-archive =
+    { $count ->
+        [one] Would archive 1 memo
+       *[other] Would archive { $count } memos
+    }
+
+## cap review
+
+review-actions = [t]ag  [a]rchive  to[d]o  [s]kip  [q]uit:
+review-tags = Tags:
+review-paused = Paused after { $done } of { $total }; run the same review again to continue
+review-finished = Review of the week of { $week } finished
+review-nothing-left = Nothing left to review for the week of { $week }
+
+## cap doctor
+
+db-migrated = Upgraded a database from an older cap; the original is backed up at { $backup }
+doctor-no-database = No database yet at { $path }
+doctor-up-to-date = { $path } is up to date
+doctor-outdated-schema = { $path } lacks: { $changes }; cap backs it up and upgrades it the next time it opens it
+doctor-legacy-schema = { $path } was written by an older cap and lacks: { $changes }
+    Run `cap doctor --migrate-legacy` to back it up and upgrade it.
+doctor-migrated = Upgraded the database (added { $changes }); the original is backed up at { $backup }
+
+## Shell completion
+
+completion-private-memo = private memo
+completion-memo-count =
+    { $count ->
+        [one] 1 memo
+       *[other] { $count } memos
+    }
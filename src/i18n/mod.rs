@@ -0,0 +1,190 @@
+use fluent_bundle::{FluentArgs, FluentResource, concurrent::FluentBundle};
+use std::{env, sync::OnceLock};
+use unic_langid::LanguageIdentifier;
+
+/// Message catalogs compiled into the binary; the first one is the fallback for
+/// messages a translation doesn't have yet.
+const CATALOGS: [(&str, &str); 2] = [
+    ("en-US", include_str!("en-US.ftl")),
+    ("zh-CN", include_str!("zh-CN.ftl")),
+];
+
+/// Overrides the configured and system locale, e.g. `CAP_LANG=zh-CN`.
+pub(crate) const LOCALE_VAR: &str = "CAP_LANG";
+
+type Bundle = FluentBundle<FluentResource>;
+
+struct Catalog {
+    primary: Option<Bundle>,
+    fallback: Bundle,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Picks the message language: `CAP_LANG`, then `[ui] locale`, then the usual
+/// `LC_ALL`/`LC_MESSAGES`/`LANG`. Unsupported locales fall back to English.
+pub(crate) fn init(configured: Option<&str>) {
+    let _ = CATALOG.set(Catalog::new(requested_locale(configured).as_deref()));
+}
+
+fn catalog() -> &'static Catalog {
+    // Tests render English regardless of the machine's locale.
+    CATALOG.get_or_init(|| {
+        let requested = requested_locale(None).filter(|_| !cfg!(test));
+        Catalog::new(requested.as_deref())
+    })
+}
+
+fn requested_locale(configured: Option<&str>) -> Option<String> {
+    let from_env = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    from_env(LOCALE_VAR)
+        .or_else(|| configured.map(str::to_string))
+        .or_else(|| from_env("LC_ALL"))
+        .or_else(|| from_env("LC_MESSAGES"))
+        .or_else(|| from_env("LANG"))
+}
+
+impl Catalog {
+    fn new(requested: Option<&str>) -> Self {
+        let (fallback_id, fallback_source) = CATALOGS[0];
+        let primary = requested
+            .and_then(parse_locale)
+            .and_then(|requested| {
+                CATALOGS[1..]
+                    .iter()
+                    .find(|(id, _)| matches_locale(&requested, id))
+            })
+            .map(|(id, source)| bundle(id, source));
+        Self {
+            primary,
+            fallback: bundle(fallback_id, fallback_source),
+        }
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs<'_>>) -> String {
+        let mut errors = Vec::new();
+        for bundle in self.primary.iter().chain([&self.fallback]) {
+            if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+                return bundle
+                    .format_pattern(pattern, args, &mut errors)
+                    .into_owned();
+            }
+        }
+        // A missing message is a bug, but showing its id beats showing nothing.
+        id.to_string()
+    }
+}
+
+fn bundle(id: &str, source: &str) -> Bundle {
+    let langid: LanguageIdentifier = id.parse().expect("catalog ids are valid locales");
+    let resource = FluentResource::try_new(source.to_string())
+        .unwrap_or_else(|(_, errors)| panic!("invalid {} catalog: {:?}", id, errors));
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Bidi isolation marks show up as stray characters in most terminals.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .unwrap_or_else(|errors| panic!("duplicate messages in {} catalog: {:?}", id, errors));
+    bundle
+}
+
+/// Accepts POSIX-style values like `zh_CN.UTF-8` as well as `zh-CN`; `C` and
+/// `POSIX` mean no preference.
+fn parse_locale(value: &str) -> Option<LanguageIdentifier> {
+    let tag = value.split(['.', '@']).next()?.replace('_', "-");
+    if tag.is_empty() || tag == "C" || tag == "POSIX" {
+        return None;
+    }
+    tag.parse().ok()
+}
+
+/// Same language, and no conflicting region or script: `zh` and `zh-Hans-CN`
+/// get `zh-CN`, `zh-TW` does not.
+fn matches_locale(requested: &LanguageIdentifier, catalog_id: &str) -> bool {
+    let Ok(available) = catalog_id.parse::<LanguageIdentifier>() else {
+        return false;
+    };
+    requested.language == available.language
+        && requested
+            .region
+            .is_none_or(|region| Some(region) == available.region)
+}
+
+pub(crate) fn text(id: &str) -> String {
+    catalog().format(id, None)
+}
+
+pub(crate) fn text_with(id: &str, args: &FluentArgs<'_>) -> String {
+    catalog().format(id, Some(args))
+}
+
+/// Looks up a message in the active locale: `t!("memo-saved")`, or with
+/// arguments, `t!("memos-deleted", count = memos.len())`.
+macro_rules! t {
+    ($id:literal) => {
+        $crate::i18n::text($id)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::text_with($id, &args)
+    }};
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zh() -> Catalog {
+        Catalog::new(Some("zh_CN.UTF-8"))
+    }
+
+    #[test]
+    fn posix_locales_select_a_catalog() {
+        assert!(zh().primary.is_some());
+        assert!(Catalog::new(Some("zh-Hans")).primary.is_some());
+        assert!(Catalog::new(Some("zh_TW.UTF-8")).primary.is_none());
+        assert!(Catalog::new(Some("C.UTF-8")).primary.is_none());
+        assert!(Catalog::new(Some("en_GB")).primary.is_none());
+    }
+
+    #[test]
+    fn messages_are_translated_with_arguments() {
+        let mut args = FluentArgs::new();
+        args.set("count", 3);
+        assert_eq!(zh().format("memos-deleted", Some(&args)), "已删除 3 条备忘");
+        assert_eq!(
+            Catalog::new(None).format("memos-deleted", Some(&args)),
+            "Deleted 3 memos"
+        );
+        args.set("count", 1);
+        assert_eq!(
+            Catalog::new(None).format("memos-deleted", Some(&args)),
+            "Deleted 1 memo"
+        );
+    }
+
+    fn message_ids(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter_map(|line| line.split_once(" ="))
+            .map(|(id, _)| id)
+            .filter(|id| id.starts_with(|ch: char| ch.is_ascii_lowercase()))
+            .collect()
+    }
+
+    #[test]
+    fn translations_cover_the_same_messages() {
+        let english = message_ids(CATALOGS[0].1);
+        for (id, source) in &CATALOGS[1..] {
+            let translated = message_ids(source);
+            for message in &english {
+                assert!(translated.contains(message), "{} lacks {}", id, message);
+            }
+            for message in &translated {
+                assert!(english.contains(message), "{} has stray {}", id, message);
+            }
+        }
+    }
+}
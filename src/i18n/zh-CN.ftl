@@ -0,0 +1,191 @@
+# 简体中文。标签后的空格用于对齐，中文字符按两列宽计算。
+
+## Confirmation prompts
+
+prompt-dry-run = 试运行，未做任何更改：{ $action }
+prompt-nothing-changed = 未做任何更改
+prompt-not-a-terminal = { $action }：标准输入不是终端；请加 --yes 确认，或加 --dry-run 预览
+prompt-save-as-attachment = 备忘大小为 { $size }（上限 { $limit }）。是否另存为附件并保留一条占位备忘？
+
+## Actions awaiting confirmation
+
+action-delete-memos = 删除 { $count } 条备忘
+action-edit-memos = 更新 { $updated } 条备忘并删除 { $deleted } 条
+action-merge-memos = 将 { $count } 条备忘合并到 { $id }
+action-merge-database = 合并{ $summary }
+action-import = 导入{ $summary }
+action-purge = 清除 { $count } 条已删除的备忘
+action-compact = 清除 { $count } 条已删除的备忘并压缩 { $size } 的数据库
+
+## Results
+
+memos-added = 已添加 { $count } 条备忘
+memos-added-with-attachments = 已添加 { $count } 条备忘（其中 { $attachments } 条存为附件）
+memos-ignored = 已跳过 { $count } 条符合忽略规则的备忘
+memo-ignored = 未保存：该备忘符合忽略规则
+memos-deleted = 已删除 { $count } 条备忘
+picker-delete = 删除备忘，已选 { $count } 条（空格选择，a 全选，回车删除，Esc 取消）
+picker-cancelled = 未选择任何备忘
+memos-edited = 已更新 { $updated } 条备忘，删除 { $deleted } 条
+edit-large-deletions = 有 { $count } 条编辑后的备忘删掉了一半以上的内容
+memos-merged = 已将 { $count } 条备忘合并到 { $id }
+memo-split = 已拆分为 { $count } 条备忘
+database-merge-summary = 来自 { $path } 的 { $merged } 条备忘（{ $present } 条已存在，{ $duplicates } 条内容重复）
+database-merged = 已合并{ $summary }
+import-summary = 来自 { $path } 的 { $imported } 条笔记（{ $present } 条已存在，{ $ignored } 条被忽略规则过滤）
+notes-imported = 已导入{ $summary }
+private-memos-skipping = 跳过 { $count } 条私密备忘
+private-memos-skipped = 已跳过 { $count } 条私密备忘
+passphrase-set = 已设置密码；可用 cap add --private 添加私密备忘
+tombstones-purged = 已清除 { $count } 条已删除的备忘
+database-compacted = 已清除 { $count } 条已删除的备忘，释放 { $reclaimed }（{ $before } -> { $after }）
+database-backed-up = 已将数据库备份到 { $path }
+timer-tracking = 正在计时：{ $label }
+timer-stopped = 已停止 { $label }，用时 { $duration }
+
+## Sign-in
+
+logged-in = 已登录：{ $user }
+otp-sent = 登录验证码已发送至 { $email }；请用 cap login --verify <验证码> 完成登录
+oauth-open-browser = 请在浏览器中使用 { $provider } 登录：
+oauth-waiting = 正在等待登录完成……
+
+## cap show
+
+show-id = 编号:      { $value }
+show-created = 创建于:    { $value }
+show-notebook = 笔记本:    { $value }
+show-tags = 标签:      { $value }
+show-mood = 心情:      { $mood }/{ $max }
+show-location = 位置:      { $value }
+show-meta = 字段:      { $key }={ $value }
+
+## cap stats and cap streak
+
+stats-memos = 备忘:           { $count }
+stats-current-streak = 当前连续:       { $days } 天
+stats-longest-streak = 最长连续:       { $days } 天
+stats-database-size = 数据库大小:     { $size }
+stats-memos-per-week = 每周备忘:
+stats-top-tags = 常用标签:
+streak-goal-met = 今天:           { $today }/{ $goal } 条备忘，已达成目标
+streak-to-go = 今天:           { $today }/{ $goal } 条备忘，还差 { $remaining } 条
+streak-summary = 连续 { $days } 天 · 今天 { $today }/{ $goal }
+mood-none = 还没有带心情的备忘；可用 cap add --mood 4 ... 添加
+mood-average = 平均心情:       { $average }（{ $count } 条已评分）
+mood-per-week = 每周心情:
+mood-ratings = 评分分布:
+
+## cap sync status
+
+sync-never = 从未
+sync-summary = 同步于 { $last } · { $pending } 条待同步 · { $deletions } 条待删除
+sync-last = 上次同步:          { $value }
+sync-pending-changes = 待同步更改:        { $count }
+sync-pending-deletions = 待同步删除:        { $count }
+sync-backend = 后端:              { $value }
+sync-account = 账号:              { $value }
+sync-not-logged-in = 未登录
+sync-pushed = 已上传 { $count } 条更改
+sync-pulled = 已下载 { $count } 条更改（合并 { $merged } 条，冲突副本 { $conflicts } 条）
+share-sent = 已分享给 { $email }
+inbox-empty = 还没有人向你分享备忘
+inbox-entry = { $time }  来自 { $from }  { $text }
+inbox-accepted = 已添加 { $count } 条分享的备忘
+remind-set = 已设置提醒：{ $time }
+remind-cleared = 已移除提醒
+remind-none = 这条备忘没有提醒
+remind-watching = 正在等待提醒；按 Ctrl-C 停止
+remind-due = 提醒 { $id }  { $text }
+remind-summary = cap 提醒
+remind-private = 一条私密备忘
+remind-snooze = 稍后提醒
+remind-done = 完成
+
+## TUI
+
+tui-input = 输入
+tui-history = 历史
+tui-tags = 标签
+tui-tags-all = 全部
+tui-stats-chart = 每周备忘（按 S 关闭）
+tui-stats-summary = 概览
+tui-stats-top-tags = 常用标签
+tui-passphrase = 密码:
+tui-notebook = 笔记本：{ $name }
+tui-all-notebooks = 全部笔记本
+tui-unlocked = 私密备忘已解锁
+tui-unlock-failed = 解锁失败：{ $error }
+tui-unknown-date = 无法识别的日期：{ $date }
+tui-memo-too-large = { $size } 超过 { $limit }，再次提交将存为附件
+tui-saved-as-attachment = 已存为附件
+tui-memo-saved = 备忘已保存
+tui-draft-restored = 已恢复上次未发送的备忘
+tui-edit-tags = 标签（Tab 补全，Enter 保存，Esc 取消）
+tui-private-tags = 私密备忘不能加标签
+tui-tags-saved = 标签已保存
+tui-tags-inline-kept = { $tags } 仍保留：它们写在备忘正文里
+tui-no-suggestions = 没有拼写建议
+
+## cap --plain
+
+plain-welcome = 输入备忘后按回车保存。命令：/list [数量]、/search <文本>、/help、/quit
+plain-no-memos = 没有备忘
+explain-fts = 文本按 FTS5 短语 { $phrase } 匹配备忘、存档网页和图片文字，并按子串匹配时间：
+explain-scan = 文本太短，无法使用搜索索引，按子串匹配备忘、存档网页、图片文字和时间，不排序：
+explain-no-matches = 没有匹配的备忘
+explain-ranking = 按 bm25 分数排序（越低越匹配），其次按时间从新到旧：
+explain-matched = 匹配于 { $reasons }
+explain-content-ranked = 内容（bm25 { $score }）
+explain-content = 内容
+explain-archive-ranked = 存档网页（bm25 { $score }）
+explain-archive = 存档网页
+explain-image = 图片文字
+explain-time = 时间
+plain-unknown-command = 未知命令 { $command }；输入 /help 查看命令列表
+
+## cap serve
+
+serve-webdav = 正在通过 WebDAV 只读提供备忘：{ $url }（按 Ctrl-C 停止）
+serve-listen = 正在 { $path } 上接收备忘（按 Ctrl-C 停止）
+
+## cap fetch
+
+article-archived = 已存档 { $url }（{ $size }），搜索将包含其正文
+
+## cap add --audio
+
+audio-transcribing = 已保存 { $file }，正在后台转写
+audio-saved-untranscribed = 已保存 { $file }；在配置中设置 [transcribe] command 或 api_url 即可将录音转为文字
+
+## cap add --image
+
+ocr-failed = 图片已保存，但无法识别其中的文字：{ $error }
+
+## cap maintain
+
+maintain-archived = 已归档 { $count } 条备忘
+maintain-would-archive = 将归档 { $count } 条备忘
+
+## cap review
+
+review-actions = [t]标签  [a]归档  [d]待办  [s]跳过  [q]退出：
+review-tags = 标签：
+review-paused = 已回顾 { $done }/{ $total } 条后暂停；再次运行同一回顾即可继续
+review-finished = { $week } 这一周的回顾已完成
+review-nothing-left = { $week } 这一周没有待回顾的备忘
+
+## cap doctor
+
+db-migrated = 已升级旧版 cap 创建的数据库，原文件备份在 { $backup }
+doctor-no-database = { $path } 尚无数据库
+doctor-up-to-date = { $path } 已是最新
+doctor-outdated-schema = { $path } 缺少：{ $changes }；cap 下次打开时会先备份再升级
+doctor-legacy-schema = { $path } 由旧版 cap 创建，缺少：{ $changes }
+    运行 `cap doctor --migrate-legacy` 以备份并升级。
+doctor-migrated = 数据库已升级（新增 { $changes }），原文件备份在 { $backup }
+
+## Shell completion
+
+completion-private-memo = 私密备忘
+completion-memo-count = { $count } 条备忘
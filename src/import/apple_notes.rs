@@ -0,0 +1,88 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::process::Command;
+
+use crate::capture::CaptureRequest;
+
+/// Lists every note as JSON through the Notes scripting bridge. Fetching each
+/// property for a whole folder at once keeps it to a few Apple Events per folder.
+const SCRIPT: &str = r#"
+const notes = [];
+for (const account of Application("Notes").accounts()) {
+  for (const folder of account.folders()) {
+    const name = folder.name();
+    if (name === "Recently Deleted") continue;
+    const texts = folder.notes.plaintext();
+    const created = folder.notes.creationDate();
+    const locked = folder.notes.passwordProtected();
+    texts.forEach((text, i) => notes.push({
+      folder: name, text: text, created: created[i].toISOString(), locked: locked[i],
+    }));
+  }
+}
+JSON.stringify(notes);
+"#;
+
+#[derive(Deserialize)]
+struct Note {
+    folder: String,
+    text: String,
+    created: Option<String>,
+    #[serde(default)]
+    locked: bool,
+}
+
+/// Asks Notes for every note; macOS shows a prompt to allow it the first time.
+pub(super) fn fetch() -> Result<String> {
+    if !cfg!(target_os = "macos") {
+        bail!("Apple Notes can only be read on macOS");
+    }
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", SCRIPT])
+        .output()
+        .context("failed to run osascript")?;
+    if !output.status.success() {
+        bail!(
+            "couldn't read Apple Notes: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Notes with their folder as the notebook. Locked notes can't be read and
+/// are left out.
+pub(super) fn parse(text: &str) -> Result<Vec<CaptureRequest>> {
+    let notes: Vec<Note> = serde_json::from_str(text)?;
+    Ok(notes
+        .into_iter()
+        .filter(|note| !note.locked && !note.text.trim().is_empty())
+        .map(|note| CaptureRequest {
+            content: note.text,
+            notebook: Some(note.folder).filter(|folder| !folder.trim().is_empty()),
+            created_at: note.created,
+            ..Default::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_become_notebooks_and_locked_notes_are_skipped() {
+        let notes = parse(
+            r#"[{"folder": "Recipes", "text": "Pancakes\nflour, eggs", "created": "2023-04-01T09:00:00.000Z", "locked": false},
+                {"folder": "Private", "text": "", "created": "2023-04-02T09:00:00.000Z", "locked": true}]"#,
+        )
+        .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].content, "Pancakes\nflour, eggs");
+        assert_eq!(notes[0].notebook.as_deref(), Some("Recipes"));
+        assert_eq!(
+            notes[0].created_at.as_deref(),
+            Some("2023-04-01T09:00:00.000Z")
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use crate::{
+    attachments,
+    capture::{self, CaptureRequest},
+    config::Config,
+    db::{self, Db},
+    domain::memo::NewMemo,
+};
+
+mod apple_notes;
+mod simplenote;
+mod standard_notes;
+mod twitter;
+
+/// Tag given to notes that were pinned in the app they came from.
+const PINNED_TAG: &str = "pinned";
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum Source {
+    /// Notes.app on this Mac, with folders as notebooks
+    AppleNotes,
+    /// A decrypted backup (`.txt`), or the zip it comes in
+    StandardNotes,
+    /// The export zip, or the `notes.json` inside it
+    Simplenote,
+    /// The archive zip from "Download an archive of your data"; your tweets, threads joined
+    TwitterArchive,
+}
+
+impl Source {
+    /// Stored as each imported memo's `source` field.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::AppleNotes => "apple-notes",
+            Self::StandardNotes => "standard-notes",
+            Self::Simplenote => "simplenote",
+            Self::TwitterArchive => "twitter-archive",
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ImportReport {
+    pub(crate) imported: usize,
+    /// Notes whose content is already in the database, e.g. from an earlier import.
+    pub(crate) duplicates: usize,
+    /// Notes dropped by an ignore rule.
+    pub(crate) ignored: usize,
+}
+
+/// The notes an import would add, worked out before anything is written.
+pub(crate) struct ImportPlan {
+    pub(crate) report: ImportReport,
+    memos: Vec<NewMemo>,
+}
+
+/// Reads the notes in an export from `source`, or from the app itself for
+/// Apple Notes, as capture requests.
+pub(crate) fn read(source: Source, path: Option<&Path>) -> Result<Vec<CaptureRequest>> {
+    let notes = match source {
+        Source::AppleNotes => {
+            if path.is_some() {
+                bail!("--from apple-notes reads from Notes itself and takes no file");
+            }
+            apple_notes::parse(&apple_notes::fetch()?).context("Notes sent back unexpected data")?
+        }
+        Source::StandardNotes => {
+            let path = export_path(source, path)?;
+            read_export(path, |name| {
+                name.ends_with("Backup and Import File.txt") || name.ends_with(".json")
+            })
+            .and_then(|text| standard_notes::parse(&text))
+            .with_context(|| unreadable(source, path))?
+        }
+        Source::Simplenote => {
+            let path = export_path(source, path)?;
+            read_export(path, |name| name.ends_with("notes.json"))
+                .and_then(|text| simplenote::parse(&text))
+                .with_context(|| unreadable(source, path))?
+        }
+        Source::TwitterArchive => {
+            let path = export_path(source, path)?;
+            read_export_parts(path, twitter::is_tweets_file)
+                .and_then(|parts| twitter::parse(&parts))
+                .with_context(|| unreadable(source, path))?
+        }
+    };
+    Ok(notes
+        .into_iter()
+        .map(|mut note| {
+            note.source = Some(source.name().to_string());
+            note
+        })
+        .collect())
+}
+
+/// Prepares the notes the way `cap capture` would and leaves out any whose
+/// content is already stored, so importing the same export twice adds nothing
+/// new. Only reads the database, so a dry run or a declined prompt locks nothing.
+pub(crate) fn plan(db: &Db, config: &Config, notes: Vec<CaptureRequest>) -> Result<ImportPlan> {
+    let mut report = ImportReport::default();
+    let mut memos = Vec::new();
+    let mut planned = HashSet::new();
+    for note in notes {
+        let Some(new_memo) = capture::prepare(config, note)? else {
+            report.ignored += 1;
+            continue;
+        };
+        if planned.contains(&new_memo.content) || db::content_exists(db, &new_memo.content)? {
+            report.duplicates += 1;
+            continue;
+        }
+        planned.insert(new_memo.content.clone());
+        memos.push(new_memo);
+        report.imported += 1;
+    }
+    Ok(ImportPlan { report, memos })
+}
+
+/// Saves the notes `plan` found new, all or none. Oversized notes become
+/// attachments, whose files are only kept once their memos are committed.
+pub(crate) fn import(db: &Db, config: &Config, plan: &ImportPlan) -> Result<()> {
+    let staged = db.atomically(|db| {
+        let mut staged = Vec::new();
+        for new_memo in &plan.memos {
+            if attachments::exceeds_max_size(&new_memo.content, &config.memo) {
+                staged.push(attachments::stage_memo_as_attachment(db, new_memo)?.1);
+            } else {
+                db::add_memo(db, new_memo)?;
+            }
+        }
+        Ok(staged)
+    })?;
+    for file in staged {
+        file.keep()?;
+    }
+    Ok(())
+}
+
+fn export_path(source: Source, path: Option<&Path>) -> Result<&Path> {
+    path.with_context(|| format!("--from {} needs the export file", source.name()))
+}
+
+fn unreadable(source: Source, path: &Path) -> String {
+    format!(
+        "{} isn't a readable {} export",
+        path.display(),
+        source.name()
+    )
+}
+
+/// The export's text: the file itself, or the first entry `wanted` picks when
+/// it is a zip archive.
+fn read_export(path: &Path, wanted: impl Fn(&str) -> bool) -> Result<String> {
+    read_export_parts(path, wanted)?
+        .into_iter()
+        .next()
+        .with_context(|| format!("{} doesn't contain the notes file", path.display()))
+}
+
+/// Like `read_export`, but every entry `wanted` picks, in archive order.
+fn read_export_parts(path: &Path, wanted: impl Fn(&str) -> bool) -> Result<Vec<String>> {
+    let bytes = fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    if !bytes.starts_with(b"PK\x03\x04") {
+        let text =
+            String::from_utf8(bytes).with_context(|| format!("{} isn't text", path.display()))?;
+        return Ok(vec![text]);
+    }
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut parts = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if wanted(entry.name()) {
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            parts.push(text);
+        }
+    }
+    Ok(parts)
+}
+
+fn with_pinned_tag(mut tags: Vec<String>, pinned: bool) -> Vec<String> {
+    if pinned {
+        tags.push(PINNED_TAG.to_string());
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reimporting_skips_notes_already_present() {
+        let db = Db::open_in_memory().unwrap();
+        let config = Config::default();
+        let notes = || {
+            vec![
+                CaptureRequest::new("first"),
+                CaptureRequest::new("second"),
+                CaptureRequest::new("first"),
+            ]
+        };
+        let first = plan(&db, &config, notes()).unwrap();
+        assert_eq!((first.report.imported, first.report.duplicates), (2, 1));
+        // Planning alone writes nothing.
+        assert!(db::fetch_memos(&db, None).unwrap().is_empty());
+        import(&db, &config, &first).unwrap();
+        let second = plan(&db, &config, notes()).unwrap();
+        assert_eq!((second.report.imported, second.report.duplicates), (0, 3));
+        import(&db, &config, &second).unwrap();
+        assert_eq!(db::fetch_memos(&db, None).unwrap().len(), 2);
+    }
+}
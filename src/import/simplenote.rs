@@ -0,0 +1,65 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::with_pinned_tag;
+use crate::capture::CaptureRequest;
+
+/// `source/notes.json` from a Simplenote export; trashed notes are listed separately.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    active_notes: Vec<Note>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Note {
+    content: String,
+    creation_date: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    pinned: bool,
+}
+
+/// Notes from a Simplenote export, leaving out the trash. The first line of a
+/// Simplenote note is its title, as it is for a memo.
+pub(super) fn parse(text: &str) -> Result<Vec<CaptureRequest>> {
+    let export: Export = serde_json::from_str(text)?;
+    Ok(export
+        .active_notes
+        .into_iter()
+        .filter(|note| !note.content.trim().is_empty())
+        .map(|note| CaptureRequest {
+            content: note.content,
+            tags: with_pinned_tag(note.tags, note.pinned),
+            created_at: note.creation_date,
+            ..Default::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_notes_keep_their_tags_pins_and_times() {
+        let notes = parse(
+            r#"{"activeNotes": [
+                    {"id": "a1", "content": "Trip ideas\nLisbon", "creationDate": "2019-02-15T07:34:59.787Z",
+                     "lastModified": "2019-03-01T10:00:00.000Z", "tags": ["travel"], "pinned": true},
+                    {"id": "a2", "content": "  ", "creationDate": "2019-02-16T07:34:59.787Z"}
+                ],
+                "trashedNotes": [{"id": "t1", "content": "old", "creationDate": "2018-01-01T00:00:00.000Z"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].content, "Trip ideas\nLisbon");
+        assert_eq!(notes[0].tags, ["travel", "pinned"]);
+        assert_eq!(
+            notes[0].created_at.as_deref(),
+            Some("2019-02-15T07:34:59.787Z")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::with_pinned_tag;
+use crate::capture::CaptureRequest;
+
+/// A decrypted backup: notes, tags, and everything else as one list of items.
+#[derive(Deserialize)]
+struct Backup {
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    uuid: String,
+    content_type: String,
+    created_at: Option<String>,
+    #[serde(default)]
+    deleted: bool,
+    /// An object in decrypted backups; encrypted ones hold a ciphertext string.
+    #[serde(default)]
+    content: Value,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Content {
+    title: String,
+    text: String,
+    /// On a tag, the notes it is attached to.
+    references: Vec<Reference>,
+    /// Newer apps store the pinned and trashed state here ...
+    pinned: Option<bool>,
+    trashed: bool,
+    /// ... and older ones under `appData["org.standardnotes.sn"]`.
+    #[serde(rename = "appData")]
+    app_data: HashMap<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct Reference {
+    uuid: String,
+}
+
+impl Content {
+    fn pinned(&self) -> bool {
+        self.pinned.unwrap_or_else(|| {
+            self.app_data
+                .get("org.standardnotes.sn")
+                .and_then(|data| data.get("pinned"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Notes from a Standard Notes decrypted backup, with the tags that reference
+/// them. Trashed and deleted notes are left out.
+pub(super) fn parse(text: &str) -> Result<Vec<CaptureRequest>> {
+    let backup: Backup = serde_json::from_str(text)?;
+    let mut items = Vec::new();
+    for mut item in backup.items {
+        if item.deleted
+            || item.content.is_null()
+            || !matches!(item.content_type.as_str(), "Note" | "Tag")
+        {
+            continue;
+        }
+        if item.content.is_string() {
+            bail!("the backup is encrypted; export a decrypted backup from Standard Notes");
+        }
+        let content: Content = serde_json::from_value(item.content.take())?;
+        items.push((item, content));
+    }
+
+    let mut tags: HashMap<&str, Vec<String>> = HashMap::new();
+    for (item, content) in &items {
+        if item.content_type != "Tag" {
+            continue;
+        }
+        for reference in &content.references {
+            tags.entry(reference.uuid.as_str())
+                .or_default()
+                .push(content.title.clone());
+        }
+    }
+
+    let mut notes = Vec::new();
+    for (item, content) in &items {
+        if item.content_type != "Note" || content.trashed {
+            continue;
+        }
+        let body = match (content.title.trim(), content.text.trim()) {
+            ("", text) => text.to_string(),
+            (title, "") => title.to_string(),
+            (title, text) => format!("{}\n\n{}", title, text),
+        };
+        if body.is_empty() {
+            continue;
+        }
+        notes.push(CaptureRequest {
+            content: body,
+            tags: with_pinned_tag(
+                tags.get(item.uuid.as_str()).cloned().unwrap_or_default(),
+                content.pinned(),
+            ),
+            created_at: item.created_at.clone(),
+            ..Default::default()
+        });
+    }
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_keep_their_tags_pins_and_times() {
+        let notes = parse(
+            r#"{"version": "004", "items": [
+                {"uuid": "n1", "content_type": "Note", "created_at": "2021-05-03T10:11:12.000Z",
+                 "content": {"title": "Groceries", "text": "eggs\nmilk", "pinned": true}},
+                {"uuid": "n2", "content_type": "Note", "created_at": "2021-05-04T08:00:00.000Z",
+                 "content": {"title": "", "text": "old style pin",
+                             "appData": {"org.standardnotes.sn": {"pinned": true}}}},
+                {"uuid": "n3", "content_type": "Note", "content": {"title": "gone", "trashed": true}},
+                {"uuid": "n4", "content_type": "Note", "deleted": true},
+                {"uuid": "t1", "content_type": "Tag",
+                 "content": {"title": "home", "references": [{"uuid": "n1", "content_type": "Note"}]}},
+                {"uuid": "c1", "content_type": "SN|Component", "content": {"name": "editor"}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].content, "Groceries\n\neggs\nmilk");
+        assert_eq!(notes[0].tags, ["home", "pinned"]);
+        assert_eq!(
+            notes[0].created_at.as_deref(),
+            Some("2021-05-03T10:11:12.000Z")
+        );
+        assert_eq!(notes[1].content, "old style pin");
+        assert_eq!(notes[1].tags, ["pinned"]);
+
+        let encrypted =
+            r#"{"items": [{"uuid": "n1", "content_type": "Note", "content": "004:abc"}]}"#;
+        assert!(parse(encrypted).is_err());
+    }
+}
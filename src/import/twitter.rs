@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::capture::CaptureRequest;
+
+/// Tag given to every imported tweet.
+const TWEET_TAG: &str = "tweet";
+/// Field holding the id of the first tweet, so the original stays findable.
+const TWEET_ID_KEY: &str = "tweet_id";
+
+#[derive(Deserialize)]
+struct Tweet {
+    id_str: String,
+    full_text: String,
+    /// E.g. `Wed Oct 10 20:19:24 +0000 2018`.
+    created_at: String,
+    in_reply_to_status_id_str: Option<String>,
+    /// Characters before the start are the `@name`s a reply is addressed to.
+    #[serde(default)]
+    display_text_range: Vec<String>,
+    #[serde(default)]
+    entities: Entities,
+}
+
+#[derive(Deserialize, Default)]
+struct Entities {
+    #[serde(default)]
+    urls: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+struct Link {
+    url: String,
+    expanded_url: Option<String>,
+}
+
+/// Whether an archive entry holds tweets: `data/tweets.js`, the older
+/// `data/tweet.js`, or one of their `-partN` continuations.
+pub(super) fn is_tweets_file(name: &str) -> bool {
+    let Some(stem) = name
+        .rsplit('/')
+        .next()
+        .and_then(|file| file.strip_suffix(".js"))
+    else {
+        return false;
+    };
+    let stem = match stem.split_once("-part") {
+        Some((stem, part)) if part.chars().all(|ch| ch.is_ascii_digit()) => stem,
+        Some(_) => return false,
+        None => stem,
+    };
+    matches!(stem, "tweets" | "tweet")
+}
+
+/// Your own tweets from the `tweets.js` parts of an archive, retweets left
+/// out. A thread of replies to yourself becomes one memo, dated by its first tweet.
+pub(super) fn parse(parts: &[String]) -> Result<Vec<CaptureRequest>> {
+    let mut tweets = BTreeMap::new();
+    for part in parts {
+        // Each part is a script assigning the array, e.g. `window.YTD.tweets.part0 = [...]`.
+        let json = part.split_once('=').map_or(part.as_str(), |(_, json)| json);
+        let entries: Vec<Value> = serde_json::from_str(json)?;
+        for entry in entries {
+            // Newer archives wrap each tweet in `{"tweet": ...}`.
+            let tweet = match entry {
+                Value::Object(mut object) if object.contains_key("tweet") => object["tweet"].take(),
+                other => other,
+            };
+            let tweet: Tweet = serde_json::from_value(tweet)?;
+            if tweet.full_text.starts_with("RT @") {
+                continue;
+            }
+            let created = DateTime::parse_from_str(&tweet.created_at, "%a %b %d %H:%M:%S %z %Y")
+                .with_context(|| format!("tweet {} has an unreadable time", tweet.id_str))?;
+            tweets.insert((created, tweet.id_str.clone()), tweet);
+        }
+    }
+
+    let ids: HashSet<&str> = tweets.values().map(|tweet| tweet.id_str.as_str()).collect();
+    let mut replies: HashMap<&str, Vec<&Tweet>> = HashMap::new();
+    let mut roots = Vec::new();
+    for ((created, _), tweet) in &tweets {
+        match tweet.in_reply_to_status_id_str.as_deref() {
+            Some(parent) if ids.contains(parent) => replies.entry(parent).or_default().push(tweet),
+            _ => roots.push((created, tweet)),
+        }
+    }
+
+    let mut notes = Vec::new();
+    for (created, root) in roots {
+        let mut texts = vec![text(root, false)];
+        let mut pending = vec![root];
+        while let Some(tweet) = pending.pop() {
+            // Oldest reply first; later ones are pushed first so they pop last.
+            for reply in replies
+                .get(tweet.id_str.as_str())
+                .into_iter()
+                .flatten()
+                .rev()
+            {
+                pending.push(reply);
+            }
+            if !std::ptr::eq(tweet, root) {
+                texts.push(text(tweet, true));
+            }
+        }
+        notes.push(CaptureRequest {
+            content: texts.join("\n\n"),
+            tags: vec![TWEET_TAG.to_string()],
+            created_at: Some(created.to_rfc3339()),
+            meta: BTreeMap::from([(TWEET_ID_KEY.to_string(), root.id_str.clone())]),
+            ..Default::default()
+        });
+    }
+    Ok(notes)
+}
+
+/// The tweet as written, with full links. Thread continuations lose the
+/// `@yourname` Twitter puts in front of replies.
+fn text(tweet: &Tweet, continuation: bool) -> String {
+    let start = if continuation {
+        tweet
+            .display_text_range
+            .first()
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let mut text: String = tweet.full_text.chars().skip(start).collect();
+    for link in &tweet.entities.urls {
+        if let Some(expanded) = &link.expanded_url {
+            text = text.replace(&link.url, expanded);
+        }
+    }
+    // Tweet text comes HTML-escaped.
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_are_joined_and_retweets_skipped() {
+        let part = r#"window.YTD.tweets.part0 = [
+            {"tweet": {"id_str": "2", "full_text": "@me and the second half", "display_text_range": ["4", "23"],
+                       "created_at": "Wed Oct 10 20:25:00 +0000 2018", "in_reply_to_status_id_str": "1"}},
+            {"tweet": {"id_str": "1", "full_text": "A thought &amp; a link https://t.co/x",
+                       "created_at": "Wed Oct 10 20:19:24 +0000 2018",
+                       "entities": {"urls": [{"url": "https://t.co/x", "expanded_url": "https://example.com"}]}}},
+            {"tweet": {"id_str": "3", "full_text": "RT @someone: not mine", "created_at": "Thu Oct 11 08:00:00 +0000 2018"}},
+            {"tweet": {"id_str": "4", "full_text": "@friend agreed", "display_text_range": ["8", "14"],
+                       "created_at": "Fri Oct 12 08:00:00 +0000 2018", "in_reply_to_status_id_str": "99"}}
+        ]"#;
+        let notes = parse(&[part.to_string()]).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(
+            notes[0].content,
+            "A thought & a link https://example.com\n\nand the second half"
+        );
+        assert_eq!(notes[0].tags, ["tweet"]);
+        assert_eq!(notes[0].meta[TWEET_ID_KEY], "1");
+        assert_eq!(
+            notes[0].created_at.as_deref(),
+            Some("2018-10-10T20:19:24+00:00")
+        );
+        assert_eq!(notes[1].content, "@friend agreed");
+    }
+
+    #[test]
+    fn only_tweet_files_are_read() {
+        assert!(is_tweets_file("data/tweets.js"));
+        assert!(is_tweets_file("data/tweets-part1.js"));
+        assert!(is_tweets_file("data/tweet.js"));
+        assert!(!is_tweets_file("data/tweet-headers.js"));
+        assert!(!is_tweets_file("data/deleted-tweets.js"));
+    }
+}
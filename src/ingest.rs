@@ -0,0 +1,32 @@
+use crate::{
+    config::Config,
+    domain::{memo::NewMemo, tidy},
+};
+
+/// Readies a new public memo the same way wherever it comes from: tidies its
+/// text and runs the `[[rules]]`. False when a rule says to ignore the memo,
+/// which should then not be saved.
+pub(crate) fn prepare_new_memo(config: &Config, new_memo: &mut NewMemo) -> bool {
+    new_memo.content = tidy::tidy(&new_memo.content, &config.tidy);
+    config.rules.apply(new_memo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_see_the_tidied_text() {
+        let config: Config = toml::from_str(
+            "[tidy]\nnormalize_list_markers = true\n\n\
+             [[rules]]\ncontent = \"^- \"\ntags = [\"list\"]\n\n\
+             [[rules]]\ncontent = \"^skip\"\nignore = true",
+        )
+        .unwrap();
+        let mut new_memo = NewMemo::new("* milk");
+        assert!(prepare_new_memo(&config, &mut new_memo));
+        assert_eq!(new_memo.content, "- milk");
+        assert_eq!(new_memo.tags, ["list"]);
+        assert!(!prepare_new_memo(&config, &mut NewMemo::new("skip me")));
+    }
+}
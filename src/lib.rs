@@ -0,0 +1,81 @@
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use std::{env, process};
+
+mod app;
+mod archive;
+mod attachments;
+mod auth;
+#[doc(hidden)]
+pub mod bench;
+mod capture;
+mod cli;
+mod config;
+mod crypto;
+mod date_parse;
+mod db;
+pub(crate) mod domain;
+mod export;
+mod format;
+mod http;
+mod i18n;
+mod import;
+mod ingest;
+mod locator;
+mod metrics;
+mod ocr;
+mod post;
+mod promote;
+mod publish;
+mod remind;
+mod retention;
+mod serve;
+mod shutdown;
+mod spell;
+mod stats;
+mod sync;
+mod timesheet;
+mod topics;
+mod transcribe;
+mod tui;
+mod workspace;
+
+/// Runs the `cap` command line with the process arguments.
+pub fn run() -> Result<()> {
+    CompleteEnv::with_factory(cli::args::Cli::command)
+        .var(cli::completion::COMPLETE_VAR)
+        .complete();
+    // A broken config still leaves `--help` and `cap doctor` working; the
+    // error is reported once a command needs the config.
+    let config = config::load();
+    let (locale, aliases) = match &config {
+        Ok(config) => (config.ui.locale.as_deref(), Some(&config.aliases)),
+        Err(_) => (None, None),
+    };
+    i18n::init(locale);
+    let args = cli::alias::hoist_plain_flag(env::args_os().collect());
+    let args = match aliases {
+        Some(aliases) => cli::alias::expand(args, aliases)?,
+        None => args,
+    };
+    let cli = cli::args::Cli::parse_from(args);
+    let command = match cli.command {
+        // Opening the app's database migrates it, so look before that happens.
+        Some(cli::args::CliCommand::Doctor { migrate_legacy }) => {
+            return cli::commands::doctor(migrate_legacy);
+        }
+        Some(cli::args::CliCommand::App(command)) => Some(*command),
+        None => None,
+    };
+    let mut config = config?;
+    config.ui.plain |= cli.plain;
+    let app = app::AppContext::new(config)?;
+    let result = cli::commands::dispatch(&app, command, cli.content);
+    // Close the database before exiting, as a signal-ended run would otherwise skip that.
+    drop(app);
+    match shutdown::requested() {
+        Some(signal) if result.is_ok() => process::exit(shutdown::exit_code(signal)),
+        _ => result,
+    }
+}
@@ -0,0 +1,42 @@
+use anyhow::{Context, Result, bail};
+use std::process::Command;
+
+use crate::{
+    config::LocationConfig,
+    domain::location::{self, Location},
+};
+
+/// `--location` value that asks the configured locator instead of naming coordinates.
+pub(crate) const CURRENT_LOCATION: &str = "here";
+
+/// Resolves a `--location` argument: `here` runs the locator command, anything
+/// else must be `lat,lon`.
+pub(crate) fn resolve(value: &str, config: &LocationConfig) -> Result<Location> {
+    if value.trim() == CURRENT_LOCATION {
+        locate(config)
+    } else {
+        location::parse_location(value)
+    }
+}
+
+/// Runs `[location] command` through the shell and parses `lat,lon` from its output.
+fn locate(config: &LocationConfig) -> Result<Location> {
+    let Some(command) = config.command.as_deref() else {
+        bail!("set [location] command in the config to use --location here");
+    };
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .with_context(|| format!("failed to run locator `{}`", command))?;
+    if !output.status.success() {
+        bail!(
+            "locator `{}` failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    location::parse_location(stdout.trim())
+        .with_context(|| format!("locator `{}` printed unexpected output", command))
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::{db::Db, stats, sync};
+
+/// Everything `cap stats` knows, for dashboards and status bar modules.
+#[derive(Serialize)]
+pub(crate) struct Metrics {
+    pub(crate) memos: usize,
+    pub(crate) memos_today: usize,
+    pub(crate) daily_goal: usize,
+    pub(crate) current_streak_days: usize,
+    pub(crate) longest_streak_days: usize,
+    /// Oldest first, keyed by the Monday that starts each week.
+    pub(crate) weekly_counts: Vec<WeekCount>,
+    pub(crate) top_tags: Vec<TagCount>,
+    pub(crate) database_size_bytes: u64,
+    pub(crate) sync: SyncMetrics,
+}
+
+#[derive(Serialize)]
+pub(crate) struct WeekCount {
+    pub(crate) week: String,
+    pub(crate) count: u64,
+}
+
+#[derive(Serialize)]
+pub(crate) struct TagCount {
+    pub(crate) tag: String,
+    pub(crate) count: usize,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SyncMetrics {
+    pub(crate) last_success_at: Option<String>,
+    /// Seconds since the last successful sync; `None` if it never happened.
+    pub(crate) lag_seconds: Option<i64>,
+    pub(crate) pending_changes: usize,
+    pub(crate) pending_deletions: usize,
+}
+
+pub(crate) fn collect(db: &Db, daily_goal: usize, now: DateTime<Local>) -> Result<Metrics> {
+    let (stats, streak, sync) = db.read_snapshot(|db| {
+        Ok((
+            stats::collect(db, daily_goal)?,
+            stats::streak(db, daily_goal)?,
+            sync::load_status(db)?,
+        ))
+    })?;
+    let lag_seconds = sync
+        .last_sync_at
+        .as_deref()
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|last| (now - last.with_timezone(&Local)).num_seconds());
+    Ok(Metrics {
+        memos: stats.total_memos,
+        memos_today: streak.today_count,
+        daily_goal: streak.daily_goal,
+        current_streak_days: stats.current_streak,
+        longest_streak_days: stats.longest_streak,
+        weekly_counts: stats
+            .weekly_counts
+            .into_iter()
+            .map(|(week, count)| WeekCount { week, count })
+            .collect(),
+        top_tags: stats
+            .top_tags
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect(),
+        database_size_bytes: stats.db_size_bytes,
+        sync: SyncMetrics {
+            last_success_at: sync.last_sync_at,
+            lag_seconds,
+            pending_changes: sync.pending_changes,
+            pending_deletions: sync.pending_deletions,
+        },
+    })
+}
+
+impl Metrics {
+    /// The gauges in the Prometheus text format, e.g. for the node exporter's
+    /// textfile collector.
+    pub(crate) fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let gauges: [(&str, &str, f64); 7] = [
+            (
+                "cap_memos",
+                "Memos, not counting deleted ones.",
+                self.memos as f64,
+            ),
+            (
+                "cap_memos_today",
+                "Memos captured today.",
+                self.memos_today as f64,
+            ),
+            (
+                "cap_streak_current_days",
+                "Consecutive days meeting the daily goal, up to today.",
+                self.current_streak_days as f64,
+            ),
+            (
+                "cap_streak_longest_days",
+                "Longest run of days meeting the daily goal.",
+                self.longest_streak_days as f64,
+            ),
+            (
+                "cap_database_size_bytes",
+                "Size of the SQLite database.",
+                self.database_size_bytes as f64,
+            ),
+            (
+                "cap_sync_pending_changes",
+                "Memo changes not yet pushed to the sync backend.",
+                self.sync.pending_changes as f64,
+            ),
+            (
+                "cap_sync_pending_deletions",
+                "Deletions not yet pushed to the sync backend.",
+                self.sync.pending_deletions as f64,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            write_gauge(&mut out, name, help, &[("", value)]);
+        }
+        if let Some(lag) = self.sync.lag_seconds {
+            write_gauge(
+                &mut out,
+                "cap_sync_lag_seconds",
+                "Seconds since the last successful sync.",
+                &[("", lag as f64)],
+            );
+        }
+        let tags: Vec<(String, f64)> = self
+            .top_tags
+            .iter()
+            .map(|tag| {
+                (
+                    format!("tag=\"{}\"", escape_label(&tag.tag)),
+                    tag.count as f64,
+                )
+            })
+            .collect();
+        if !tags.is_empty() {
+            let tags: Vec<(&str, f64)> = tags
+                .iter()
+                .map(|(labels, value)| (labels.as_str(), *value))
+                .collect();
+            write_gauge(&mut out, "cap_tag_memos", "Memos per top tag.", &tags);
+        }
+        out
+    }
+}
+
+/// Appends one metric family; each sample is its labels (without braces) and value.
+pub(crate) fn write_gauge(out: &mut String, name: &str, help: &str, samples: &[(&str, f64)]) {
+    write_family(out, name, "gauge", help, samples);
+}
+
+/// Upper bounds, in seconds, of the `cap serve` request latency buckets.
+const LATENCY_BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 1.0, 5.0];
+
+/// A latency histogram with fixed buckets, cumulative like Prometheus expects.
+#[derive(Default)]
+pub(crate) struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    pub(crate) fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Appends a histogram family; each series is its labels (without braces) and histogram.
+pub(crate) fn write_histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    series: &[(&str, &Histogram)],
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (labels, histogram) in series {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, histogram.count
+        );
+        write_sample(out, &format!("{}_sum", name), labels, histogram.sum);
+        write_sample(
+            out,
+            &format!("{}_count", name),
+            labels,
+            histogram.count as f64,
+        );
+    }
+}
+
+pub(crate) fn write_family(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: &[(&str, f64)],
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        write_sample(out, name, labels, *value);
+    }
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &str, value: f64) {
+    if labels.is_empty() {
+        let _ = writeln!(out, "{} {}", name, value);
+    } else {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+pub(crate) fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, domain::memo::NewMemo};
+
+    #[test]
+    fn json_and_prometheus_cover_the_same_numbers() {
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("standup #work")).unwrap();
+        db::add_memo(&db, &NewMemo::new("retro #work")).unwrap();
+        let metrics = collect(&db, 1, Local::now()).unwrap();
+
+        let json = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(json["memos"], 2);
+        assert_eq!(json["memos_today"], 2);
+        assert_eq!(json["top_tags"][0]["tag"], "work");
+        assert_eq!(json["sync"]["pending_changes"], 2);
+        assert!(json["sync"]["lag_seconds"].is_null());
+
+        let text = metrics.to_prometheus();
+        assert!(
+            text.contains("# TYPE cap_memos gauge\ncap_memos 2\n"),
+            "{}",
+            text
+        );
+        assert!(text.contains("cap_tag_memos{tag=\"work\"} 2\n"), "{}", text);
+        assert!(!text.contains("cap_sync_lag_seconds"), "{}", text);
+    }
+}
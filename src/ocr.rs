@@ -0,0 +1,175 @@
+use anyhow::{Context, Result, bail};
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+use crate::{
+    attachments,
+    config::OcrConfig,
+    db::{self, Db},
+    domain::memo::MemoId,
+    http::{self, HttpClient},
+};
+
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff"];
+
+pub(crate) fn is_configured(config: &OcrConfig) -> bool {
+    config.command.is_some() || config.api_url.is_some()
+}
+
+pub(crate) fn is_image(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Reads the text in every image attached to the memo and indexes it for
+/// search, replacing what an earlier run found. Returns the text.
+pub(crate) fn ocr_memo(db: &Db, config: &OcrConfig, memo_id: &MemoId) -> Result<String> {
+    let images: Vec<_> = db::fetch_attachments(db, memo_id)?
+        .into_iter()
+        .filter(|(file_name, _)| is_image(file_name))
+        .collect();
+    if images.is_empty() {
+        bail!("memo has no image attachment");
+    }
+    let mut texts = Vec::new();
+    for (_, path) in images {
+        let text = recognize(config, &path)?;
+        if !text.is_empty() {
+            texts.push(text);
+        }
+    }
+    let text = texts.join("\n\n");
+    db::set_image_text(db, memo_id, &text)?;
+    Ok(text)
+}
+
+fn recognize(config: &OcrConfig, path: &Path) -> Result<String> {
+    let text = match (&config.command, &config.api_url) {
+        (Some(command), _) => attachments::run_command("OCR command", command, path)?,
+        (None, Some(api_url)) => {
+            let client = HttpClient::new()?;
+            http::block_on(request_api(&client, config, api_url, path))?
+        }
+        (None, None) => bail!("set [ocr] command or api_url in the config to read images"),
+    };
+    Ok(text.trim().to_string())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct OcrResponse {
+    #[serde(default)]
+    parsed_results: Vec<ParsedResult>,
+    #[serde(default)]
+    is_errored_on_processing: bool,
+    #[serde(default)]
+    error_message: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ParsedResult {
+    parsed_text: String,
+}
+
+/// Uploads the image to an OCR.space-style `parse/image` endpoint.
+async fn request_api(
+    http: &HttpClient,
+    config: &OcrConfig,
+    api_url: &str,
+    path: &Path,
+) -> Result<String> {
+    let image = fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (content_type, body) =
+        http::multipart(&[("language", &config.language)], &file_name, &image);
+    let mut request = http
+        .post(api_url)
+        .header(CONTENT_TYPE, content_type)
+        .body(body);
+    if let Some(key) = &config.api_key {
+        request = request.header("apikey", key);
+    }
+    let response: OcrResponse = http.send(request).await?.error_for_status()?.json().await?;
+    if response.is_errored_on_processing {
+        bail!(
+            "OCR failed: {}",
+            response
+                .error_message
+                .map(|message| message.to_string())
+                .unwrap_or_default()
+        );
+    }
+    Ok(response
+        .parsed_results
+        .into_iter()
+        .map(|result| result.parsed_text)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_string_contains, header, method},
+    };
+
+    #[test]
+    fn image_text_is_stored_and_searchable() {
+        let dir = std::env::temp_dir().join(format!("cap-ocr-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("screenshot.png");
+        fs::write(&image, "Error 0x80070005: access denied").unwrap();
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = db::add_memo(&db, &NewMemo::new("[image: screenshot.png, 31 B]")).unwrap();
+        db::add_attachment(&db, &memo_id, "screenshot.png", &image, 31).unwrap();
+        let config = OcrConfig {
+            command: Some(r#"cat "$1""#.to_string()),
+            ..Default::default()
+        };
+
+        let text = ocr_memo(&db, &config, &memo_id).unwrap();
+        assert_eq!(text, "Error 0x80070005: access denied");
+        for query in ["0x80070005", "denied", "ac"] {
+            let found = db::search_memos(&db, query, None).unwrap();
+            assert_eq!(found.len(), 1, "{}", query);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn api_results_are_joined() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("apikey", "k"))
+            .and(body_string_contains("name=\"language\"\r\n\r\neng"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"ParsedResults": [{"ParsedText": "line one"}, {"ParsedText": "line two"}],
+                    "IsErroredOnProcessing": false}"#,
+            ))
+            .mount(&server)
+            .await;
+        let dir = std::env::temp_dir().join(format!("cap-ocr-api-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("shot.png");
+        fs::write(&image, "PNG").unwrap();
+        let config = OcrConfig {
+            api_key: Some("k".to_string()),
+            ..Default::default()
+        };
+        let text = request_api(&HttpClient::new().unwrap(), &config, &server.uri(), &image)
+            .await
+            .unwrap();
+        assert_eq!(text, "line one\nline two");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::{config::PostConfig, http::HttpClient};
+
+const MASTODON_TOKEN_VAR: &str = "MASTODON_TOKEN";
+
+/// Publishes `status` on the configured Mastodon instance and returns the
+/// post's URL. The memo id doubles as the idempotency key, so retrying after
+/// a dropped response doesn't post twice.
+pub(crate) async fn mastodon(
+    http: &HttpClient,
+    config: &PostConfig,
+    memo_id: &str,
+    status: &str,
+) -> Result<String> {
+    let instance = config
+        .mastodon_url
+        .as_deref()
+        .context("set post.mastodon_url to your instance, e.g. `https://mastodon.social`")?;
+    let token = env::var(MASTODON_TOKEN_VAR)
+        .ok()
+        .or_else(|| config.mastodon_token.clone())
+        .context(
+            "posting to Mastodon needs a token with the write:statuses scope in MASTODON_TOKEN or post.mastodon_token",
+        )?;
+    let request = http
+        .post(format!(
+            "{}/api/v1/statuses",
+            instance.trim_end_matches('/')
+        ))
+        .bearer_auth(token)
+        .header("Idempotency-Key", memo_id)
+        .json(&StatusRequest {
+            status,
+            visibility: config.mastodon_visibility.as_deref(),
+        });
+    let response = http.send(request).await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        // Mastodon explains rejections, e.g. a status over the length limit.
+        let reason = response
+            .json::<ErrorResponse>()
+            .await
+            .map(|body| body.error)
+            .unwrap_or_else(|_| status.to_string());
+        bail!("{} refused the post: {}", instance, reason);
+    }
+    let post: StatusResponse = response.json().await?;
+    Ok(post.url)
+}
+
+#[derive(Serialize)]
+struct StatusRequest<'a> {
+    status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    visibility: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct StatusResponse {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_json, header, method, path},
+    };
+
+    use super::*;
+
+    fn config(server: &MockServer) -> PostConfig {
+        PostConfig {
+            mastodon_url: Some(format!("{}/", server.uri())),
+            mastodon_token: Some("md-token".to_string()),
+            mastodon_visibility: Some("unlisted".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn mastodon_posts_a_status_and_returns_its_url() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/statuses"))
+            .and(header("authorization", "Bearer md-token"))
+            .and(header("idempotency-key", "memo-1"))
+            .and(body_json(
+                json!({ "status": "hello", "visibility": "unlisted" }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "url": "https://example.social/@me/1" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new().unwrap();
+        let url = mastodon(&http, &config(&server), "memo-1", "hello")
+            .await
+            .unwrap();
+        assert_eq!(url, "https://example.social/@me/1");
+    }
+
+    #[tokio::test]
+    async fn mastodon_rejections_carry_the_instance_reason() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/statuses"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(
+                json!({ "error": "Validation failed: Text character limit of 500 exceeded" }),
+            ))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new().unwrap();
+        let err = mastodon(&http, &config(&server), "memo-1", "long")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("character limit"), "{}", err);
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result, bail};
+use reqwest::header::ACCEPT;
+use serde::{Deserialize, Serialize};
+use std::{env, process::Command};
+
+use crate::{
+    config::{PromoteConfig, PublishConfig},
+    http::HttpClient,
+    publish,
+};
+
+const TODOIST_TOKEN_VAR: &str = "TODOIST_API_TOKEN";
+
+/// Adds a Taskwarrior task titled `title` and returns its UUID.
+pub(crate) fn taskwarrior(config: &PromoteConfig, title: &str) -> Result<String> {
+    let command = &config.taskwarrior_command;
+    let output = run(command, &["add", "--", title])?;
+    let id = created_task_id(&output).with_context(|| {
+        format!(
+            "`{} add` didn't report a task id: {}",
+            command,
+            output.trim()
+        )
+    })?;
+    let uuid = run(command, &["_get", &format!("{}.uuid", id)])?;
+    Ok(uuid.trim().to_string())
+}
+
+fn run(command: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(command)
+        .args(["rc.confirmation=off", "rc.verbose=new-id"])
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run `{}`; is Taskwarrior installed?", command))?;
+    if !output.status.success() {
+        bail!(
+            "`{} {}` failed: {}",
+            command,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The id in Taskwarrior's `Created task 12.`
+fn created_task_id(output: &str) -> Option<u64> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Created task ")?
+            .trim_end_matches('.')
+            .parse()
+            .ok()
+    })
+}
+
+/// Creates a Todoist task and returns its URL.
+pub(crate) async fn todoist(
+    http: &HttpClient,
+    config: &PromoteConfig,
+    title: &str,
+    description: &str,
+) -> Result<String> {
+    let token = env::var(TODOIST_TOKEN_VAR)
+        .ok()
+        .or_else(|| config.todoist_token.clone())
+        .context(
+            "promoting to Todoist needs a token in TODOIST_API_TOKEN or promote.todoist_token",
+        )?;
+    let request = http
+        .post(format!(
+            "{}/tasks",
+            config.todoist_api_url.trim_end_matches('/')
+        ))
+        .bearer_auth(token)
+        .json(&TodoistRequest {
+            content: title,
+            description,
+        });
+    let task: TodoistResponse = http.send(request).await?.error_for_status()?.json().await?;
+    Ok(task.url)
+}
+
+/// Opens an issue in `[promote] github_repo` and returns its page URL.
+pub(crate) async fn github_issue(
+    http: &HttpClient,
+    config: &PromoteConfig,
+    publish_config: &PublishConfig,
+    title: &str,
+    body: &str,
+) -> Result<String> {
+    let repo = config
+        .github_repo
+        .as_deref()
+        .context("set promote.github_repo to `owner/name` to open issues")?;
+    let token = publish::github_token(publish_config).context(
+        "opening an issue needs a token with the repo scope in GITHUB_TOKEN or publish.github_token",
+    )?;
+    let request = http
+        .post(format!(
+            "{}/repos/{}/issues",
+            publish_config.github_api_url.trim_end_matches('/'),
+            repo
+        ))
+        .bearer_auth(token)
+        .header(ACCEPT, "application/vnd.github+json")
+        .json(&IssueRequest { title, body });
+    let issue: IssueResponse = http.send(request).await?.error_for_status()?.json().await?;
+    Ok(issue.html_url)
+}
+
+#[derive(Serialize)]
+struct TodoistRequest<'a> {
+    content: &'a str,
+    description: &'a str,
+}
+
+#[derive(Deserialize)]
+struct TodoistResponse {
+    url: String,
+}
+
+#[derive(Serialize)]
+struct IssueRequest<'a> {
+    title: &'a str,
+    body: &'a str,
+}
+
+#[derive(Deserialize)]
+struct IssueResponse {
+    html_url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_json, header, method, path},
+    };
+
+    use super::*;
+
+    #[test]
+    fn reads_the_new_task_id() {
+        assert_eq!(created_task_id("Created task 12.\n"), Some(12));
+        assert_eq!(
+            created_task_id("Configuration override\nCreated task 3."),
+            Some(3)
+        );
+        assert_eq!(created_task_id("nothing"), None);
+    }
+
+    #[tokio::test]
+    async fn todoist_and_github_return_the_created_links() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tasks"))
+            .and(header("authorization", "Bearer td-token"))
+            .and(body_json(
+                json!({ "content": "Call the bank", "description": "re: fees" }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(
+                    json!({ "id": "1", "url": "https://todoist.com/showTask?id=1" }),
+                ),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/repos/me/notes/issues"))
+            .and(header("authorization", "Bearer gh-token"))
+            .and(body_json(
+                json!({ "title": "Call the bank", "body": "re: fees" }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(json!({ "html_url": "https://github.com/me/notes/issues/7" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let config = PromoteConfig {
+            todoist_token: Some("td-token".to_string()),
+            todoist_api_url: server.uri(),
+            github_repo: Some("me/notes".to_string()),
+            ..Default::default()
+        };
+        let publish_config = PublishConfig {
+            github_token: Some("gh-token".to_string()),
+            github_api_url: server.uri(),
+            ..Default::default()
+        };
+
+        let http = HttpClient::new().unwrap();
+        let url = todoist(&http, &config, "Call the bank", "re: fees")
+            .await
+            .unwrap();
+        assert_eq!(url, "https://todoist.com/showTask?id=1");
+        let url = github_issue(&http, &config, &publish_config, "Call the bank", "re: fees")
+            .await
+            .unwrap();
+        assert_eq!(url, "https://github.com/me/notes/issues/7");
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result, bail};
+use reqwest::header::{ACCEPT, LOCATION};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env};
+
+use crate::{config::PublishConfig, http::HttpClient};
+
+const GITHUB_TOKEN_VAR: &str = "GITHUB_TOKEN";
+
+/// Uploads `content` as a one-file gist, secret unless configured otherwise,
+/// and returns the gist's page URL.
+pub(crate) async fn gist(
+    http: &HttpClient,
+    config: &PublishConfig,
+    file_name: &str,
+    content: &str,
+) -> Result<String> {
+    let token = github_token(config)
+        .context("publishing a gist needs a token with the gist scope in GITHUB_TOKEN or publish.github_token")?;
+    let body = GistRequest {
+        description: "Shared from cap",
+        public: config.gist_public,
+        files: HashMap::from([(file_name, GistFile { content })]),
+    };
+    let request = http
+        .post(format!(
+            "{}/gists",
+            config.github_api_url.trim_end_matches('/')
+        ))
+        .bearer_auth(token)
+        .header(ACCEPT, "application/vnd.github+json")
+        .json(&body);
+    let gist: GistResponse = http.send(request).await?.error_for_status()?.json().await?;
+    Ok(gist.html_url)
+}
+
+/// `GITHUB_TOKEN`, or the configured token.
+pub(crate) fn github_token(config: &PublishConfig) -> Option<String> {
+    env::var(GITHUB_TOKEN_VAR)
+        .ok()
+        .or_else(|| config.github_token.clone())
+}
+
+/// Posts `content` to a dpaste-style service, which answers with the paste URL
+/// in `Location` or as the response body.
+pub(crate) async fn paste(
+    http: &HttpClient,
+    config: &PublishConfig,
+    content: &str,
+) -> Result<String> {
+    let request = http
+        .post(&config.paste_url)
+        .form(&[("content", content), ("syntax", "markdown")]);
+    let response = http.send(request).await?.error_for_status()?;
+    let location = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await?;
+    let url = location.unwrap_or_else(|| body.trim().to_string());
+    if !url.starts_with("http") {
+        bail!("{} answered without a paste URL", config.paste_url);
+    }
+    Ok(url)
+}
+
+#[derive(Serialize)]
+struct GistRequest<'a> {
+    description: &'a str,
+    public: bool,
+    files: HashMap<&'a str, GistFile<'a>>,
+}
+
+#[derive(Serialize)]
+struct GistFile<'a> {
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct GistResponse {
+    html_url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_json, body_string_contains, header, method, path},
+    };
+
+    use super::*;
+
+    fn config(server: &MockServer) -> PublishConfig {
+        PublishConfig {
+            github_token: Some("gh-token".to_string()),
+            github_api_url: server.uri(),
+            paste_url: format!("{}/api/v2/", server.uri()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn gist_posts_a_secret_gist_and_returns_its_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/gists"))
+            .and(header("authorization", "Bearer gh-token"))
+            .and(body_json(json!({
+                "description": "Shared from cap",
+                "public": false,
+                "files": { "memo-1234abcd.md": { "content": "hello" } },
+            })))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .set_body_json(json!({ "html_url": "https://gist.github.com/abc" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new().unwrap();
+        let url = gist(&http, &config(&server), "memo-1234abcd.md", "hello")
+            .await
+            .unwrap();
+        assert_eq!(url, "https://gist.github.com/abc");
+    }
+
+    #[tokio::test]
+    async fn paste_reads_the_url_from_the_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/"))
+            .and(body_string_contains("content=hello+there"))
+            .respond_with(ResponseTemplate::new(201).set_body_string("https://dpaste.com/XYZ\n"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new().unwrap();
+        let url = paste(&http, &config(&server), "hello there").await.unwrap();
+        assert_eq!(url, "https://dpaste.com/XYZ");
+    }
+}
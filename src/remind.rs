@@ -0,0 +1,202 @@
+//! Reminders: `cap remind` stores when a memo should come back as its
+//! `remind_at` field, and `cap remindd` watches for the due ones. With
+//! `[remind] notifications` on they pop up as desktop notifications whose
+//! snooze and done buttons update the memo.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use std::{sync::mpsc, thread, time};
+
+use crate::{
+    config::RemindConfig,
+    db::{self, Db},
+    domain::memo::{Memo, MemoId},
+    i18n::t,
+    shutdown,
+};
+
+pub(crate) const REMIND_AT_KEY: &str = "remind_at";
+/// Tag the done button adds.
+const DONE_TAG: &str = "done";
+/// How long the daemon sleeps between looks for due reminders.
+const POLL: time::Duration = time::Duration::from_secs(1);
+
+/// What the user chose on a notification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
+    Snooze,
+    Done,
+}
+
+pub(crate) fn set(db: &Db, memo_id: &MemoId, at: DateTime<Local>) -> Result<()> {
+    db::set_meta(db, memo_id, REMIND_AT_KEY, &at.to_rfc3339())
+}
+
+/// Removes the memo's reminder; returns whether it had one.
+pub(crate) fn clear(db: &Db, memo_id: &MemoId) -> Result<bool> {
+    db::unset_meta(db, memo_id, REMIND_AT_KEY)
+}
+
+/// Memos whose reminder is due by `now`, oldest reminder first. Their reminders
+/// are cleared, so each one fires once unless it is snoozed.
+pub(crate) fn take_due(db: &Db, now: DateTime<Local>) -> Result<Vec<Memo>> {
+    db.atomically(|db| {
+        let mut due: Vec<_> = db::fetch_meta_values(db, REMIND_AT_KEY)?
+            .into_iter()
+            .filter_map(|(memo_id, value)| {
+                let at = DateTime::parse_from_rfc3339(&value).ok()?;
+                (at <= now).then_some((at, memo_id))
+            })
+            .collect();
+        due.sort_by_key(|(at, _)| *at);
+        let mut memos = Vec::new();
+        for (_, memo_id) in due {
+            clear(db, &memo_id)?;
+            memos.push(db::fetch_memo_by_id_prefix(db, memo_id.as_str())?);
+        }
+        Ok(memos)
+    })
+}
+
+/// Maps a notification button back onto the memo: snooze brings the reminder
+/// back later, done tags the memo `#done`.
+pub(crate) fn apply(
+    db: &Db,
+    config: &RemindConfig,
+    memo_id: &MemoId,
+    action: Action,
+    now: DateTime<Local>,
+) -> Result<()> {
+    match action {
+        Action::Snooze => set(
+            db,
+            memo_id,
+            now + Duration::minutes(i64::from(config.snooze_minutes)),
+        ),
+        Action::Done => {
+            clear(db, memo_id)?;
+            db::add_explicit_tags(db, memo_id, &[DONE_TAG.to_string()])
+        }
+    }
+}
+
+/// Fires reminders as they come due until a signal stops the daemon. Button
+/// presses arrive from the notification threads and are applied here, so the
+/// database is only ever written from this thread.
+pub(crate) fn run(db: &Db, config: &RemindConfig) -> Result<()> {
+    shutdown::install()?;
+    let (sender, actions) = mpsc::channel();
+    println!("{}", t!("remind-watching"));
+    while shutdown::requested().is_none() {
+        for (memo_id, action) in actions.try_iter() {
+            apply(db, config, &memo_id, action, Local::now())?;
+        }
+        for memo in take_due(db, Local::now())? {
+            let text = reminder_text(&memo);
+            if !config.notifications {
+                println!(
+                    "{}",
+                    t!("remind-due", id = memo.memo_id.as_str(), text = text)
+                );
+                continue;
+            }
+            // A missing notification server shouldn't stop the other reminders.
+            if let Err(err) = notify(memo.memo_id, &text, sender.clone()) {
+                eprintln!("cap remindd: {:#}", err);
+            }
+        }
+        thread::sleep(POLL);
+    }
+    Ok(())
+}
+
+/// The memo's first line; private memos stay hidden.
+fn reminder_text(memo: &Memo) -> String {
+    if memo.private {
+        return t!("remind-private");
+    }
+    memo.content.lines().next().unwrap_or_default().to_string()
+}
+
+/// Shows the notification and waits for a button on a thread of its own.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notify(memo_id: MemoId, text: &str, sender: mpsc::Sender<(MemoId, Action)>) -> Result<()> {
+    let handle = notify_rust::Notification::new()
+        .summary(&t!("remind-summary"))
+        .body(text)
+        .action("snooze", &t!("remind-snooze"))
+        .action("done", &t!("remind-done"))
+        .show()?;
+    thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            let action = match action {
+                "snooze" => Action::Snooze,
+                "done" => Action::Done,
+                _ => return,
+            };
+            // The daemon may already be gone; the press is then moot.
+            let _ = sender.send((memo_id, action));
+        })
+    });
+    Ok(())
+}
+
+/// Only Linux and the BSDs report which button was pressed, so elsewhere the
+/// notification is shown without any.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn notify(_memo_id: MemoId, text: &str, _sender: mpsc::Sender<(MemoId, Action)>) -> Result<()> {
+    notify_rust::Notification::new()
+        .summary(&t!("remind-summary"))
+        .body(text)
+        .show()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+
+    #[test]
+    fn due_reminders_fire_once_in_order() {
+        let db = Db::open_in_memory().unwrap();
+        let now = Local::now();
+        let later = db::add_memo(&db, &NewMemo::new("water the plants")).unwrap();
+        let second = db::add_memo(&db, &NewMemo::new("call back")).unwrap();
+        let first = db::add_memo(&db, &NewMemo::new("stand up")).unwrap();
+        set(&db, &later, now + Duration::hours(1)).unwrap();
+        set(&db, &second, now - Duration::minutes(1)).unwrap();
+        set(&db, &first, now - Duration::minutes(5)).unwrap();
+
+        let due: Vec<_> = take_due(&db, now)
+            .unwrap()
+            .into_iter()
+            .map(|memo| memo.content)
+            .collect();
+        assert_eq!(due, ["stand up", "call back"]);
+        assert!(take_due(&db, now).unwrap().is_empty());
+        assert_eq!(take_due(&db, now + Duration::hours(2)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn snooze_and_done_update_the_memo() {
+        let db = Db::open_in_memory().unwrap();
+        let now = Local::now();
+        let memo_id = db::add_memo(&db, &NewMemo::new("renew passport")).unwrap();
+        let config = RemindConfig::default();
+
+        apply(&db, &config, &memo_id, Action::Snooze, now).unwrap();
+        assert!(
+            take_due(&db, now + Duration::minutes(9))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(take_due(&db, now + Duration::minutes(10)).unwrap().len(), 1);
+
+        set(&db, &memo_id, now).unwrap();
+        apply(&db, &config, &memo_id, Action::Done, now).unwrap();
+        assert!(take_due(&db, now).unwrap().is_empty());
+        let memo = db::fetch_memo_by_id_prefix(&db, memo_id.as_str()).unwrap();
+        assert_eq!(memo.tags, ["done"]);
+    }
+}
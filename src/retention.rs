@@ -0,0 +1,91 @@
+use anyhow::Result;
+use chrono::{DateTime, Days, Local};
+
+use crate::{
+    config::RetentionConfig,
+    db::{self, Db},
+    domain::{memo::Memo, tags},
+};
+
+/// Memos the `[[retention]]` rules archive as of `now`: those matching a rule
+/// that are older than its age limit and not archived yet, oldest first.
+pub(crate) fn due_for_archive(
+    db: &Db,
+    rules: &[RetentionConfig],
+    now: DateTime<Local>,
+) -> Result<Vec<Memo>> {
+    let mut due: Vec<Memo> = Vec::new();
+    for rule in rules {
+        let filter = db::MemoFilter {
+            tag: rule.tag.as_deref().map(tags::normalize_tag),
+            notebook: rule.notebook.clone(),
+            until: now.checked_sub_days(Days::new(u64::from(rule.archive_after_days))),
+            ..Default::default()
+        };
+        for memo in db::fetch_filtered_memos(db, &filter)? {
+            let archived = memo.tags.iter().any(|tag| tag == tags::ARCHIVED_TAG);
+            if !archived
+                && !due
+                    .iter()
+                    .any(|seen| seen.memo_id.as_str() == memo.memo_id.as_str())
+            {
+                due.push(memo);
+            }
+        }
+    }
+    due.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(due)
+}
+
+/// Tags each memo `#archived`.
+pub(crate) fn archive(db: &Db, memos: &[Memo]) -> Result<()> {
+    db.atomically(|db| {
+        for memo in memos {
+            db::add_explicit_tags(db, &memo.memo_id, &[tags::ARCHIVED_TAG.to_string()])?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+    use chrono::TimeZone;
+
+    fn add(db: &Db, content: &str, notebook: Option<&str>, day: u32) {
+        let created_at = Local.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap();
+        let new_memo = NewMemo::new(content)
+            .with_notebook(notebook.map(str::to_string))
+            .with_created_at(Some(created_at));
+        db::add_memo(db, &new_memo).unwrap();
+    }
+
+    #[test]
+    fn old_matching_memos_are_archived_once() {
+        let db = Db::open_in_memory().unwrap();
+        add(&db, "old thought #fleeting", None, 1);
+        add(&db, "new thought #fleeting", None, 25);
+        add(&db, "old keeper #project", None, 1);
+        add(&db, "old inbox item", Some("inbox"), 2);
+        let rules = [
+            RetentionConfig {
+                tag: Some("#Fleeting".to_string()),
+                archive_after_days: 10,
+                ..Default::default()
+            },
+            RetentionConfig {
+                notebook: Some("inbox".to_string()),
+                archive_after_days: 7,
+                ..Default::default()
+            },
+        ];
+        let now = Local.with_ymd_and_hms(2024, 5, 30, 12, 0, 0).unwrap();
+
+        let due = due_for_archive(&db, &rules, now).unwrap();
+        let contents: Vec<&str> = due.iter().map(|memo| memo.content.as_str()).collect();
+        assert_eq!(contents, ["old thought #fleeting", "old inbox item"]);
+        archive(&db, &due).unwrap();
+        assert!(due_for_archive(&db, &rules, now).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+use serde_json::json;
+
+use super::{Request, Response, token_matches};
+use crate::{
+    capture::{self, CaptureRequest},
+    config::{Config, ServeConfig},
+    db::Db,
+};
+
+pub(super) const PATH: &str = "/capture";
+const JSON: &str = "application/json";
+
+/// `POST /capture` takes the same JSON as `cap capture`, so a bookmarklet can
+/// send the page title, URL, and selection from any browser:
+///
+/// ```text
+/// javascript:fetch('http://127.0.0.1:8420/capture', {method: 'POST',
+///   headers: {Authorization: 'Bearer TOKEN', 'Content-Type': 'application/json'},
+///   body: JSON.stringify({content: document.title + '\n' + location.href + '\n\n'
+///     + getSelection(), source: 'bookmarklet'})})
+/// ```
+pub(super) fn handle(db: &Db, config: &Config, request: &Request) -> Response {
+    let serve = &config.serve;
+    let Some(token) = serve
+        .capture_token
+        .as_deref()
+        .filter(|token| !token.is_empty())
+    else {
+        return Response::new("404 Not Found");
+    };
+    let response = match request.method.as_str() {
+        "OPTIONS" => preflight(request),
+        "POST" if !authorized(request, token) => Response::new("401 Unauthorized")
+            .body(
+                JSON,
+                json!({ "error": "bad capture token", "code": "unauthorized" }).to_string(),
+            )
+            .header("WWW-Authenticate", "Bearer"),
+        "POST" => match serde_json::from_slice::<CaptureRequest>(&request.body) {
+            Ok(capture_request) => match capture::capture(db, config, capture_request) {
+                Ok(memo_id) => Response::new("201 Created")
+                    .body(JSON, json!({ "id": memo_id.as_str() }).to_string()),
+                Err(err) => error(&err),
+            },
+            Err(err) => error(&err.into()),
+        },
+        _ => Response::new("405 Method Not Allowed").header("Allow", "OPTIONS, POST"),
+    };
+    with_cors(response, request, serve)
+}
+
+fn error(err: &anyhow::Error) -> Response {
+    Response::new("400 Bad Request").body(JSON, capture::error_json(err).to_string())
+}
+
+fn preflight(request: &Request) -> Response {
+    let response = Response::new("204 No Content")
+        .header("Access-Control-Allow-Methods", "POST")
+        .header(
+            "Access-Control-Allow-Headers",
+            "Authorization, Content-Type",
+        )
+        .header("Access-Control-Max-Age", "600");
+    // Chrome asks before letting a public page talk to a loopback address.
+    if request.header("Access-Control-Request-Private-Network") == Some("true") {
+        return response.header("Access-Control-Allow-Private-Network", "true");
+    }
+    response
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| token_matches(given, token))
+}
+
+fn with_cors(response: Response, request: &Request, config: &ServeConfig) -> Response {
+    let Some(origin) = request.header("Origin") else {
+        return response;
+    };
+    let allowed = config
+        .cors_origins
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin);
+    if !allowed {
+        return response;
+    }
+    response
+        .header("Access-Control-Allow-Origin", origin)
+        .header("Vary", "Origin")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    fn config(origins: &[&str]) -> Config {
+        Config {
+            serve: ServeConfig {
+                capture_token: Some("s3cret".to_string()),
+                cors_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn request(method: &str, headers: &[(&str, &str)], body: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: PATH.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn authorized_posts_capture_a_memo() {
+        let db = Db::open_in_memory().unwrap();
+        let response = handle(
+            &db,
+            &config(&[]),
+            &request(
+                "POST",
+                &[("Authorization", "Bearer s3cret")],
+                r#"{"content": "Page title\nhttps://example.com", "source": "bookmarklet"}"#,
+            ),
+        );
+        assert_eq!(response.status, "201 Created");
+        let memos = db::fetch_memos(&db, None).unwrap();
+        assert_eq!(memos.len(), 1);
+        assert!(
+            String::from_utf8(response.body)
+                .unwrap()
+                .contains(memos[0].memo_id.as_str())
+        );
+    }
+
+    #[test]
+    fn wrong_or_missing_tokens_are_refused() {
+        let db = Db::open_in_memory().unwrap();
+        for headers in [&[][..], &[("Authorization", "Bearer s3creT")][..]] {
+            let response = handle(
+                &db,
+                &config(&[]),
+                &request("POST", headers, r#"{"content": "x"}"#),
+            );
+            assert_eq!(response.status, "401 Unauthorized");
+        }
+        let unconfigured = handle(
+            &db,
+            &Config::default(),
+            &request(
+                "POST",
+                &[("Authorization", "Bearer ")],
+                r#"{"content": "x"}"#,
+            ),
+        );
+        assert_eq!(unconfigured.status, "404 Not Found");
+        assert!(db::fetch_memos(&db, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cors_headers_only_go_to_allowed_origins() {
+        let db = Db::open_in_memory().unwrap();
+        let preflight = request(
+            "OPTIONS",
+            &[
+                ("Origin", "https://example.com"),
+                ("Access-Control-Request-Private-Network", "true"),
+            ],
+            "",
+        );
+        let response = handle(&db, &config(&["https://example.com/"]), &preflight);
+        assert_eq!(response.status, "204 No Content");
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Origin"),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Private-Network"),
+            Some("true")
+        );
+
+        let response = handle(&db, &config(&["https://other.org"]), &preflight);
+        assert_eq!(header(&response, "Access-Control-Allow-Origin"), None);
+        let response = handle(&db, &config(&["*"]), &preflight);
+        assert_eq!(
+            header(&response, "Access-Control-Allow-Origin"),
+            Some("https://example.com")
+        );
+    }
+}
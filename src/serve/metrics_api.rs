@@ -0,0 +1,139 @@
+use anyhow::Result;
+use chrono::Local;
+use std::{collections::BTreeMap, time::Duration};
+
+use super::{Request, Response};
+use crate::{
+    config::Config,
+    db::Db,
+    metrics::{self, Histogram},
+};
+
+pub(super) const PATH: &str = "/metrics";
+const PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// What `cap serve` has handled since it started, kept in memory only.
+#[derive(Default)]
+pub(super) struct RequestLog {
+    /// Keyed by route and status code.
+    counts: BTreeMap<(&'static str, String), u64>,
+    latencies: BTreeMap<&'static str, Histogram>,
+}
+
+impl RequestLog {
+    pub(super) fn record(&mut self, route: &'static str, status: &str, elapsed: Duration) {
+        let code = status.split(' ').next().unwrap_or(status).to_string();
+        *self.counts.entry((route, code)).or_default() += 1;
+        self.latencies
+            .entry(route)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    fn write(&self, out: &mut String) {
+        if self.counts.is_empty() {
+            return;
+        }
+        let counts: Vec<(String, f64)> = self
+            .counts
+            .iter()
+            .map(|((route, code), count)| {
+                (
+                    format!("route=\"{}\",code=\"{}\"", route, code),
+                    *count as f64,
+                )
+            })
+            .collect();
+        let counts: Vec<(&str, f64)> = counts
+            .iter()
+            .map(|(labels, count)| (labels.as_str(), *count))
+            .collect();
+        metrics::write_family(
+            out,
+            "cap_http_requests_total",
+            "counter",
+            "Requests handled by cap serve since it started.",
+            &counts,
+        );
+        let labels: Vec<(String, &Histogram)> = self
+            .latencies
+            .iter()
+            .map(|(route, histogram)| (format!("route=\"{}\"", route), histogram))
+            .collect();
+        let labels: Vec<(&str, &Histogram)> = labels
+            .iter()
+            .map(|(labels, histogram)| (labels.as_str(), *histogram))
+            .collect();
+        metrics::write_histogram(
+            out,
+            "cap_http_request_duration_seconds",
+            "Time spent answering a request, from parsed request to written response.",
+            &labels,
+        );
+    }
+}
+
+/// `GET /metrics` reports the same gauges as `cap stats --prometheus`, plus
+/// request counts and latencies for this server, for a Prometheus scraper.
+pub(super) fn handle(
+    db: &Db,
+    config: &Config,
+    request: &Request,
+    log: &RequestLog,
+) -> Result<Response> {
+    if !matches!(request.method.as_str(), "GET" | "HEAD") {
+        return Ok(Response::new("405 Method Not Allowed").header("Allow", "GET, HEAD"));
+    }
+    let mut out = metrics::collect(db, config.streak.daily_goal, Local::now())?.to_prometheus();
+    log.write(&mut out);
+    Ok(Response::new("200 OK").body(PROMETHEUS, out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, domain::memo::NewMemo};
+
+    fn request(method: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: PATH.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reports_memo_gauges_and_request_metrics() {
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("first")).unwrap();
+        let mut log = RequestLog::default();
+        log.record("webdav", "207 Multi-Status", Duration::from_millis(3));
+        log.record("webdav", "404 Not Found", Duration::from_millis(30));
+
+        let response = handle(&db, &Config::default(), &request("GET"), &log).unwrap();
+        assert_eq!(response.status, "200 OK");
+        let text = String::from_utf8(response.body).unwrap();
+        assert!(text.contains("\ncap_memos 1\n"), "{}", text);
+        assert!(
+            text.contains("cap_http_requests_total{route=\"webdav\",code=\"404\"} 1\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains(
+                "cap_http_request_duration_seconds_bucket{route=\"webdav\",le=\"0.005\"} 1\n"
+            ),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("cap_http_request_duration_seconds_count{route=\"webdav\"} 2\n"),
+            "{}",
+            text
+        );
+
+        let response = handle(&db, &Config::default(), &request("POST"), &log).unwrap();
+        assert_eq!(response.status, "405 Method Not Allowed");
+    }
+}
@@ -0,0 +1,349 @@
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{Config, ServeConfig},
+    db::Db,
+    i18n::t,
+    shutdown,
+};
+
+mod capture_api;
+mod metrics_api;
+#[cfg(unix)]
+mod socket;
+mod webdav;
+
+#[cfg(unix)]
+pub(crate) use socket::run_socket;
+
+/// Room for a captured page selection; PROPFIND bodies are far smaller.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+/// Drops clients that connect and then go quiet, since connections are served one at a time.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an idle server waits between checks for a shutdown signal.
+const SHUTDOWN_POLL: Duration = Duration::from_millis(200);
+
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Response {
+    status: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: &'static str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn body(mut self, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self.header("Content-Type", content_type)
+    }
+}
+
+/// Serves the memo store read-only over WebDAV until the process is stopped,
+/// plus `POST /capture` when a capture token is configured and `GET /metrics`
+/// for Prometheus. Every request reads the database afresh, so new memos show
+/// up right away. Beyond loopback, every request but `/capture` needs
+/// `[serve] webdav_token`.
+pub(crate) fn run_webdav(db: &Db, config: &Config, addr: &str) -> Result<()> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("couldn't listen on {}", addr))?;
+    if !listener.local_addr()?.ip().is_loopback() && webdav_token(&config.serve).is_none() {
+        bail!(
+            "listening on {} lets other machines read your memos; set [serve] webdav_token first",
+            addr
+        );
+    }
+    println!(
+        "{}",
+        t!(
+            "serve-webdav",
+            url = format!("http://{}/", listener.local_addr()?)
+        )
+    );
+    let local = listener.local_addr()?;
+    let mut log = metrics_api::RequestLog::default();
+    listener.set_nonblocking(true)?;
+    serve_until_shutdown(
+        || {
+            let (stream, _) = listener.accept()?;
+            stream.set_nonblocking(false)?;
+            Ok(stream)
+        },
+        |stream| handle_connection(db, config, local, &mut log, stream),
+    )
+}
+
+/// Hands each connection from a non-blocking `accept` to `handle` until a
+/// signal asks the server to stop. The connection in progress always finishes,
+/// so its database write isn't cut short.
+fn serve_until_shutdown<S>(
+    mut accept: impl FnMut() -> io::Result<S>,
+    mut handle: impl FnMut(S) -> Result<()>,
+) -> Result<()> {
+    shutdown::install()?;
+    while shutdown::requested().is_none() {
+        match accept() {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(SHUTDOWN_POLL),
+            // One broken client shouldn't take the server down.
+            result => {
+                if let Err(err) = result.map_err(anyhow::Error::from).and_then(&mut handle) {
+                    eprintln!("cap serve: {:#}", err);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(
+    db: &Db,
+    config: &Config,
+    local: SocketAddr,
+    log: &mut metrics_api::RequestLog,
+    mut stream: TcpStream,
+) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let request = read_request(&stream)?;
+    let started = Instant::now();
+    let head = request.method == "HEAD";
+    let (route, response) = if !host_allowed(request.header("Host"), local) {
+        ("rejected", Response::new("421 Misdirected Request"))
+    } else if request.path == capture_api::PATH {
+        ("capture", capture_api::handle(db, config, &request))
+    } else if !local.ip().is_loopback() && !webdav_authorized(&request, &config.serve) {
+        (
+            "rejected",
+            Response::new("401 Unauthorized").header("WWW-Authenticate", "Basic realm=\"cap\""),
+        )
+    } else if request.path == metrics_api::PATH {
+        ("metrics", metrics_api::handle(db, config, &request, log)?)
+    } else {
+        ("webdav", webdav::handle(db, &request)?)
+    };
+    let status = response.status;
+    write_response(&mut stream, response, head)?;
+    log.record(route, status, started.elapsed());
+    Ok(())
+}
+
+/// Whether the `Host` header names this server: the address it listens on,
+/// `localhost`, or loopback. A web page that points its own domain at
+/// 127.0.0.1 then can't read memos through the visitor's browser. Listening on
+/// every interface lets any IP address through, as other machines use those.
+fn host_allowed(host: Option<&str>, local: SocketAddr) -> bool {
+    let Some(host) = host else {
+        return false;
+    };
+    let name = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or(bracketed),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    if name.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    name.parse::<IpAddr>()
+        .is_ok_and(|ip| ip.is_loopback() || ip == local.ip() || local.ip().is_unspecified())
+}
+
+fn webdav_token(config: &ServeConfig) -> Option<&str> {
+    config
+        .webdav_token
+        .as_deref()
+        .filter(|token| !token.is_empty())
+}
+
+/// Whether the request carries `[serve] webdav_token`, as a Bearer token or as
+/// the password of Basic auth, which is what file managers mounting the share send.
+fn webdav_authorized(request: &Request, config: &ServeConfig) -> bool {
+    let (Some(token), Some(value)) = (webdav_token(config), request.header("Authorization")) else {
+        return false;
+    };
+    if let Some(given) = value.strip_prefix("Bearer ") {
+        return token_matches(given, token);
+    }
+    value
+        .strip_prefix("Basic ")
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|pair| {
+            let (_, password) = pair.split_once(':')?;
+            Some(token_matches(password, token))
+        })
+        .unwrap_or(false)
+}
+
+/// Compares every byte so the response time doesn't hint at a matching prefix.
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line {:?}", request_line.trim_end());
+    };
+    let path = target.split(['?', '#']).next().unwrap_or("/").to_string();
+    let mut request = Request {
+        method: method.to_ascii_uppercase(),
+        path,
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap_or(0);
+        }
+        request
+            .headers
+            .push((name.trim().to_string(), value.to_string()));
+    }
+    if content_length > MAX_BODY_BYTES {
+        bail!("request body of {} bytes is too large", content_length);
+    }
+    reader.take(content_length).read_to_end(&mut request.body)?;
+    Ok(request)
+}
+
+fn write_response(stream: &mut TcpStream, response: Response, head: bool) -> Result<()> {
+    let mut out = format!("HTTP/1.1 {}\r\n", response.status);
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    stream.write_all(out.as_bytes())?;
+    if !head {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_hosts_naming_this_server_are_served() {
+        let loopback: SocketAddr = "127.0.0.1:8420".parse().unwrap();
+        for host in [
+            "127.0.0.1:8420",
+            "localhost:8420",
+            "LOCALHOST",
+            "[::1]:8420",
+        ] {
+            assert!(host_allowed(Some(host), loopback), "{host}");
+        }
+        for host in ["evil.example:8420", "192.168.1.20:8420", "127.0.0.1.nip.io"] {
+            assert!(!host_allowed(Some(host), loopback), "{host}");
+        }
+        assert!(!host_allowed(None, loopback));
+
+        let everywhere: SocketAddr = "0.0.0.0:8420".parse().unwrap();
+        assert!(host_allowed(Some("192.168.1.20:8420"), everywhere));
+        assert!(!host_allowed(Some("evil.example"), everywhere));
+        let lan: SocketAddr = "192.168.1.20:8420".parse().unwrap();
+        assert!(host_allowed(Some("192.168.1.20:8420"), lan));
+    }
+
+    #[test]
+    fn webdav_beyond_loopback_needs_the_token() {
+        let config = ServeConfig {
+            webdav_token: Some("s3cret".to_string()),
+            ..Default::default()
+        };
+        let request = |authorization: Option<&str>| Request {
+            method: "PROPFIND".to_string(),
+            path: "/".to_string(),
+            headers: authorization
+                .map(|value| ("Authorization".to_string(), value.to_string()))
+                .into_iter()
+                .collect(),
+            body: Vec::new(),
+        };
+        let basic = |pair: &str| format!("Basic {}", STANDARD.encode(pair));
+        assert!(webdav_authorized(&request(Some("Bearer s3cret")), &config));
+        assert!(webdav_authorized(
+            &request(Some(&basic("me:s3cret"))),
+            &config
+        ));
+        assert!(!webdav_authorized(&request(Some("Bearer s3cre")), &config));
+        assert!(!webdav_authorized(
+            &request(Some(&basic("s3cret:"))),
+            &config
+        ));
+        assert!(!webdav_authorized(&request(None), &config));
+        assert!(!webdav_authorized(
+            &request(Some("Bearer ")),
+            &ServeConfig::default()
+        ));
+    }
+
+    #[test]
+    fn listening_beyond_loopback_needs_a_token() {
+        let error = run_webdav(
+            &Db::open_in_memory().unwrap(),
+            &Config::default(),
+            "0.0.0.0:0",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("webdav_token"));
+    }
+}
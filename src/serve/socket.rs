@@ -0,0 +1,166 @@
+use anyhow::{Context, Result, bail};
+use serde_json::json;
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+};
+
+use super::{READ_TIMEOUT, serve_until_shutdown};
+use crate::{
+    capture::{self, CaptureRequest},
+    config::Config,
+    db::Db,
+    i18n::t,
+};
+
+/// Takes memos on a unix socket so editor plugins and keybindings skip the
+/// process start and database open of running `cap` each time.
+///
+/// Each line is one memo. A line starting with `{` is a capture request as
+/// `cap capture` reads it, e.g. `{"content": "...", "tags": ["idea"]}`, and is answered
+/// with `{"id": "..."}` or `{"error": "..."}`; any other line is the memo text
+/// itself and is answered with `ok <id>` or `error <message>`. Connections
+/// are served one at a time, so clients should close once they have their reply.
+pub(crate) fn run_socket(db: &Db, config: &Config, path: &Path) -> Result<()> {
+    remove_stale_socket(path)?;
+    let listener =
+        bind_private(path).with_context(|| format!("couldn't listen on {}", path.display()))?;
+    println!("{}", t!("serve-listen", path = path.display().to_string()));
+    listener.set_nonblocking(true)?;
+    let result = serve_until_shutdown(
+        || {
+            let (stream, _) = listener.accept()?;
+            stream.set_nonblocking(false)?;
+            Ok(stream)
+        },
+        |stream| handle_connection(db, config, stream),
+    );
+    // Leave nothing behind for the next server's stale-socket check.
+    let _ = fs::remove_file(path);
+    result
+}
+
+/// Anyone who can connect can write memos, so the socket is bound in a fresh
+/// folder only this user can enter, narrowed to mode 0600 there, and only then
+/// moved to `path`. Binding at `path` directly would leave it open to others
+/// until the mode is changed.
+fn bind_private(path: &Path) -> Result<UnixListener> {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    // Socket paths are limited to about 100 bytes, so the detour is kept short.
+    let staging = tempfile::Builder::new().prefix(".cap").tempdir_in(parent)?;
+    let staged = staging.path().join("s");
+    let listener = UnixListener::bind(&staged)?;
+    fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+    fs::rename(&staged, path)?;
+    Ok(listener)
+}
+
+/// A socket left behind by a server that didn't shut down cleanly blocks
+/// `bind`; one that still answers belongs to a running server.
+fn remove_stale_socket(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if UnixStream::connect(path).is_ok() {
+        bail!(
+            "another cap server is already listening on {}",
+            path.display()
+        );
+    }
+    fs::remove_file(path).with_context(|| format!("couldn't remove {}", path.display()))
+}
+
+fn handle_connection(db: &Db, config: &Config, stream: UnixStream) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = &stream;
+    for line in BufReader::new(&stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = handle_line(db, config, &line);
+        writeln!(writer, "{}", reply)?;
+    }
+    Ok(())
+}
+
+fn handle_line(db: &Db, config: &Config, line: &str) -> String {
+    if line.trim_start().starts_with('{') {
+        let result = serde_json::from_str::<CaptureRequest>(line)
+            .map_err(anyhow::Error::from)
+            .and_then(|request| capture::capture(db, config, request));
+        return match result {
+            Ok(memo_id) => json!({ "id": memo_id.as_str() }).to_string(),
+            Err(err) => capture::error_json(&err).to_string(),
+        };
+    }
+    match capture::capture(db, config, CaptureRequest::new(line)) {
+        Ok(memo_id) => format!("ok {}", memo_id.as_str()),
+        Err(err) => format!("error {:#}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use std::{env, process};
+
+    #[test]
+    fn lines_and_json_payloads_become_memos() {
+        let db = Db::open_in_memory().unwrap();
+        let config = Config::default();
+        assert!(handle_line(&db, &config, "plain line #quick").starts_with("ok "));
+        let reply: serde_json::Value = serde_json::from_str(&handle_line(
+            &db,
+            &config,
+            r#"{"content": "from vim", "tags": ["Editor"], "notebook": "work"}"#,
+        ))
+        .unwrap();
+        assert!(reply["id"].is_string(), "{}", reply);
+        let reply = handle_line(&db, &config, r#"{"tags": []}"#);
+        assert!(reply.contains("missing field `content`"), "{}", reply);
+        assert_eq!(
+            handle_line(&db, &config, "   \t"),
+            "error memo content is empty"
+        );
+
+        let memos = db::fetch_memos(&db, None).unwrap();
+        assert_eq!(memos.len(), 2);
+        assert_eq!(memos[0].content, "from vim");
+        assert_eq!(memos[0].tags, ["editor"]);
+        assert_eq!(memos[0].notebook.as_deref(), Some("work"));
+        assert_eq!(memos[1].tags, ["quick"]);
+    }
+
+    #[test]
+    fn sockets_are_private_from_the_start() {
+        let path = env::temp_dir().join(format!("cap-socket-mode-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let _listener = bind_private(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mut client = UnixStream::connect(&path).unwrap();
+        writeln!(client, "ping").unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_live_socket_is_not_taken_over() {
+        let path = env::temp_dir().join(format!("cap-socket-test-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        assert!(remove_stale_socket(&path).is_err());
+        drop(listener);
+        remove_stale_socket(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+use super::{Request, Response};
+use crate::{
+    db::{self, Db},
+    domain::memo::Memo,
+};
+
+const ALLOWED_METHODS: &str = "OPTIONS, GET, HEAD, PROPFIND";
+const SHORT_ID_LEN: usize = 8;
+const MARKDOWN: &str = "text/markdown; charset=utf-8";
+
+/// What a request path points at: the root, a month folder, or one memo file.
+enum Resource<'a> {
+    Root,
+    Month(&'a str, &'a [MemoFile]),
+    File(&'a MemoFile),
+}
+
+struct MemoFile {
+    name: String,
+    modified: Option<DateTime<Utc>>,
+    body: String,
+}
+
+/// Public memos laid out as `/YYYY-MM/YYYY-MM-DD-<id>.md`, newest month last.
+struct Tree {
+    months: BTreeMap<String, Vec<MemoFile>>,
+}
+
+impl Tree {
+    fn load(db: &Db) -> Result<Self> {
+        let mut months: BTreeMap<String, Vec<MemoFile>> = BTreeMap::new();
+        for memo in db::fetch_memos(db, None)? {
+            if memo.private {
+                continue;
+            }
+            let (Some(month), Some(day)) = (memo.created_at.get(..7), memo.created_at.get(..10))
+            else {
+                continue;
+            };
+            let id = memo.memo_id.as_str();
+            let name = format!("{}-{}.md", day, id.get(..SHORT_ID_LEN).unwrap_or(id));
+            months.entry(month.to_string()).or_default().push(MemoFile {
+                name,
+                modified: DateTime::parse_from_rfc3339(&memo.updated_at)
+                    .ok()
+                    .map(|value| value.with_timezone(&Utc)),
+                body: render_memo(&memo),
+            });
+        }
+        for files in months.values_mut() {
+            files.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        Ok(Self { months })
+    }
+
+    fn resolve(&self, path: &str) -> Option<Resource<'_>> {
+        let segments: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        match segments.as_slice() {
+            [] => Some(Resource::Root),
+            [month] => self
+                .months
+                .get_key_value(*month)
+                .map(|(month, files)| Resource::Month(month, files)),
+            [month, name] => self
+                .months
+                .get(*month)?
+                .iter()
+                .find(|file| file.name == *name)
+                .map(Resource::File),
+            _ => None,
+        }
+    }
+}
+
+fn render_memo(memo: &Memo) -> String {
+    let mut front_matter = format!(
+        "---\nid: {}\ncreated: {}\nupdated: {}\n",
+        memo.memo_id.as_str(),
+        memo.created_at,
+        memo.updated_at
+    );
+    if !memo.tags.is_empty() {
+        let tags: Vec<String> = memo.tags.iter().map(|tag| yaml_string(tag)).collect();
+        front_matter.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    }
+    if let Some(notebook) = &memo.notebook {
+        front_matter.push_str(&format!("notebook: {}\n", yaml_string(notebook)));
+    }
+    format!("{}---\n\n{}\n", front_matter, memo.content)
+}
+
+pub(super) fn handle(db: &Db, request: &Request) -> Result<Response> {
+    let response = match request.method.as_str() {
+        "OPTIONS" => Response::new("200 OK")
+            .header("DAV", "1")
+            .header("Allow", ALLOWED_METHODS),
+        "GET" | "HEAD" | "PROPFIND" => {
+            let tree = Tree::load(db)?;
+            match tree.resolve(&request.path) {
+                None => Response::new("404 Not Found"),
+                Some(resource) if request.method == "PROPFIND" => {
+                    // The PROPFIND body only asks for properties we send anyway.
+                    let children = request.header("Depth") != Some("0");
+                    Response::new("207 Multi-Status").body(
+                        "application/xml; charset=utf-8",
+                        propfind(&tree, &resource, children),
+                    )
+                }
+                Some(Resource::File(file)) => {
+                    let response = Response::new("200 OK").body(MARKDOWN, file.body.as_str());
+                    match file.modified {
+                        Some(modified) => response.header("Last-Modified", http_date(modified)),
+                        None => response,
+                    }
+                }
+                Some(resource) => Response::new("200 OK").body(MARKDOWN, index(&tree, &resource)),
+            }
+        }
+        // Read-only: PUT, DELETE, MKCOL, LOCK and friends are all refused.
+        _ => Response::new("405 Method Not Allowed").header("Allow", ALLOWED_METHODS),
+    };
+    Ok(response)
+}
+
+/// A Markdown listing for browsers and clients that GET a folder.
+fn index(tree: &Tree, resource: &Resource) -> String {
+    match resource {
+        Resource::Month(month, files) => {
+            let mut out = format!("# {}\n\n", month);
+            for file in *files {
+                out.push_str(&format!("- [{0}](/{1}/{0})\n", file.name, month));
+            }
+            out
+        }
+        _ => {
+            let mut out = String::from("# cap\n\n");
+            for (month, files) in &tree.months {
+                out.push_str(&format!("- [{0}/](/{0}/) ({1})\n", month, files.len()));
+            }
+            out
+        }
+    }
+}
+
+fn propfind(tree: &Tree, resource: &Resource, children: bool) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n",
+    );
+    match resource {
+        Resource::Root => {
+            push_folder(&mut out, "/", "cap", None);
+            if children {
+                for (month, files) in &tree.months {
+                    push_folder(&mut out, &format!("/{}/", month), month, latest(files));
+                }
+            }
+        }
+        Resource::Month(month, files) => {
+            push_folder(&mut out, &format!("/{}/", month), month, latest(files));
+            if children {
+                for file in *files {
+                    push_file(&mut out, month, file);
+                }
+            }
+        }
+        Resource::File(file) => {
+            let month = file.name.get(..7).unwrap_or_default();
+            push_file(&mut out, month, file);
+        }
+    }
+    out.push_str("</D:multistatus>\n");
+    out
+}
+
+fn latest(files: &[MemoFile]) -> Option<DateTime<Utc>> {
+    files.iter().filter_map(|file| file.modified).max()
+}
+
+fn push_folder(out: &mut String, href: &str, name: &str, modified: Option<DateTime<Utc>>) {
+    push_entry(
+        out,
+        href,
+        name,
+        "<D:resourcetype><D:collection/></D:resourcetype>",
+        modified,
+    );
+}
+
+fn push_file(out: &mut String, month: &str, file: &MemoFile) {
+    let props = format!(
+        "<D:resourcetype/><D:getcontenttype>{}</D:getcontenttype>\
+         <D:getcontentlength>{}</D:getcontentlength>",
+        MARKDOWN,
+        file.body.len()
+    );
+    push_entry(
+        out,
+        &format!("/{}/{}", month, file.name),
+        &file.name,
+        &props,
+        file.modified,
+    );
+}
+
+fn push_entry(
+    out: &mut String,
+    href: &str,
+    name: &str,
+    props: &str,
+    modified: Option<DateTime<Utc>>,
+) {
+    let modified = modified
+        .map(|modified| {
+            format!(
+                "<D:getlastmodified>{}</D:getlastmodified>",
+                http_date(modified)
+            )
+        })
+        .unwrap_or_default();
+    out.push_str(&format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>\
+         <D:displayname>{}</D:displayname>{}{}</D:prop>\
+         <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n",
+        escape_xml(href),
+        escape_xml(name),
+        props,
+        modified
+    ));
+}
+
+/// `value` as a double-quoted YAML scalar, so names with `:`, `#` or `]` can't
+/// break the front matter. JSON strings are valid YAML ones.
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+fn http_date(value: DateTime<Utc>) -> String {
+    value.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+    use chrono::{Local, TimeZone};
+
+    fn request(method: &str, path: &str, depth: Option<&str>) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: depth
+                .map(|depth| vec![("Depth".to_string(), depth.to_string())])
+                .unwrap_or_default(),
+            body: Vec::new(),
+        }
+    }
+
+    fn body(db: &Db, request: &Request) -> (&'static str, String) {
+        let response = handle(db, request).unwrap();
+        (response.status, String::from_utf8(response.body).unwrap())
+    }
+
+    fn db_with_memos() -> Db {
+        let db = Db::open_in_memory().unwrap();
+        let at = Local.with_ymd_and_hms(2024, 3, 5, 9, 30, 0).unwrap();
+        db::add_memo(&db, &NewMemo::new("first #idea").with_created_at(Some(at))).unwrap();
+        db::add_memo(
+            &db,
+            &NewMemo::encrypted("ciphertext").with_created_at(Some(at)),
+        )
+        .unwrap();
+        db
+    }
+
+    #[test]
+    fn propfind_lists_month_folders_and_memo_files() {
+        let db = db_with_memos();
+        let (status, root) = body(&db, &request("PROPFIND", "/", Some("1")));
+        assert_eq!(status, "207 Multi-Status");
+        assert!(root.contains("<D:href>/2024-03/</D:href>"), "{}", root);
+
+        let (_, month) = body(&db, &request("PROPFIND", "/2024-03", Some("1")));
+        assert_eq!(
+            month.matches("<D:response>").count(),
+            2,
+            "private memo hidden"
+        );
+        assert!(month.contains("/2024-03/2024-03-05-"));
+
+        let (_, only_self) = body(&db, &request("PROPFIND", "/2024-03/", Some("0")));
+        assert_eq!(only_self.matches("<D:response>").count(), 1);
+    }
+
+    #[test]
+    fn get_returns_the_memo_as_markdown() {
+        let db = db_with_memos();
+        let memo = db::fetch_memos(&db, None)
+            .unwrap()
+            .into_iter()
+            .find(|memo| !memo.private)
+            .unwrap();
+        let path = format!("/2024-03/2024-03-05-{}.md", &memo.memo_id.as_str()[..8]);
+        let (status, markdown) = body(&db, &request("GET", &path, None));
+        assert_eq!(status, "200 OK");
+        assert!(markdown.starts_with(&format!("---\nid: {}\n", memo.memo_id.as_str())));
+        assert!(markdown.contains("tags: [\"idea\"]\n"));
+        assert!(markdown.ends_with("---\n\nfirst #idea\n"));
+
+        let (status, _) = body(&db, &request("GET", "/2024-03/missing.md", None));
+        assert_eq!(status, "404 Not Found");
+    }
+
+    #[test]
+    fn front_matter_quotes_names() {
+        let memo = Memo {
+            memo_id: "a1b2c3d4".to_string().into(),
+            content: "body".to_string(),
+            created_at: "2024-03-05T09:30:00+00:00".to_string(),
+            updated_at: "2024-03-05T09:30:00+00:00".to_string(),
+            private: false,
+            tags: vec!["c#".to_string(), "a]b".to_string()],
+            timezone: None,
+            notebook: Some("work: \"q1\"".to_string()),
+            location: None,
+            mood: None,
+            dirty: false,
+        };
+        let markdown = render_memo(&memo);
+        assert!(markdown.contains("tags: [\"c#\", \"a]b\"]\n"), "{markdown}");
+        assert!(
+            markdown.contains("notebook: \"work: \\\"q1\\\"\"\n"),
+            "{markdown}"
+        );
+    }
+
+    #[test]
+    fn writes_are_refused() {
+        let db = db_with_memos();
+        for method in ["PUT", "DELETE", "MKCOL", "LOCK"] {
+            let response = handle(&db, &request(method, "/2024-03/new.md", None)).unwrap();
+            assert_eq!(response.status, "405 Method Not Allowed");
+        }
+        assert_eq!(db::fetch_memos(&db, None).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,50 @@
+//! SIGINT, SIGTERM and SIGHUP for the long-running modes (the TUI and `cap
+//! serve`). The handlers only set a flag; the loops check it between units of
+//! work, so a signal never cuts a database write short.
+
+use anyhow::Result;
+
+#[cfg(unix)]
+use std::sync::{
+    Arc, OnceLock,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// The last signal received, or 0; shared with the signal handlers.
+#[cfg(unix)]
+static REQUESTED: OnceLock<Arc<AtomicUsize>> = OnceLock::new();
+
+/// Turns the signals into a shutdown request instead of killing the process.
+/// One-shot commands don't call this, so Ctrl-C still stops them outright.
+#[cfg(unix)]
+pub(crate) fn install() -> Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+    let requested = REQUESTED.get_or_init(|| Arc::new(AtomicUsize::new(0)));
+    for signal in [SIGINT, SIGTERM, SIGHUP] {
+        signal_hook::flag::register_usize(signal, Arc::clone(requested), signal as usize)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn install() -> Result<()> {
+    Ok(())
+}
+
+/// The signal asking the process to stop, once one has arrived.
+#[cfg(unix)]
+pub(crate) fn requested() -> Option<i32> {
+    let signal = REQUESTED.get()?.load(Ordering::SeqCst);
+    (signal != 0).then_some(signal as i32)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn requested() -> Option<i32> {
+    None
+}
+
+/// The shell's convention for a process ended by a signal.
+pub(crate) fn exit_code(signal: i32) -> i32 {
+    128 + signal
+}
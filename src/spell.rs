@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use std::{collections::HashSet, fs, ops::Range, path::Path};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Word lists tried when `[tui] dictionary` isn't set.
+const DEFAULT_DICTIONARIES: [&str; 4] = [
+    "/usr/share/hunspell/en_US.dic",
+    "/usr/share/myspell/en_US.dic",
+    "/usr/share/dict/words",
+    "/usr/share/dict/american-english",
+];
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Known words, read from a plain word list or a hunspell `.dic` file. Affix
+/// rules aren't applied, so a hunspell dictionary only knows its stems.
+pub(crate) struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Loads `path`, or the first system word list found when it's `None`.
+    pub(crate) fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => DEFAULT_DICTIONARIES
+                .iter()
+                .map(Path::new)
+                .find(|path| path.is_file())
+                .context("no spelling dictionary found; set [tui] dictionary in the config")?,
+        };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read dictionary {}", path.display()))?;
+        Ok(Self::from_words(&text))
+    }
+
+    /// One word per line; a hunspell `/FLAGS` suffix and leading count line are skipped.
+    pub(crate) fn from_words(text: &str) -> Self {
+        let words = text
+            .lines()
+            .filter_map(|line| line.split('/').next())
+            .map(str::trim)
+            .filter(|word| !word.is_empty() && !word.chars().all(|ch| ch.is_ascii_digit()))
+            .map(str::to_lowercase)
+            .collect();
+        Self { words }
+    }
+
+    fn knows(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.words.contains(&word)
+            || word
+                .strip_suffix("'s")
+                .is_some_and(|stem| self.words.contains(stem))
+    }
+
+    /// Byte ranges of the words in `line` the dictionary doesn't know. Only
+    /// Latin-letter words are checked; tags, links, and addresses are skipped.
+    pub(crate) fn misspelled(&self, line: &str) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut offset = 0;
+        for token in line.split_inclusive(char::is_whitespace) {
+            let start = offset;
+            offset += token.len();
+            if token.starts_with('#') || token.contains("://") || token.contains('@') {
+                continue;
+            }
+            for (index, word) in token.split_word_bound_indices() {
+                if is_checkable(word) && !self.knows(word) {
+                    ranges.push(start + index..start + index + word.len());
+                }
+            }
+        }
+        ranges
+    }
+
+    /// Known words one edit away from `word`, keeping its capitalization.
+    pub(crate) fn suggestions(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let chars: Vec<char> = lower.chars().collect();
+        let mut candidates = Vec::new();
+        for index in 0..=chars.len() {
+            if index < chars.len() {
+                candidates.push([&chars[..index], &chars[index + 1..]].concat());
+            }
+            if index + 1 < chars.len() {
+                let mut swapped = chars.clone();
+                swapped.swap(index, index + 1);
+                candidates.push(swapped);
+            }
+            for letter in 'a'..='z' {
+                if index < chars.len() {
+                    let mut replaced = chars.clone();
+                    replaced[index] = letter;
+                    candidates.push(replaced);
+                }
+                let mut inserted = chars.clone();
+                inserted.insert(index, letter);
+                candidates.push(inserted);
+            }
+        }
+        let mut found: Vec<String> = candidates
+            .into_iter()
+            .map(|chars| chars.into_iter().collect::<String>())
+            .filter(|candidate| *candidate != lower && self.words.contains(candidate))
+            .collect();
+        found.sort();
+        found.dedup();
+        found.truncate(MAX_SUGGESTIONS);
+        if word.chars().next().is_some_and(char::is_uppercase) {
+            found = found
+                .iter()
+                .map(|candidate| capitalize(candidate))
+                .collect();
+        }
+        found
+    }
+}
+
+fn is_checkable(word: &str) -> bool {
+    word.chars().count() > 1
+        && word
+            .chars()
+            .all(|ch| ch.is_ascii_alphabetic() || ch == '\'')
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        Dictionary::from_words("4\nthe/S\nquick\nbrown\nfox\nform\nfrom\n")
+    }
+
+    #[test]
+    fn flags_unknown_words_only() {
+        let dictionary = dictionary();
+        let line = "The quikc brown fox's #tagz https://exmaple.com 工作 x1";
+        let flagged: Vec<&str> = dictionary
+            .misspelled(line)
+            .into_iter()
+            .map(|range| &line[range])
+            .collect();
+        assert_eq!(flagged, ["quikc"]);
+    }
+
+    #[test]
+    fn suggests_words_one_edit_away() {
+        let dictionary = dictionary();
+        assert_eq!(dictionary.suggestions("quikc"), ["quick"]);
+        assert_eq!(dictionary.suggestions("Frm"), ["Form", "From"]);
+        assert!(dictionary.suggestions("zzzz").is_empty());
+    }
+}
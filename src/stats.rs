@@ -0,0 +1,268 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    db::{self, Db},
+    domain::mood::{MAX_MOOD, MIN_MOOD},
+    i18n::t,
+};
+
+const WEEKS_SHOWN: usize = 12;
+const TOP_TAGS_SHOWN: usize = 5;
+
+pub(crate) struct Stats {
+    pub(crate) total_memos: usize,
+    /// Oldest first; labelled by the Monday that starts each week.
+    pub(crate) weekly_counts: Vec<(String, u64)>,
+    pub(crate) current_streak: usize,
+    pub(crate) longest_streak: usize,
+    pub(crate) top_tags: Vec<(String, usize)>,
+    pub(crate) db_size_bytes: u64,
+}
+
+pub(crate) struct Streak {
+    pub(crate) current: usize,
+    pub(crate) longest: usize,
+    pub(crate) today_count: usize,
+    pub(crate) daily_goal: usize,
+}
+
+impl Streak {
+    pub(crate) fn goal_met(&self) -> bool {
+        self.today_count >= self.daily_goal
+    }
+
+    /// Short form for the TUI status bar, e.g. `streak 4d · today 1/3`.
+    pub(crate) fn summary(&self) -> String {
+        t!(
+            "streak-summary",
+            days = self.current,
+            today = self.today_count,
+            goal = self.daily_goal
+        )
+    }
+}
+
+/// Streaks count days on which at least `daily_goal` memos were captured.
+pub(crate) fn streak(db: &Db, daily_goal: usize) -> Result<Streak> {
+    let days = capture_days(db)?;
+    let today = Local::now().date_naive();
+    let (current, longest) = streaks(&days, today, daily_goal);
+    Ok(Streak {
+        current,
+        longest,
+        today_count: days.iter().filter(|day| **day == today).count(),
+        daily_goal: daily_goal.max(1),
+    })
+}
+
+fn capture_days(db: &Db) -> Result<Vec<NaiveDate>> {
+    Ok(db::fetch_created_at(db)?
+        .iter()
+        .filter_map(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|timestamp| timestamp.with_timezone(&Local).date_naive())
+        .collect())
+}
+
+pub(crate) fn collect(db: &Db, daily_goal: usize) -> Result<Stats> {
+    let days = capture_days(db)?;
+    let today = Local::now().date_naive();
+    let (current_streak, longest_streak) = streaks(&days, today, daily_goal);
+    let mut top_tags = db::fetch_tag_counts(db)?;
+    top_tags.truncate(TOP_TAGS_SHOWN);
+
+    Ok(Stats {
+        total_memos: days.len(),
+        weekly_counts: weekly_counts(&days, today),
+        current_streak,
+        longest_streak,
+        top_tags,
+        db_size_bytes: db::database_size(db)?,
+    })
+}
+
+pub(crate) struct MoodTrend {
+    pub(crate) rated_memos: usize,
+    pub(crate) average: Option<f64>,
+    /// Oldest first: week label, average mood, and number of rated memos.
+    pub(crate) weekly: Vec<(String, Option<f64>, usize)>,
+    /// Count of memos per rating, index 0 holding mood 1.
+    pub(crate) distribution: [usize; MAX_MOOD as usize],
+}
+
+pub(crate) fn mood_trend(db: &Db) -> Result<MoodTrend> {
+    let moods: Vec<(NaiveDate, u8)> = db::fetch_moods(db)?
+        .into_iter()
+        .filter(|(_, mood)| (MIN_MOOD..=MAX_MOOD).contains(mood))
+        .filter_map(|(created_at, mood)| {
+            let timestamp = DateTime::parse_from_rfc3339(&created_at).ok()?;
+            Some((timestamp.with_timezone(&Local).date_naive(), mood))
+        })
+        .collect();
+    let this_week = week_start(Local::now().date_naive());
+    let mut sums = vec![(0u64, 0usize); WEEKS_SHOWN];
+    let mut distribution = [0usize; MAX_MOOD as usize];
+    for (day, mood) in &moods {
+        distribution[usize::from(mood - MIN_MOOD)] += 1;
+        let weeks_ago = (this_week - week_start(*day)).num_weeks();
+        if (0..WEEKS_SHOWN as i64).contains(&weeks_ago) {
+            let slot = &mut sums[WEEKS_SHOWN - 1 - weeks_ago as usize];
+            slot.0 += u64::from(*mood);
+            slot.1 += 1;
+        }
+    }
+    let weekly = sums
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (sum, count))| {
+            let start = this_week - Duration::weeks((WEEKS_SHOWN - 1 - idx) as i64);
+            let average = (count > 0).then(|| sum as f64 / count as f64);
+            (start.format("%m-%d").to_string(), average, count)
+        })
+        .collect();
+    let total: u64 = moods.iter().map(|(_, mood)| u64::from(*mood)).sum();
+    Ok(MoodTrend {
+        rated_memos: moods.len(),
+        average: (!moods.is_empty()).then(|| total as f64 / moods.len() as f64),
+        weekly,
+        distribution,
+    })
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(i64::from(day.weekday().num_days_from_monday()))
+}
+
+fn weekly_counts(days: &[NaiveDate], today: NaiveDate) -> Vec<(String, u64)> {
+    let this_week = week_start(today);
+    let mut counts = vec![0u64; WEEKS_SHOWN];
+    for day in days {
+        let weeks_ago = (this_week - week_start(*day)).num_weeks();
+        if (0..WEEKS_SHOWN as i64).contains(&weeks_ago) {
+            counts[WEEKS_SHOWN - 1 - weeks_ago as usize] += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(idx, count)| {
+            let start = this_week - Duration::weeks((WEEKS_SHOWN - 1 - idx) as i64);
+            (start.format("%m-%d").to_string(), count)
+        })
+        .collect()
+}
+
+/// Returns `(current, longest)` runs of consecutive days with at least
+/// `daily_goal` memos. The current streak stays alive until the end of today.
+fn streaks(days: &[NaiveDate], today: NaiveDate, daily_goal: usize) -> (usize, usize) {
+    let mut per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for day in days {
+        *per_day.entry(*day).or_default() += 1;
+    }
+    let unique: BTreeSet<NaiveDate> = per_day
+        .into_iter()
+        .filter(|(_, count)| *count >= daily_goal.max(1))
+        .map(|(day, _)| day)
+        .collect();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in &unique {
+        run = match previous {
+            Some(prev) if *day - prev == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let mut cursor = if unique.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    let mut current = 0;
+    while unique.contains(&cursor) {
+        current += 1;
+        cursor -= Duration::days(1);
+    }
+    (current, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::NewMemo;
+
+    fn day(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn weeks_are_counted_oldest_first_from_their_monday() {
+        // 2024-05-15 is a Wednesday; its week starts on Monday the 13th.
+        let today = day("2024-05-15");
+        let days = [
+            day("2024-05-13"),
+            day("2024-05-15"),
+            day("2024-05-12"),
+            day("2023-01-01"),
+        ];
+        let weeks = weekly_counts(&days, today);
+        assert_eq!(weeks.len(), WEEKS_SHOWN);
+        assert_eq!(weeks[WEEKS_SHOWN - 1], ("05-13".to_string(), 2));
+        assert_eq!(weeks[WEEKS_SHOWN - 2], ("05-06".to_string(), 1));
+        assert_eq!(weeks.iter().map(|(_, count)| count).sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn streaks_need_the_daily_goal_on_consecutive_days() {
+        let today = day("2024-05-15");
+        let days = [
+            day("2024-05-08"),
+            day("2024-05-09"),
+            day("2024-05-10"),
+            day("2024-05-13"),
+            day("2024-05-14"),
+            day("2024-05-14"),
+        ];
+        assert_eq!(streaks(&days, today, 1), (2, 3));
+        // A goal of two only counts the 14th; today is not over yet.
+        assert_eq!(streaks(&days, today, 2), (1, 1));
+        assert_eq!(streaks(&days, day("2024-05-16"), 1), (0, 3));
+        assert_eq!(streaks(&[], today, 0), (0, 0));
+    }
+
+    #[test]
+    fn the_goal_is_met_once_today_reaches_it() {
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("one")).unwrap();
+        let progress = streak(&db, 2).unwrap();
+        assert_eq!((progress.current, progress.today_count), (0, 1));
+        assert!(!progress.goal_met());
+
+        db::add_memo(&db, &NewMemo::new("two")).unwrap();
+        let progress = streak(&db, 2).unwrap();
+        assert_eq!((progress.current, progress.longest), (1, 1));
+        assert!(progress.goal_met());
+        assert!(streak(&db, 0).unwrap().goal_met());
+    }
+
+    #[test]
+    fn collect_summarizes_live_memos_and_tags() {
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("one #work")).unwrap();
+        db::add_memo(&db, &NewMemo::new("two #work #home")).unwrap();
+        let gone = db::add_memo(&db, &NewMemo::new("three #home")).unwrap();
+        db::delete_memo(&db, &gone).unwrap();
+
+        let stats = collect(&db, 1).unwrap();
+        assert_eq!(stats.total_memos, 2);
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.weekly_counts.last().unwrap().1, 2);
+        assert_eq!(stats.top_tags[0], ("work".to_string(), 2));
+        assert!(stats.db_size_bytes > 0);
+    }
+}
@@ -0,0 +1,475 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth,
+    db::{self, Db, PendingMemo, ServerMemo},
+    domain::memo::NewMemo,
+    format,
+    http::{self, HttpClient},
+    i18n::t,
+};
+
+mod share;
+mod text_merge;
+
+pub(crate) use share::{accept_inbox, print_inbox, share};
+
+const LAST_SYNC_KEY: &str = "sync_last_success_at";
+/// Table the sync backend stores memos in, keyed by `memo_id`.
+const REMOTE_TABLE: &str = "memos";
+/// `updated_at` of the newest change `cap sync pull` has seen.
+const PULL_CURSOR_KEY: &str = "sync_pull_cursor";
+/// Field on a conflicted copy naming the memo it was split from.
+const CONFLICT_OF_META: &str = "conflict_of";
+
+pub(crate) struct SyncStatus {
+    pub(crate) last_sync_at: Option<String>,
+    pub(crate) pending_changes: usize,
+    pub(crate) pending_deletions: usize,
+    pub(crate) backend: String,
+    pub(crate) account: Option<String>,
+}
+
+impl SyncStatus {
+    pub(crate) fn last_sync_display(&self) -> String {
+        self.last_sync_at
+            .as_deref()
+            .map(format::format_display_time)
+            .unwrap_or_else(|| t!("sync-never"))
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        t!(
+            "sync-summary",
+            last = self.last_sync_display(),
+            pending = self.pending_changes,
+            deletions = self.pending_deletions
+        )
+    }
+}
+
+pub(crate) fn load_status(db: &Db) -> Result<SyncStatus> {
+    let pending = db::count_pending_changes(db)?;
+    Ok(SyncStatus {
+        last_sync_at: db::get_kv(db, LAST_SYNC_KEY)?,
+        pending_changes: pending.changes,
+        pending_deletions: pending.deletions,
+        backend: auth::supabase_url(),
+        account: auth::current_account(db)?,
+    })
+}
+
+pub(crate) fn print_status(db: &Db) -> Result<()> {
+    let status = load_status(db)?;
+    println!("{}", t!("sync-last", value = status.last_sync_display()));
+    println!(
+        "{}",
+        t!("sync-pending-changes", count = status.pending_changes)
+    );
+    println!(
+        "{}",
+        t!("sync-pending-deletions", count = status.pending_deletions)
+    );
+    println!("{}", t!("sync-backend", value = status.backend.as_str()));
+    println!(
+        "{}",
+        t!(
+            "sync-account",
+            value = status.account.unwrap_or_else(|| t!("sync-not-logged-in"))
+        )
+    );
+    Ok(())
+}
+
+/// Where pending changes are uploaded to, and as whom.
+struct Remote {
+    url: String,
+    anon_key: String,
+    access_token: String,
+    user_id: String,
+}
+
+impl Remote {
+    /// The configured backend, signed in as the stored account.
+    fn connect(db: &Db) -> Result<Self> {
+        let access_token = auth::access_token(db, false)?;
+        let user_id =
+            db::get_kv(db, "auth_user_id")?.context("not logged in; run cap login first")?;
+        Ok(Self {
+            url: auth::supabase_url(),
+            anon_key: auth::supabase_anon_key(),
+            access_token,
+            user_id,
+        })
+    }
+
+    /// PostgREST address of `table`, followed by `query`.
+    fn table(&self, table: &str, query: &str) -> String {
+        format!(
+            "{}/rest/v1/{}{}",
+            self.url.trim_end_matches('/'),
+            table,
+            query
+        )
+    }
+
+    /// `request` with the headers every call to the backend carries.
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header("apikey", &self.anon_key)
+            .bearer_auth(&self.access_token)
+    }
+}
+
+/// One row of the remote table.
+#[derive(Serialize)]
+struct RemoteMemo<'a> {
+    memo_id: &'a str,
+    user_id: &'a str,
+    content: &'a str,
+    created_at: &'a str,
+    updated_at: &'a str,
+    deleted: bool,
+    private: bool,
+    notebook: Option<&'a str>,
+}
+
+/// Uploads every pending change, deletions included, then records the sync
+/// time `cap sync status` shows.
+pub(crate) fn push(db: &Db) -> Result<()> {
+    let remote = Remote::connect(db)?;
+    let pushed = http::block_on(push_pending(&HttpClient::new()?, &remote, db))?;
+    println!("{}", t!("sync-pushed", count = pushed));
+    Ok(())
+}
+
+async fn push_pending(http: &HttpClient, remote: &Remote, db: &Db) -> Result<usize> {
+    let pending = db::fetch_pending_memos(db)?;
+    if !pending.is_empty() {
+        let rows: Vec<_> = pending
+            .iter()
+            .map(|memo| remote_memo(memo, &remote.user_id))
+            .collect();
+        let request = remote
+            .authorize(http.post(remote.table(REMOTE_TABLE, "?on_conflict=memo_id")))
+            .header("Prefer", "resolution=merge-duplicates,return=minimal")
+            .json(&rows);
+        http.send(request)
+            .await?
+            .error_for_status()
+            .context("the backend refused the changes; cap auth check shows why")?;
+        db::mark_synced(db, &pending)?;
+    }
+    db::set_kv(db, LAST_SYNC_KEY, &Local::now().to_rfc3339())?;
+    Ok(pending.len())
+}
+
+fn remote_memo<'a>(memo: &'a PendingMemo, user_id: &'a str) -> RemoteMemo<'a> {
+    RemoteMemo {
+        memo_id: &memo.memo_id,
+        user_id,
+        content: &memo.content,
+        created_at: &memo.created_at,
+        updated_at: &memo.updated_at,
+        deleted: memo.deleted,
+        private: memo.private,
+        notebook: memo.notebook.as_deref(),
+    }
+}
+
+/// A row of the remote table as `cap sync pull` reads it.
+#[derive(Deserialize)]
+struct PulledMemo {
+    memo_id: String,
+    content: String,
+    created_at: String,
+    updated_at: String,
+    deleted: bool,
+    private: bool,
+    notebook: Option<String>,
+}
+
+impl From<PulledMemo> for ServerMemo {
+    fn from(memo: PulledMemo) -> Self {
+        Self {
+            memo_id: memo.memo_id,
+            content: memo.content,
+            created_at: memo.created_at,
+            updated_at: memo.updated_at,
+            deleted: memo.deleted,
+            private: memo.private,
+            notebook: memo.notebook,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct PullSummary {
+    received: usize,
+    merged: usize,
+    conflicts: usize,
+}
+
+/// Downloads what changed on the server since the last pull and folds it
+/// into the local memos.
+pub(crate) fn pull(db: &Db) -> Result<()> {
+    let remote = Remote::connect(db)?;
+    let summary = http::block_on(pull_changes(&HttpClient::new()?, &remote, db))?;
+    println!(
+        "{}",
+        t!(
+            "sync-pulled",
+            count = summary.received,
+            merged = summary.merged,
+            conflicts = summary.conflicts
+        )
+    );
+    Ok(())
+}
+
+async fn pull_changes(http: &HttpClient, remote: &Remote, db: &Db) -> Result<PullSummary> {
+    let mut query = vec![
+        (
+            "select",
+            "memo_id,content,created_at,updated_at,deleted,private,notebook".to_string(),
+        ),
+        ("user_id", format!("eq.{}", remote.user_id)),
+        ("order", "updated_at".to_string()),
+    ];
+    if let Some(cursor) = db::get_kv(db, PULL_CURSOR_KEY)? {
+        query.push(("updated_at", format!("gt.{}", cursor)));
+    }
+    let request = remote
+        .authorize(http.get(remote.table(REMOTE_TABLE, "")))
+        .query(&query);
+    let pulled: Vec<PulledMemo> = http
+        .send(request)
+        .await?
+        .error_for_status()
+        .context("the backend refused the download; cap auth check shows why")?
+        .json()
+        .await?;
+    let memos: Vec<ServerMemo> = pulled.into_iter().map(ServerMemo::from).collect();
+    let summary = apply_remote_changes(db, &memos)?;
+    db::set_kv(db, LAST_SYNC_KEY, &Local::now().to_rfc3339())?;
+    Ok(summary)
+}
+
+/// Folds the server's changes into the local memos. A memo changed on both
+/// sides is merged line by line from the text both last agreed on. When the
+/// edits collide the local version stays, and the server's becomes a
+/// conflicted copy: a new memo whose `conflict_of` field names the original.
+fn apply_remote_changes(db: &Db, memos: &[ServerMemo]) -> Result<PullSummary> {
+    let tx = db.conn().unchecked_transaction()?;
+    let mut summary = PullSummary {
+        received: memos.len(),
+        ..PullSummary::default()
+    };
+    for memo in memos {
+        let local = match db::fetch_sync_state(db, &memo.memo_id)? {
+            Some(local)
+                if local.dirty
+                    && (local.content != memo.content || local.deleted != memo.deleted) =>
+            {
+                local
+            }
+            _ => {
+                db::store_remote_memo(db, memo)?;
+                continue;
+            }
+        };
+        // A local edit outlives a deletion on the server; the next push
+        // brings the memo back there.
+        if memo.deleted {
+            continue;
+        }
+        // Deletions and encrypted text have no lines to merge.
+        let merged = if local.deleted || local.private || memo.private {
+            None
+        } else {
+            let base = local.synced_content.as_deref().unwrap_or_default();
+            text_merge::merge(base, &local.content, &memo.content)
+        };
+        match merged {
+            Some(merged) => {
+                db::store_merged_content(db, &memo.memo_id, &merged, &memo.content)?;
+                summary.merged += 1;
+            }
+            None => {
+                add_conflicted_copy(db, memo)?;
+                summary.conflicts += 1;
+            }
+        }
+    }
+    if let Some(newest) = memos.last() {
+        db::set_kv(db, PULL_CURSOR_KEY, &newest.updated_at)?;
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+fn add_conflicted_copy(db: &Db, memo: &ServerMemo) -> Result<()> {
+    let copy = if memo.private {
+        NewMemo::encrypted(memo.content.clone())
+    } else {
+        NewMemo::new(memo.content.clone())
+    };
+    let copy = copy
+        .with_notebook(memo.notebook.clone())
+        .with_meta(&[(CONFLICT_OF_META.to_string(), memo.memo_id.clone())]);
+    db::add_memo(db, &copy)?;
+    Ok(())
+}
+
+/// Tombstones deleted before this are past the `retention_days` window and may
+/// be hard-deleted once they have reached the server; unsynced deletions are
+/// always kept.
+pub(crate) fn tombstone_cutoff(retention_days: u32) -> DateTime<Local> {
+    Local::now() - Duration::days(i64::from(retention_days))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_partial_json, header, method, path},
+    };
+
+    use super::*;
+    use crate::domain::memo::{MemoId, NewMemo};
+
+    fn remote(server: &MockServer) -> Remote {
+        Remote {
+            url: server.uri(),
+            anon_key: "anon".to_string(),
+            access_token: "token".to_string(),
+            user_id: "user-1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_successful_push_clears_pending_changes_and_records_the_time() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/rest/v1/memos"))
+            .and(header("authorization", "Bearer token"))
+            .and(body_partial_json(json!([
+                { "user_id": "user-1", "content": "hello", "deleted": false }
+            ])))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("hello")).unwrap();
+
+        let http = HttpClient::new().unwrap();
+        assert_eq!(push_pending(&http, &remote(&server), &db).await.unwrap(), 1);
+        let status = load_status(&db).unwrap();
+        assert_eq!(status.pending_changes, 0);
+        assert!(status.last_sync_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn a_refused_push_keeps_changes_pending() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        let db = Db::open_in_memory().unwrap();
+        db::add_memo(&db, &NewMemo::new("hello")).unwrap();
+
+        let http = HttpClient::new().unwrap();
+        assert!(push_pending(&http, &remote(&server), &db).await.is_err());
+        let status = load_status(&db).unwrap();
+        assert_eq!(status.pending_changes, 1);
+        assert!(status.last_sync_at.is_none());
+    }
+
+    const BASE: &str = "one\ntwo\nthree\nfour\nfive";
+
+    /// A memo whose `BASE` text has reached the server.
+    fn synced_memo(db: &Db) -> MemoId {
+        let memo_id = db::add_memo(db, &NewMemo::new(BASE)).unwrap();
+        db::mark_synced(db, &db::fetch_pending_memos(db).unwrap()).unwrap();
+        memo_id
+    }
+
+    fn from_server(memo_id: &MemoId, content: &str) -> ServerMemo {
+        ServerMemo {
+            memo_id: memo_id.as_str().to_string(),
+            content: content.to_string(),
+            created_at: Local::now().to_rfc3339(),
+            updated_at: Local::now().to_rfc3339(),
+            deleted: false,
+            private: false,
+            notebook: None,
+        }
+    }
+
+    fn content(db: &Db, memo_id: &MemoId) -> String {
+        db::fetch_memo_by_id_prefix(db, memo_id.as_str())
+            .unwrap()
+            .content
+    }
+
+    #[test]
+    fn edits_on_both_sides_merge_from_the_synced_text() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = synced_memo(&db);
+        db::update_memo_content(&db, &memo_id, "ONE\ntwo\nthree\nfour\nfive").unwrap();
+
+        let server = from_server(&memo_id, "one\ntwo\nthree\nfour\nFIVE");
+        let summary = apply_remote_changes(&db, &[server]).unwrap();
+        assert_eq!(summary.merged, 1);
+        assert_eq!(content(&db, &memo_id), "ONE\ntwo\nthree\nfour\nFIVE");
+        // The merge still has to reach the server.
+        assert_eq!(db::count_pending_changes(&db).unwrap().changes, 1);
+    }
+
+    #[test]
+    fn colliding_edits_keep_a_conflicted_copy() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = synced_memo(&db);
+        db::update_memo_content(&db, &memo_id, "one\n2\nthree\nfour\nfive").unwrap();
+
+        let server = from_server(&memo_id, "one\nTWO\nthree\nfour\nfive");
+        let summary = apply_remote_changes(&db, &[server]).unwrap();
+        assert_eq!(summary.conflicts, 1);
+        assert_eq!(content(&db, &memo_id), "one\n2\nthree\nfour\nfive");
+        let filter = db::MemoFilter {
+            meta: vec![(CONFLICT_OF_META.to_string(), memo_id.as_str().to_string())],
+            ..db::MemoFilter::default()
+        };
+        let copies = db::fetch_filtered_memos(&db, &filter).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].content, "one\nTWO\nthree\nfour\nfive");
+    }
+
+    #[test]
+    fn memos_without_local_changes_take_the_servers_version() {
+        let db = Db::open_in_memory().unwrap();
+        let memo_id = synced_memo(&db);
+        let new_id = MemoId::new();
+
+        let summary = apply_remote_changes(
+            &db,
+            &[
+                from_server(&memo_id, "rewritten #elsewhere"),
+                from_server(&new_id, "written on the phone"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(summary.received, 2);
+        assert_eq!(content(&db, &memo_id), "rewritten #elsewhere");
+        assert_eq!(content(&db, &new_id), "written on the phone");
+        let memo = db::fetch_memo_by_id_prefix(&db, memo_id.as_str()).unwrap();
+        assert_eq!(memo.tags, ["elsewhere"]);
+        assert_eq!(db::count_pending_changes(&db).unwrap().changes, 0);
+    }
+}
@@ -0,0 +1,484 @@
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use crypto_box::{
+    PublicKey, SalsaBox, SecretKey,
+    aead::{Aead, AeadCore, OsRng},
+};
+use serde::{Deserialize, Serialize};
+
+use super::Remote;
+use crate::{
+    crypto::{self, MemoKey},
+    db::{self, Db},
+    domain::memo::{Memo, NewMemo},
+    http::{self, HttpClient},
+    i18n::t,
+};
+
+/// Every user's public key, readable by all signed-in users. The backend fills
+/// `email` from `auth.email()` and its insert and update policies check
+/// `user_id = auth.uid() AND email = auth.email()`, so a key can only be
+/// registered under the address its account signed in with.
+const KEYS_TABLE: &str = "public_keys";
+/// Shared memos waiting for their recipient, who alone may read and delete them.
+/// `sender_email` is filled from `auth.email()` the same way, and inserts must
+/// have `sender_id = auth.uid()`.
+const SHARES_TABLE: &str = "shares";
+/// The secret half of the sharing key pair, encrypted with the memo key.
+const SECRET_KEY_KEY: &str = "share_secret_key";
+/// Field on accepted memos naming the share they came from, so one accepted
+/// again after a failed delete isn't added twice.
+const SHARE_ID_META: &str = "share_id";
+/// Field on accepted memos naming who shared them.
+const SHARED_BY_META: &str = "shared_by";
+const NONCE_LEN: usize = 24;
+
+#[derive(Serialize)]
+struct RemoteKey<'a> {
+    user_id: &'a str,
+    public_key: String,
+}
+
+/// A row of `public_keys`, as another account registered it.
+#[derive(Deserialize)]
+struct RegisteredKey {
+    user_id: String,
+    public_key: String,
+}
+
+#[derive(Serialize)]
+struct NewShare<'a> {
+    sender_id: &'a str,
+    sender_key: String,
+    recipient_id: &'a str,
+    payload: String,
+}
+
+#[derive(Deserialize)]
+struct Share {
+    id: String,
+    sender_id: String,
+    sender_email: String,
+    sender_key: String,
+    payload: String,
+    created_at: String,
+}
+
+/// Encrypts `memo` to the account registered as `email` and leaves it in
+/// their inbox. The recipient must have opened `cap inbox` once, which
+/// registers their public key.
+pub(crate) fn share(db: &Db, memo_key: &MemoKey, memo: &Memo, email: &str) -> Result<()> {
+    let content = if memo.private {
+        crypto::decrypt(memo_key, &memo.content)?
+    } else {
+        memo.content.clone()
+    };
+    let remote = Remote::connect(db)?;
+    let secret = secret_key(db, memo_key)?;
+    http::block_on(async {
+        let http = HttpClient::new()?;
+        register_key(&http, &remote, &secret).await?;
+        send_share(&http, &remote, &secret, email, &content).await
+    })?;
+    println!("{}", t!("share-sent", email = email));
+    Ok(())
+}
+
+/// Lists the memos others have shared with this account.
+pub(crate) fn print_inbox(db: &Db, memo_key: &MemoKey) -> Result<()> {
+    let secret = secret_key(db, memo_key)?;
+    let shares = fetch_inbox(db, &secret)?;
+    if shares.is_empty() {
+        println!("{}", t!("inbox-empty"));
+    }
+    for (share, content) in &shares {
+        let first_line = content.lines().next().unwrap_or_default();
+        println!(
+            "{}",
+            t!(
+                "inbox-entry",
+                from = share.sender_email.as_str(),
+                time = crate::format::format_display_time(&share.created_at),
+                text = first_line
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Adds every pending share as a local memo and removes it from the inbox.
+pub(crate) fn accept_inbox(db: &Db, memo_key: &MemoKey) -> Result<()> {
+    let secret = secret_key(db, memo_key)?;
+    let remote = Remote::connect(db)?;
+    let shares = fetch_inbox(db, &secret)?;
+    let accepted = http::block_on(async {
+        let http = HttpClient::new()?;
+        accept_shares(&http, &remote, db, shares).await
+    })?;
+    println!("{}", t!("inbox-accepted", count = accepted));
+    Ok(())
+}
+
+fn fetch_inbox(db: &Db, secret: &SecretKey) -> Result<Vec<(Share, String)>> {
+    let remote = Remote::connect(db)?;
+    http::block_on(async {
+        let http = HttpClient::new()?;
+        // Opening the inbox is what makes this account reachable for shares.
+        register_key(&http, &remote, secret).await?;
+        let shares = pending_shares(&http, &remote).await?;
+        let keys = sender_keys(&http, &remote, &shares).await?;
+        shares
+            .into_iter()
+            .map(|share| {
+                check_sender(&keys, &share)?;
+                let content = open(secret, &share)?;
+                Ok((share, content))
+            })
+            .collect()
+    })
+}
+
+/// This account's sharing key, made on first use. It is kept encrypted with
+/// the memo key, so shares are as safe on disk as private memos.
+fn secret_key(db: &Db, memo_key: &MemoKey) -> Result<SecretKey> {
+    if let Some(stored) = db::get_kv(db, SECRET_KEY_KEY)? {
+        let bytes = STANDARD.decode(crypto::decrypt(memo_key, &stored)?)?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("the stored sharing key is damaged"))?;
+        return Ok(SecretKey::from(bytes));
+    }
+    let secret = SecretKey::generate(&mut OsRng);
+    let encoded = STANDARD.encode(secret.to_bytes());
+    db::set_kv(db, SECRET_KEY_KEY, &crypto::encrypt(memo_key, &encoded)?)?;
+    Ok(secret)
+}
+
+async fn register_key(http: &HttpClient, remote: &Remote, secret: &SecretKey) -> Result<()> {
+    let row = RemoteKey {
+        user_id: &remote.user_id,
+        public_key: STANDARD.encode(secret.public_key().as_bytes()),
+    };
+    let request = remote
+        .authorize(http.post(remote.table(KEYS_TABLE, "?on_conflict=user_id")))
+        .header("Prefer", "resolution=merge-duplicates,return=minimal")
+        .json(&row);
+    http.send(request)
+        .await?
+        .error_for_status()
+        .context("the backend refused this account's public key")?;
+    Ok(())
+}
+
+async fn send_share(
+    http: &HttpClient,
+    remote: &Remote,
+    secret: &SecretKey,
+    email: &str,
+    content: &str,
+) -> Result<()> {
+    let request = remote
+        .authorize(http.get(remote.table(KEYS_TABLE, "?select=user_id,public_key")))
+        .query(&[("email", format!("eq.{email}"))]);
+    let recipients: Vec<RegisteredKey> =
+        http.send(request).await?.error_for_status()?.json().await?;
+    let Some(recipient) = recipients.into_iter().next() else {
+        bail!(
+            "{} can't receive shares yet; they need to open cap inbox once",
+            email
+        );
+    };
+    let recipient_key = decode_key(&recipient.public_key)?;
+    let row = NewShare {
+        sender_id: &remote.user_id,
+        sender_key: STANDARD.encode(secret.public_key().as_bytes()),
+        recipient_id: &recipient.user_id,
+        payload: seal(secret, &recipient_key, content)?,
+    };
+    let request = remote
+        .authorize(http.post(remote.table(SHARES_TABLE, "")))
+        .header("Prefer", "return=minimal")
+        .json(&row);
+    http.send(request)
+        .await?
+        .error_for_status()
+        .context("the backend refused the share")?;
+    Ok(())
+}
+
+async fn pending_shares(http: &HttpClient, remote: &Remote) -> Result<Vec<Share>> {
+    let query =
+        "?select=id,sender_id,sender_email,sender_key,payload,created_at&order=created_at.asc";
+    let request = remote
+        .authorize(http.get(remote.table(SHARES_TABLE, query)))
+        .query(&[("recipient_id", format!("eq.{}", remote.user_id))]);
+    Ok(http.send(request).await?.error_for_status()?.json().await?)
+}
+
+/// The keys the senders of `shares` registered themselves.
+async fn sender_keys(
+    http: &HttpClient,
+    remote: &Remote,
+    shares: &[Share],
+) -> Result<Vec<RegisteredKey>> {
+    if shares.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut sender_ids: Vec<&str> = shares
+        .iter()
+        .map(|share| share.sender_id.as_str())
+        .collect();
+    sender_ids.sort_unstable();
+    sender_ids.dedup();
+    let request = remote
+        .authorize(http.get(remote.table(KEYS_TABLE, "?select=user_id,public_key")))
+        .query(&[("user_id", format!("in.({})", sender_ids.join(",")))]);
+    Ok(http.send(request).await?.error_for_status()?.json().await?)
+}
+
+/// Whoever stores a share picks its `sender_key`, so it is only trusted when
+/// it is the key `sender_id` registered in `public_keys`.
+fn check_sender(keys: &[RegisteredKey], share: &Share) -> Result<()> {
+    let registered = keys.iter().find(|key| key.user_id == share.sender_id);
+    match registered {
+        Some(key) if key.public_key == share.sender_key => Ok(()),
+        _ => bail!(
+            "share from {} isn't from their registered key",
+            share.sender_email
+        ),
+    }
+}
+
+async fn accept_shares(
+    http: &HttpClient,
+    remote: &Remote,
+    db: &Db,
+    shares: Vec<(Share, String)>,
+) -> Result<usize> {
+    for (share, content) in &shares {
+        let filter = db::MemoFilter {
+            meta: vec![(SHARE_ID_META.to_string(), share.id.clone())],
+            ..Default::default()
+        };
+        if db::fetch_filtered_memos(db, &filter)?.is_empty() {
+            let new_memo = NewMemo::new(content.as_str()).with_meta(&[
+                (SHARED_BY_META.to_string(), share.sender_email.clone()),
+                (SHARE_ID_META.to_string(), share.id.clone()),
+            ]);
+            db::add_memo(db, &new_memo)?;
+        }
+        let request = remote
+            .authorize(http.delete(remote.table(SHARES_TABLE, "")))
+            .query(&[("id", format!("eq.{}", share.id))]);
+        http.send(request).await?.error_for_status()?;
+    }
+    Ok(shares.len())
+}
+
+/// `content` encrypted from `secret`'s owner to `recipient`, as base64 of the
+/// nonce followed by the box.
+fn seal(secret: &SecretKey, recipient: &PublicKey, content: &str) -> Result<String> {
+    let sealer = SalsaBox::new(recipient, secret);
+    let nonce = SalsaBox::generate_nonce(&mut OsRng);
+    let ciphertext = sealer
+        .encrypt(&nonce, content.as_bytes())
+        .map_err(|_| anyhow!("failed to encrypt the memo"))?;
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(payload))
+}
+
+/// Opens a share sent to `secret`'s owner. The box only opens with the
+/// sender's key, so the sender can't be forged by whoever stored the row.
+fn open(secret: &SecretKey, share: &Share) -> Result<String> {
+    let payload = STANDARD.decode(&share.payload)?;
+    if payload.len() < NONCE_LEN {
+        bail!("share from {} is truncated", share.sender_email);
+    }
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let opener = SalsaBox::new(&decode_key(&share.sender_key)?, secret);
+    let plaintext = opener
+        .decrypt(nonce.into(), ciphertext)
+        .map_err(|_| anyhow!("share from {} can't be decrypted", share.sender_email))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+fn decode_key(encoded: &str) -> Result<PublicKey> {
+    let bytes: [u8; 32] = STANDARD
+        .decode(encoded)?
+        .try_into()
+        .map_err(|_| anyhow!("public key has the wrong length"))?;
+    Ok(PublicKey::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_partial_json, method, path, query_param},
+    };
+
+    use super::*;
+
+    fn remote(server: &MockServer) -> Remote {
+        Remote {
+            url: server.uri(),
+            anon_key: "anon".to_string(),
+            access_token: "token".to_string(),
+            user_id: "user-1".to_string(),
+        }
+    }
+
+    fn share_from(sender: &SecretKey, recipient: &SecretKey, content: &str) -> Share {
+        Share {
+            id: "share-1".to_string(),
+            sender_id: "user-1".to_string(),
+            sender_email: "alice@example.com".to_string(),
+            sender_key: STANDARD.encode(sender.public_key().as_bytes()),
+            payload: seal(sender, &recipient.public_key(), content).unwrap(),
+            created_at: "2024-05-01T09:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn only_the_recipient_opens_a_share() {
+        let alice = SecretKey::generate(&mut OsRng);
+        let bob = SecretKey::generate(&mut OsRng);
+        let share = share_from(&alice, &bob, "for bob");
+        assert_eq!(open(&bob, &share).unwrap(), "for bob");
+        assert!(open(&alice, &share).is_err());
+
+        let forged = Share {
+            sender_key: STANDARD.encode(SecretKey::generate(&mut OsRng).public_key().as_bytes()),
+            ..share_from(&alice, &bob, "for bob")
+        };
+        assert!(open(&bob, &forged).is_err());
+    }
+
+    #[tokio::test]
+    async fn shares_are_checked_against_the_senders_registered_key() {
+        let server = MockServer::start().await;
+        let alice = SecretKey::generate(&mut OsRng);
+        let bob = SecretKey::generate(&mut OsRng);
+        Mock::given(method("GET"))
+            .and(path("/rest/v1/public_keys"))
+            .and(query_param("user_id", "in.(user-1)"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "user_id": "user-1",
+                "public_key": STANDARD.encode(alice.public_key().as_bytes()),
+            }])))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new().unwrap();
+        let share = share_from(&alice, &bob, "from alice");
+        let keys = sender_keys(&http, &remote(&server), std::slice::from_ref(&share))
+            .await
+            .unwrap();
+        assert!(check_sender(&keys, &share).is_ok());
+
+        // Someone else signs with their own key but names alice as the sender.
+        let mallory = SecretKey::generate(&mut OsRng);
+        let impostor = share_from(&mallory, &bob, "from alice");
+        assert!(check_sender(&keys, &impostor).is_err());
+        let stranger = Share {
+            sender_id: "user-3".to_string(),
+            ..share_from(&alice, &bob, "from alice")
+        };
+        assert!(check_sender(&keys, &stranger).is_err());
+    }
+
+    #[test]
+    fn email_addresses_are_left_to_the_backend() {
+        let key = serde_json::to_value(RemoteKey {
+            user_id: "user-1",
+            public_key: String::new(),
+        })
+        .unwrap();
+        assert!(key.get("email").is_none());
+        let share = serde_json::to_value(NewShare {
+            sender_id: "user-1",
+            sender_key: String::new(),
+            recipient_id: "user-2",
+            payload: String::new(),
+        })
+        .unwrap();
+        assert!(share.get("sender_email").is_none());
+    }
+
+    #[tokio::test]
+    async fn shares_go_to_the_recipients_key() {
+        let server = MockServer::start().await;
+        let bob = SecretKey::generate(&mut OsRng);
+        Mock::given(method("GET"))
+            .and(path("/rest/v1/public_keys"))
+            .and(query_param("email", "eq.bob@example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "user_id": "user-2",
+                "public_key": STANDARD.encode(bob.public_key().as_bytes()),
+            }])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/rest/v1/public_keys"))
+            .and(query_param("email", "eq.eve@example.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/rest/v1/shares"))
+            .and(body_partial_json(json!({
+                "sender_id": "user-1",
+                "recipient_id": "user-2",
+            })))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new().unwrap();
+        let alice = SecretKey::generate(&mut OsRng);
+        let remote = remote(&server);
+        send_share(&http, &remote, &alice, "bob@example.com", "hi")
+            .await
+            .unwrap();
+        let error = send_share(&http, &remote, &alice, "eve@example.com", "hi")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("cap inbox"));
+    }
+
+    #[tokio::test]
+    async fn accepted_shares_are_added_once_and_cleared() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/rest/v1/shares"))
+            .and(query_param("id", "eq.share-1"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let db = Db::open_in_memory().unwrap();
+        let alice = SecretKey::generate(&mut OsRng);
+        let bob = SecretKey::generate(&mut OsRng);
+
+        let http = HttpClient::new().unwrap();
+        let remote = remote(&server);
+        // A second accept happens when the delete failed after the memo was added.
+        for _ in 0..2 {
+            let share = share_from(&alice, &bob, "from alice");
+            let content = open(&bob, &share).unwrap();
+            accept_shares(&http, &remote, &db, vec![(share, content)])
+                .await
+                .unwrap();
+        }
+        let filter = db::MemoFilter {
+            meta: vec![(SHARED_BY_META.to_string(), "alice@example.com".to_string())],
+            ..Default::default()
+        };
+        let memos = db::fetch_filtered_memos(&db, &filter).unwrap();
+        assert_eq!(memos.len(), 1);
+        assert_eq!(memos[0].content, "from alice");
+    }
+}
@@ -0,0 +1,151 @@
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+
+/// Base lines `start..end` replaced by `lines`.
+struct Change<'a> {
+    start: usize,
+    end: usize,
+    lines: &'a [&'a str],
+    ours: bool,
+}
+
+/// Line-based three-way merge of the edits `ours` and `theirs` each made to
+/// `base`. `None` when the two touched the same or neighbouring lines
+/// differently, which is left to a conflicted copy.
+pub(super) fn merge(base: &str, ours: &str, theirs: &str) -> Option<String> {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let mut changes: Vec<_> = changes(&base, &ours, true)
+        .chain(changes(&base, &theirs, false))
+        .collect();
+    changes.sort_by_key(|change| (change.start, change.end));
+
+    let mut merged: Vec<&str> = Vec::new();
+    let mut next = 0;
+    let mut index = 0;
+    while index < changes.len() {
+        // Changes that overlap or touch form one group; a group from a single
+        // side applies as is, and one from both sides only if they agree.
+        let mut end = changes[index].end;
+        let mut group_end = index + 1;
+        while group_end < changes.len() && changes[group_end].start <= end {
+            end = end.max(changes[group_end].end);
+            group_end += 1;
+        }
+        let group = &changes[index..group_end];
+        let mixed =
+            group.iter().any(|change| change.ours) && group.iter().any(|change| !change.ours);
+        let group = match group {
+            [first, second]
+                if mixed
+                    && (first.start, first.end, first.lines)
+                        == (second.start, second.end, second.lines) =>
+            {
+                &group[..1]
+            }
+            _ if mixed => return None,
+            _ => group,
+        };
+        for change in group {
+            merged.extend(&base[next..change.start]);
+            merged.extend(change.lines);
+            next = change.end;
+        }
+        index = group_end;
+    }
+    merged.extend(&base[next..]);
+    Some(merged.concat())
+}
+
+/// What turned `base` into `new`, as the side `ours` says.
+fn changes<'a>(base: &[&str], new: &'a [&'a str], ours: bool) -> impl Iterator<Item = Change<'a>> {
+    capture_diff_slices(Algorithm::Myers, base, new)
+        .into_iter()
+        .filter_map(move |op| {
+            let (start, end, lines) = match op {
+                DiffOp::Equal { .. } => return None,
+                DiffOp::Delete {
+                    old_index, old_len, ..
+                } => (old_index, old_index + old_len, &new[..0]),
+                DiffOp::Insert {
+                    old_index,
+                    new_index,
+                    new_len,
+                } => (old_index, old_index, &new[new_index..new_index + new_len]),
+                DiffOp::Replace {
+                    old_index,
+                    old_len,
+                    new_index,
+                    new_len,
+                } => (
+                    old_index,
+                    old_index + old_len,
+                    &new[new_index..new_index + new_len],
+                ),
+            };
+            Some(Change {
+                start,
+                end,
+                lines,
+                ours,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "one\ntwo\nthree\nfour\nfive";
+
+    #[test]
+    fn edits_to_different_lines_merge() {
+        assert_eq!(
+            merge(
+                BASE,
+                "ONE\ntwo\nthree\nfour\nfive",
+                "one\ntwo\nthree\nfour\nFIVE"
+            )
+            .as_deref(),
+            Some("ONE\ntwo\nthree\nfour\nFIVE")
+        );
+        assert_eq!(
+            merge(
+                BASE,
+                "zero\none\ntwo\nthree\nfour\nfive",
+                "one\ntwo\nfour\nfive"
+            )
+            .as_deref(),
+            Some("zero\none\ntwo\nfour\nfive")
+        );
+    }
+
+    #[test]
+    fn one_sided_and_identical_edits_apply_once() {
+        let edited = "one\ntwo\n3\nfour\nfive";
+        assert_eq!(merge(BASE, edited, BASE).as_deref(), Some(edited));
+        assert_eq!(merge(BASE, BASE, edited).as_deref(), Some(edited));
+        assert_eq!(merge(BASE, edited, edited).as_deref(), Some(edited));
+    }
+
+    #[test]
+    fn edits_to_the_same_or_neighbouring_lines_conflict() {
+        assert_eq!(
+            merge(
+                BASE,
+                "one\n2\nthree\nfour\nfive",
+                "one\nTWO\nthree\nfour\nfive"
+            ),
+            None
+        );
+        assert_eq!(
+            merge(
+                BASE,
+                "one\n2\nthree\nfour\nfive",
+                "one\ntwo\nTHREE\nfour\nfive"
+            ),
+            None
+        );
+        assert_eq!(merge("", "mine", "theirs"), None);
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+use crate::{
+    date_parse,
+    db::{self, Db, TimeEntry},
+    domain::memo::NewMemo,
+    format,
+};
+
+/// Explicit tag on start/stop memos so they can be filtered out of or into listings.
+const TIME_TAG: &str = "time";
+
+pub(crate) struct Stopped {
+    pub(crate) label: String,
+    pub(crate) seconds: i64,
+}
+
+/// Writes a start memo and opens a time entry. Any running entry is stopped
+/// first and returned.
+pub(crate) fn start(db: &Db, label: &str) -> Result<Option<Stopped>> {
+    let label = label.trim();
+    if label.is_empty() {
+        bail!("give the activity a name, e.g. cap start \"deep work\"");
+    }
+    let tx = db.conn().unchecked_transaction()?;
+    let stopped = stop_running(db, None)?;
+    let now = Local::now();
+    let memo = NewMemo::new(format!("Started: {}", label))
+        .with_tags(&[TIME_TAG.to_string()])
+        .with_created_at(Some(now));
+    let memo_id = db::add_memo(db, &memo)?;
+    db::start_time_entry(db, label, &memo_id, now)?;
+    tx.commit()?;
+    Ok(stopped)
+}
+
+/// Writes a stop memo (with an optional note) and closes the running entry.
+pub(crate) fn stop(db: &Db, note: Option<&str>) -> Result<Stopped> {
+    let tx = db.conn().unchecked_transaction()?;
+    let Some(stopped) = stop_running(db, note)? else {
+        bail!("nothing is being tracked; start with cap start \"<activity>\"");
+    };
+    tx.commit()?;
+    Ok(stopped)
+}
+
+fn stop_running(db: &Db, note: Option<&str>) -> Result<Option<Stopped>> {
+    let Some(entry) = db::fetch_running_entry(db)? else {
+        return Ok(None);
+    };
+    let now = Local::now();
+    let seconds = entry_seconds(&entry, None, now);
+    let mut content = format!(
+        "Stopped: {} ({})",
+        entry.label,
+        format::format_duration(seconds)
+    );
+    if let Some(note) = note.map(str::trim).filter(|note| !note.is_empty()) {
+        content.push_str(" - ");
+        content.push_str(note);
+    }
+    let memo = NewMemo::new(content)
+        .with_tags(&[TIME_TAG.to_string()])
+        .with_created_at(Some(now));
+    let memo_id = db::add_memo(db, &memo)?;
+    db::stop_running_entry(db, &memo_id, now)?;
+    Ok(Some(Stopped {
+        label: entry.label,
+        seconds,
+    }))
+}
+
+pub(crate) struct Timesheet {
+    /// Seconds per label, largest first.
+    pub(crate) by_label: Vec<(String, i64)>,
+    /// Seconds per local day, oldest first.
+    pub(crate) by_day: Vec<(String, i64)>,
+    pub(crate) total_seconds: i64,
+    pub(crate) running: Option<String>,
+}
+
+/// Sums tracked time between `since` and now; entries straddling `since` or
+/// midnight are split, and a running entry counts up to now.
+pub(crate) fn collect(db: &Db, since: DateTime<Local>) -> Result<Timesheet> {
+    let entries = db::fetch_time_entries(db, since)?;
+    Ok(summarize(&entries, since, Local::now()))
+}
+
+fn summarize(entries: &[TimeEntry], since: DateTime<Local>, now: DateTime<Local>) -> Timesheet {
+    let mut by_label: BTreeMap<String, i64> = BTreeMap::new();
+    let mut by_day: BTreeMap<String, i64> = BTreeMap::new();
+    for entry in entries {
+        *by_label.entry(entry.label.clone()).or_default() += entry_seconds(entry, Some(since), now);
+        let Some((start, end)) = entry_bounds(entry, Some(since), now) else {
+            continue;
+        };
+        let mut cursor = start;
+        while cursor < end {
+            let chunk_end = cursor
+                .date_naive()
+                .succ_opt()
+                .map(date_parse::start_of_day)
+                .map_or(end, |midnight| midnight.min(end));
+            if chunk_end <= cursor {
+                break;
+            }
+            *by_day
+                .entry(cursor.format("%Y-%m-%d %a").to_string())
+                .or_default() += (chunk_end - cursor).num_seconds();
+            cursor = chunk_end;
+        }
+    }
+    let mut by_label: Vec<(String, i64)> = by_label.into_iter().collect();
+    by_label.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Timesheet {
+        total_seconds: by_label.iter().map(|(_, seconds)| seconds).sum(),
+        by_label,
+        by_day: by_day.into_iter().collect(),
+        running: entries
+            .iter()
+            .find(|entry| entry.stopped_at.is_none())
+            .map(|entry| entry.label.clone()),
+    }
+}
+
+fn entry_bounds(
+    entry: &TimeEntry,
+    since: Option<DateTime<Local>>,
+    now: DateTime<Local>,
+) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    let start = DateTime::parse_from_rfc3339(&entry.started_at)
+        .ok()?
+        .with_timezone(&Local);
+    let end = match &entry.stopped_at {
+        Some(value) => DateTime::parse_from_rfc3339(value)
+            .ok()?
+            .with_timezone(&Local),
+        None => now,
+    };
+    let start = since.map_or(start, |since| start.max(since));
+    (end > start).then_some((start, end))
+}
+
+fn entry_seconds(entry: &TimeEntry, since: Option<DateTime<Local>>, now: DateTime<Local>) -> i64 {
+    entry_bounds(entry, since, now)
+        .map(|(start, end)| (end - start).num_seconds())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 5, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn entry(
+        label: &str,
+        started_at: DateTime<Local>,
+        stopped_at: Option<DateTime<Local>>,
+    ) -> TimeEntry {
+        TimeEntry {
+            label: label.to_string(),
+            started_at: started_at.to_rfc3339(),
+            stopped_at: stopped_at.map(|time| time.to_rfc3339()),
+        }
+    }
+
+    #[test]
+    fn entries_are_clipped_to_since_and_split_at_midnight() {
+        let since = date_parse::start_of_day(NaiveDate::from_ymd_opt(2024, 5, 13).unwrap());
+        let entries = [
+            entry("reading", at(12, 23, 0), Some(at(13, 1, 0))),
+            entry("deep work", at(13, 23, 0), Some(at(14, 0, 30))),
+            entry("reading", at(14, 9, 0), Some(at(14, 9, 30))),
+            entry("email", at(14, 10, 0), None),
+        ];
+        let sheet = summarize(&entries, since, at(14, 10, 15));
+        assert_eq!(
+            sheet.by_label,
+            [
+                ("deep work".to_string(), 90 * 60),
+                ("reading".to_string(), 90 * 60),
+                ("email".to_string(), 15 * 60),
+            ]
+        );
+        assert_eq!(
+            sheet.by_day,
+            [
+                ("2024-05-13 Mon".to_string(), 120 * 60),
+                ("2024-05-14 Tue".to_string(), 75 * 60),
+            ]
+        );
+        assert_eq!(sheet.total_seconds, 195 * 60);
+        assert_eq!(sheet.running.as_deref(), Some("email"));
+    }
+
+    #[test]
+    fn starting_stops_the_running_entry() {
+        let db = Db::open_in_memory().unwrap();
+        assert!(start(&db, "  ").is_err());
+        assert!(stop(&db, None).is_err());
+        assert!(start(&db, "reading").unwrap().is_none());
+        let stopped = start(&db, "writing").unwrap().unwrap();
+        assert_eq!(stopped.label, "reading");
+
+        let sheet = collect(&db, Local::now() - chrono::Duration::hours(1)).unwrap();
+        assert_eq!(sheet.running.as_deref(), Some("writing"));
+        assert_eq!(stop(&db, Some("done")).unwrap().label, "writing");
+        assert!(
+            collect(&db, Local::now() - chrono::Duration::hours(1))
+                .unwrap()
+                .running
+                .is_none()
+        );
+    }
+}
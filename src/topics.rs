@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use crate::domain::memo::Memo;
+
+const MIN_WORD_CHARS: usize = 3;
+
+const STOPWORDS: &[&str] = &[
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "also",
+    "and",
+    "any",
+    "are",
+    "aren't",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "can",
+    "can't",
+    "could",
+    "did",
+    "didn't",
+    "does",
+    "doesn't",
+    "doing",
+    "don't",
+    "down",
+    "during",
+    "each",
+    "even",
+    "few",
+    "for",
+    "from",
+    "further",
+    "get",
+    "got",
+    "had",
+    "has",
+    "have",
+    "having",
+    "her",
+    "here",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "i'm",
+    "i've",
+    "into",
+    "isn't",
+    "it's",
+    "its",
+    "itself",
+    "just",
+    "let",
+    "like",
+    "more",
+    "most",
+    "much",
+    "must",
+    "myself",
+    "need",
+    "not",
+    "now",
+    "off",
+    "once",
+    "one",
+    "only",
+    "other",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "really",
+    "same",
+    "she",
+    "should",
+    "some",
+    "still",
+    "such",
+    "than",
+    "that",
+    "that's",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "these",
+    "they",
+    "thing",
+    "things",
+    "this",
+    "those",
+    "through",
+    "too",
+    "under",
+    "until",
+    "very",
+    "was",
+    "wasn't",
+    "way",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "whom",
+    "why",
+    "will",
+    "with",
+    "won't",
+    "would",
+    "yet",
+    "you",
+    "your",
+    "yours",
+    "yourself",
+];
+
+pub(crate) struct Topics {
+    pub(crate) memos: usize,
+    pub(crate) keywords: Vec<(String, usize)>,
+    pub(crate) bigrams: Vec<(String, usize)>,
+}
+
+/// Counts recurring words and adjacent word pairs across `memos`, skipping
+/// stopwords, numbers, URLs, and locked private memos.
+pub(crate) fn collect(memos: &[Memo], limit: usize) -> Topics {
+    let mut keywords: HashMap<String, usize> = HashMap::new();
+    let mut bigrams: HashMap<String, usize> = HashMap::new();
+    let mut counted = 0;
+    for memo in memos.iter().filter(|memo| !memo.private) {
+        counted += 1;
+        let words = words(&memo.content);
+        for word in words.iter().flatten() {
+            *keywords.entry(word.clone()).or_default() += 1;
+        }
+        // A skipped token breaks the pair, so bigrams only join words that were adjacent.
+        for pair in words.windows(2) {
+            if let [Some(first), Some(second)] = pair
+                && first != second
+            {
+                *bigrams.entry(format!("{} {}", first, second)).or_default() += 1;
+            }
+        }
+    }
+    Topics {
+        memos: counted,
+        keywords: top(keywords, limit, 1),
+        bigrams: top(bigrams, limit, 2),
+    }
+}
+
+/// Tokens in order; `None` marks a stopword or other skipped token.
+fn words(content: &str) -> Vec<Option<String>> {
+    content
+        .split_whitespace()
+        .filter(|token| !token.contains("://"))
+        .flat_map(|token| token.split(|ch: char| !(ch.is_alphanumeric() || ch == '\'')))
+        .map(|word| word.trim_matches('\'').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let keep = word.chars().count() >= MIN_WORD_CHARS
+                && !word.chars().all(|ch| ch.is_numeric())
+                && !STOPWORDS.contains(&word.as_str());
+            keep.then_some(word)
+        })
+        .collect()
+}
+
+fn top(counts: HashMap<String, usize>, limit: usize, min_count: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_count)
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{self, Db},
+        domain::memo::NewMemo,
+    };
+
+    fn memos(contents: &[&str]) -> Vec<Memo> {
+        let db = Db::open_in_memory().unwrap();
+        for &content in contents {
+            db::add_memo(&db, &NewMemo::new(content)).unwrap();
+        }
+        db::add_memo(&db, &NewMemo::encrypted("ciphertext ciphertext")).unwrap();
+        db::fetch_memos(&db, None).unwrap()
+    }
+
+    #[test]
+    fn recurring_words_and_pairs_rank_first() {
+        let topics = collect(
+            &memos(&[
+                "Release planning for the mobile app",
+                "mobile app crash in release build",
+                "Planning the offsite, see https://example.com/mobile-app",
+            ]),
+            3,
+        );
+        assert_eq!(topics.memos, 3);
+        assert_eq!(
+            topics.keywords,
+            [
+                ("app".to_string(), 2),
+                ("mobile".to_string(), 2),
+                ("planning".to_string(), 2)
+            ]
+        );
+        assert_eq!(topics.bigrams, [("mobile app".to_string(), 2)]);
+    }
+
+    #[test]
+    fn skipped_words_break_pairs() {
+        assert_eq!(
+            words("Coffee with the 2024 team's lead"),
+            [
+                Some("coffee".to_string()),
+                None,
+                None,
+                None,
+                Some("team's".to_string()),
+                Some("lead".to_string())
+            ]
+        );
+        let topics = collect(&memos(&["coffee the coffee", "coffee 42 coffee"]), 5);
+        assert!(topics.bigrams.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result, bail};
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+use crate::{
+    attachments,
+    config::TranscribeConfig,
+    db::{self, Db},
+    domain::memo::MemoId,
+    http::{self, HttpClient},
+};
+
+/// Metadata key tracking a recording's transcription: `pending`, `done`, or `failed`.
+pub(crate) const STATUS_KEY: &str = "transcription";
+/// Metadata key holding why the last transcription failed.
+const ERROR_KEY: &str = "transcription_error";
+const AUDIO_EXTENSIONS: [&str; 9] = [
+    "wav", "mp3", "m4a", "ogg", "oga", "opus", "flac", "webm", "aac",
+];
+
+pub(crate) fn is_configured(config: &TranscribeConfig) -> bool {
+    config.command.is_some() || config.api_url.is_some()
+}
+
+pub(crate) fn is_audio(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Transcribes the memo's audio attachment and puts the transcript in front of
+/// its content. The outcome is recorded in the memo's metadata either way, since
+/// this usually runs in the background with nobody watching. A memo is only
+/// transcribed once.
+pub(crate) fn transcribe_memo(
+    db: &Db,
+    config: &TranscribeConfig,
+    memo_id: &MemoId,
+) -> Result<String> {
+    if is_transcribed(db, memo_id)? {
+        bail!("memo is already transcribed");
+    }
+    let result = db::fetch_attachments(db, memo_id)?
+        .into_iter()
+        .find(|(file_name, _)| is_audio(file_name))
+        .context("memo has no audio attachment")
+        .and_then(|(_, path)| transcribe(config, &path));
+    match result {
+        Ok(transcript) => {
+            // The memo may have been edited, or transcribed by another run,
+            // while the backend worked, so it is read again where it is written.
+            db.atomically(|db| {
+                if is_transcribed(db, memo_id)? {
+                    bail!("memo is already transcribed");
+                }
+                let memo = db::fetch_memo_by_id_prefix(db, memo_id.as_str())?;
+                let content = format!("{}\n\n{}", transcript, memo.content);
+                db::update_memo_content(db, memo_id, &content)?;
+                db::set_meta(db, memo_id, STATUS_KEY, "done")?;
+                db::unset_meta(db, memo_id, ERROR_KEY)
+            })?;
+            Ok(transcript)
+        }
+        Err(err) => {
+            db::set_meta(db, memo_id, STATUS_KEY, "failed")?;
+            db::set_meta(db, memo_id, ERROR_KEY, &format!("{:#}", err))?;
+            Err(err)
+        }
+    }
+}
+
+fn is_transcribed(db: &Db, memo_id: &MemoId) -> Result<bool> {
+    Ok(db::fetch_meta(db, memo_id)?
+        .iter()
+        .any(|(key, value)| key == STATUS_KEY && value == "done"))
+}
+
+fn transcribe(config: &TranscribeConfig, path: &Path) -> Result<String> {
+    let transcript = match (&config.command, &config.api_url) {
+        (Some(command), _) => attachments::run_command("transcriber", command, path)?,
+        (None, Some(api_url)) => {
+            let client = HttpClient::new()?;
+            http::block_on(request_api(&client, config, api_url, path))?
+        }
+        (None, None) => {
+            bail!("set [transcribe] command or api_url in the config to transcribe audio")
+        }
+    };
+    let transcript = transcript.trim();
+    if transcript.is_empty() {
+        bail!("the transcription backend returned no text");
+    }
+    Ok(transcript.to_string())
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+async fn request_api(
+    http: &HttpClient,
+    config: &TranscribeConfig,
+    api_url: &str,
+    path: &Path,
+) -> Result<String> {
+    let audio = fs::read(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (content_type, body) = http::multipart(
+        &[("model", &config.model), ("response_format", "json")],
+        &file_name,
+        &audio,
+    );
+    let mut request = http
+        .post(api_url)
+        .header(CONTENT_TYPE, content_type)
+        .body(body);
+    if let Some(key) = &config.api_key {
+        request = request.bearer_auth(key);
+    }
+    let response: TranscriptionResponse =
+        http.send(request).await?.error_for_status()?.json().await?;
+    Ok(response.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::memo::{Memo, NewMemo};
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_string_contains, header, method, path},
+    };
+
+    fn memo_with_audio(db: &Db, audio: &Path) -> Memo {
+        let memo_id = db::add_memo(db, &NewMemo::new("[audio: note.wav, 5 B]")).unwrap();
+        db::add_attachment(db, &memo_id, "note.wav", audio, 5).unwrap();
+        fetch(db, &memo_id)
+    }
+
+    fn fetch(db: &Db, memo_id: &MemoId) -> Memo {
+        db::fetch_memo_by_id_prefix(db, memo_id.as_str()).unwrap()
+    }
+
+    #[test]
+    fn command_output_becomes_the_memo_text() {
+        let dir = std::env::temp_dir().join(format!("cap-transcribe-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let audio = dir.join("note.wav");
+        fs::write(&audio, "hello").unwrap();
+        let db = Db::open_in_memory().unwrap();
+        let memo = memo_with_audio(&db, &audio);
+        let config = TranscribeConfig {
+            command: Some("tr a-z A-Z < \"$1\"".to_string()),
+            ..Default::default()
+        };
+
+        // Edits made before the transcript arrives are kept.
+        db::update_memo_content(&db, &memo.memo_id, "edited [audio: note.wav, 5 B]").unwrap();
+        assert_eq!(
+            transcribe_memo(&db, &config, &memo.memo_id).unwrap(),
+            "HELLO"
+        );
+        let updated = fetch(&db, &memo.memo_id);
+        assert_eq!(updated.content, "HELLO\n\nedited [audio: note.wav, 5 B]");
+        let meta = db::fetch_meta(&db, &memo.memo_id).unwrap();
+        assert!(meta.contains(&(STATUS_KEY.to_string(), "done".to_string())));
+
+        // A second run doesn't put the transcript in twice.
+        assert!(transcribe_memo(&db, &config, &memo.memo_id).is_err());
+        assert_eq!(fetch(&db, &memo.memo_id).content, updated.content);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failures_are_recorded_on_the_memo() {
+        let db = Db::open_in_memory().unwrap();
+        let memo = memo_with_audio(&db, Path::new("/nonexistent/note.wav"));
+        let config = TranscribeConfig {
+            command: Some("false".to_string()),
+            ..Default::default()
+        };
+        assert!(transcribe_memo(&db, &config, &memo.memo_id).is_err());
+        assert_eq!(fetch(&db, &memo.memo_id).content, memo.content);
+        let meta = db::fetch_meta(&db, &memo.memo_id).unwrap();
+        assert!(meta.contains(&(STATUS_KEY.to_string(), "failed".to_string())));
+        assert!(meta.iter().any(|(key, _)| key == ERROR_KEY));
+    }
+
+    #[tokio::test]
+    async fn api_receives_the_audio_as_multipart() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/transcriptions"))
+            .and(header("Authorization", "Bearer sk-test"))
+            .and(body_string_contains("name=\"model\"\r\n\r\nwhisper-1"))
+            .and(body_string_contains("filename=\"clip.ogg\""))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"text": " Hi there "}"#))
+            .mount(&server)
+            .await;
+        let dir = std::env::temp_dir().join(format!("cap-transcribe-api-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let clip = dir.join("clip.ogg");
+        fs::write(&clip, b"OggS").unwrap();
+        let config = TranscribeConfig {
+            api_key: Some("sk-test".to_string()),
+            ..Default::default()
+        };
+        let url = format!("{}/v1/audio/transcriptions", server.uri());
+        let text = request_api(&HttpClient::new().unwrap(), &config, &url, &clip)
+            .await
+            .unwrap();
+        assert_eq!(text, " Hi there ");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recognizes_audio_by_extension() {
+        assert!(is_audio("memo.M4A"));
+        assert!(!is_audio("pasted.txt"));
+        assert!(!is_audio("wav"));
+    }
+}
@@ -1,10 +1,24 @@
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use chrono::Local;
+use crossterm::{
+    clipboard::CopyToClipboard,
+    event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+};
+use std::{io, time::Instant};
 
-use super::state::{Focus, TuiState};
+use super::{
+    keys::Keymap,
+    state::{Focus, TuiState},
+};
 use crate::{
+    app::AppContext,
+    attachments, crypto, date_parse,
     db::{self, Db},
     domain::memo::NewMemo,
+    format,
+    i18n::t,
+    ingest, stats, sync, workspace,
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -12,30 +26,96 @@ enum Action {
     Quit,
     ToggleFocus,
     ActivateSearch,
+    ActivateUnlock,
+    ActivateJump,
+    EditTags,
+    CompleteTag,
+    CloseTagEditor,
+    ToggleTagPane,
+    ToggleStats,
+    CycleNotebook,
     SubmitInput,
     InsertNewline,
     MoveUp,
     MoveDown,
     MoveLeft,
     MoveRight,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    CycleSpelling,
     Backspace,
     Delete,
     InsertChar(char),
 }
 
-pub(crate) fn handle_tui_key(db: &Db, state: &mut TuiState, key: KeyEvent) -> Result<bool> {
+pub(crate) fn handle_tui_key(
+    app: &AppContext,
+    state: &mut TuiState,
+    key: KeyEvent,
+) -> Result<bool> {
     if key.kind == KeyEventKind::Release {
         return Ok(false);
     }
-    match key_to_action(&key, state.focus) {
-        Some(action) => apply_action(db, state, action),
-        None => Ok(false),
+    let has_selection = state.input.has_selection();
+    let Some(action) = key_to_action(&key, state.focus, &state.keymap, has_selection) else {
+        return Ok(false);
+    };
+    // Anything but more typing acts on the results, so a pending search runs first.
+    if state.search_due().is_some() && !matches!(action, Action::InsertChar(_) | Action::Backspace)
+    {
+        run_search(app.db(), state)?;
+    }
+    apply_action(app, state, action)
+}
+
+pub(crate) fn handle_tui_paste(state: &mut TuiState, text: &str) {
+    match state.focus {
+        Focus::Input => state.input.insert_str(text),
+        Focus::Search => {
+            text.chars()
+                .filter(|ch| !ch.is_control())
+                .for_each(|ch| state.search.insert_char(ch));
+            state.schedule_search(Instant::now());
+        }
+        Focus::TagEditor => text
+            .chars()
+            .filter(|ch| !ch.is_control())
+            .for_each(|ch| state.tag_editor.insert_char(ch)),
+        _ => {}
     }
 }
 
-fn key_to_action(key: &KeyEvent, focus: Focus) -> Option<Action> {
+fn key_to_action(
+    key: &KeyEvent,
+    focus: Focus,
+    keymap: &Keymap,
+    has_selection: bool,
+) -> Option<Action> {
     let code = key.code;
     let modifiers = key.modifiers;
+    let is_input_focus = matches!(focus, Focus::Input);
+
+    if matches!(focus, Focus::TagEditor) {
+        match code {
+            KeyCode::Esc => return Some(Action::CloseTagEditor),
+            KeyCode::Tab => return Some(Action::CompleteTag),
+            _ => {}
+        }
+    }
+
+    // Ctrl+C copies while text is selected and quits otherwise.
+    if is_input_focus
+        && has_selection
+        && (code, modifiers) == (KeyCode::Char('c'), KeyModifiers::CONTROL)
+    {
+        return Some(Action::Copy);
+    }
 
     if matches!(
         (code, modifiers),
@@ -44,7 +124,9 @@ fn key_to_action(key: &KeyEvent, focus: Focus) -> Option<Action> {
         return Some(Action::Quit);
     }
 
-    if matches!(focus, Focus::History) && matches!(code, KeyCode::Char('q') | KeyCode::Char('Q')) {
+    let is_list_focus = matches!(focus, Focus::History | Focus::Tags);
+
+    if is_list_focus && matches!(code, KeyCode::Char('q') | KeyCode::Char('Q')) {
         return Some(Action::Quit);
     }
 
@@ -56,10 +138,66 @@ fn key_to_action(key: &KeyEvent, focus: Focus) -> Option<Action> {
         return Some(Action::ActivateSearch);
     }
 
-    if is_submit_key(code, modifiers) {
+    if matches!(focus, Focus::History) && matches!(code, KeyCode::Char('u')) {
+        return Some(Action::ActivateUnlock);
+    }
+
+    if matches!(focus, Focus::History) && matches!(code, KeyCode::Char(':')) {
+        return Some(Action::ActivateJump);
+    }
+
+    if matches!(focus, Focus::History) && matches!(code, KeyCode::Char('t')) {
+        return Some(Action::EditTags);
+    }
+
+    if is_list_focus && matches!(code, KeyCode::Char('T')) {
+        return Some(Action::ToggleTagPane);
+    }
+
+    if is_list_focus && matches!(code, KeyCode::Char('S')) {
+        return Some(Action::ToggleStats);
+    }
+
+    if is_list_focus && matches!(code, KeyCode::Char('N')) {
+        return Some(Action::CycleNotebook);
+    }
+
+    if keymap.is_submit(key) {
         return Some(Action::SubmitInput);
     }
 
+    if is_input_focus && code == KeyCode::F(7) {
+        return Some(Action::CycleSpelling);
+    }
+
+    if is_input_focus && modifiers == KeyModifiers::CONTROL {
+        match code {
+            KeyCode::Char('a') => return Some(Action::SelectAll),
+            KeyCode::Char('x') => return Some(Action::Cut),
+            KeyCode::Char('v') => return Some(Action::Paste),
+            _ => {}
+        }
+    }
+
+    if is_input_focus && modifiers.contains(KeyModifiers::SHIFT) {
+        match code {
+            KeyCode::Up => return Some(Action::SelectUp),
+            KeyCode::Down => return Some(Action::SelectDown),
+            KeyCode::Left => return Some(Action::SelectLeft),
+            KeyCode::Right => return Some(Action::SelectRight),
+            _ => {}
+        }
+    }
+
+    // Ctrl+J/Ctrl+K navigate unless a submit binding claimed them above.
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        match code {
+            KeyCode::Char('j') => return Some(Action::MoveDown),
+            KeyCode::Char('k') => return Some(Action::MoveUp),
+            _ => {}
+        }
+    }
+
     if is_newline_key(code) {
         return Some(Action::InsertNewline);
     }
@@ -69,73 +207,176 @@ fn key_to_action(key: &KeyEvent, focus: Focus) -> Option<Action> {
         KeyCode::Down => Some(Action::MoveDown),
         KeyCode::Left => Some(Action::MoveLeft),
         KeyCode::Right => Some(Action::MoveRight),
-        KeyCode::Char('k') if matches!(focus, Focus::History) => Some(Action::MoveUp),
-        KeyCode::Char('j') if matches!(focus, Focus::History) => Some(Action::MoveDown),
+        KeyCode::Char('k') if is_list_focus => Some(Action::MoveUp),
+        KeyCode::Char('j') if is_list_focus => Some(Action::MoveDown),
         KeyCode::Backspace => Some(Action::Backspace),
         KeyCode::Delete if matches!(focus, Focus::Input) => Some(Action::Delete),
         KeyCode::Char(ch) => match focus {
-            Focus::History => None,
-            Focus::Input | Focus::Search => Some(Action::InsertChar(ch)),
+            Focus::History | Focus::Tags => None,
+            Focus::Input | Focus::Search | Focus::Unlock | Focus::Jump | Focus::TagEditor => {
+                Some(Action::InsertChar(ch))
+            }
         },
         _ => None,
     }
 }
 
-fn apply_action(db: &Db, state: &mut TuiState, action: Action) -> Result<bool> {
+fn apply_action(app: &AppContext, state: &mut TuiState, action: Action) -> Result<bool> {
     match action {
         Action::Quit => Ok(true),
         Action::ToggleFocus => {
-            state.toggle_focus();
+            if !(matches!(state.focus, Focus::Input) && state.input.expand_snippet()) {
+                state.toggle_focus();
+            }
             Ok(false)
         }
         Action::ActivateSearch => {
             state.activate_search();
             Ok(false)
         }
+        Action::ActivateUnlock => {
+            state.activate_unlock();
+            Ok(false)
+        }
+        Action::ActivateJump => {
+            state.activate_jump();
+            Ok(false)
+        }
+        Action::EditTags => {
+            match state.selected_memo().cloned() {
+                Some(memo) if memo.private => state.notify(t!("tui-private-tags")),
+                Some(memo) => state.open_tag_editor(&memo),
+                None => {}
+            }
+            Ok(false)
+        }
+        Action::CompleteTag => {
+            state.complete_tag();
+            Ok(false)
+        }
+        Action::CloseTagEditor => {
+            state.close_tag_editor();
+            Ok(false)
+        }
+        Action::ToggleTagPane => {
+            state.toggle_tag_pane();
+            Ok(false)
+        }
+        Action::ToggleStats => {
+            if state.stats.is_some() {
+                state.hide_stats();
+            } else {
+                state.show_stats(stats::collect(app.db(), app.config().streak.daily_goal)?);
+            }
+            Ok(false)
+        }
+        Action::CycleNotebook => {
+            state.cycle_notebook();
+            match &state.notebook {
+                Some(name) => state.notify(t!("tui-notebook", name = name.as_str())),
+                None => state.notify(t!("tui-all-notebooks")),
+            }
+            Ok(false)
+        }
         Action::SubmitInput => {
-            submit_input_if_ready(db, state)?;
+            submit_input_if_ready(app, state)?;
             Ok(false)
         }
         Action::InsertNewline => {
-            insert_newline_if_input_focus(state);
+            match state.focus {
+                Focus::Unlock => unlock_private_memos(app.db(), state)?,
+                Focus::Jump => jump_to_date(state),
+                Focus::Tags => state.select_highlighted_tag(),
+                Focus::TagEditor => save_tags(app.db(), state)?,
+                _ => insert_newline_if_input_focus(state),
+            }
             Ok(false)
         }
         Action::MoveUp => {
             match state.focus {
                 Focus::History => state.move_history_selection_up(),
-                Focus::Input => state.input.move_up(),
-                Focus::Search => {}
+                Focus::Input => {
+                    state.input.set_selecting(false);
+                    state.input.move_up();
+                }
+                Focus::Tags => state.tag_pane.move_up(),
+                Focus::Search | Focus::Unlock | Focus::Jump | Focus::TagEditor => {}
             }
             Ok(false)
         }
         Action::MoveDown => {
             match state.focus {
                 Focus::History => state.move_history_selection_down(),
-                Focus::Input => state.input.move_down(),
-                Focus::Search => {}
+                Focus::Input => {
+                    state.input.set_selecting(false);
+                    state.input.move_down();
+                }
+                Focus::Tags => state.tag_pane.move_down(),
+                Focus::Search | Focus::Unlock | Focus::Jump | Focus::TagEditor => {}
             }
             Ok(false)
         }
         Action::MoveLeft => {
             if matches!(state.focus, Focus::Input) {
+                state.input.set_selecting(false);
                 state.input.move_left();
             }
             Ok(false)
         }
         Action::MoveRight => {
             if matches!(state.focus, Focus::Input) {
+                state.input.set_selecting(false);
                 state.input.move_right();
             }
             Ok(false)
         }
+        Action::SelectUp | Action::SelectDown | Action::SelectLeft | Action::SelectRight => {
+            state.input.set_selecting(true);
+            match action {
+                Action::SelectUp => state.input.move_up(),
+                Action::SelectDown => state.input.move_down(),
+                Action::SelectLeft => state.input.move_left(),
+                _ => state.input.move_right(),
+            }
+            Ok(false)
+        }
+        Action::SelectAll => {
+            state.input.select_all();
+            Ok(false)
+        }
+        Action::Copy => {
+            if let Some(text) = state.input.copy() {
+                copy_to_system_clipboard(&text);
+            }
+            Ok(false)
+        }
+        Action::Cut => {
+            if let Some(text) = state.input.cut() {
+                copy_to_system_clipboard(&text);
+            }
+            Ok(false)
+        }
+        Action::Paste => {
+            state.input.paste();
+            Ok(false)
+        }
         Action::Backspace => {
             match state.focus {
                 Focus::Input => state.input.backspace(),
                 Focus::Search => {
                     state.search.backspace();
-                    state.apply_search();
+                    state.schedule_search(Instant::now());
                 }
-                Focus::History => {}
+                Focus::Unlock => state.passphrase.backspace(),
+                Focus::Jump => state.jump.backspace(),
+                Focus::TagEditor => state.tag_editor.backspace(),
+                Focus::History | Focus::Tags => {}
+            }
+            Ok(false)
+        }
+        Action::CycleSpelling => {
+            if state.dictionary.is_some() && !state.cycle_spelling() {
+                state.notify(t!("tui-no-suggestions"));
             }
             Ok(false)
         }
@@ -150,54 +391,158 @@ fn apply_action(db: &Db, state: &mut TuiState, action: Action) -> Result<bool> {
                 Focus::Input => state.input.insert_char(ch),
                 Focus::Search => {
                     state.search.insert_char(ch);
-                    state.apply_search();
+                    state.schedule_search(Instant::now());
                 }
-                Focus::History => {}
+                Focus::Unlock => state.passphrase.insert_char(ch),
+                Focus::Jump => state.jump.insert_char(ch),
+                Focus::TagEditor => state.tag_editor.insert_char(ch),
+                Focus::History | Focus::Tags => {}
             }
             Ok(false)
         }
     }
 }
 
-fn is_submit_key(code: KeyCode, modifiers: KeyModifiers) -> bool {
-    if !modifiers.contains(KeyModifiers::CONTROL) {
-        return false;
-    }
-    matches!(
-        code,
-        KeyCode::Enter
-            | KeyCode::Char('\n')
-            | KeyCode::Char('\r')
-            | KeyCode::Char('m')
-            | KeyCode::Char('j')
-    )
+/// Hands copied text to the terminal's clipboard with OSC 52. Terminals that
+/// don't support it ignore the sequence, and the text still pastes with Ctrl+V.
+fn copy_to_system_clipboard(text: &str) {
+    let _ = execute!(io::stdout(), CopyToClipboard::to_clipboard_from(text));
 }
 
 fn is_newline_key(code: KeyCode) -> bool {
-    matches!(code, KeyCode::Enter | KeyCode::Char('\n') | KeyCode::Char('\r'))
+    matches!(
+        code,
+        KeyCode::Enter | KeyCode::Char('\n') | KeyCode::Char('\r')
+    )
 }
 
 fn refresh_history(db: &Db, state: &mut TuiState) -> Result<()> {
     let history = db::fetch_memos(db, None)?;
     state.set_history(history);
+    state.set_tag_counts(db::fetch_tag_counts(db)?);
+    refresh_notebooks(db, state)?;
+    run_search(db, state)?;
+    refresh_status_bar(db, state)
+}
+
+pub(crate) fn refresh_notebooks(db: &Db, state: &mut TuiState) -> Result<()> {
+    let mut notebooks: Vec<String> = db::fetch_notebook_counts(db)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    // Keep the current notebook reachable even before its first memo.
+    if let Some(current) = &state.notebook
+        && !notebooks.contains(current)
+    {
+        notebooks.push(current.clone());
+        notebooks.sort();
+    }
+    state.set_notebooks(notebooks);
+    Ok(())
+}
+
+/// Re-runs the search query against the whole database.
+pub(crate) fn run_search(db: &Db, state: &mut TuiState) -> Result<()> {
+    let query = state.search.query.trim().to_string();
+    if query.is_empty() {
+        state.set_search_results(Vec::new());
+    } else {
+        state.set_search_results(db::search_memos(db, &query, None)?);
+    }
+    Ok(())
+}
+
+pub(crate) fn refresh_status_bar(db: &Db, state: &mut TuiState) -> Result<()> {
+    let status = sync::load_status(db)?;
+    state.set_sync_summary(status.summary());
+    if let Some(daily_goal) = state.streak_goal {
+        state.streak_summary = Some(stats::streak(db, daily_goal)?.summary());
+    }
+    Ok(())
+}
+
+fn unlock_private_memos(db: &Db, state: &mut TuiState) -> Result<()> {
+    let passphrase = std::mem::take(&mut state.passphrase.query);
+    state.focus = Focus::History;
+    match crypto::unlock(db, &passphrase) {
+        Ok(key) => {
+            state.memo_key = Some(key);
+            refresh_history(db, state)?;
+            state.notify(t!("tui-unlocked"));
+        }
+        Err(err) => state.notify(t!("tui-unlock-failed", error = err.to_string())),
+    }
+    Ok(())
+}
+
+fn jump_to_date(state: &mut TuiState) {
+    let query = std::mem::take(&mut state.jump.query);
+    state.focus = Focus::History;
+    match date_parse::parse_date(&query, Local::now().date_naive()) {
+        Some(date) => state.jump_to_date(date),
+        None => state.notify(t!("tui-unknown-date", date = query)),
+    }
+}
+
+/// Writes the popup's tags to the memo it was opened on and keeps that memo selected.
+fn save_tags(db: &Db, state: &mut TuiState) -> Result<()> {
+    let tags = state.edited_tags();
+    let Some(memo_id) = state.close_tag_editor() else {
+        return Ok(());
+    };
+    let inline = db::replace_explicit_tags(db, &memo_id, &tags)?;
+    refresh_history(db, state)?;
+    state.select_memo(&memo_id);
+    if inline.is_empty() {
+        state.notify(t!("tui-tags-saved"));
+    } else {
+        let inline: Vec<String> = inline.iter().map(|tag| format!("#{}", tag)).collect();
+        state.notify(t!("tui-tags-inline-kept", tags = inline.join(" ")));
+    }
     Ok(())
 }
 
 fn insert_newline_if_input_focus(state: &mut TuiState) {
     if matches!(state.focus, Focus::Input) {
-        state.input.newline();
+        state.input.newline_continuing_list();
     }
 }
 
-fn submit_input_if_ready(db: &Db, state: &mut TuiState) -> Result<()> {
+fn submit_input_if_ready(app: &AppContext, state: &mut TuiState) -> Result<()> {
     if !matches!(state.focus, Focus::Input) {
         return Ok(());
     }
     if state.input.is_empty() {
         return Ok(());
     }
-    let new_memo = NewMemo::new(state.input.text());
-    db::add_memo(db, &new_memo)?;
+    let db = app.db();
+    // Memos typed while viewing every notebook go to the configured default.
+    let notebook = app.config().notebook.resolve(state.notebook.clone());
+    let mut new_memo = NewMemo::new(state.input.text()).with_notebook(notebook);
+    workspace::record(&app.config().workspace, &mut new_memo);
+    if !ingest::prepare_new_memo(app.config(), &mut new_memo) {
+        state.notify(t!("memo-ignored"));
+        state.input.clear();
+        return Ok(());
+    }
+    let memo_config = &app.config().memo;
+    if attachments::exceeds_max_size(&new_memo.content, memo_config) {
+        // The first submit only warns; submitting again unchanged confirms.
+        if !state.input.attachment_confirm_pending {
+            state.notify(t!(
+                "tui-memo-too-large",
+                size = format::format_bytes(new_memo.content.len() as u64),
+                limit = format::format_bytes(memo_config.max_size_bytes as u64)
+            ));
+            state.input.attachment_confirm_pending = true;
+            return Ok(());
+        }
+        attachments::add_memo_as_attachment(db, &new_memo)?;
+        state.notify(t!("tui-saved-as-attachment"));
+    } else {
+        db::add_memo(db, &new_memo)?;
+        state.notify(t!("tui-memo-saved"));
+    }
     refresh_history(db, state)?;
     state.input.clear();
     Ok(())
@@ -0,0 +1,137 @@
+use anyhow::{Result, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A key plus required modifiers, parsed from config strings like `ctrl+enter` or `f2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub(crate) fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim().to_lowercase();
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = spec.split('+').collect();
+        let Some(key) = parts.pop() else {
+            bail!("empty key binding");
+        };
+        for part in parts {
+            modifiers |= match part {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => bail!("unknown modifier `{}` in key binding `{}`", other, spec),
+            };
+        }
+        let code = match key {
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            _ if key.len() > 1 && key.starts_with('f') => match key[1..].parse::<u8>() {
+                Ok(number) => KeyCode::F(number),
+                Err(_) => bail!("unknown key `{}` in key binding `{}`", key, spec),
+            },
+            _ if key.chars().count() == 1 => KeyCode::Char(key.chars().next().unwrap_or(' ')),
+            _ => bail!("unknown key `{}` in key binding `{}`", key, spec),
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    /// Whether `key` is this chord with exactly its modifiers, so `ctrl+enter`
+    /// doesn't fire on Ctrl+Alt+Enter. For characters Shift is judged by the
+    /// letter's case, since terminals report `A` either way.
+    pub(crate) fn matches(&self, key: &KeyEvent) -> bool {
+        let without_shift = |modifiers: KeyModifiers| modifiers - KeyModifiers::SHIFT;
+        match self.code {
+            KeyCode::Char(wanted) => {
+                let KeyCode::Char(typed) = key.code else {
+                    return false;
+                };
+                if without_shift(key.modifiers) != without_shift(self.modifiers) {
+                    return false;
+                }
+                if !wanted.is_alphabetic() {
+                    return typed == wanted;
+                }
+                let shifted = key.modifiers.contains(KeyModifiers::SHIFT) || typed.is_uppercase();
+                typed.to_lowercase().eq(wanted.to_lowercase())
+                    && shifted == self.modifiers.contains(KeyModifiers::SHIFT)
+            }
+            _ if key.modifiers != self.modifiers => false,
+            // Terminals report Enter as CR/LF (and Ctrl+Enter as Ctrl+M) without keyboard
+            // enhancement.
+            KeyCode::Enter => {
+                matches!(
+                    key.code,
+                    KeyCode::Enter | KeyCode::Char('\n') | KeyCode::Char('\r')
+                ) || (self.modifiers.contains(KeyModifiers::CONTROL)
+                    && key.code == KeyCode::Char('m'))
+            }
+            code => key.code == code,
+        }
+    }
+}
+
+pub(crate) struct Keymap {
+    pub(crate) submit: Vec<KeyChord>,
+}
+
+impl Keymap {
+    pub(crate) fn from_specs(submit_specs: &[String]) -> Result<Self> {
+        let submit = submit_specs
+            .iter()
+            .map(|spec| KeyChord::parse(spec))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { submit })
+    }
+
+    pub(crate) fn is_submit(&self, key: &KeyEvent) -> bool {
+        self.submit.iter().any(|chord| chord.matches(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn chords_need_exactly_their_modifiers() {
+        let ctrl_enter = KeyChord::parse("ctrl+enter").unwrap();
+        assert!(ctrl_enter.matches(&key(KeyCode::Enter, KeyModifiers::CONTROL)));
+        assert!(ctrl_enter.matches(&key(KeyCode::Char('m'), KeyModifiers::CONTROL)));
+        assert!(!ctrl_enter.matches(&key(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!ctrl_enter.matches(&key(
+            KeyCode::Enter,
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        )));
+
+        let f2 = KeyChord::parse("f2").unwrap();
+        assert!(f2.matches(&key(KeyCode::F(2), KeyModifiers::NONE)));
+        assert!(!f2.matches(&key(KeyCode::F(2), KeyModifiers::SHIFT)));
+    }
+
+    #[test]
+    fn shift_on_characters_follows_the_letter_case() {
+        let ctrl_s = KeyChord::parse("ctrl+s").unwrap();
+        assert!(ctrl_s.matches(&key(KeyCode::Char('s'), KeyModifiers::CONTROL)));
+        assert!(!ctrl_s.matches(&key(
+            KeyCode::Char('S'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT
+        )));
+
+        let ctrl_shift_s = KeyChord::parse("ctrl+shift+s").unwrap();
+        assert!(ctrl_shift_s.matches(&key(KeyCode::Char('S'), KeyModifiers::CONTROL)));
+        assert!(ctrl_shift_s.matches(&key(
+            KeyCode::Char('s'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT
+        )));
+
+        let question = KeyChord::parse("?").unwrap();
+        assert!(question.matches(&key(KeyCode::Char('?'), KeyModifiers::SHIFT)));
+    }
+}
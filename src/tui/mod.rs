@@ -1,45 +1,167 @@
 use anyhow::Result;
 use crossterm::{
+    cursor::Show,
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event,
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event,
     },
     execute,
-    terminal::{
-        EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
-    },
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
-use std::io;
+use std::{
+    io, panic,
+    sync::{
+        Once,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 mod handler;
+mod keys;
+mod picker;
+mod plain;
 mod state;
+mod toast;
 mod view;
+mod wait;
 
-use crate::db::Db;
-use handler::handle_tui_key;
+use crate::{
+    app::AppContext,
+    auth,
+    db::{self, Db},
+    domain::tags,
+    i18n::t,
+    shutdown,
+    spell::Dictionary,
+};
+use handler::{
+    handle_tui_key, handle_tui_paste, refresh_notebooks, refresh_status_bar, run_search,
+};
+use keys::Keymap;
+pub(crate) use picker::pick_memos;
+pub(crate) use plain::run_plain;
 use state::TuiState;
 use view::draw_tui;
+use wait::Waiter;
 
-const TUI_POLL_MS: u64 = 200;
+// Upper bound on how long the loop blocks when nothing time-based is pending.
+const TUI_IDLE_TICK: Duration = Duration::from_secs(30);
+/// Input left unsent when a signal closed the TUI, put back on the next start.
+const DRAFT_KEY: &str = "tui_draft";
 
-pub(crate) fn run_tui(db: &Db) -> Result<()> {
+/// Whether the terminal is in raw mode on the alternate screen, for the panic hook.
+static TERMINAL_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// A search to open with, from `cap tui --query` or `cap search -i`.
+#[derive(Default)]
+pub(crate) struct StartQuery {
+    pub(crate) text: String,
+    pub(crate) tag: Option<String>,
+    pub(crate) notebook: Option<String>,
+}
+
+impl StartQuery {
+    /// `tag:NAME` and `notebook:NAME` terms narrow history like the tag pane and
+    /// `N` do; the remaining words are the search text.
+    pub(crate) fn parse(query: &str) -> Self {
+        let mut start = Self::default();
+        let mut words = Vec::new();
+        for word in query.split_whitespace() {
+            if let Some(tag) = word.strip_prefix("tag:") {
+                start.tag = Some(tags::normalize_tag(tag));
+            } else if let Some(notebook) = word.strip_prefix("notebook:") {
+                start.notebook = Some(notebook.to_string());
+            } else {
+                words.push(word);
+            }
+        }
+        start.text = words.join(" ");
+        start
+    }
+}
+
+pub(crate) fn run_tui(app: &AppContext, start: Option<StartQuery>) -> Result<()> {
+    let db = app.db();
+    let tui_config = &app.config().tui;
+    let keymap = Keymap::from_specs(&tui_config.submit_keys)?;
+    let dictionary = tui_config
+        .spellcheck
+        .then(|| Dictionary::load(tui_config.dictionary.as_deref()))
+        .transpose()?;
+    shutdown::install()?;
     let mut guard = TerminalGuard::new()?;
-    let mut state = TuiState::new(crate::db::fetch_memos(db, None)?);
+    let mut waiter = Waiter::new()?;
+    let mut state = TuiState::new(db::fetch_memos(db, None)?, keymap);
+    state.relative_time = tui_config.relative_time;
+    state.line_style = app.config().list.line_style();
+    state.input.snippets = tui_config.snippets.clone();
+    state.dictionary = dictionary;
+    state.show_sync = auth::current_account(db)?.is_some();
+    let streak_config = &app.config().streak;
+    state.streak_goal = streak_config
+        .show_in_tui
+        .then_some(streak_config.daily_goal);
+    state.notebook = app.config().notebook.default.clone();
+    state.default_notebook = app.config().notebook.default.clone();
+    state.set_tag_counts(db::fetch_tag_counts(db)?);
+    if let Some(start) = start {
+        state.start_with(start);
+    }
+    if let Some(draft) = db::get_kv(db, DRAFT_KEY)? {
+        state.input.insert_str(&draft);
+        db::delete_kv(db, DRAFT_KEY)?;
+        state.notify(t!("tui-draft-restored"));
+    }
+    refresh_notebooks(db, &mut state)?;
+    run_search(db, &mut state)?;
+    refresh_status_bar(db, &mut state)?;
 
-    let result = run_tui_loop(guard.terminal_mut(), db, &mut state);
+    let result = run_tui_loop(guard.terminal_mut(), app, &mut state, &mut waiter);
     let _ = drain_pending_events();
     let restore_result = guard.restore();
     result.and(restore_result)
 }
 
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    install_panic_hook();
+    TERMINAL_TAKEN.store(true, Ordering::SeqCst);
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture,)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     Ok(Terminal::new(backend)?)
 }
 
+/// Restores the terminal before the panic message prints. `TerminalGuard`'s
+/// `Drop` runs too late for that: by then the message has gone to the
+/// alternate screen and the shell is left in raw mode.
+fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if TERMINAL_TAKEN.swap(false, Ordering::SeqCst) {
+                let _ = disable_raw_mode();
+                let _ = execute!(
+                    io::stdout(),
+                    DisableBracketedPaste,
+                    DisableMouseCapture,
+                    LeaveAlternateScreen,
+                    Show
+                );
+            }
+            previous(info);
+        }));
+    });
+}
+
 struct TerminalGuard {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     restored: bool,
@@ -78,23 +200,24 @@ impl Drop for TerminalGuard {
 }
 
 fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    TERMINAL_TAKEN.store(false, Ordering::SeqCst);
     let mut first_error: Option<anyhow::Error> = None;
     if let Err(err) = disable_raw_mode() {
         first_error = Some(err.into());
     }
     if let Err(err) = execute!(
         terminal.backend_mut(),
+        DisableBracketedPaste,
         DisableMouseCapture,
         LeaveAlternateScreen
-    ) {
-        if first_error.is_none() {
-            first_error = Some(err.into());
-        }
+    ) && first_error.is_none()
+    {
+        first_error = Some(err.into());
     }
-    if let Err(err) = terminal.show_cursor() {
-        if first_error.is_none() {
-            first_error = Some(err.into());
-        }
+    if let Err(err) = terminal.show_cursor()
+        && first_error.is_none()
+    {
+        first_error = Some(err.into());
     }
     if let Some(err) = first_error {
         return Err(err);
@@ -104,33 +227,73 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Re
 
 fn run_tui_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    db: &Db,
+    app: &AppContext,
     state: &mut TuiState,
+    waiter: &mut Waiter,
 ) -> Result<()> {
+    let mut needs_redraw = true;
     loop {
-        terminal.draw(|frame| draw_tui(frame, state))?;
-        if !poll_event()? {
-            continue;
+        if shutdown::requested().is_some() {
+            save_draft(app.db(), state)?;
+            break;
         }
-        match event::read()? {
-            Event::Key(key) => {
-                if handle_tui_key(db, state, key)? {
-                    break;
-                }
+        if needs_redraw {
+            terminal.draw(|frame| draw_tui(frame, state))?;
+        }
+        if !poll_event(state, waiter)? {
+            let now = Instant::now();
+            let searched = state.search_is_due(now);
+            if searched {
+                run_search(app.db(), state)?;
             }
-            Event::Mouse(_) => {}
-            _ => {}
+            // Relative timestamps age, so idle ticks repaint them.
+            needs_redraw = state.toasts.expire(now) || state.relative_time || searched;
+            continue;
         }
+        needs_redraw = match event::read()? {
+            Event::Key(key) if handle_tui_key(app, state, key)? => break,
+            Event::Key(_) | Event::Resize(_, _) => true,
+            Event::Paste(text) => {
+                handle_tui_paste(state, &text);
+                true
+            }
+            _ => false,
+        };
     }
     Ok(())
 }
 
-fn poll_event() -> Result<bool> {
-    Ok(event::poll(std::time::Duration::from_millis(TUI_POLL_MS))?)
+/// Blocks until input arrives, the next toast is due to expire, a search is
+/// due, or a signal asks the TUI to close.
+fn poll_event(state: &TuiState, waiter: &mut Waiter) -> Result<bool> {
+    let now = Instant::now();
+    let deadline = [state.toasts.next_expiry(), state.search_due()]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(now + TUI_IDLE_TICK);
+    loop {
+        // Crossterm may already hold input it read alongside the last event.
+        if event::poll(Duration::ZERO)? {
+            return Ok(true);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || shutdown::requested().is_some() {
+            return Ok(false);
+        }
+        waiter.wait(remaining)?;
+    }
+}
+
+fn save_draft(db: &Db, state: &TuiState) -> Result<()> {
+    if state.input.is_empty() {
+        return Ok(());
+    }
+    db::set_kv(db, DRAFT_KEY, &state.input.text())
 }
 
 fn drain_pending_events() -> Result<()> {
-    while event::poll(std::time::Duration::from_millis(0))? {
+    while event::poll(Duration::from_millis(0))? {
         let _ = event::read();
     }
     Ok(())
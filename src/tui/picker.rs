@@ -0,0 +1,227 @@
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{
+    Frame,
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListState},
+};
+
+use super::{TerminalGuard, drain_pending_events, view::memo_item};
+use crate::{domain::memo::Memo, format, format::LineStyle};
+
+const MARKED: &str = "[x] ";
+const UNMARKED: &str = "[ ] ";
+
+/// A list of memos to mark several of at once.
+struct Picker {
+    memos: Vec<Memo>,
+    marked: Vec<bool>,
+    index: usize,
+}
+
+enum Outcome {
+    Pending,
+    Confirmed,
+    Cancelled,
+}
+
+impl Picker {
+    fn new(memos: Vec<Memo>) -> Self {
+        let marked = vec![false; memos.len()];
+        Self {
+            memos,
+            marked,
+            index: 0,
+        }
+    }
+
+    fn marked_count(&self) -> usize {
+        self.marked.iter().filter(|marked| **marked).count()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let last = self.memos.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Outcome::Cancelled,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Outcome::Cancelled;
+            }
+            KeyCode::Enter => {
+                // Enter with nothing marked takes the highlighted memo.
+                if self.marked_count() == 0
+                    && let Some(marked) = self.marked.get_mut(self.index)
+                {
+                    *marked = true;
+                }
+                return Outcome::Confirmed;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.index = self.index.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.index = (self.index + 1).min(last),
+            KeyCode::Home | KeyCode::Char('g') => self.index = 0,
+            KeyCode::End | KeyCode::Char('G') => self.index = last,
+            KeyCode::Char(' ') => {
+                if let Some(marked) = self.marked.get_mut(self.index) {
+                    *marked = !*marked;
+                }
+                self.index = (self.index + 1).min(last);
+            }
+            KeyCode::Char('a') => {
+                let all = self.marked.iter().all(|marked| *marked);
+                self.marked.fill(!all);
+            }
+            _ => {}
+        }
+        Outcome::Pending
+    }
+
+    fn into_marked(self) -> Vec<Memo> {
+        self.memos
+            .into_iter()
+            .zip(self.marked)
+            .filter_map(|(memo, marked)| marked.then_some(memo))
+            .collect()
+    }
+}
+
+/// Lets the user mark memos from `memos` in a full-screen list; `title` names
+/// the action and gets the marked count. Empty when the picker is cancelled.
+pub(crate) fn pick_memos(
+    memos: Vec<Memo>,
+    style: &LineStyle,
+    title: impl Fn(usize) -> String,
+) -> Result<Vec<Memo>> {
+    let mut picker = Picker::new(memos);
+    let mut guard = TerminalGuard::new()?;
+    let outcome = loop {
+        guard
+            .terminal_mut()
+            .draw(|frame| draw_picker(frame, &picker, style, &title))?;
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match picker.handle_key(key) {
+                Outcome::Pending => {}
+                outcome => break outcome,
+            },
+            _ => {}
+        }
+    };
+    let _ = drain_pending_events();
+    guard.restore()?;
+    Ok(match outcome {
+        Outcome::Confirmed => picker.into_marked(),
+        _ => Vec::new(),
+    })
+}
+
+fn draw_picker(
+    frame: &mut Frame<'_>,
+    picker: &Picker,
+    style: &LineStyle,
+    title: &impl Fn(usize) -> String,
+) {
+    let area = frame.area();
+    let width = area.width.saturating_sub(2) as usize;
+    let items: Vec<_> = picker
+        .memos
+        .iter()
+        .zip(&picker.marked)
+        .map(|(memo, marked)| {
+            let mark = if *marked { MARKED } else { UNMARKED };
+            let time = format::format_display_time(&memo.created_at);
+            memo_item(memo, &format!("{mark}{time}"), width, style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title(picker.marked_count())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default();
+    list_state.select(Some(picker.index));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{Terminal, backend::TestBackend};
+
+    use super::*;
+
+    fn memos() -> Vec<Memo> {
+        ["first", "second", "third"]
+            .into_iter()
+            .map(|content| Memo {
+                memo_id: content.to_string().into(),
+                content: content.to_string(),
+                created_at: "2024-05-01T09:00:00+00:00".to_string(),
+                updated_at: "2024-05-01T09:00:00+00:00".to_string(),
+                private: false,
+                tags: Vec::new(),
+                timezone: None,
+                notebook: None,
+                location: None,
+                mood: None,
+                dirty: false,
+            })
+            .collect()
+    }
+
+    fn press(picker: &mut Picker, code: KeyCode) -> Outcome {
+        picker.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn contents(memos: Vec<Memo>) -> Vec<String> {
+        memos.into_iter().map(|memo| memo.content).collect()
+    }
+
+    #[test]
+    fn space_marks_and_enter_returns_the_marked_memos() {
+        let mut picker = Picker::new(memos());
+        press(&mut picker, KeyCode::Char(' '));
+        press(&mut picker, KeyCode::Down);
+        press(&mut picker, KeyCode::Char(' '));
+        assert_eq!(picker.marked_count(), 2);
+        assert!(matches!(
+            press(&mut picker, KeyCode::Enter),
+            Outcome::Confirmed
+        ));
+        assert_eq!(contents(picker.into_marked()), ["first", "third"]);
+
+        let mut picker = Picker::new(memos());
+        press(&mut picker, KeyCode::Char('a'));
+        assert_eq!(picker.marked_count(), 3);
+        press(&mut picker, KeyCode::Char('a'));
+        assert_eq!(picker.marked_count(), 0);
+    }
+
+    #[test]
+    fn enter_without_marks_takes_the_highlighted_memo() {
+        let mut picker = Picker::new(memos());
+        press(&mut picker, KeyCode::Char('j'));
+        press(&mut picker, KeyCode::Enter);
+        assert_eq!(contents(picker.into_marked()), ["second"]);
+        assert!(matches!(
+            press(&mut Picker::new(memos()), KeyCode::Esc),
+            Outcome::Cancelled
+        ));
+    }
+
+    #[test]
+    fn marked_memos_are_checked() {
+        let mut picker = Picker::new(memos());
+        press(&mut picker, KeyCode::Char(' '));
+        let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+        terminal
+            .draw(|frame| {
+                draw_picker(frame, &picker, &LineStyle::default(), &|count| {
+                    format!("Delete {count}")
+                })
+            })
+            .unwrap();
+        let screen = terminal.backend().to_string();
+        assert!(screen.contains("Delete 1"));
+        assert!(screen.contains("[x] "));
+        assert!(screen.contains("[ ] "));
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    app::AppContext,
+    attachments,
+    config::Config,
+    crypto,
+    db::{self, Db},
+    domain::memo::{Memo, NewMemo},
+    format,
+    i18n::t,
+    ingest,
+    tui::StartQuery,
+    workspace,
+};
+
+const DEFAULT_LIST_COUNT: usize = 10;
+
+/// Line-oriented stand-in for the TUI: no screen redraws, so screen readers
+/// and braille displays only ever see new lines.
+/// A start query is answered right after the welcome line.
+pub(crate) fn run_plain(app: &AppContext, start: Option<StartQuery>) -> Result<()> {
+    let stdin = io::stdin();
+    run_session(
+        app.db(),
+        app.config(),
+        start,
+        stdin.lock(),
+        io::stdout().lock(),
+    )
+}
+
+fn run_session(
+    db: &Db,
+    config: &Config,
+    start: Option<StartQuery>,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> Result<()> {
+    writeln!(out, "{}", t!("plain-welcome"))?;
+    if let Some(start) = start {
+        let unfiled = start
+            .notebook
+            .as_deref()
+            .is_some_and(|name| config.notebook.holds_unfiled(name));
+        let filter = db::MemoFilter {
+            text: Some(start.text).filter(|text| !text.is_empty()),
+            tag: start.tag,
+            notebook: start.notebook,
+            unfiled,
+            ..Default::default()
+        };
+        print_memos(config, db::fetch_filtered_memos(db, &filter)?, &mut out)?;
+    }
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(command) = line.strip_prefix('/') else {
+            add_memo(db, config, line, &mut out)?;
+            continue;
+        };
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .map(|(name, arg)| (name, arg.trim()))
+            .unwrap_or((command, ""));
+        match name {
+            "quit" | "q" => break,
+            "help" | "h" => writeln!(out, "{}", t!("plain-welcome"))?,
+            "list" | "l" => {
+                let count = arg.parse().unwrap_or(DEFAULT_LIST_COUNT);
+                print_memos(config, db::fetch_memos(db, Some(count))?, &mut out)?;
+            }
+            "search" | "s" if !arg.is_empty() => {
+                print_memos(config, db::search_memos(db, arg, None)?, &mut out)?;
+            }
+            _ => writeln!(out, "{}", t!("plain-unknown-command", command = line))?,
+        }
+    }
+    Ok(())
+}
+
+fn add_memo(db: &Db, config: &Config, content: &str, out: &mut impl Write) -> Result<()> {
+    let mut new_memo = NewMemo::new(content).with_notebook(config.notebook.resolve(None));
+    workspace::record(&config.workspace, &mut new_memo);
+    if !ingest::prepare_new_memo(config, &mut new_memo) {
+        writeln!(out, "{}", t!("memo-ignored"))?;
+        return Ok(());
+    }
+    if attachments::exceeds_max_size(&new_memo.content, &config.memo) {
+        attachments::add_memo_as_attachment(db, &new_memo)?;
+        writeln!(out, "{}", t!("tui-saved-as-attachment"))?;
+    } else {
+        db::add_memo(db, &new_memo)?;
+        writeln!(out, "{}", t!("tui-memo-saved"))?;
+    }
+    Ok(())
+}
+
+fn print_memos(config: &Config, mut memos: Vec<Memo>, out: &mut impl Write) -> Result<()> {
+    if memos.is_empty() {
+        writeln!(out, "{}", t!("plain-no-memos"))?;
+        return Ok(());
+    }
+    crypto::reveal_memos(&mut memos, None);
+    for memo in memos {
+        let display_time = format::format_display_time(&memo.created_at);
+        writeln!(
+            out,
+            "{}",
+            format::format_memo_line(
+                &display_time,
+                memo.title(),
+                usize::MAX,
+                &config.list.line_style()
+            )
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(db: &Db, input: &str) -> String {
+        session_from(db, None, input)
+    }
+
+    fn session_from(db: &Db, start: Option<StartQuery>, input: &str) -> String {
+        let mut out = Vec::new();
+        run_session(db, &Config::default(), start, input.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn lines_become_memos_and_commands_list_them() {
+        let db = Db::open_in_memory().unwrap();
+        let long = "word ".repeat(100);
+        let out = session(
+            &db,
+            &format!(
+                "buy milk\n\n{}\n/list\n/search milk\n/quit\nignored\n",
+                long
+            ),
+        );
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 6, "{}", out);
+        assert_eq!(lines[1], "memo saved");
+        assert_eq!(lines[2], "memo saved");
+        assert!(
+            lines[3].ends_with(long.trim()),
+            "not truncated: {}",
+            lines[3]
+        );
+        assert!(lines[4].ends_with("  buy milk"));
+        assert!(lines[5].ends_with("  buy milk"));
+        assert_eq!(db::fetch_memos(&db, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn start_query_results_come_first() {
+        let db = Db::open_in_memory().unwrap();
+        session(&db, "standup notes #work\nstandup at school #kids\n");
+        let start = StartQuery::parse("tag:Work  standup");
+        assert_eq!(
+            (start.text.as_str(), start.tag.as_deref()),
+            ("standup", Some("work"))
+        );
+        let out = session_from(&db, Some(start), "");
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", out);
+        assert!(lines[1].ends_with("  standup notes #work"));
+    }
+
+    #[test]
+    fn unknown_commands_and_empty_results_are_reported() {
+        let db = Db::open_in_memory().unwrap();
+        let out = session(&db, "/list\n/frobnicate\n");
+        assert!(out.contains("No memos"));
+        assert!(out.contains("Unknown command /frobnicate"));
+    }
+}
@@ -1,42 +1,138 @@
+use chrono::{DateTime, Local, NaiveDate};
 use ratatui::layout::Rect;
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ops::Range,
+    time::{Duration, Instant},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-use crate::domain::memo::Memo;
+use super::{StartQuery, keys::Keymap, toast::ToastQueue};
+use crate::{
+    crypto::{self, MemoKey},
+    domain::{
+        memo::{Memo, MemoId},
+        tags,
+    },
+    format::LineStyle,
+    spell::Dictionary,
+    stats::Stats,
+};
+
+/// How many existing tags the tag editor offers at once.
+const TAG_SUGGESTIONS: usize = 5;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub(crate) enum Focus {
     Search,
     Input,
     History,
+    Unlock,
+    Jump,
+    Tags,
+    TagEditor,
 }
 
+/// How long typing in the search box must pause before the database is searched.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
 pub(crate) struct TuiState {
     pub(crate) search: SearchState,
+    pub(crate) passphrase: SearchState,
+    pub(crate) jump: SearchState,
+    /// Space-separated tags in the `t` popup.
+    pub(crate) tag_editor: SearchState,
+    /// The memo whose tags the popup is editing.
+    tag_editor_memo: Option<MemoId>,
     pub(crate) input: InputState,
     pub(crate) history: Vec<Memo>,
     all_history: Vec<Memo>,
+    /// Ranked database matches for the current search query.
+    search_results: Vec<Memo>,
+    /// When the edited query is next searched for; typing pushes it back, so
+    /// the database is queried once typing pauses instead of per keystroke.
+    search_due: Option<Instant>,
     pub(crate) focus: Focus,
     pub(crate) history_index: Option<usize>,
+    pub(crate) sync_summary: Option<String>,
+    /// Daily goal when the streak indicator is enabled (`[streak] show_in_tui`).
+    pub(crate) streak_goal: Option<usize>,
+    pub(crate) streak_summary: Option<String>,
+    pub(crate) memo_key: Option<MemoKey>,
+    pub(crate) tag_pane: TagPaneState,
+    /// Notebooks to cycle through with `N`.
+    pub(crate) notebooks: Vec<String>,
+    /// Notebook history is narrowed to; `None` shows every notebook.
+    pub(crate) notebook: Option<String>,
+    /// `[notebook] default`, which also shows memos in no notebook.
+    pub(crate) default_notebook: Option<String>,
+    /// Present while the stats dashboard replaces the main view.
+    pub(crate) stats: Option<Stats>,
+    pub(crate) toasts: ToastQueue,
+    pub(crate) keymap: Keymap,
+    /// Renders history timestamps relative to now (`[tui] relative_time`).
+    pub(crate) relative_time: bool,
+    /// How history lines are shortened (`[list]` ellipsis and newline settings).
+    pub(crate) line_style: LineStyle,
+    /// Marks memos with unsynced changes; on while signed in to a sync backend.
+    pub(crate) show_sync: bool,
+    /// Present when `[tui] spellcheck` is on.
+    pub(crate) dictionary: Option<Dictionary>,
+    /// Where F7 last put a suggestion, so pressing it again moves to the next.
+    spell_cycle: Option<SpellCycle>,
+}
+
+struct SpellCycle {
+    line: usize,
+    start: usize,
+    /// The suggestions followed by the word as typed.
+    words: Vec<String>,
+    index: usize,
 }
 
 impl TuiState {
-    pub(crate) fn new(history: Vec<Memo>) -> Self {
+    pub(crate) fn new(history: Vec<Memo>, keymap: Keymap) -> Self {
         let mut state = Self {
             search: SearchState::new(),
+            passphrase: SearchState::new(),
+            jump: SearchState::new(),
+            tag_editor: SearchState::new(),
+            tag_editor_memo: None,
             input: InputState::new(),
             history: Vec::new(),
-            all_history: history,
+            all_history: Vec::new(),
+            search_results: Vec::new(),
+            search_due: None,
             focus: Focus::Input,
             history_index: None,
+            sync_summary: None,
+            streak_goal: None,
+            streak_summary: None,
+            memo_key: None,
+            tag_pane: TagPaneState::new(),
+            notebooks: Vec::new(),
+            notebook: None,
+            default_notebook: None,
+            stats: None,
+            toasts: ToastQueue::new(),
+            keymap,
+            relative_time: false,
+            line_style: LineStyle::default(),
+            show_sync: false,
+            dictionary: None,
+            spell_cycle: None,
         };
-        state.apply_search();
+        state.set_history(history);
         state
     }
 
     pub(crate) fn toggle_focus(&mut self) {
         self.focus = match self.focus {
-            Focus::Search => Focus::History,
-            Focus::History => Focus::Input,
+            Focus::Search | Focus::Unlock | Focus::Jump | Focus::TagEditor => Focus::History,
+            Focus::History if self.tag_pane.visible => Focus::Tags,
+            Focus::History | Focus::Tags => Focus::Input,
             Focus::Input => Focus::History,
         };
     }
@@ -47,26 +143,286 @@ impl TuiState {
         self.apply_search();
     }
 
-    pub(crate) fn set_history(&mut self, history: Vec<Memo>) {
+    /// Applies a search given on the command line and focuses history, so the
+    /// results can be browsed right away. Run the search afterwards.
+    pub(crate) fn start_with(&mut self, start: StartQuery) {
+        self.search.query = start.text;
+        self.tag_pane.active = start.tag;
+        if start.notebook.is_some() {
+            self.notebook = start.notebook;
+        }
+        self.focus = Focus::History;
+    }
+
+    pub(crate) fn activate_unlock(&mut self) {
+        self.focus = Focus::Unlock;
+        self.passphrase.clear();
+    }
+
+    pub(crate) fn activate_jump(&mut self) {
+        self.focus = Focus::Jump;
+        self.jump.clear();
+    }
+
+    pub(crate) fn selected_memo(&self) -> Option<&Memo> {
+        self.history_index.and_then(|index| self.history.get(index))
+    }
+
+    /// Opens the tag popup on `memo`, prefilled with its current tags.
+    pub(crate) fn open_tag_editor(&mut self, memo: &Memo) {
+        self.tag_editor.query = memo.tags.iter().map(|tag| format!("{} ", tag)).collect();
+        self.tag_editor_memo = Some(memo.memo_id.clone());
+        self.focus = Focus::TagEditor;
+    }
+
+    /// Closes the tag popup, returning the memo it was editing.
+    pub(crate) fn close_tag_editor(&mut self) -> Option<MemoId> {
+        self.focus = Focus::History;
+        self.tag_editor.clear();
+        self.tag_editor_memo.take()
+    }
+
+    /// The tags typed into the popup, normalized and without repeats.
+    pub(crate) fn edited_tags(&self) -> Vec<String> {
+        let mut edited = Vec::new();
+        for tag in self
+            .tag_editor
+            .query
+            .split_whitespace()
+            .map(tags::normalize_tag)
+        {
+            if !tag.is_empty() && !edited.contains(&tag) {
+                edited.push(tag);
+            }
+        }
+        edited
+    }
+
+    /// Existing tags, most used first, that start with the word being typed
+    /// and aren't in the popup yet.
+    pub(crate) fn tag_suggestions(&self) -> Vec<&str> {
+        let query = &self.tag_editor.query;
+        let partial = if query.ends_with(char::is_whitespace) {
+            String::new()
+        } else {
+            query
+                .split_whitespace()
+                .next_back()
+                .map(tags::normalize_tag)
+                .unwrap_or_default()
+        };
+        let edited = self.edited_tags();
+        self.tag_pane
+            .tags
+            .iter()
+            .map(|(tag, _)| tag.as_str())
+            .filter(|tag| tag.starts_with(&partial) && !edited.iter().any(|e| e == tag))
+            .take(TAG_SUGGESTIONS)
+            .collect()
+    }
+
+    /// Replaces the word being typed with the first suggestion.
+    pub(crate) fn complete_tag(&mut self) {
+        let Some(suggestion) = self.tag_suggestions().first().map(|tag| tag.to_string()) else {
+            return;
+        };
+        let query = &mut self.tag_editor.query;
+        let kept = query.trim_end_matches(|ch: char| !ch.is_whitespace()).len();
+        query.truncate(kept);
+        query.push_str(&suggestion);
+        query.push(' ');
+    }
+
+    /// Selects the memo with this id if it is still visible.
+    pub(crate) fn select_memo(&mut self, memo_id: &MemoId) {
+        if let Some(index) = self
+            .history
+            .iter()
+            .position(|memo| memo.memo_id.as_str() == memo_id.as_str())
+        {
+            self.history_index = Some(index);
+        }
+    }
+
+    /// Selects the newest visible memo created on or before `date`, or the
+    /// oldest one when everything is newer.
+    pub(crate) fn jump_to_date(&mut self, date: NaiveDate) {
+        if self.history.is_empty() {
+            return;
+        }
+        let index = self
+            .history
+            .iter()
+            .position(|memo| {
+                DateTime::parse_from_rfc3339(&memo.created_at)
+                    .map(|timestamp| timestamp.with_timezone(&Local).date_naive() <= date)
+                    .unwrap_or(false)
+            })
+            .unwrap_or(self.history.len() - 1);
+        self.history_index = Some(index);
+    }
+
+    pub(crate) fn set_history(&mut self, mut history: Vec<Memo>) {
+        crypto::reveal_memos(&mut history, self.memo_key.as_ref());
         self.all_history = history;
         self.apply_search();
     }
 
+    pub(crate) fn set_tag_counts(&mut self, counts: Vec<(String, usize)>) {
+        self.tag_pane.tags = counts;
+        self.tag_pane.index = self.tag_pane.index.min(self.tag_pane.tags.len());
+        if let Some(active) = &self.tag_pane.active
+            && !self.tag_pane.tags.iter().any(|(tag, _)| tag == active)
+        {
+            self.tag_pane.active = None;
+            self.apply_search();
+        }
+    }
+
+    pub(crate) fn toggle_tag_pane(&mut self) {
+        self.tag_pane.visible = !self.tag_pane.visible;
+        if self.tag_pane.visible {
+            self.focus = Focus::Tags;
+        } else if matches!(self.focus, Focus::Tags) {
+            self.focus = Focus::History;
+        }
+    }
+
+    /// Filters history by the highlighted sidebar entry; the "All" row clears the filter.
+    pub(crate) fn select_highlighted_tag(&mut self) {
+        self.tag_pane.active = self.tag_pane.highlighted_tag().map(str::to_string);
+        self.apply_search();
+    }
+
+    pub(crate) fn set_notebooks(&mut self, notebooks: Vec<String>) {
+        self.notebooks = notebooks;
+    }
+
+    /// Steps to the next notebook, wrapping through "all notebooks".
+    pub(crate) fn cycle_notebook(&mut self) {
+        let next = match &self.notebook {
+            None => self.notebooks.first(),
+            Some(current) => self
+                .notebooks
+                .iter()
+                .position(|name| name == current)
+                .and_then(|idx| self.notebooks.get(idx + 1)),
+        };
+        self.notebook = next.cloned();
+        self.apply_search();
+    }
+
+    /// Replaces the word at the cursor with its next spelling suggestion.
+    /// Returns false when spellcheck is off or there is nothing to suggest.
+    pub(crate) fn cycle_spelling(&mut self) -> bool {
+        let Some(dictionary) = &self.dictionary else {
+            return false;
+        };
+        let Some((line, range)) = self.input.word_at_cursor() else {
+            return false;
+        };
+        let word = &self.input.lines[line][range.clone()];
+        let cycle = match self.spell_cycle.take() {
+            Some(cycle)
+                if (cycle.line, cycle.start) == (line, range.start)
+                    && cycle.words[cycle.index] == word =>
+            {
+                SpellCycle {
+                    index: (cycle.index + 1) % cycle.words.len(),
+                    ..cycle
+                }
+            }
+            _ => {
+                if dictionary.misspelled(word).is_empty() {
+                    return false;
+                }
+                let mut words = dictionary.suggestions(word);
+                if words.is_empty() {
+                    return false;
+                }
+                words.push(word.to_string());
+                SpellCycle {
+                    line,
+                    start: range.start,
+                    words,
+                    index: 0,
+                }
+            }
+        };
+        self.input
+            .replace_word(line, range, &cycle.words[cycle.index]);
+        self.spell_cycle = Some(cycle);
+        true
+    }
+
+    pub(crate) fn notify(&mut self, message: impl Into<String>) {
+        self.toasts.push(message);
+    }
+
+    pub(crate) fn show_stats(&mut self, stats: Stats) {
+        self.stats = Some(stats);
+        self.focus = Focus::History;
+    }
+
+    pub(crate) fn hide_stats(&mut self) {
+        self.stats = None;
+    }
+
+    pub(crate) fn set_sync_summary(&mut self, summary: String) {
+        self.sync_summary = Some(summary);
+    }
+
+    /// Asks for the query to be searched for once `SEARCH_DEBOUNCE` passes
+    /// without further edits.
+    pub(crate) fn schedule_search(&mut self, now: Instant) {
+        self.search_due = Some(now + SEARCH_DEBOUNCE);
+    }
+
+    pub(crate) fn search_due(&self) -> Option<Instant> {
+        self.search_due
+    }
+
+    pub(crate) fn search_is_due(&self, now: Instant) -> bool {
+        self.search_due.is_some_and(|due| due <= now)
+    }
+
+    pub(crate) fn set_search_results(&mut self, mut results: Vec<Memo>) {
+        self.search_due = None;
+        crypto::reveal_memos(&mut results, self.memo_key.as_ref());
+        self.search_results = results;
+        self.apply_search();
+    }
+
+    /// Shows the database matches for the query, followed by unlocked private memos
+    /// (which are never indexed) that match in memory.
     pub(crate) fn apply_search(&mut self) {
-        if self.search.query.is_empty() {
-            self.history = self.all_history.clone();
+        let needle = self.search.query.trim().to_lowercase();
+        let active_tag = self.tag_pane.active.as_deref();
+        let notebook = self.notebook.as_deref();
+        let unfiled = notebook.is_some() && notebook == self.default_notebook.as_deref();
+        let candidates: Vec<&Memo> = if needle.is_empty() {
+            self.all_history.iter().collect()
         } else {
-            let needle = self.search.query.to_lowercase();
-            self.history = self
-                .all_history
+            let unlocked_private = self.all_history.iter().filter(|memo| {
+                memo.private
+                    && self.memo_key.is_some()
+                    && memo.content.to_lowercase().contains(&needle)
+            });
+            self.search_results
                 .iter()
-                .filter(|memo| {
-                    memo.content.to_lowercase().contains(&needle)
-                        || memo.created_at.to_lowercase().contains(&needle)
-                })
-                .cloned()
-                .collect();
-        }
+                .filter(|memo| !memo.private)
+                .chain(unlocked_private)
+                .collect()
+        };
+        self.history = candidates
+            .into_iter()
+            .filter(|memo| active_tag.is_none_or(|tag| memo.tags.iter().any(|t| t == tag)))
+            .filter(|memo| {
+                notebook.is_none_or(|name| memo.notebook.as_deref() == Some(name))
+                    || (unfiled && memo.notebook.is_none())
+            })
+            .cloned()
+            .collect();
         self.history_index = self.first_history_index();
     }
 
@@ -91,10 +447,18 @@ impl TuiState {
         }
     }
 
+    pub(crate) fn history_total(&self) -> usize {
+        self.all_history.len()
+    }
+
     pub(crate) fn is_search_visible(&self) -> bool {
         matches!(self.focus, Focus::Search) || !self.search.query.is_empty()
     }
 
+    pub(crate) fn is_prompt_visible(&self) -> bool {
+        self.is_search_visible() || matches!(self.focus, Focus::Unlock | Focus::Jump)
+    }
+
     fn first_history_index(&self) -> Option<usize> {
         if self.history.is_empty() {
             None
@@ -104,6 +468,42 @@ impl TuiState {
     }
 }
 
+pub(crate) struct TagPaneState {
+    pub(crate) visible: bool,
+    pub(crate) tags: Vec<(String, usize)>,
+    /// Highlighted row; 0 is the "All" entry and `n` is `tags[n - 1]`.
+    pub(crate) index: usize,
+    pub(crate) active: Option<String>,
+}
+
+impl TagPaneState {
+    fn new() -> Self {
+        Self {
+            visible: false,
+            tags: Vec::new(),
+            index: 0,
+            active: None,
+        }
+    }
+
+    pub(crate) fn move_up(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    pub(crate) fn move_down(&mut self) {
+        if self.index < self.tags.len() {
+            self.index += 1;
+        }
+    }
+
+    fn highlighted_tag(&self) -> Option<&str> {
+        self.index
+            .checked_sub(1)
+            .and_then(|idx| self.tags.get(idx))
+            .map(|(tag, _)| tag.as_str())
+    }
+}
+
 pub(crate) struct SearchState {
     pub(crate) query: String,
 }
@@ -120,7 +520,9 @@ impl SearchState {
     }
 
     pub(crate) fn backspace(&mut self) {
-        self.query.pop();
+        if let Some((idx, _)) = self.query.grapheme_indices(true).next_back() {
+            self.query.truncate(idx);
+        }
     }
 
     pub(crate) fn clear(&mut self) {
@@ -135,40 +537,210 @@ impl SearchState {
 
 pub(crate) struct InputState {
     pub(crate) lines: Vec<String>,
-    pub(crate) status: Option<String>,
+    pub(crate) attachment_confirm_pending: bool,
     cursor: InputCursor,
+    /// Text width of the input box at the last draw, so Up/Down can move
+    /// through the rows long lines wrap into.
+    wrap_width: Cell<usize>,
+    /// `(line, col)` where the selection started; the cursor is its other end.
+    anchor: Option<(usize, usize)>,
+    /// Text last copied or cut, for Ctrl+V.
+    clipboard: String,
+    /// `[tui] snippets` from the config: abbreviation to template.
+    pub(crate) snippets: HashMap<String, String>,
+}
+
+/// One row of the input as drawn: `text` starts at grapheme `col` of `line`.
+pub(crate) struct VisualRow<'a> {
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+    pub(crate) text: &'a str,
 }
 
 impl InputState {
     fn new() -> Self {
         Self {
             lines: vec![String::new()],
-            status: None,
+            attachment_confirm_pending: false,
             cursor: InputCursor::new(),
+            wrap_width: Cell::new(usize::MAX),
+            anchor: None,
+            clipboard: String::new(),
+            snippets: HashMap::new(),
+        }
+    }
+
+    /// Starts a selection at the cursor, or drops it, before the cursor moves.
+    pub(crate) fn set_selecting(&mut self, selecting: bool) {
+        if !selecting {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.ensure_invariants();
+            self.anchor = Some((self.cursor.line, self.cursor.col));
         }
     }
 
+    pub(crate) fn select_all(&mut self) {
+        self.anchor = Some((0, 0));
+        self.cursor.line = self.lines.len().saturating_sub(1);
+        self.cursor.col = self.current_line_len();
+        self.cursor.preferred_col = None;
+    }
+
+    pub(crate) fn has_selection(&self) -> bool {
+        self.selection().is_some()
+    }
+
+    /// Selected columns of `line`, for highlighting.
+    pub(crate) fn selected_cols(&self, line: usize) -> Option<Range<usize>> {
+        let ((start_line, start_col), (end_line, end_col)) = self.selection()?;
+        if line < start_line || line > end_line {
+            return None;
+        }
+        let from = if line == start_line { start_col } else { 0 };
+        let to = if line == end_line {
+            end_col
+        } else {
+            usize::MAX
+        };
+        Some(from..to)
+    }
+
+    /// Copies the selection to the internal clipboard and returns it.
+    pub(crate) fn copy(&mut self) -> Option<String> {
+        let text = self.selected_text()?;
+        self.clipboard = text.clone();
+        Some(text)
+    }
+
+    /// Copies the selection, then removes it.
+    pub(crate) fn cut(&mut self) -> Option<String> {
+        let text = self.copy()?;
+        self.delete_selection();
+        Some(text)
+    }
+
+    /// Inserts the last copied or cut text over the selection.
+    pub(crate) fn paste(&mut self) {
+        let text = self.clipboard.clone();
+        self.insert_str(&text);
+    }
+
+    /// Start and end of the selection in text order; `None` when nothing is selected.
+    fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor?;
+        let cursor = (self.cursor.line, self.cursor.col);
+        match anchor.cmp(&cursor) {
+            std::cmp::Ordering::Less => Some((anchor, cursor)),
+            std::cmp::Ordering::Greater => Some((cursor, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn selected_text(&self) -> Option<String> {
+        let ((start_line, start_col), (end_line, end_col)) = self.selection()?;
+        let mut parts = Vec::new();
+        for line in start_line..=end_line {
+            let text = &self.lines[line];
+            let from = if line == start_line {
+                byte_index_at_grapheme(text, start_col)
+            } else {
+                0
+            };
+            let to = if line == end_line {
+                byte_index_at_grapheme(text, end_col)
+            } else {
+                text.len()
+            };
+            parts.push(&text[from..to]);
+        }
+        Some(parts.join("\n"))
+    }
+
+    /// Removes the selected text, leaving the cursor where it began. Returns
+    /// whether there was anything to remove.
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        let Some(((start_line, start_col), (end_line, end_col))) = selection else {
+            return false;
+        };
+        let end = &self.lines[end_line];
+        let tail = end[byte_index_at_grapheme(end, end_col)..].to_string();
+        let start = &mut self.lines[start_line];
+        start.truncate(byte_index_at_grapheme(start, start_col));
+        start.push_str(&tail);
+        self.lines.drain(start_line + 1..=end_line);
+        self.cursor.line = start_line;
+        self.cursor.col = start_col;
+        self.reset_edit_state();
+        true
+    }
+
     pub(crate) fn insert_char(&mut self, ch: char) {
+        if ch == ' ' && self.expand_snippet() {
+            return;
+        }
+        self.delete_selection();
         self.ensure_invariants();
         let line = &mut self.lines[self.cursor.line];
-        let byte_index = byte_index_at_char(line, self.cursor.col);
+        let byte_index = byte_index_at_grapheme(line, self.cursor.col);
         line.insert(byte_index, ch);
-        self.cursor.col = self.cursor.col.saturating_add(1);
+        // A combining mark or ZWJ may join the previous cluster instead of adding one.
+        self.cursor.col = grapheme_index_at_byte(line, byte_index + ch.len_utf8());
+        self.reset_edit_state();
+    }
+
+    /// Replaces the abbreviation just before the cursor with its snippet.
+    /// Returns false, changing nothing, when the word there isn't one.
+    pub(crate) fn expand_snippet(&mut self) -> bool {
+        if self.has_selection() || self.snippets.is_empty() {
+            return false;
+        }
+        self.ensure_invariants();
+        let line = &self.lines[self.cursor.line];
+        let end = byte_index_at_grapheme(line, self.cursor.col);
+        let start = line[..end]
+            .trim_end_matches(|ch: char| !ch.is_whitespace())
+            .len();
+        let Some(template) = self.snippets.get(&line[start..end]) else {
+            return false;
+        };
+        let expansion = expand_placeholders(template, Local::now());
+        let start_col = grapheme_index_at_byte(line, start);
+        self.lines[self.cursor.line].replace_range(start..end, "");
+        self.cursor.col = start_col;
+        self.insert_str(&expansion);
+        true
+    }
+
+    /// Inserts pasted or IME-committed text, splitting it into lines.
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        self.delete_selection();
+        let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
+        for (idx, segment) in normalized.split('\n').enumerate() {
+            if idx > 0 {
+                self.newline();
+            }
+            self.ensure_invariants();
+            let line = &mut self.lines[self.cursor.line];
+            let byte_index = byte_index_at_grapheme(line, self.cursor.col);
+            line.insert_str(byte_index, segment);
+            self.cursor.col = grapheme_index_at_byte(line, byte_index + segment.len());
+        }
         self.reset_edit_state();
     }
 
     pub(crate) fn backspace(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
         self.ensure_invariants();
         if self.cursor.col > 0 {
             let line = &mut self.lines[self.cursor.line];
-            let remove_at = byte_index_at_char(line, self.cursor.col.saturating_sub(1));
-            if let Some((byte_len, _)) = line[remove_at..]
-                .chars()
-                .next()
-                .map(|ch| (ch.len_utf8(), ch))
-            {
-                line.replace_range(remove_at..remove_at + byte_len, "");
-            }
+            let remove_from = byte_index_at_grapheme(line, self.cursor.col.saturating_sub(1));
+            let remove_to = byte_index_at_grapheme(line, self.cursor.col);
+            line.replace_range(remove_from..remove_to, "");
             self.cursor.col = self.cursor.col.saturating_sub(1);
             self.reset_edit_state();
             return;
@@ -177,7 +749,7 @@ impl InputState {
             let current_line = self.lines.remove(self.cursor.line);
             self.cursor.line = self.cursor.line.saturating_sub(1);
             let line = &mut self.lines[self.cursor.line];
-            let prev_len = line.chars().count();
+            let prev_len = line.graphemes(true).count();
             line.push_str(&current_line);
             self.cursor.col = prev_len;
             self.reset_edit_state();
@@ -185,18 +757,16 @@ impl InputState {
     }
 
     pub(crate) fn delete_char(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
         self.ensure_invariants();
         let line_len = self.current_line_len();
         if self.cursor.col < line_len {
             let line = &mut self.lines[self.cursor.line];
-            let remove_at = byte_index_at_char(line, self.cursor.col);
-            if let Some((byte_len, _)) = line[remove_at..]
-                .chars()
-                .next()
-                .map(|ch| (ch.len_utf8(), ch))
-            {
-                line.replace_range(remove_at..remove_at + byte_len, "");
-            }
+            let remove_from = byte_index_at_grapheme(line, self.cursor.col);
+            let remove_to = byte_index_at_grapheme(line, self.cursor.col + 1);
+            line.replace_range(remove_from..remove_to, "");
             self.reset_edit_state();
             return;
         }
@@ -208,9 +778,10 @@ impl InputState {
     }
 
     pub(crate) fn newline(&mut self) {
+        self.delete_selection();
         self.ensure_invariants();
         let line = &mut self.lines[self.cursor.line];
-        let split_at = byte_index_at_char(line, self.cursor.col);
+        let split_at = byte_index_at_grapheme(line, self.cursor.col);
         let tail = line[split_at..].to_string();
         line.truncate(split_at);
         let insert_at = self.cursor.line + 1;
@@ -220,19 +791,70 @@ impl InputState {
         self.reset_edit_state();
     }
 
+    /// Enter in the editor: the new line keeps the current line's indentation
+    /// and list marker, numbered items count up, and checklist items start
+    /// unchecked. Enter on an item with no text removes its marker instead.
+    pub(crate) fn newline_continuing_list(&mut self) {
+        self.delete_selection();
+        self.ensure_invariants();
+        let line = &self.lines[self.cursor.line];
+        let (indent, marker) = line_prefix(line);
+        let prefix_len = indent.len() + marker.len();
+        if !marker.is_empty() && line[prefix_len..].trim().is_empty() {
+            let indent = indent.to_string();
+            self.lines[self.cursor.line] = indent;
+            self.cursor.col = self.current_line_len();
+            self.reset_edit_state();
+            return;
+        }
+        let continued = format!("{}{}", indent, next_marker(marker));
+        // Splitting inside the prefix itself would duplicate it.
+        if byte_index_at_grapheme(line, self.cursor.col) < prefix_len {
+            self.newline();
+            return;
+        }
+        self.newline();
+        self.lines[self.cursor.line].insert_str(0, &continued);
+        self.cursor.col = continued.graphemes(true).count();
+    }
+
     pub(crate) fn clear(&mut self) {
         self.lines.clear();
         self.lines.push(String::new());
         self.cursor = InputCursor::new();
-        self.status = None;
+        self.anchor = None;
+        self.attachment_confirm_pending = false;
     }
 
     pub(crate) fn text(&self) -> String {
         self.lines.join("\n")
     }
 
+    /// The input as drawn in a box `width` columns wide. Lines are wrapped here
+    /// rather than by the widget so the rows match what the cursor moves through.
+    pub(crate) fn visual_rows(&self, width: usize) -> Vec<VisualRow<'_>> {
+        let mut rows = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            let starts = row_starts(line, width);
+            for (row, col) in starts.iter().enumerate() {
+                let from = byte_index_at_grapheme(line, *col);
+                let to = starts
+                    .get(row + 1)
+                    .map(|next| byte_index_at_grapheme(line, *next))
+                    .unwrap_or(line.len());
+                rows.push(VisualRow {
+                    line: index,
+                    col: *col,
+                    text: &line[from..to],
+                });
+            }
+        }
+        rows
+    }
+
     pub(crate) fn cursor_position(&self, area: Rect) -> (u16, u16) {
         let content_width = area.width.saturating_sub(2).max(1) as usize;
+        self.wrap_width.set(content_width);
         let (row, col) = wrapped_cursor_position(&self.lines, &self.cursor, content_width);
         (area.x + col as u16 + 1, area.y + row as u16 + 1)
     }
@@ -264,26 +886,62 @@ impl InputState {
         self.cursor.preferred_col = None;
     }
 
+    /// Moves to the visual row above, which may be part of the same wrapped line.
     pub(crate) fn move_up(&mut self) {
         self.ensure_invariants();
-        if self.cursor.line == 0 {
+        let width = self.wrap_width.get();
+        let (row, x) = row_and_x(&self.lines[self.cursor.line], self.cursor.col, width);
+        let (line, row) = if row > 0 {
+            (self.cursor.line, row - 1)
+        } else if self.cursor.line > 0 {
+            let line = self.cursor.line - 1;
+            (line, row_starts(&self.lines[line], width).len() - 1)
+        } else {
             return;
-        }
-        let target_col = self.cursor.preferred_col.unwrap_or(self.cursor.col);
-        self.cursor.line = self.cursor.line.saturating_sub(1);
-        self.cursor.col = target_col.min(self.current_line_len());
-        self.cursor.preferred_col = Some(target_col);
+        };
+        self.move_to_row(line, row, x);
     }
 
+    /// Moves to the visual row below, which may be part of the same wrapped line.
     pub(crate) fn move_down(&mut self) {
         self.ensure_invariants();
-        if self.cursor.line + 1 >= self.lines.len() {
+        let width = self.wrap_width.get();
+        let (row, x) = row_and_x(&self.lines[self.cursor.line], self.cursor.col, width);
+        let rows = row_starts(&self.lines[self.cursor.line], width).len();
+        let (line, row) = if row + 1 < rows {
+            (self.cursor.line, row + 1)
+        } else if self.cursor.line + 1 < self.lines.len() {
+            (self.cursor.line + 1, 0)
+        } else {
             return;
+        };
+        self.move_to_row(line, row, x);
+    }
+
+    /// Puts the cursor on `row` of `line` as near to display column `x` as the
+    /// row allows; `preferred_col` keeps the column across shorter rows.
+    fn move_to_row(&mut self, line: usize, row: usize, x: usize) {
+        let target_x = self.cursor.preferred_col.unwrap_or(x);
+        let text = &self.lines[line];
+        let starts = row_starts(text, self.wrap_width.get());
+        let start = starts[row];
+        // Landing on the next row's first grapheme would show the cursor there.
+        let end = starts
+            .get(row + 1)
+            .map(|next| next - 1)
+            .unwrap_or_else(|| text.graphemes(true).count());
+        let mut col = start;
+        let mut width = 0;
+        for grapheme in text.graphemes(true).skip(start).take(end - start) {
+            width += grapheme.width();
+            if width > target_x {
+                break;
+            }
+            col += 1;
         }
-        let target_col = self.cursor.preferred_col.unwrap_or(self.cursor.col);
-        self.cursor.line = self.cursor.line.saturating_add(1);
-        self.cursor.col = target_col.min(self.current_line_len());
-        self.cursor.preferred_col = Some(target_col);
+        self.cursor.line = line;
+        self.cursor.col = col;
+        self.cursor.preferred_col = Some(target_x);
     }
 
     fn ensure_invariants(&mut self) {
@@ -302,16 +960,41 @@ impl InputState {
     fn current_line_len(&self) -> usize {
         self.lines
             .get(self.cursor.line)
-            .map(|line| line.chars().count())
+            .map(|line| line.graphemes(true).count())
             .unwrap_or(0)
     }
 
     fn reset_edit_state(&mut self) {
         self.cursor.preferred_col = None;
-        self.status = None;
+        self.attachment_confirm_pending = false;
+    }
+
+    /// The cursor's line and the byte range in it of the word the cursor is
+    /// in or just after.
+    pub(crate) fn word_at_cursor(&self) -> Option<(usize, Range<usize>)> {
+        let line = self.lines.get(self.cursor.line)?;
+        let cursor = byte_index_at_grapheme(line, self.cursor.col);
+        line.split_word_bound_indices()
+            .find(|(start, word)| {
+                (*start..=start + word.len()).contains(&cursor)
+                    && word.chars().any(char::is_alphabetic)
+            })
+            .map(|(start, word)| (self.cursor.line, start..start + word.len()))
+    }
+
+    /// Swaps bytes `range` of `line` for `word` and puts the cursor after it.
+    pub(crate) fn replace_word(&mut self, line: usize, range: Range<usize>, word: &str) {
+        self.anchor = None;
+        let text = &mut self.lines[line];
+        text.replace_range(range.clone(), word);
+        self.cursor.line = line;
+        self.cursor.col = grapheme_index_at_byte(text, range.start + word.len());
+        self.reset_edit_state();
     }
 }
 
+/// `col` counts grapheme clusters, not chars or bytes; `preferred_col` is the
+/// display column Up/Down aim for.
 struct InputCursor {
     line: usize,
     col: usize,
@@ -328,49 +1011,308 @@ impl InputCursor {
     }
 }
 
-fn byte_index_at_char(value: &str, char_index: usize) -> usize {
-    if char_index == 0 {
-        return 0;
-    }
+fn byte_index_at_grapheme(value: &str, grapheme_index: usize) -> usize {
     value
-        .char_indices()
-        .nth(char_index)
+        .grapheme_indices(true)
+        .nth(grapheme_index)
         .map(|(idx, _)| idx)
-        .unwrap_or_else(|| value.len())
+        .unwrap_or(value.len())
 }
 
-fn width_up_to_char(value: &str, char_index: usize) -> usize {
+fn grapheme_index_at_byte(value: &str, byte_index: usize) -> usize {
     value
-        .chars()
-        .take(char_index)
-        .map(|ch| UnicodeWidthChar::width(ch).unwrap_or(0))
+        .grapheme_indices(true)
+        .take_while(|(idx, _)| *idx < byte_index)
+        .count()
+}
+
+fn width_up_to_grapheme(value: &str, grapheme_index: usize) -> usize {
+    value
+        .graphemes(true)
+        .take(grapheme_index)
+        .map(UnicodeWidthStr::width)
         .sum()
 }
 
+/// Fills in a snippet's `{date}` and `{time}`.
+fn expand_placeholders(template: &str, now: DateTime<Local>) -> String {
+    template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H:%M").to_string())
+}
+
+/// Splits a line's leading whitespace and list marker (`- `, `* `, `+ `,
+/// `1. `, `- [ ] `) from its text; the marker is empty for other lines.
+fn line_prefix(line: &str) -> (&str, &str) {
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    for checkbox in ["- [ ] ", "- [x] ", "- [X] ", "* [ ] ", "* [x] ", "* [X] "] {
+        if body.starts_with(checkbox) {
+            return (indent, &body[..checkbox.len()]);
+        }
+    }
+    if ["- ", "* ", "+ "]
+        .iter()
+        .any(|bullet| body.starts_with(bullet))
+    {
+        return (indent, &body[..2]);
+    }
+    let digits = body.len()
+        - body
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    if digits > 0 && body[digits..].starts_with(". ") {
+        return (indent, &body[..digits + 2]);
+    }
+    (indent, "")
+}
+
+/// The marker for the item after one marked `marker`.
+fn next_marker(marker: &str) -> String {
+    if let Some(number) = marker
+        .strip_suffix(". ")
+        .and_then(|number| number.parse::<u64>().ok())
+    {
+        return format!("{}. ", number + 1);
+    }
+    // Checked boxes continue as unchecked ones.
+    marker.replace("[x]", "[ ]").replace("[X]", "[ ]")
+}
+
+/// Grapheme index where each visual row of `line` starts when wrapped at
+/// `width` columns. Rows break after the last space that fits, or mid-word
+/// when a word is wider than the box; a grapheme is never split.
+fn row_starts(line: &str, width: usize) -> Vec<usize> {
+    let widths: Vec<usize> = line.graphemes(true).map(UnicodeWidthStr::width).collect();
+    let spaces: Vec<bool> = line
+        .graphemes(true)
+        .map(|grapheme| grapheme.chars().all(char::is_whitespace))
+        .collect();
+    let mut starts = vec![0];
+    let mut row_start = 0;
+    let mut row_width = 0;
+    for index in 0..widths.len() {
+        if row_width > 0 && row_width + widths[index] > width && !spaces[index] {
+            let word_start = (row_start + 1..=index)
+                .rev()
+                .find(|&i| spaces[i - 1] && !spaces[i])
+                .unwrap_or(index);
+            row_start = word_start;
+            row_width = widths[word_start..index].iter().sum();
+            starts.push(row_start);
+        }
+        row_width += widths[index];
+    }
+    starts
+}
+
+/// The visual row within `line` and the display column a cursor before
+/// grapheme `col` sits at. A cursor after a full last row wraps onto a row of its own.
+fn row_and_x(line: &str, col: usize, width: usize) -> (usize, usize) {
+    let starts = row_starts(line, width);
+    let row = starts.iter().rposition(|start| *start <= col).unwrap_or(0);
+    let x = width_up_to_grapheme(line, col) - width_up_to_grapheme(line, starts[row]);
+    if x >= width { (row + 1, 0) } else { (row, x) }
+}
+
 fn wrapped_cursor_position(
     lines: &[String],
     cursor: &InputCursor,
     content_width: usize,
 ) -> (usize, usize) {
-    let mut rows_before = 0usize;
     let cursor_line = cursor.line.min(lines.len().saturating_sub(1));
-    for line in lines.iter().take(cursor_line) {
-        let line_width = UnicodeWidthStr::width(line.as_str());
-        let wrapped_rows = if line_width == 0 {
-            0
-        } else {
-            (line_width - 1) / content_width
-        };
-        rows_before += wrapped_rows + 1;
+    let rows_before: usize = lines
+        .iter()
+        .take(cursor_line)
+        .map(|line| row_starts(line, content_width).len())
+        .sum();
+    let line = lines.get(cursor_line).map(String::as_str).unwrap_or("");
+    let cursor_col = cursor.col.min(line.graphemes(true).count());
+    let (row, col) = row_and_x(line, cursor_col, content_width);
+    (rows_before + row, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn input(text: &str, width: usize) -> InputState {
+        let mut input = InputState::new();
+        input.insert_str(text);
+        input.wrap_width.set(width);
+        input
     }
 
-    let line = lines.get(cursor_line).map(String::as_str).unwrap_or("");
-    let cursor_col = cursor.col.min(line.chars().count());
-    let prefix_width = width_up_to_char(line, cursor_col);
-    let row_in_line = prefix_width / content_width;
-    let col_in_line = prefix_width % content_width;
-    let row = rows_before.saturating_add(row_in_line);
-    let col = col_in_line;
-
-    (row, col)
+    fn position(input: &InputState) -> (usize, usize) {
+        wrapped_cursor_position(&input.lines, &input.cursor, input.wrap_width.get())
+    }
+
+    #[test]
+    fn up_and_down_step_through_wrapped_rows() {
+        // "abcdefghij" wraps into "abcd", "efgh", "ij" at width 4.
+        let mut input = input("abcdefghij\nxy", 4);
+        assert_eq!(position(&input), (3, 2));
+
+        input.move_up();
+        assert_eq!(
+            (input.cursor.line, input.cursor.col, position(&input)),
+            (0, 10, (2, 2))
+        );
+        input.move_up();
+        assert_eq!((input.cursor.col, position(&input)), (6, (1, 2)));
+        input.move_up();
+        assert_eq!((input.cursor.col, position(&input)), (2, (0, 2)));
+        input.move_up();
+        assert_eq!(input.cursor.col, 2);
+
+        input.move_down();
+        input.move_down();
+        assert_eq!((input.cursor.line, input.cursor.col), (0, 10));
+        input.move_down();
+        assert_eq!((input.cursor.line, input.cursor.col), (1, 2));
+    }
+
+    #[test]
+    fn selections_can_be_cut_and_pasted_across_lines() {
+        let mut input = input("first line\nsecond", usize::MAX);
+        input.set_selecting(true);
+        input.move_up();
+        for _ in 0..4 {
+            input.move_left();
+        }
+        assert_eq!(input.selected_cols(0), Some(2..usize::MAX));
+        assert_eq!(input.cut().as_deref(), Some("rst line\nsecond"));
+        assert_eq!(input.text(), "fi");
+        assert!(!input.has_selection());
+
+        input.paste();
+        input.paste();
+        assert_eq!(input.text(), "first line\nsecondrst line\nsecond");
+
+        input.select_all();
+        input.insert_char('x');
+        assert_eq!(input.text(), "x");
+    }
+
+    #[test]
+    fn abbreviations_expand_on_space() {
+        let mut input = input("notes ;sig", usize::MAX);
+        input.snippets = HashMap::from([(";sig".to_string(), "-- me\nbye".to_string())]);
+        input.insert_char(' ');
+        assert_eq!(input.text(), "notes -- me\nbye");
+        input.insert_char(' ');
+        assert_eq!(input.text(), "notes -- me\nbye ");
+        assert!(!input.expand_snippet());
+
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 8, 5, 0).unwrap();
+        assert_eq!(
+            expand_placeholders("Meeting {date} {time}", now),
+            "Meeting 2024-03-09 08:05"
+        );
+    }
+
+    #[test]
+    fn f7_cycles_through_suggestions_and_back() {
+        let mut state = TuiState::new(Vec::new(), Keymap::from_specs(&[]).unwrap());
+        state.input.insert_str("a frm");
+        assert!(!state.cycle_spelling());
+        state.dictionary = Some(Dictionary::from_words("a\nform\nfrom\n"));
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            assert!(state.cycle_spelling());
+            seen.push(state.input.text());
+        }
+        assert_eq!(seen, ["a form", "a from", "a frm"]);
+    }
+
+    #[test]
+    fn enter_continues_list_markers() {
+        let mut checklist = input("  - [x] milk", usize::MAX);
+        checklist.newline_continuing_list();
+        checklist.insert_str("eggs");
+        checklist.newline_continuing_list();
+        checklist.newline_continuing_list();
+        assert_eq!(checklist.text(), "  - [x] milk\n  - [ ] eggs\n  ");
+
+        let mut numbered = input("9. ninth", usize::MAX);
+        numbered.newline_continuing_list();
+        assert_eq!(numbered.text(), "9. ninth\n10. ");
+        let mut plain = input("plain", usize::MAX);
+        plain.newline_continuing_list();
+        assert_eq!(plain.text(), "plain\n");
+    }
+
+    #[test]
+    fn rows_break_between_words() {
+        let input = input("ab cd ef\nlongword", 5);
+        let rows: Vec<&str> = input.visual_rows(5).iter().map(|row| row.text).collect();
+        assert_eq!(rows, ["ab cd ", "ef", "longw", "ord"]);
+    }
+
+    #[test]
+    fn wide_characters_wrap_whole() {
+        // Three columns fit one two-column character per row.
+        assert_eq!(row_starts("日本語", 3), [0, 1, 2]);
+        assert_eq!(row_and_x("日本語", 3, 3), (2, 2));
+        assert_eq!(row_and_x("日本", 2, 2), (2, 0));
+        let mut input = input("日本語", 3);
+        input.move_up();
+        assert_eq!((input.cursor.col, position(&input)), (1, (1, 0)));
+    }
+
+    #[test]
+    fn searches_wait_for_typing_to_pause() {
+        let mut state = TuiState::new(Vec::new(), Keymap::from_specs(&[]).unwrap());
+        let start = Instant::now();
+        assert!(!state.search_is_due(start));
+
+        state.schedule_search(start);
+        state.schedule_search(start + Duration::from_millis(100));
+        assert!(!state.search_is_due(start + SEARCH_DEBOUNCE));
+        assert!(state.search_is_due(start + Duration::from_millis(100) + SEARCH_DEBOUNCE));
+
+        state.set_search_results(Vec::new());
+        assert_eq!(state.search_due(), None);
+    }
+
+    #[test]
+    fn the_default_notebook_shows_memos_in_no_notebook() {
+        let memo = |content: &str, notebook: Option<&str>| Memo {
+            memo_id: content.to_string().into(),
+            content: content.to_string(),
+            created_at: "2024-05-01T09:00:00+00:00".to_string(),
+            updated_at: "2024-05-01T09:00:00+00:00".to_string(),
+            private: false,
+            tags: Vec::new(),
+            timezone: None,
+            notebook: notebook.map(str::to_string),
+            location: None,
+            mood: None,
+            dirty: false,
+        };
+        let mut state = TuiState::new(
+            vec![
+                memo("unfiled", None),
+                memo("work", Some("work")),
+                memo("home", Some("home")),
+            ],
+            Keymap::from_specs(&[]).unwrap(),
+        );
+        let shown = |state: &TuiState| -> Vec<String> {
+            state
+                .history
+                .iter()
+                .map(|memo| memo.content.clone())
+                .collect()
+        };
+        state.default_notebook = Some("work".to_string());
+        state.notebook = Some("work".to_string());
+        state.apply_search();
+        assert_eq!(shown(&state), ["unfiled", "work"]);
+
+        state.notebook = Some("home".to_string());
+        state.apply_search();
+        assert_eq!(shown(&state), ["home"]);
+    }
 }
@@ -0,0 +1,54 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+const TOAST_TTL: Duration = Duration::from_secs(3);
+const MAX_TOASTS: usize = 3;
+
+pub(crate) struct Toast {
+    pub(crate) message: String,
+    expires_at: Instant,
+}
+
+/// Short-lived notifications shown in a corner overlay; oldest are dropped first.
+pub(crate) struct ToastQueue {
+    toasts: VecDeque<Toast>,
+}
+
+impl ToastQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            toasts: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, message: impl Into<String>) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            message: message.into(),
+            expires_at: Instant::now() + TOAST_TTL,
+        });
+    }
+
+    /// Drops expired toasts and reports whether any were removed.
+    pub(crate) fn expire(&mut self, now: Instant) -> bool {
+        let before = self.toasts.len();
+        self.toasts.retain(|toast| toast.expires_at > now);
+        self.toasts.len() != before
+    }
+
+    pub(crate) fn next_expiry(&self) -> Option<Instant> {
+        self.toasts.iter().map(|toast| toast.expires_at).min()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Toast> {
+        self.toasts.iter()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+}
@@ -1,55 +1,183 @@
+use chrono::Local;
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    text::{Line, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap,
+    },
 };
 
-use super::state::{Focus, TuiState};
-use crate::format;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::state::{Focus, TuiState, VisualRow};
+use crate::{
+    domain::{memo::Memo, mentions},
+    format::{self, LineStyle},
+    i18n::t,
+    stats::Stats,
+};
+
+const MENTION_COLOR: Color = Color::Cyan;
 
 pub(crate) fn draw_tui(frame: &mut Frame<'_>, state: &TuiState) {
-    let layout = split_layout(frame.area(), state.is_search_visible());
+    if let Some(stats) = &state.stats {
+        let [main_area, status_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .areas(frame.area());
+        draw_stats(frame, stats, main_area);
+        draw_status_bar(frame, state, status_area);
+        draw_toasts(frame, state);
+        return;
+    }
+
+    let layout = split_layout(
+        frame.area(),
+        state.is_prompt_visible(),
+        state.tag_pane.visible,
+    );
 
     draw_input(frame, state, layout.input_area);
     draw_history(frame, state, layout.history_area);
     if let Some(search_area) = layout.search_area {
-        draw_search(frame, state, search_area);
+        match state.focus {
+            Focus::Unlock => draw_unlock_prompt(frame, state, search_area),
+            Focus::Jump => draw_jump_prompt(frame, state, search_area),
+            _ => draw_search(frame, state, search_area),
+        }
     }
+    if let Some(tags_area) = layout.tags_area {
+        draw_tag_pane(frame, state, tags_area);
+    }
+    draw_status_bar(frame, state, layout.status_area);
+    if matches!(state.focus, Focus::TagEditor) {
+        draw_tag_editor(frame, state, layout.history_area);
+    }
+    draw_toasts(frame, state);
 }
 
-fn draw_input(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
-    let input_lines: Vec<Line> = state
-        .input
-        .lines
+const TOAST_MAX_WIDTH: u16 = 48;
+
+fn draw_toasts(frame: &mut Frame<'_>, state: &TuiState) {
+    if state.toasts.is_empty() {
+        return;
+    }
+    let lines: Vec<Line> = state
+        .toasts
         .iter()
-        .map(|line| Line::from(line.as_str()))
+        .map(|toast| Line::from(toast.message.as_str()))
         .collect();
-    let input_title = format_input_title(state);
-    let input_widget = Paragraph::new(Text::from(input_lines))
+    let area = frame.area();
+    let longest = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+    let width = (longest + 2).min(TOAST_MAX_WIDTH).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let toast_area = Rect::new(area.right().saturating_sub(width), area.y, width, height);
+    let toast_widget = Paragraph::new(Text::from(lines))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(input_title)
-                .border_style(focus_style(state.focus, Focus::Input)),
+                .border_style(Style::default().fg(Color::Yellow)),
         )
-        .wrap(Wrap { trim: false });
+        .wrap(Wrap { trim: true });
+    frame.render_widget(Clear, toast_area);
+    frame.render_widget(toast_widget, toast_area);
+}
+
+fn draw_input(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
+    let content_width = area.width.saturating_sub(2).max(1) as usize;
+    let input_lines: Vec<Line> = state
+        .input
+        .visual_rows(content_width)
+        .into_iter()
+        .map(|row| input_row_line(state, row))
+        .collect();
+    let input_widget = Paragraph::new(Text::from(input_lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t!("tui-input"))
+            .border_style(focus_style(state.focus, Focus::Input)),
+    );
     frame.render_widget(input_widget, area);
     if matches!(state.focus, Focus::Input) {
         frame.set_cursor_position(state.input.cursor_position(area));
     }
 }
 
+/// An input row with its selected part shown reversed, misspelled words
+/// underlined, and mentions highlighted.
+fn input_row_line<'a>(state: &TuiState, row: VisualRow<'a>) -> Line<'a> {
+    let selected = state.input.selected_cols(row.line);
+    let line = &state.input.lines[row.line];
+    let misspelled = state
+        .dictionary
+        .as_ref()
+        .map(|dictionary| dictionary.misspelled(line))
+        .unwrap_or_default();
+    let mentions = mentions::mention_ranges(line);
+    if selected.is_none() && misspelled.is_empty() && mentions.is_empty() {
+        return Line::from(row.text);
+    }
+    let row_start = line
+        .grapheme_indices(true)
+        .nth(row.col)
+        .map_or(line.len(), |(idx, _)| idx);
+
+    let mut spans: Vec<Span<'a>> = Vec::new();
+    let mut run: Option<(usize, Style)> = None;
+    for (col, (idx, _)) in row.text.grapheme_indices(true).enumerate() {
+        let mut style = Style::default();
+        if mentions
+            .iter()
+            .any(|range| range.contains(&(row_start + idx)))
+        {
+            style = style.fg(MENTION_COLOR);
+        }
+        if selected
+            .as_ref()
+            .is_some_and(|cols| cols.contains(&(row.col + col)))
+        {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        if misspelled
+            .iter()
+            .any(|range| range.contains(&(row_start + idx)))
+        {
+            style = style.add_modifier(Modifier::UNDERLINED).fg(Color::Red);
+        }
+        match run {
+            Some((_, current)) if current == style => {}
+            Some((start, current)) => {
+                spans.push(Span::styled(&row.text[start..idx], current));
+                run = Some((idx, style));
+            }
+            None => run = Some((idx, style)),
+        }
+    }
+    if let Some((start, style)) = run {
+        spans.push(Span::styled(&row.text[start..], style));
+    }
+    Line::from(spans)
+}
+
 fn draw_history(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
     let available_width = area.width.saturating_sub(2) as usize;
+    let now = Local::now();
     let history_items: Vec<ListItem> = state
         .history
         .iter()
         .map(|memo| {
-            let display_time = format::format_display_time(&memo.created_at);
-            let line = format::format_memo_line(&display_time, &memo.content, available_width);
-            ListItem::new(line)
+            let mut display_time = if state.relative_time {
+                format::format_relative_time(&memo.created_at, now)
+            } else {
+                format::format_display_time(&memo.created_at)
+            };
+            if state.show_sync {
+                display_time = format!("{}{}", memo.sync_marker(), display_time);
+            }
+            memo_item(memo, &display_time, available_width, &state.line_style)
         })
         .collect();
     let history_widget = List::new(history_items)
@@ -67,6 +195,125 @@ fn draw_history(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
     frame.render_stateful_widget(history_widget, area, &mut list_state);
 }
 
+/// One memo's line in a list: `prefix`, then its title cut to `width`.
+pub(super) fn memo_item(
+    memo: &Memo,
+    prefix: &str,
+    width: usize,
+    style: &LineStyle,
+) -> ListItem<'static> {
+    let line = format::format_memo_line(prefix, memo.title(), width, style);
+    ListItem::new(highlight_mentions(line))
+}
+
+fn highlight_mentions(line: String) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for range in mentions::mention_ranges(&line) {
+        spans.push(Span::raw(line[end..range.start].to_string()));
+        spans.push(Span::styled(
+            line[range.clone()].to_string(),
+            Style::default().fg(MENTION_COLOR),
+        ));
+        end = range.end;
+    }
+    spans.push(Span::raw(line[end..].to_string()));
+    Line::from(spans)
+}
+
+fn draw_stats(frame: &mut Frame<'_>, stats: &Stats, area: Rect) {
+    let [chart_area, details_area] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .areas(area);
+    let [summary_area, tags_area] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .areas(details_area);
+
+    let bars: Vec<Bar> = stats
+        .weekly_counts
+        .iter()
+        .map(|(week, count)| {
+            Bar::default()
+                .label(Line::from(week.as_str()))
+                .value(*count)
+        })
+        .collect();
+    let bar_width = (chart_area.width.saturating_sub(2) / stats.weekly_counts.len().max(1) as u16)
+        .saturating_sub(1)
+        .max(1);
+    let chart = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t!("tui-stats-chart")),
+        )
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(Color::Green));
+    frame.render_widget(chart, chart_area);
+
+    let summary = vec![
+        Line::from(t!("stats-memos", count = stats.total_memos)),
+        Line::from(t!("stats-current-streak", days = stats.current_streak)),
+        Line::from(t!("stats-longest-streak", days = stats.longest_streak)),
+        Line::from(t!(
+            "stats-database-size",
+            size = format::format_bytes(stats.db_size_bytes)
+        )),
+    ];
+    let summary_widget = Paragraph::new(Text::from(summary)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t!("tui-stats-summary")),
+    );
+    frame.render_widget(summary_widget, summary_area);
+
+    let tag_items: Vec<ListItem> = stats
+        .top_tags
+        .iter()
+        .map(|(tag, count)| ListItem::new(format!("#{} ({})", tag, count)))
+        .collect();
+    let tags_widget = List::new(tag_items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t!("tui-stats-top-tags")),
+    );
+    frame.render_widget(tags_widget, tags_area);
+}
+
+fn draw_tag_pane(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
+    let pane = &state.tag_pane;
+    let total: usize = state.history_total();
+    let mut items = vec![ListItem::new(tag_pane_line(
+        &t!("tui-tags-all"),
+        total,
+        pane.active.is_none(),
+    ))];
+    items.extend(pane.tags.iter().map(|(tag, count)| {
+        let is_active = pane.active.as_deref() == Some(tag.as_str());
+        ListItem::new(tag_pane_line(&format!("#{}", tag), *count, is_active))
+    }));
+    let tags_widget = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t!("tui-tags"))
+                .border_style(focus_style(state.focus, Focus::Tags)),
+        )
+        .highlight_style(focus_style(state.focus, Focus::Tags));
+    let mut list_state = ListState::default();
+    list_state.select(Some(pane.index));
+    frame.render_stateful_widget(tags_widget, area, &mut list_state);
+}
+
+fn tag_pane_line(label: &str, count: usize, is_active: bool) -> String {
+    let marker = if is_active { "*" } else { " " };
+    format!("{}{} ({})", marker, label, count)
+}
+
 fn draw_search(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
     let search_style = focus_style(state.focus, Focus::Search);
     let search_line = Line::from(format!("/{}", state.search.query));
@@ -79,16 +326,77 @@ fn draw_search(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
     }
 }
 
-fn format_input_title(state: &TuiState) -> String {
-    match state.input.status.as_deref() {
-        Some(status) => format!("Input - {}", status),
-        None => "Input".to_string(),
-    }
+fn draw_unlock_prompt(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
+    let label = format!("{} ", t!("tui-passphrase"));
+    let masked = "*".repeat(state.passphrase.query.chars().count());
+    let prompt_widget = Paragraph::new(Line::from(format!("{}{}", label, masked)))
+        .style(focus_style(state.focus, Focus::Unlock));
+    frame.render_widget(prompt_widget, area);
+    let col = (label.width() + masked.len()) as u16;
+    frame.set_cursor_position((area.x + col, area.y));
+}
+
+fn draw_jump_prompt(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
+    let prompt_widget = Paragraph::new(Line::from(format!(":{}", state.jump.query)))
+        .style(focus_style(state.focus, Focus::Jump));
+    frame.render_widget(prompt_widget, area);
+    frame.set_cursor_position(state.jump.cursor_position_inline(area));
+}
+
+const TAG_EDITOR_WIDTH: u16 = 52;
+
+/// The `t` popup, centered over history, with matching existing tags below the input.
+fn draw_tag_editor(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
+    let suggestions = state.tag_suggestions();
+    let width = TAG_EDITOR_WIDTH.min(area.width);
+    let height = (suggestions.len() as u16 + 3).min(area.height);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    let mut lines = vec![Line::from(state.tag_editor.query.as_str())];
+    lines.extend(
+        suggestions
+            .iter()
+            .map(|tag| Line::styled(format!("#{}", tag), Style::default().fg(Color::DarkGray))),
+    );
+    let editor_widget = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(t!("tui-edit-tags"))
+            .border_style(focus_style(state.focus, Focus::TagEditor)),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(editor_widget, popup);
+    let col = state.tag_editor.query.width() as u16;
+    frame.set_cursor_position((popup.x + 1 + col, popup.y + 1));
+}
+
+fn draw_status_bar(frame: &mut Frame<'_>, state: &TuiState, area: Rect) {
+    let status = [
+        state.sync_summary.as_deref(),
+        state.streak_summary.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+    let status_widget =
+        Paragraph::new(Line::from(status)).style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(status_widget, area);
 }
 
 fn history_title(state: &TuiState) -> String {
-    let _ = state;
-    "History".to_string()
+    let mut title = t!("tui-history");
+    if let Some(notebook) = &state.notebook {
+        title.push_str(&format!(" - {}", notebook));
+    }
+    if let Some(tag) = &state.tag_pane.active {
+        title.push_str(&format!(" - #{}", tag));
+    }
+    title
 }
 
 fn focus_style(current: Focus, target: Focus) -> Style {
@@ -103,9 +411,26 @@ struct LayoutAreas {
     input_area: Rect,
     history_area: Rect,
     search_area: Option<Rect>,
+    tags_area: Option<Rect>,
+    status_area: Rect,
 }
 
-fn split_layout(area: Rect, show_search: bool) -> LayoutAreas {
+const TAG_PANE_WIDTH: u16 = 24;
+
+fn split_layout(area: Rect, show_search: bool, show_tags: bool) -> LayoutAreas {
+    let [main_area, status_area] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .areas(area);
+    let (tags_area, main_area) = if show_tags {
+        let [tags_area, main_area] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(TAG_PANE_WIDTH), Constraint::Min(0)])
+            .areas(main_area);
+        (Some(tags_area), main_area)
+    } else {
+        (None, main_area)
+    };
     // Search is a single-line prompt shown beneath the history list (vim-style).
     if show_search {
         let areas = Layout::default()
@@ -115,21 +440,184 @@ fn split_layout(area: Rect, show_search: bool) -> LayoutAreas {
                 Constraint::Percentage(50),
                 Constraint::Length(1),
             ])
-            .split(area);
+            .split(main_area);
         LayoutAreas {
             input_area: areas[0],
             history_area: areas[1],
             search_area: Some(areas[2]),
+            tags_area,
+            status_area,
         }
     } else {
         let areas = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(area);
+            .split(main_area);
         LayoutAreas {
             input_area: areas[0],
             history_area: areas[1],
             search_area: None,
+            tags_area,
+            status_area,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    use super::*;
+    use crate::{spell::Dictionary, tui::keys::Keymap};
+
+    /// A memo created at `hour:00` local time, so snapshots don't depend on the zone.
+    fn memo(content: &str, hour: u32, tags: &[&str]) -> Memo {
+        let created_at = Local
+            .with_ymd_and_hms(2024, 5, 1, hour, 0, 0)
+            .single()
+            .unwrap()
+            .to_rfc3339();
+        Memo {
+            memo_id: format!("memo-{}", hour).into(),
+            content: content.to_string(),
+            created_at: created_at.clone(),
+            updated_at: created_at,
+            private: false,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            timezone: None,
+            notebook: None,
+            location: None,
+            mood: None,
+            dirty: hour > 12,
+        }
+    }
+
+    fn history() -> Vec<Memo> {
+        vec![
+            memo("ship the release notes #work", 18, &["work"]),
+            memo("lunch with Sam", 12, &[]),
+            memo("morning run 5k #health", 7, &["health"]),
+        ]
+    }
+
+    fn state(history: Vec<Memo>) -> TuiState {
+        TuiState::new(history, Keymap::from_specs(&["enter".to_string()]).unwrap())
+    }
+
+    fn render(state: &TuiState, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| draw_tui(frame, state)).unwrap();
+        terminal.backend().to_string()
+    }
+
+    #[test]
+    fn default_view() {
+        let mut state = state(history());
+        state.input.insert_str("half-written thought");
+        state.set_sync_summary("3 pending".to_string());
+        insta::assert_snapshot!(render(&state, 60, 16));
+    }
+
+    #[test]
+    fn search_visible() {
+        let mut state = state(history());
+        state.activate_search();
+        for ch in "run".chars() {
+            state.search.insert_char(ch);
         }
+        state.set_search_results(vec![memo("morning run 5k #health", 7, &["health"])]);
+        insta::assert_snapshot!(render(&state, 60, 16));
+    }
+
+    #[test]
+    fn unsynced_memos_are_marked() {
+        let mut state = state(history());
+        state.show_sync = true;
+        insta::assert_snapshot!(render(&state, 60, 12));
+    }
+
+    #[test]
+    fn misspelled_words_are_underlined() {
+        let mut state = state(Vec::new());
+        state.dictionary = Some(Dictionary::from_words("the\nquick\n"));
+        state.input.insert_str("the qiuck");
+        let line = input_row_line(&state, state.input.visual_rows(80).remove(0));
+        let underlined: Vec<&str> = line
+            .spans
+            .iter()
+            .filter(|span| span.style.add_modifier.contains(Modifier::UNDERLINED))
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(underlined, ["qiuck"]);
+    }
+
+    #[test]
+    fn mentions_are_highlighted() {
+        let line = highlight_mentions("09:00  1:1 with @alice".to_string());
+        let styled: Vec<&str> = line
+            .spans
+            .iter()
+            .filter(|span| span.style.fg == Some(MENTION_COLOR))
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(styled, ["@alice"]);
+    }
+
+    #[test]
+    fn empty_history() {
+        let state = state(Vec::new());
+        insta::assert_snapshot!(render(&state, 60, 12));
+    }
+
+    #[test]
+    fn long_content() {
+        let long = format!(
+            "{} #notes\nsecond line that should not show",
+            "a very long memo that keeps going well past the edge of the pane ".repeat(3)
+        );
+        let mut state = state(vec![memo(&long, 9, &["notes"]), memo("short", 8, &[])]);
+        state
+            .input
+            .insert_str(&"typing past the width of the input box ".repeat(3));
+        insta::assert_snapshot!(render(&state, 50, 14));
+    }
+
+    #[test]
+    fn small_terminal() {
+        let mut state = state(history());
+        state.toggle_tag_pane();
+        state.set_tag_counts(vec![("health".to_string(), 1), ("work".to_string(), 1)]);
+        insta::assert_snapshot!(render(&state, 30, 8));
+    }
+
+    #[test]
+    fn tag_editor_suggests_existing_tags() {
+        let mut state = state(history());
+        state.set_tag_counts(vec![
+            ("work".to_string(), 3),
+            ("health".to_string(), 1),
+            ("writing".to_string(), 1),
+        ]);
+        state.focus = Focus::History;
+        let memo = state.selected_memo().unwrap().clone();
+        state.open_tag_editor(&memo);
+        state.tag_editor.insert_char('w');
+        assert_eq!(state.tag_suggestions(), ["writing"]);
+        insta::assert_snapshot!(render(&state, 60, 16));
+
+        state.complete_tag();
+        assert_eq!(state.edited_tags(), ["work", "writing"]);
+    }
+
+    #[test]
+    fn tag_pane_with_active_tag() {
+        let mut state = state(history());
+        state.set_tag_counts(vec![("health".to_string(), 1), ("work".to_string(), 1)]);
+        state.toggle_tag_pane();
+        state.tag_pane.move_down();
+        state.tag_pane.move_down();
+        state.select_highlighted_tag();
+        insta::assert_snapshot!(render(&state, 70, 12));
     }
 }
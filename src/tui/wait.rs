@@ -0,0 +1,113 @@
+//! Blocking until the TUI has something to do. On unix the wait covers the
+//! terminal and a signal-hook self-pipe together, so keystrokes, SIGWINCH and
+//! the shutdown signals all end it at once instead of on the next poll tick.
+
+use std::{io, time::Duration};
+
+#[cfg(unix)]
+use std::{
+    fs::File,
+    io::{IsTerminal, Read},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::net,
+    },
+};
+
+#[cfg(unix)]
+use mio::{Events, Interest, Poll, Token, net::UnixStream, unix::SourceFd};
+#[cfg(unix)]
+use signal_hook::{
+    SigId,
+    consts::{SIGHUP, SIGINT, SIGTERM, SIGWINCH},
+    low_level,
+};
+
+#[cfg(unix)]
+const TTY: Token = Token(0);
+#[cfg(unix)]
+const SIGNALS: Token = Token(1);
+
+#[cfg(unix)]
+pub(super) struct Waiter {
+    poll: Poll,
+    events: Events,
+    /// Where crossterm reads input when stdin isn't the terminal.
+    _tty: Option<File>,
+    wake_read: UnixStream,
+    registered: Vec<SigId>,
+}
+
+#[cfg(unix)]
+impl Waiter {
+    pub(super) fn new() -> io::Result<Self> {
+        let poll = Poll::new()?;
+        // Same choice of input as crossterm's own reader.
+        let tty = if io::stdin().is_terminal() {
+            None
+        } else {
+            Some(File::open("/dev/tty")?)
+        };
+        let tty_fd: RawFd = tty
+            .as_ref()
+            .map_or(io::stdin().as_raw_fd(), |file| file.as_raw_fd());
+        poll.registry()
+            .register(&mut SourceFd(&tty_fd), TTY, Interest::READABLE)?;
+
+        let (wake_read, wake_write) = net::UnixStream::pair()?;
+        wake_read.set_nonblocking(true)?;
+        let mut wake_read = UnixStream::from_std(wake_read);
+        poll.registry()
+            .register(&mut wake_read, SIGNALS, Interest::READABLE)?;
+        let mut waiter = Self {
+            poll,
+            events: Events::with_capacity(4),
+            _tty: tty,
+            wake_read,
+            registered: Vec::new(),
+        };
+        for signal in [SIGINT, SIGTERM, SIGHUP, SIGWINCH] {
+            // signal-hook closes its copy of the write end on unregister.
+            let id = low_level::pipe::register(signal, wake_write.try_clone()?)?;
+            waiter.registered.push(id);
+        }
+        Ok(waiter)
+    }
+
+    /// Returns once the terminal is readable, a signal arrives, or `timeout`
+    /// runs out, whichever comes first.
+    pub(super) fn wait(&mut self, timeout: Duration) -> io::Result<()> {
+        match self.poll.poll(&mut self.events, Some(timeout)) {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            result => result?,
+        }
+        // Empty the pipe so the next wait blocks again.
+        let mut buf = [0u8; 64];
+        while matches!(self.wake_read.read(&mut buf), Ok(read) if read > 0) {}
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        for id in self.registered.drain(..) {
+            low_level::unregister(id);
+        }
+    }
+}
+
+/// Without signals to listen for, waiting on input alone is enough.
+#[cfg(not(unix))]
+pub(super) struct Waiter;
+
+#[cfg(not(unix))]
+impl Waiter {
+    pub(super) fn new() -> io::Result<Self> {
+        Ok(Self)
+    }
+
+    pub(super) fn wait(&mut self, timeout: Duration) -> io::Result<()> {
+        crossterm::event::poll(timeout).map(drop)
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    config::{ContextField, WorkspaceConfig, WorkspaceRecord},
+    domain::{memo::NewMemo, tags},
+};
+
+/// Field holding the project name with `record = "meta"`.
+pub(crate) const PROJECT_KEY: &str = "project";
+
+/// Labels `new_memo` with the project the shell is in and the context fields
+/// `[workspace]` asks for. Fields given with `--meta` are left alone.
+pub(crate) fn record(config: &WorkspaceConfig, new_memo: &mut NewMemo) {
+    // Looked up for each memo: a TUI session outlives the tmux window it started in.
+    for (key, value) in context(&config.context, |name| env::var(name).ok()) {
+        if !new_memo.meta.iter().any(|(given, _)| *given == key) {
+            new_memo.meta.push((key, value));
+        }
+    }
+    record_project(config, new_memo);
+}
+
+fn record_project(config: &WorkspaceConfig, new_memo: &mut NewMemo) {
+    if config.record == WorkspaceRecord::Off {
+        return;
+    }
+    let Some(project) = env::current_dir().ok().and_then(|dir| {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        project_name(&dir, home.as_deref(), config.git_only)
+    }) else {
+        return;
+    };
+    match config.record {
+        WorkspaceRecord::Off => {}
+        WorkspaceRecord::Tag => {
+            let tag =
+                tags::normalize_tag(&project.split_whitespace().collect::<Vec<_>>().join("-"));
+            if !tag.is_empty() && !new_memo.tags.contains(&tag) {
+                new_memo.tags.push(tag);
+            }
+        }
+        WorkspaceRecord::Meta => {
+            if !new_memo.meta.iter().any(|(key, _)| key == PROJECT_KEY) {
+                new_memo.meta.push((PROJECT_KEY.to_string(), project));
+            }
+        }
+    }
+}
+
+/// The `key=value` fields for `fields` that apply here; tmux outside tmux,
+/// say, adds nothing.
+fn context(fields: &[ContextField], var: impl Fn(&str) -> Option<String>) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    for field in fields {
+        match field {
+            ContextField::Tmux => {
+                if var("TMUX").is_none() {
+                    continue;
+                }
+                let mut args = vec!["display-message", "-p"];
+                let pane = var("TMUX_PANE");
+                if let Some(pane) = &pane {
+                    args.extend(["-t", pane]);
+                }
+                args.push("#{session_name}\t#{window_name}");
+                if let Some((session, window)) = run("tmux", &args)
+                    .as_deref()
+                    .and_then(|output| output.split_once('\t'))
+                {
+                    found.push(("tmux_session".to_string(), session.to_string()));
+                    found.push(("tmux_window".to_string(), window.to_string()));
+                }
+            }
+            ContextField::Host => {
+                if let Some(host) = run("hostname", &[]) {
+                    found.push(("hostname".to_string(), host));
+                }
+            }
+            ContextField::Ssh => {
+                if let Some(from) = var("SSH_CONNECTION")
+                    .or_else(|| var("SSH_CLIENT"))
+                    .as_deref()
+                    .and_then(ssh_origin)
+                {
+                    found.push(("ssh_from".to_string(), from));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// The client address from `SSH_CONNECTION` (`client port server port`) or
+/// `SSH_CLIENT` (`client port port`).
+fn ssh_origin(connection: &str) -> Option<String> {
+    connection.split_whitespace().next().map(str::to_string)
+}
+
+/// The trimmed output of a successful `program`; `None` when it is missing or fails.
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (output.status.success() && !stdout.is_empty()).then(|| stdout.to_string())
+}
+
+/// The top folder's name of the git repository `dir` is in, or else `dir`'s
+/// own name unless `git_only` is set. The home folder and the filesystem root
+/// name no project, so a dotfiles repository in `home` doesn't label every memo.
+fn project_name(dir: &Path, home: Option<&Path>, git_only: bool) -> Option<String> {
+    // `.git` is a folder in a clone and a file in a worktree or submodule.
+    let root = dir.ancestors().find(|dir| dir.join(".git").exists());
+    let project = match root {
+        Some(root) => root,
+        None if git_only => return None,
+        None => dir,
+    };
+    if Some(project) == home {
+        return None;
+    }
+    project
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn projects_are_named_after_the_repository_root() {
+        let dir = env::temp_dir().join(format!("cap-workspace-{}", std::process::id()));
+        let nested = dir.join("my-repo").join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(dir.join("my-repo").join(".git")).unwrap();
+        let loose = dir.join("scratch");
+        fs::create_dir_all(&loose).unwrap();
+
+        assert_eq!(
+            project_name(&nested, None, true).as_deref(),
+            Some("my-repo")
+        );
+        assert_eq!(
+            project_name(&loose, None, false).as_deref(),
+            Some("scratch")
+        );
+        assert_eq!(project_name(&loose, None, true), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn home_and_root_are_not_projects() {
+        let home = env::temp_dir().join(format!("cap-workspace-home-{}", std::process::id()));
+        let notes = home.join("notes");
+        fs::create_dir_all(&notes).unwrap();
+        fs::create_dir_all(home.join(".git")).unwrap();
+
+        assert_eq!(project_name(&notes, Some(&home), false), None);
+        assert_eq!(project_name(&home, Some(&home), false), None);
+        assert_eq!(project_name(Path::new("/"), None, false), None);
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn ssh_context_names_the_client() {
+        let vars = |name: &str| {
+            (name == "SSH_CONNECTION").then(|| "203.0.113.7 50022 10.0.0.2 22".to_string())
+        };
+        assert_eq!(
+            context(&[ContextField::Ssh, ContextField::Tmux], vars),
+            [("ssh_from".to_string(), "203.0.113.7".to_string())]
+        );
+        assert!(context(&[ContextField::Ssh], |_| None).is_empty());
+    }
+
+    #[test]
+    fn explicit_project_meta_wins() {
+        let config = WorkspaceConfig {
+            record: WorkspaceRecord::Meta,
+            git_only: false,
+            context: Vec::new(),
+        };
+        let mut new_memo =
+            NewMemo::new("note").with_meta(&[(PROJECT_KEY.to_string(), "given".to_string())]);
+        record(&config, &mut new_memo);
+        assert_eq!(
+            new_memo.meta,
+            [(PROJECT_KEY.to_string(), "given".to_string())]
+        );
+    }
+}